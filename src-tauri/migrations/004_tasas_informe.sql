@@ -0,0 +1,32 @@
+-- Migración 004: Tasas configurables para informes de pago
+-- Objetivo: gastos generales, utilidades e IVA por contrato, con historial.
+-- Cada informe guarda las tasas con que se calculó.
+
+-- Tasas vigentes del contrato (porcentajes, ej: 19 = 19%)
+ALTER TABLE configuracion_contrato ADD COLUMN porcentaje_gastos_generales REAL NOT NULL DEFAULT 0;
+ALTER TABLE configuracion_contrato ADD COLUMN porcentaje_utilidades REAL NOT NULL DEFAULT 10;
+ALTER TABLE configuracion_contrato ADD COLUMN porcentaje_iva REAL NOT NULL DEFAULT 19;
+
+-- Historial de cambios de tasas
+CREATE TABLE IF NOT EXISTS historial_tasas (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    vigente_desde TEXT NOT NULL,
+    porcentaje_gastos_generales REAL NOT NULL,
+    porcentaje_utilidades REAL NOT NULL,
+    porcentaje_iva REAL NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+-- Tasas históricas (hasta ahora fijas en el código: 10% utilidades, 19% IVA)
+INSERT INTO historial_tasas (vigente_desde, porcentaje_gastos_generales, porcentaje_utilidades, porcentaje_iva)
+SELECT COALESCE((SELECT MIN(fecha_creacion) FROM informes_pago), date('now')), 0, 10, 19;
+
+-- Tasas aplicadas en cada informe (los existentes quedan con 10% / 19%)
+ALTER TABLE informes_pago ADD COLUMN neto_exento REAL NOT NULL DEFAULT 0;
+ALTER TABLE informes_pago ADD COLUMN gastos_generales REAL NOT NULL DEFAULT 0;
+ALTER TABLE informes_pago ADD COLUMN porcentaje_gastos_generales REAL NOT NULL DEFAULT 0;
+ALTER TABLE informes_pago ADD COLUMN porcentaje_utilidades REAL NOT NULL DEFAULT 10;
+ALTER TABLE informes_pago ADD COLUMN porcentaje_iva REAL NOT NULL DEFAULT 19;
+
+-- Partidas exentas de IVA
+ALTER TABLE partidas ADD COLUMN exenta_iva INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,31 @@
+-- Migración 005: Reglas de multa configurables
+-- Objetivo: la multa y los días de atraso se calculan en Rust (multas.rs)
+-- según la regla del contrato, y se guardan en cada requerimiento junto
+-- con el detalle del cálculo. Reemplaza los triggers calcular_multa_*.
+
+-- Regla del contrato (valores por defecto = regla anterior: MAX(días × 7500, días × precio_total / plazo))
+ALTER TABLE configuracion_contrato ADD COLUMN multa_modo TEXT NOT NULL DEFAULT 'fijo_o_proporcional'
+    CHECK (multa_modo IN ('fijo_o_proporcional', 'utm', 'porcentaje'));
+ALTER TABLE configuracion_contrato ADD COLUMN multa_monto_diario REAL NOT NULL DEFAULT 7500;
+ALTER TABLE configuracion_contrato ADD COLUMN multa_utm_diaria REAL NOT NULL DEFAULT 0;
+ALTER TABLE configuracion_contrato ADD COLUMN valor_utm REAL NOT NULL DEFAULT 0;
+ALTER TABLE configuracion_contrato ADD COLUMN multa_porcentaje_diario REAL NOT NULL DEFAULT 0;
+ALTER TABLE configuracion_contrato ADD COLUMN multa_tope_porcentaje REAL;
+ALTER TABLE configuracion_contrato ADD COLUMN multa_dias_gracia INTEGER NOT NULL DEFAULT 0;
+
+-- Resultado guardado por requerimiento
+ALTER TABLE requerimientos ADD COLUMN dias_atraso INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE requerimientos ADD COLUMN multa_detalle TEXT;
+
+DROP TRIGGER IF EXISTS calcular_multa_insert;
+DROP TRIGGER IF EXISTS calcular_multa_update;
+
+-- Días de atraso de los requerimientos existentes (misma fórmula que usaban las consultas)
+UPDATE requerimientos
+SET dias_atraso = CASE
+    WHEN fecha_recepcion IS NOT NULL
+         AND (COALESCE(plazo_dias, 0) + COALESCE(plazo_adicional, 0)) > 0
+         AND date(fecha_inicio, '+' || (COALESCE(plazo_dias, 0) + COALESCE(plazo_adicional, 0)) || ' days') < fecha_recepcion
+    THEN CAST(julianday(fecha_recepcion) - julianday(date(fecha_inicio, '+' || (COALESCE(plazo_dias, 0) + COALESCE(plazo_adicional, 0)) || ' days')) AS INTEGER)
+    ELSE 0
+END;
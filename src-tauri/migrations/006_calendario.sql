@@ -0,0 +1,38 @@
+-- Migración 006: Calendario de plazos
+-- Objetivo: fecha_limite y días de atraso se cuentan en días corridos o
+-- hábiles según el contrato, saltando fines de semana y feriados.
+-- fecha_limite pasa a calcularse en Rust (calendario.rs / multas.rs) y se
+-- guarda en cada requerimiento; el trigger ya no la toca.
+
+CREATE TABLE IF NOT EXISTS feriados (
+    fecha TEXT PRIMARY KEY,
+    nombre TEXT NOT NULL,
+    created_at TEXT DEFAULT (datetime('now'))
+);
+
+-- 'corridos' mantiene el comportamiento anterior
+ALTER TABLE configuracion_contrato ADD COLUMN tipo_plazo TEXT NOT NULL DEFAULT 'corridos'
+    CHECK (tipo_plazo IN ('corridos', 'habiles'));
+-- Días ISO de la semana no hábiles (1 = lunes ... 7 = domingo)
+ALTER TABLE configuracion_contrato ADD COLUMN dias_no_habiles TEXT NOT NULL DEFAULT '6,7';
+
+DROP TRIGGER IF EXISTS actualizar_plazo_total_insert;
+DROP TRIGGER IF EXISTS actualizar_plazo_total_update;
+
+CREATE TRIGGER actualizar_plazo_total_insert
+AFTER INSERT ON requerimientos
+BEGIN
+    UPDATE requerimientos
+    SET plazo_total = COALESCE(NEW.plazo_dias, 0) + COALESCE(NEW.plazo_adicional, 0),
+        precio_total = COALESCE(NEW.cantidad, 0) * COALESCE(NEW.precio_unitario, 0)
+    WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER actualizar_plazo_total_update
+AFTER UPDATE OF plazo_dias, plazo_adicional, fecha_inicio, cantidad, precio_unitario ON requerimientos
+BEGIN
+    UPDATE requerimientos
+    SET plazo_total = COALESCE(NEW.plazo_dias, 0) + COALESCE(NEW.plazo_adicional, 0),
+        precio_total = COALESCE(NEW.cantidad, 0) * COALESCE(NEW.precio_unitario, 0)
+    WHERE id = NEW.id;
+END;
@@ -0,0 +1,28 @@
+-- Migración 007: Estados de requerimiento
+-- Objetivo: `estado` sigue la máquina de estados de estados.rs
+-- (pendiente, en_ot, recepcionado, en_informe, pagado, anulado).
+
+-- Filas existentes: el estado se deduce de sus vínculos
+UPDATE requerimientos
+SET estado = CASE
+    WHEN informe_pago_id IS NOT NULL THEN 'en_informe'
+    WHEN fecha_recepcion IS NOT NULL THEN 'recepcionado'
+    WHEN ot_id IS NOT NULL THEN 'en_ot'
+    ELSE 'pendiente'
+END
+WHERE estado NOT IN ('pagado', 'anulado');
+
+-- Solo valores conocidos
+CREATE TRIGGER validar_estado_requerimiento_insert
+BEFORE INSERT ON requerimientos
+WHEN NEW.estado NOT IN ('pendiente', 'en_ot', 'recepcionado', 'en_informe', 'pagado', 'anulado')
+BEGIN
+    SELECT RAISE(ABORT, 'Estado de requerimiento no válido');
+END;
+
+CREATE TRIGGER validar_estado_requerimiento_update
+BEFORE UPDATE OF estado ON requerimientos
+WHEN NEW.estado NOT IN ('pendiente', 'en_ot', 'recepcionado', 'en_informe', 'pagado', 'anulado')
+BEGIN
+    SELECT RAISE(ABORT, 'Estado de requerimiento no válido');
+END;
@@ -0,0 +1,16 @@
+-- Migración 008: Auditoría
+-- Objetivo: registro de cada modificación con operador y valores antes/después
+-- (ver auditoria.rs). Se escribe en la misma transacción que el cambio.
+
+CREATE TABLE IF NOT EXISTS auditoria (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    fecha TEXT NOT NULL DEFAULT (datetime('now')),
+    operador TEXT NOT NULL,
+    entidad TEXT NOT NULL,
+    entidad_id INTEGER,
+    accion TEXT NOT NULL,
+    cambios TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_auditoria_entidad ON auditoria(entidad, entidad_id);
+CREATE INDEX IF NOT EXISTS idx_auditoria_fecha ON auditoria(fecha);
@@ -0,0 +1,41 @@
+-- Migración 009: Adjuntos
+-- Objetivo: fotos (antes / después / recepción) y documentos asociados a
+-- requerimientos, OT e informes. La tabla guarda los metadatos; el contenido
+-- vive en la carpeta `adjuntos/` junto a database.db, nombrado por su SHA-256
+-- (ver adjuntos.rs), así un mismo archivo adjuntado dos veces ocupa una copia.
+
+CREATE TABLE IF NOT EXISTS adjuntos (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    entidad TEXT NOT NULL CHECK (entidad IN ('requerimiento', 'orden_trabajo', 'informe_pago')),
+    entidad_id INTEGER NOT NULL,
+    nombre TEXT NOT NULL,
+    tipo_mime TEXT NOT NULL,
+    tamano INTEGER NOT NULL,
+    sha256 TEXT NOT NULL,
+    etapa TEXT CHECK (etapa IN ('antes', 'despues', 'recepcion')),
+    descripcion TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_adjuntos_entidad ON adjuntos(entidad, entidad_id);
+CREATE INDEX IF NOT EXISTS idx_adjuntos_sha256 ON adjuntos(sha256);
+
+-- Al borrar la fila dueña se van sus adjuntos (los archivos sin referencias
+-- se eliminan con adjuntos::purgar)
+CREATE TRIGGER IF NOT EXISTS borrar_adjuntos_requerimiento
+AFTER DELETE ON requerimientos
+BEGIN
+    DELETE FROM adjuntos WHERE entidad = 'requerimiento' AND entidad_id = OLD.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS borrar_adjuntos_orden_trabajo
+AFTER DELETE ON ordenes_trabajo
+BEGIN
+    DELETE FROM adjuntos WHERE entidad = 'orden_trabajo' AND entidad_id = OLD.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS borrar_adjuntos_informe_pago
+AFTER DELETE ON informes_pago
+BEGIN
+    DELETE FROM adjuntos WHERE entidad = 'informe_pago' AND entidad_id = OLD.id;
+END;
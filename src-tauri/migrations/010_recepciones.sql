@@ -0,0 +1,35 @@
+-- Migración 010: Recepciones parciales
+-- Objetivo: un requerimiento se recibe en una o más entregas (fecha, cantidad
+-- y observaciones). `cantidad_recibida` y `fecha_recepcion` del requerimiento
+-- se derivan de sus recepciones (ver recepciones.rs) y la multa se calcula
+-- por entrega (ver multas.rs).
+
+CREATE TABLE IF NOT EXISTS recepciones (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    requerimiento_id INTEGER NOT NULL,
+    fecha TEXT NOT NULL,
+    cantidad REAL NOT NULL CHECK (cantidad >= 0),
+    observaciones TEXT,
+    dias_atraso INTEGER NOT NULL DEFAULT 0,
+    multa REAL NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    FOREIGN KEY (requerimiento_id) REFERENCES requerimientos(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_recepciones_requerimiento ON recepciones(requerimiento_id, fecha);
+
+ALTER TABLE requerimientos ADD COLUMN cantidad_recibida REAL NOT NULL DEFAULT 0;
+
+CREATE TRIGGER IF NOT EXISTS borrar_recepciones_requerimiento
+AFTER DELETE ON requerimientos
+BEGIN
+    DELETE FROM recepciones WHERE requerimiento_id = OLD.id;
+END;
+
+-- Filas existentes: la recepción única pasa a ser una entrega por el total
+INSERT INTO recepciones (requerimiento_id, fecha, cantidad, dias_atraso, multa)
+SELECT id, fecha_recepcion, cantidad, dias_atraso, COALESCE(multa, 0)
+FROM requerimientos
+WHERE fecha_recepcion IS NOT NULL;
+
+UPDATE requerimientos SET cantidad_recibida = cantidad WHERE fecha_recepcion IS NOT NULL;
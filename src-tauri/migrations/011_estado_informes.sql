@@ -0,0 +1,26 @@
+-- Migración 011: Estado de los informes de pago
+-- Objetivo: los informes siguen el flujo de estados.rs (borrador, emitido,
+-- aprobado, pagado, anulado) con la fecha de cada paso. Un informe anulado
+-- se conserva con una copia de sus líneas (`detalle_anulado`, JSON) para
+-- auditoría; sus requerimientos quedan libres para otro informe.
+
+ALTER TABLE informes_pago ADD COLUMN estado TEXT NOT NULL DEFAULT 'borrador'
+    CHECK (estado IN ('borrador', 'emitido', 'aprobado', 'pagado', 'anulado'));
+ALTER TABLE informes_pago ADD COLUMN fecha_emision TEXT;
+ALTER TABLE informes_pago ADD COLUMN fecha_aprobacion TEXT;
+ALTER TABLE informes_pago ADD COLUMN fecha_pago TEXT;
+ALTER TABLE informes_pago ADD COLUMN fecha_anulacion TEXT;
+ALTER TABLE informes_pago ADD COLUMN motivo_anulacion TEXT;
+ALTER TABLE informes_pago ADD COLUMN detalle_anulado TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_informe_estado ON informes_pago(estado);
+
+-- Informes existentes: pagados si todas sus líneas lo están; el resto queda
+-- en borrador para no bloquear ediciones que hoy se permiten
+UPDATE informes_pago
+SET estado = 'pagado'
+WHERE EXISTS (SELECT 1 FROM requerimientos r WHERE r.informe_pago_id = informes_pago.id)
+  AND NOT EXISTS (
+      SELECT 1 FROM requerimientos r
+      WHERE r.informe_pago_id = informes_pago.id AND r.estado <> 'pagado'
+  );
@@ -0,0 +1,20 @@
+-- Migración 012: Presupuesto del contrato
+-- Objetivo: monto contratado, vigencia y asignación por jardín para
+-- controlar cuánto se ha comprometido, recibido y facturado.
+-- Los montos son netos (sin gastos generales, utilidades ni IVA).
+
+-- 0 = sin monto definido
+ALTER TABLE configuracion_contrato ADD COLUMN monto_contratado REAL NOT NULL DEFAULT 0
+    CHECK (monto_contratado >= 0);
+ALTER TABLE configuracion_contrato ADD COLUMN fecha_inicio TEXT;
+ALTER TABLE configuracion_contrato ADD COLUMN fecha_termino TEXT;
+-- % comprometido desde el que se avisa al mandante
+ALTER TABLE configuracion_contrato ADD COLUMN umbral_alerta REAL NOT NULL DEFAULT 80
+    CHECK (umbral_alerta > 0 AND umbral_alerta <= 100);
+
+CREATE TABLE IF NOT EXISTS presupuesto_jardines (
+    jardin_codigo TEXT PRIMARY KEY,
+    monto REAL NOT NULL CHECK (monto >= 0),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    FOREIGN KEY (jardin_codigo) REFERENCES jardines(codigo) ON DELETE CASCADE
+);
@@ -0,0 +1,338 @@
+-- Migración 013: Varios contratos en una instalación
+-- Objetivo: `configuracion_contrato` (fila única, id = 1) pasa a ser la tabla
+-- `contratos`: una fila por contrato con su configuración, correlativos y firma.
+-- Jardines, partidas, recintos, OTs, informes, requerimientos, presupuesto e
+-- historial de tasas pertenecen a un contrato (`contrato_id`) y los códigos de
+-- jardín, ítem, OT e informe son únicos dentro de cada contrato.
+-- La aplicación trabaja sobre el contrato activo (`activo = 1`, vista
+-- `contrato_activo`). Los datos existentes quedan en el contrato 1.
+-- Las tablas se reconstruyen (crear, copiar, borrar, renombrar) porque SQLite
+-- no permite cambiar restricciones UNIQUE ni claves foráneas con ALTER TABLE.
+
+-- Contadores AUTOINCREMENT actuales: los IDs ya usados no se reutilizan
+CREATE TEMP TABLE secuencias AS SELECT name, seq FROM sqlite_sequence;
+
+-- CONTRATOS
+CREATE TABLE contratos (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    titulo TEXT NOT NULL DEFAULT 'Contrato Mantención',
+    prefijo_correlativo TEXT NOT NULL DEFAULT 'M',
+    contratista TEXT NOT NULL DEFAULT '',
+    ito_nombre TEXT,
+    firma_png BLOB,
+    porcentaje_gastos_generales REAL NOT NULL DEFAULT 0,
+    porcentaje_utilidades REAL NOT NULL DEFAULT 10,
+    porcentaje_iva REAL NOT NULL DEFAULT 19,
+    multa_modo TEXT NOT NULL DEFAULT 'fijo_o_proporcional'
+        CHECK (multa_modo IN ('fijo_o_proporcional', 'utm', 'porcentaje')),
+    multa_monto_diario REAL NOT NULL DEFAULT 7500,
+    multa_utm_diaria REAL NOT NULL DEFAULT 0,
+    valor_utm REAL NOT NULL DEFAULT 0,
+    multa_porcentaje_diario REAL NOT NULL DEFAULT 0,
+    multa_tope_porcentaje REAL,
+    multa_dias_gracia INTEGER NOT NULL DEFAULT 0,
+    tipo_plazo TEXT NOT NULL DEFAULT 'corridos' CHECK (tipo_plazo IN ('corridos', 'habiles')),
+    dias_no_habiles TEXT NOT NULL DEFAULT '6,7',
+    monto_contratado REAL NOT NULL DEFAULT 0 CHECK (monto_contratado >= 0),
+    fecha_inicio TEXT,
+    fecha_termino TEXT,
+    umbral_alerta REAL NOT NULL DEFAULT 80 CHECK (umbral_alerta > 0 AND umbral_alerta <= 100),
+    activo INTEGER NOT NULL DEFAULT 0 CHECK (activo IN (0, 1)),
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+INSERT INTO contratos (
+    id, titulo, prefijo_correlativo, contratista, ito_nombre, firma_png,
+    porcentaje_gastos_generales, porcentaje_utilidades, porcentaje_iva,
+    multa_modo, multa_monto_diario, multa_utm_diaria, valor_utm, multa_porcentaje_diario,
+    multa_tope_porcentaje, multa_dias_gracia, tipo_plazo, dias_no_habiles,
+    monto_contratado, fecha_inicio, fecha_termino, umbral_alerta, activo, created_at, updated_at
+)
+SELECT
+    id, titulo, prefijo_correlativo, contratista, ito_nombre, firma_png,
+    porcentaje_gastos_generales, porcentaje_utilidades, porcentaje_iva,
+    multa_modo, multa_monto_diario, multa_utm_diaria, valor_utm, multa_porcentaje_diario,
+    multa_tope_porcentaje, multa_dias_gracia, tipo_plazo, dias_no_habiles,
+    monto_contratado, fecha_inicio, fecha_termino, umbral_alerta, 1, created_at, updated_at
+FROM configuracion_contrato;
+
+DROP TABLE configuracion_contrato;
+
+-- Un solo contrato activo
+CREATE UNIQUE INDEX idx_contrato_activo ON contratos(activo) WHERE activo = 1;
+
+CREATE VIEW contrato_activo AS SELECT * FROM contratos WHERE activo = 1;
+
+-- JARDINES
+CREATE TABLE jardines_nueva (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    contrato_id INTEGER NOT NULL,
+    codigo TEXT NOT NULL,
+    nombre TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE (contrato_id, codigo),
+    FOREIGN KEY (contrato_id) REFERENCES contratos(id)
+);
+
+INSERT INTO jardines_nueva (id, contrato_id, codigo, nombre, created_at)
+SELECT id, 1, codigo, nombre, created_at FROM jardines;
+
+-- PARTIDAS (itemizado propio de cada contrato)
+CREATE TABLE partidas_nueva (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    contrato_id INTEGER NOT NULL,
+    item TEXT NOT NULL,
+    partida TEXT NOT NULL,
+    unidad TEXT,
+    precio_unitario REAL DEFAULT 0,
+    exenta_iva INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE (contrato_id, item),
+    FOREIGN KEY (contrato_id) REFERENCES contratos(id)
+);
+
+INSERT INTO partidas_nueva (id, contrato_id, item, partida, unidad, precio_unitario, exenta_iva, created_at)
+SELECT id, 1, item, partida, unidad, precio_unitario, exenta_iva, created_at FROM partidas;
+
+-- RECINTOS
+CREATE TABLE recintos_nueva (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    contrato_id INTEGER NOT NULL,
+    jardin_codigo TEXT NOT NULL,
+    nombre TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    FOREIGN KEY (contrato_id, jardin_codigo) REFERENCES jardines(contrato_id, codigo) ON DELETE CASCADE
+);
+
+INSERT INTO recintos_nueva (id, contrato_id, jardin_codigo, nombre, created_at)
+SELECT id, 1, jardin_codigo, nombre, created_at FROM recintos;
+
+-- ÓRDENES DE TRABAJO
+CREATE TABLE ordenes_trabajo_nueva (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    contrato_id INTEGER NOT NULL,
+    codigo TEXT NOT NULL,
+    jardin_codigo TEXT NOT NULL,
+    fecha_creacion TEXT NOT NULL,
+    observaciones TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE (contrato_id, codigo),
+    FOREIGN KEY (contrato_id, jardin_codigo) REFERENCES jardines(contrato_id, codigo) ON DELETE CASCADE
+);
+
+INSERT INTO ordenes_trabajo_nueva (id, contrato_id, codigo, jardin_codigo, fecha_creacion, observaciones, created_at, updated_at)
+SELECT id, 1, codigo, jardin_codigo, fecha_creacion, observaciones, created_at, updated_at FROM ordenes_trabajo;
+
+-- INFORMES DE PAGO
+CREATE TABLE informes_pago_nueva (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    contrato_id INTEGER NOT NULL,
+    codigo TEXT NOT NULL,
+    jardin_codigo TEXT NOT NULL,
+    fecha_creacion TEXT NOT NULL,
+    neto REAL NOT NULL DEFAULT 0,
+    neto_exento REAL NOT NULL DEFAULT 0,
+    gastos_generales REAL NOT NULL DEFAULT 0,
+    utilidades REAL NOT NULL DEFAULT 0,
+    iva REAL NOT NULL DEFAULT 0,
+    total_final REAL NOT NULL DEFAULT 0,
+    porcentaje_gastos_generales REAL NOT NULL DEFAULT 0,
+    porcentaje_utilidades REAL NOT NULL DEFAULT 10,
+    porcentaje_iva REAL NOT NULL DEFAULT 19,
+    observaciones TEXT,
+    estado TEXT NOT NULL DEFAULT 'borrador'
+        CHECK (estado IN ('borrador', 'emitido', 'aprobado', 'pagado', 'anulado')),
+    fecha_emision TEXT,
+    fecha_aprobacion TEXT,
+    fecha_pago TEXT,
+    fecha_anulacion TEXT,
+    motivo_anulacion TEXT,
+    detalle_anulado TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE (contrato_id, codigo),
+    FOREIGN KEY (contrato_id, jardin_codigo) REFERENCES jardines(contrato_id, codigo) ON DELETE CASCADE
+);
+
+INSERT INTO informes_pago_nueva (
+    id, contrato_id, codigo, jardin_codigo, fecha_creacion, neto, neto_exento, gastos_generales,
+    utilidades, iva, total_final, porcentaje_gastos_generales, porcentaje_utilidades, porcentaje_iva,
+    observaciones, estado, fecha_emision, fecha_aprobacion, fecha_pago, fecha_anulacion,
+    motivo_anulacion, detalle_anulado, created_at, updated_at
+)
+SELECT
+    id, 1, codigo, jardin_codigo, fecha_creacion, neto, neto_exento, gastos_generales,
+    utilidades, iva, total_final, porcentaje_gastos_generales, porcentaje_utilidades, porcentaje_iva,
+    observaciones, estado, fecha_emision, fecha_aprobacion, fecha_pago, fecha_anulacion,
+    motivo_anulacion, detalle_anulado, created_at, updated_at
+FROM informes_pago;
+
+-- REQUERIMIENTOS
+CREATE TABLE requerimientos_nueva (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    contrato_id INTEGER NOT NULL,
+    jardin_codigo TEXT NOT NULL,
+    recinto TEXT,
+    partida_item TEXT NOT NULL,
+    cantidad REAL NOT NULL DEFAULT 0,
+    cantidad_recibida REAL NOT NULL DEFAULT 0,
+    precio_unitario REAL NOT NULL DEFAULT 0,
+    precio_total REAL NOT NULL DEFAULT 0,
+    fecha_inicio TEXT NOT NULL,
+    fecha_registro TEXT NOT NULL,
+    estado TEXT NOT NULL DEFAULT 'pendiente',
+    ot_id INTEGER,
+    informe_pago_id INTEGER,
+    fecha_recepcion TEXT,
+    plazo_dias INTEGER DEFAULT 0,
+    plazo_adicional INTEGER DEFAULT 0,
+    plazo_total INTEGER DEFAULT 0,
+    fecha_limite TEXT,
+    dias_atraso INTEGER NOT NULL DEFAULT 0,
+    multa REAL DEFAULT 0,
+    multa_detalle TEXT,
+    descripcion TEXT,
+    observaciones TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    FOREIGN KEY (contrato_id, jardin_codigo) REFERENCES jardines(contrato_id, codigo) ON DELETE CASCADE,
+    FOREIGN KEY (contrato_id, partida_item) REFERENCES partidas(contrato_id, item) ON DELETE CASCADE,
+    FOREIGN KEY (ot_id) REFERENCES ordenes_trabajo(id) ON DELETE SET NULL,
+    FOREIGN KEY (informe_pago_id) REFERENCES informes_pago(id) ON DELETE SET NULL
+);
+
+INSERT INTO requerimientos_nueva (
+    id, contrato_id, jardin_codigo, recinto, partida_item, cantidad, cantidad_recibida,
+    precio_unitario, precio_total, fecha_inicio, fecha_registro, estado, ot_id, informe_pago_id,
+    fecha_recepcion, plazo_dias, plazo_adicional, plazo_total, fecha_limite, dias_atraso, multa,
+    multa_detalle, descripcion, observaciones, created_at, updated_at
+)
+SELECT
+    id, 1, jardin_codigo, recinto, partida_item, cantidad, cantidad_recibida,
+    precio_unitario, precio_total, fecha_inicio, fecha_registro, estado, ot_id, informe_pago_id,
+    fecha_recepcion, plazo_dias, plazo_adicional, plazo_total, fecha_limite, dias_atraso, multa,
+    multa_detalle, descripcion, observaciones, created_at, updated_at
+FROM requerimientos;
+
+-- PRESUPUESTO POR JARDÍN
+CREATE TABLE presupuesto_jardines_nueva (
+    contrato_id INTEGER NOT NULL,
+    jardin_codigo TEXT NOT NULL,
+    monto REAL NOT NULL CHECK (monto >= 0),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (contrato_id, jardin_codigo),
+    FOREIGN KEY (contrato_id, jardin_codigo) REFERENCES jardines(contrato_id, codigo) ON DELETE CASCADE
+);
+
+INSERT INTO presupuesto_jardines_nueva (contrato_id, jardin_codigo, monto, updated_at)
+SELECT 1, jardin_codigo, monto, updated_at FROM presupuesto_jardines;
+
+-- HISTORIAL DE TASAS
+CREATE TABLE historial_tasas_nueva (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    contrato_id INTEGER NOT NULL,
+    vigente_desde TEXT NOT NULL,
+    porcentaje_gastos_generales REAL NOT NULL,
+    porcentaje_utilidades REAL NOT NULL,
+    porcentaje_iva REAL NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    FOREIGN KEY (contrato_id) REFERENCES contratos(id)
+);
+
+INSERT INTO historial_tasas_nueva (id, contrato_id, vigente_desde, porcentaje_gastos_generales, porcentaje_utilidades, porcentaje_iva, created_at)
+SELECT id, 1, vigente_desde, porcentaje_gastos_generales, porcentaje_utilidades, porcentaje_iva, created_at FROM historial_tasas;
+
+-- Reemplazo (borra también índices y triggers de las tablas anteriores)
+DROP TABLE requerimientos;
+DROP TABLE presupuesto_jardines;
+DROP TABLE recintos;
+DROP TABLE ordenes_trabajo;
+DROP TABLE informes_pago;
+DROP TABLE partidas;
+DROP TABLE jardines;
+DROP TABLE historial_tasas;
+
+ALTER TABLE jardines_nueva RENAME TO jardines;
+ALTER TABLE partidas_nueva RENAME TO partidas;
+ALTER TABLE recintos_nueva RENAME TO recintos;
+ALTER TABLE ordenes_trabajo_nueva RENAME TO ordenes_trabajo;
+ALTER TABLE informes_pago_nueva RENAME TO informes_pago;
+ALTER TABLE requerimientos_nueva RENAME TO requerimientos;
+ALTER TABLE presupuesto_jardines_nueva RENAME TO presupuesto_jardines;
+ALTER TABLE historial_tasas_nueva RENAME TO historial_tasas;
+
+DELETE FROM sqlite_sequence WHERE name IN (SELECT name FROM temp.secuencias);
+INSERT INTO sqlite_sequence (name, seq) SELECT name, seq FROM temp.secuencias;
+DROP TABLE temp.secuencias;
+
+-- ÍNDICES
+CREATE INDEX idx_recintos_jardin ON recintos(contrato_id, jardin_codigo);
+CREATE INDEX idx_ot_jardin ON ordenes_trabajo(contrato_id, jardin_codigo);
+CREATE INDEX idx_informe_jardin ON informes_pago(contrato_id, jardin_codigo);
+CREATE INDEX idx_informe_estado ON informes_pago(contrato_id, estado);
+CREATE INDEX idx_req_estado ON requerimientos(contrato_id, estado);
+CREATE INDEX idx_req_jardin_estado ON requerimientos(contrato_id, jardin_codigo, estado);
+CREATE INDEX idx_req_partida ON requerimientos(contrato_id, partida_item);
+CREATE INDEX idx_req_ot ON requerimientos(ot_id) WHERE ot_id IS NOT NULL;
+CREATE INDEX idx_req_informe ON requerimientos(informe_pago_id) WHERE informe_pago_id IS NOT NULL;
+CREATE INDEX idx_requerimientos_fecha_inicio ON requerimientos(fecha_inicio DESC);
+CREATE INDEX idx_historial_tasas_contrato ON historial_tasas(contrato_id, vigente_desde);
+
+-- TRIGGERS (mismos de las migraciones 006, 007, 009 y 010)
+CREATE TRIGGER actualizar_plazo_total_insert
+AFTER INSERT ON requerimientos
+BEGIN
+    UPDATE requerimientos
+    SET plazo_total = COALESCE(NEW.plazo_dias, 0) + COALESCE(NEW.plazo_adicional, 0),
+        precio_total = COALESCE(NEW.cantidad, 0) * COALESCE(NEW.precio_unitario, 0)
+    WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER actualizar_plazo_total_update
+AFTER UPDATE OF plazo_dias, plazo_adicional, fecha_inicio, cantidad, precio_unitario ON requerimientos
+BEGIN
+    UPDATE requerimientos
+    SET plazo_total = COALESCE(NEW.plazo_dias, 0) + COALESCE(NEW.plazo_adicional, 0),
+        precio_total = COALESCE(NEW.cantidad, 0) * COALESCE(NEW.precio_unitario, 0)
+    WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER validar_estado_requerimiento_insert
+BEFORE INSERT ON requerimientos
+WHEN NEW.estado NOT IN ('pendiente', 'en_ot', 'recepcionado', 'en_informe', 'pagado', 'anulado')
+BEGIN
+    SELECT RAISE(ABORT, 'Estado de requerimiento no válido');
+END;
+
+CREATE TRIGGER validar_estado_requerimiento_update
+BEFORE UPDATE OF estado ON requerimientos
+WHEN NEW.estado NOT IN ('pendiente', 'en_ot', 'recepcionado', 'en_informe', 'pagado', 'anulado')
+BEGIN
+    SELECT RAISE(ABORT, 'Estado de requerimiento no válido');
+END;
+
+CREATE TRIGGER borrar_recepciones_requerimiento
+AFTER DELETE ON requerimientos
+BEGIN
+    DELETE FROM recepciones WHERE requerimiento_id = OLD.id;
+END;
+
+CREATE TRIGGER borrar_adjuntos_requerimiento
+AFTER DELETE ON requerimientos
+BEGIN
+    DELETE FROM adjuntos WHERE entidad = 'requerimiento' AND entidad_id = OLD.id;
+END;
+
+CREATE TRIGGER borrar_adjuntos_orden_trabajo
+AFTER DELETE ON ordenes_trabajo
+BEGIN
+    DELETE FROM adjuntos WHERE entidad = 'orden_trabajo' AND entidad_id = OLD.id;
+END;
+
+CREATE TRIGGER borrar_adjuntos_informe_pago
+AFTER DELETE ON informes_pago
+BEGIN
+    DELETE FROM adjuntos WHERE entidad = 'informe_pago' AND entidad_id = OLD.id;
+END;
@@ -0,0 +1,57 @@
+-- Migración 014: Historial de precios de partidas y reajustes
+-- Objetivo: listas de precios con fecha de vigencia por partida, reajustes
+-- (porcentaje o índice, ej. IPC) desde una fecha, y en cada requerimiento
+-- la versión de precio con que se valorizó.
+-- partidas.precio_unitario queda como el precio de la lista más reciente.
+
+-- Reajustes aplicados al itemizado de un contrato
+CREATE TABLE IF NOT EXISTS reajustes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    contrato_id INTEGER NOT NULL,
+    vigente_desde TEXT NOT NULL,
+    tipo TEXT NOT NULL CHECK (tipo IN ('porcentaje', 'indice')),
+    -- Variación aplicada (ej: 4.5 = +4,5%); en los de índice, calculada
+    porcentaje REAL NOT NULL,
+    indice_base REAL,
+    indice_nuevo REAL,
+    descripcion TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE (contrato_id, vigente_desde),
+    FOREIGN KEY (contrato_id) REFERENCES contratos(id)
+);
+
+-- Versiones de precio de cada partida (sin reajuste_id: precio inicial o del catálogo)
+CREATE TABLE IF NOT EXISTS precios_partida (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    partida_id INTEGER NOT NULL,
+    vigente_desde TEXT NOT NULL,
+    precio_unitario REAL NOT NULL,
+    reajuste_id INTEGER,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE (partida_id, vigente_desde),
+    FOREIGN KEY (partida_id) REFERENCES partidas(id) ON DELETE CASCADE,
+    FOREIGN KEY (reajuste_id) REFERENCES reajustes(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_precios_partida_vigencia ON precios_partida(partida_id, vigente_desde DESC);
+
+-- Precio actual como primera versión, vigente desde el inicio del contrato
+INSERT INTO precios_partida (partida_id, vigente_desde, precio_unitario)
+SELECT p.id,
+       COALESCE(c.fecha_inicio, (SELECT MIN(r.fecha_inicio) FROM requerimientos r WHERE r.contrato_id = p.contrato_id), date(p.created_at)),
+       COALESCE(p.precio_unitario, 0)
+FROM partidas p
+JOIN contratos c ON c.id = p.contrato_id;
+
+-- Versión de precio usada por cada requerimiento (NULL: precio manual o importado)
+ALTER TABLE requerimientos ADD COLUMN precio_partida_id INTEGER REFERENCES precios_partida(id) ON DELETE SET NULL;
+
+-- Los existentes que coinciden con el precio de catálogo quedan asociados a él
+UPDATE requerimientos
+SET precio_partida_id = (
+    SELECT pp.id FROM precios_partida pp
+    JOIN partidas p ON p.id = pp.partida_id
+    WHERE p.contrato_id = requerimientos.contrato_id AND p.item = requerimientos.partida_item
+      AND pp.precio_unitario = requerimientos.precio_unitario
+)
+WHERE precio_partida_id IS NULL;
@@ -0,0 +1,26 @@
+-- Migración 015: Catálogo con filas inactivas
+-- Objetivo: reimportar el catálogo sin borrar jardines ni partidas. Las filas
+-- que ya no vienen en el archivo quedan inactivas (no se ofrecen para nuevos
+-- requerimientos) y las que usan requerimientos, OTs o informes no se pueden
+-- borrar: el ON DELETE CASCADE se llevaría esos movimientos.
+
+ALTER TABLE jardines ADD COLUMN activo INTEGER NOT NULL DEFAULT 1 CHECK (activo IN (0, 1));
+ALTER TABLE partidas ADD COLUMN activo INTEGER NOT NULL DEFAULT 1 CHECK (activo IN (0, 1));
+ALTER TABLE recintos ADD COLUMN activo INTEGER NOT NULL DEFAULT 1 CHECK (activo IN (0, 1));
+
+-- Jardines y partidas en uso no se borran (los recintos se nombran por texto)
+CREATE TRIGGER proteger_jardin_en_uso
+BEFORE DELETE ON jardines
+WHEN EXISTS (SELECT 1 FROM requerimientos WHERE contrato_id = OLD.contrato_id AND jardin_codigo = OLD.codigo)
+  OR EXISTS (SELECT 1 FROM ordenes_trabajo WHERE contrato_id = OLD.contrato_id AND jardin_codigo = OLD.codigo)
+  OR EXISTS (SELECT 1 FROM informes_pago WHERE contrato_id = OLD.contrato_id AND jardin_codigo = OLD.codigo)
+BEGIN
+    SELECT RAISE(ABORT, 'Catálogo en uso: el jardín tiene requerimientos, OTs o informes');
+END;
+
+CREATE TRIGGER proteger_partida_en_uso
+BEFORE DELETE ON partidas
+WHEN EXISTS (SELECT 1 FROM requerimientos WHERE contrato_id = OLD.contrato_id AND partida_item = OLD.item)
+BEGIN
+    SELECT RAISE(ABORT, 'Catálogo en uso: la partida tiene requerimientos');
+END;
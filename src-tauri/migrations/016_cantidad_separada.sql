@@ -0,0 +1,8 @@
+-- Migración 016: Multa de requerimientos separados
+-- Objetivo: al informar lo recibido de un requerimiento incompleto,
+-- `separar_recibido` lo divide en dos filas. La multa de ambas se sigue
+-- calculando sobre el requerimiento original (el monto diario fijo o en UTM no
+-- se reparte entre filas): `cantidad_separada` guarda la cantidad del
+-- original que quedó en la otra fila.
+
+ALTER TABLE requerimientos ADD COLUMN cantidad_separada REAL NOT NULL DEFAULT 0 CHECK (cantidad_separada >= 0);
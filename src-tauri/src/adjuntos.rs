@@ -0,0 +1,196 @@
+use crate::auditoria::Entidad;
+use crate::errores::ErrorComando;
+use sha2::{Digest, Sha256};
+use sqlx::SqliteConnection;
+use std::path::{Path, PathBuf};
+
+// ========== ADJUNTOS ==========
+//
+// Almacén de contenido: cada archivo se guarda una sola vez en
+// `<base>.adjuntos/<2 primeros hex>/<sha256>` junto al archivo .db, y la tabla
+// `adjuntos` apunta a él por hash. Las miniaturas se generan a pedido y se
+// guardan en `<base>.adjuntos/miniaturas/<sha256>.jpg`. Cada base tiene su
+// propio almacén, aunque varias compartan carpeta: lo que una base deja de
+// usar se puede borrar sin mirar las demás.
+//
+// Los archivos nunca se reescriben (mismo hash = mismo contenido). Al agregar,
+// la fila se inserta antes de escribir el archivo: la transacción ya tiene el
+// bloqueo de escritura, así que una eliminación simultánea del mismo hash
+// espera a que confirme y ve la nueva referencia. Si la transacción falla
+// después de escribir, el archivo queda huérfano (ocupa espacio, no se pierde
+// nada). Al eliminar, `apartar` retira el contenido dentro de la misma
+// transacción solo si ninguna otra fila usa ese hash; nunca se barre el
+// almacén completo.
+//
+// Los respaldos automáticos (.db) solo copian la tabla; el contenido está en
+// el almacén y en la exportación completa (JSON).
+
+/// Tamaño máximo por archivo
+pub const TAMANO_MAXIMO: usize = 25 * 1024 * 1024;
+
+/// Lado mayor de las miniaturas, en píxeles
+const LADO_MINIATURA: u32 = 256;
+
+pub const ETAPAS: &[&str] = &["antes", "despues", "recepcion"];
+
+#[derive(Debug, serde::Serialize, sqlx::FromRow)]
+pub struct Adjunto {
+    pub id: i64,
+    pub entidad: String,
+    pub entidad_id: i64,
+    pub nombre: String,
+    pub tipo_mime: String,
+    pub tamano: i64,
+    pub sha256: String,
+    pub etapa: Option<String>,
+    pub descripcion: Option<String>,
+    pub created_at: String,
+}
+
+/// Entidades que aceptan adjuntos
+pub fn entidad(nombre: &str) -> Result<Entidad, ErrorComando> {
+    match nombre {
+        "requerimiento" => Ok(Entidad::Requerimiento),
+        "orden_trabajo" => Ok(Entidad::OrdenTrabajo),
+        "informe_pago" => Ok(Entidad::InformePago),
+        _ => Err(ErrorComando::validacion("entidad", format!("No se pueden adjuntar archivos a '{}'", nombre))),
+    }
+}
+
+pub fn directorio(db_path: &Path) -> PathBuf {
+    let base = db_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    db_path.parent().unwrap_or_else(|| Path::new(".")).join(format!("{}.adjuntos", base))
+}
+
+/// Antes de que cada base tuviera su almacén, los adjuntos de la base por
+/// defecto vivían en `adjuntos/` junto a ella; se mueven una sola vez.
+pub fn mover_almacen_anterior(db_path: &Path) {
+    let anterior = db_path.parent().unwrap_or_else(|| Path::new(".")).join("adjuntos");
+    let nuevo = directorio(db_path);
+    if anterior.is_dir() && !nuevo.exists() {
+        match std::fs::rename(&anterior, &nuevo) {
+            Ok(()) => println!("📎 Adjuntos movidos a {}", nuevo.display()),
+            Err(e) => println!("⚠️ No se pudieron mover los adjuntos a {}: {}", nuevo.display(), e),
+        }
+    }
+}
+
+/// SHA-256 en hexadecimal minúsculo (64 caracteres). Todo hash que arma una
+/// ruta pasa por aquí: uno importado podría traer `..` o separadores.
+pub fn hash_valido(sha256: &str) -> bool {
+    sha256.len() == 64 && sha256.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+fn verificar_hash(sha256: &str) -> std::io::Result<()> {
+    if hash_valido(sha256) {
+        Ok(())
+    } else {
+        Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("Hash de adjunto no válido: '{}'", sha256)))
+    }
+}
+
+fn ruta_contenido(dir: &Path, sha256: &str) -> std::io::Result<PathBuf> {
+    verificar_hash(sha256)?;
+    Ok(dir.join(&sha256[..2]).join(sha256))
+}
+
+fn ruta_miniatura(dir: &Path, sha256: &str) -> std::io::Result<PathBuf> {
+    verificar_hash(sha256)?;
+    Ok(dir.join("miniaturas").join(format!("{}.jpg", sha256)))
+}
+
+pub fn hash(contenido: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(contenido))
+}
+
+/// Tipo MIME según la extensión del nombre original
+pub fn tipo_mime(nombre: &str) -> &'static str {
+    let extension = nombre.rsplit_once('.').map(|(_, e)| e.to_ascii_lowercase()).unwrap_or_default();
+    match extension.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "webp" => "image/webp",
+        "pdf" => "application/pdf",
+        "doc" => "application/msword",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "xls" => "application/vnd.ms-excel",
+        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        "txt" => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Guarda el contenido en el almacén y devuelve su hash
+pub fn guardar(dir: &Path, contenido: &[u8]) -> std::io::Result<String> {
+    let sha256 = hash(contenido);
+    escribir(dir, &sha256, contenido)?;
+    Ok(sha256)
+}
+
+/// Escribe el contenido bajo un hash ya calculado, si aún no está
+pub fn escribir(dir: &Path, sha256: &str, contenido: &[u8]) -> std::io::Result<()> {
+    let ruta = ruta_contenido(dir, sha256)?;
+    if !ruta.exists() {
+        std::fs::create_dir_all(ruta.parent().unwrap_or(dir))?;
+        // Escritura atómica: un corte a mitad no deja un archivo truncado con nombre válido
+        let temporal = ruta.with_extension("tmp");
+        std::fs::write(&temporal, contenido)?;
+        std::fs::rename(&temporal, &ruta)?;
+    }
+    Ok(())
+}
+
+pub fn leer(dir: &Path, sha256: &str) -> Result<Vec<u8>, ErrorComando> {
+    std::fs::read(ruta_contenido(dir, sha256)?).map_err(|e| ErrorComando::Io {
+        mensaje: format!("No se pudo leer el adjunto {}: {}", sha256, e),
+    })
+}
+
+/// Miniatura JPEG de una imagen; None para documentos u otros formatos
+pub fn miniatura(dir: &Path, sha256: &str, tipo_mime: &str) -> Result<Option<Vec<u8>>, ErrorComando> {
+    if !tipo_mime.starts_with("image/") {
+        return Ok(None);
+    }
+    let ruta = ruta_miniatura(dir, sha256)?;
+    if let Ok(bytes) = std::fs::read(&ruta) {
+        return Ok(Some(bytes));
+    }
+
+    let Ok(imagen) = image::load_from_memory(&leer(dir, sha256)?) else {
+        return Ok(None);
+    };
+    let mut bytes = Vec::new();
+    imagen
+        .thumbnail(LADO_MINIATURA, LADO_MINIATURA)
+        .to_rgb8()
+        .write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageFormat::Jpeg)
+        .map_err(|e| ErrorComando::Internal { mensaje: format!("Error generando miniatura: {}", e) })?;
+
+    std::fs::create_dir_all(ruta.parent().unwrap_or(dir))?;
+    std::fs::write(&ruta, &bytes)?;
+    Ok(Some(bytes))
+}
+
+/// Retira el contenido de `sha256` si ninguna fila lo referencia ya. Se llama
+/// dentro de la transacción que borró la fila: el archivo se renombra (y la
+/// miniatura se borra) y se devuelve la ruta apartada, que el llamador elimina
+/// al confirmar o devuelve con `restaurar` si la confirmación falla.
+pub async fn apartar(conn: &mut SqliteConnection, dir: &Path, sha256: &str) -> Result<Option<PathBuf>, ErrorComando> {
+    let referencias: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM adjuntos WHERE sha256 = ?")
+        .bind(sha256)
+        .fetch_one(&mut *conn)
+        .await?;
+    let ruta = ruta_contenido(dir, sha256)?;
+    if referencias > 0 || !ruta.exists() {
+        return Ok(None);
+    }
+    let apartado = ruta.with_extension("eliminado");
+    std::fs::rename(&ruta, &apartado)?;
+    let _ = std::fs::remove_file(ruta_miniatura(dir, sha256)?);
+    Ok(Some(apartado))
+}
+
+/// Devuelve a su lugar un contenido apartado con `apartar`
+pub fn restaurar(dir: &Path, sha256: &str, apartado: &Path) -> std::io::Result<()> {
+    std::fs::rename(apartado, ruta_contenido(dir, sha256)?)
+}
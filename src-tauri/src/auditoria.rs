@@ -0,0 +1,216 @@
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Column, Row, SqliteConnection, TypeInfo, ValueRef};
+
+// ========== AUDITORÍA ==========
+//
+// Cada comando que modifica datos deja un registro en `auditoria` (quién,
+// qué, cuándo) dentro de la misma transacción que el cambio: si el cambio se
+// revierte, el registro también. `cambios` guarda solo los campos que
+// cambiaron: {"campo": {"antes": .., "despues": ..}}.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Entidad {
+    Requerimiento,
+    OrdenTrabajo,
+    InformePago,
+    Jardin,
+    Partida,
+    Recinto,
+    Configuracion,
+    Feriado,
+    /// Operaciones masivas (importaciones, borrado total, restauración, cambio de archivo)
+    BaseDatos,
+}
+
+impl Entidad {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Entidad::Requerimiento => "requerimiento",
+            Entidad::OrdenTrabajo => "orden_trabajo",
+            Entidad::InformePago => "informe_pago",
+            Entidad::Jardin => "jardin",
+            Entidad::Partida => "partida",
+            Entidad::Recinto => "recinto",
+            Entidad::Configuracion => "configuracion",
+            Entidad::Feriado => "feriado",
+            Entidad::BaseDatos => "base_datos",
+        }
+    }
+
+    pub fn tabla(self) -> Option<&'static str> {
+        match self {
+            Entidad::Requerimiento => Some("requerimientos"),
+            Entidad::OrdenTrabajo => Some("ordenes_trabajo"),
+            Entidad::InformePago => Some("informes_pago"),
+            Entidad::Jardin => Some("jardines"),
+            Entidad::Partida => Some("partidas"),
+            Entidad::Recinto => Some("recintos"),
+            Entidad::Configuracion => Some("contratos"),
+            Entidad::Feriado | Entidad::BaseDatos => None,
+        }
+    }
+}
+
+/// Columnas que cambian en cada escritura o se derivan de otras ya registradas
+const IGNORADAS: &[&str] = &["updated_at", "multa_detalle"];
+
+#[derive(Debug, serde::Serialize, sqlx::FromRow)]
+pub struct RegistroAuditoria {
+    pub id: i64,
+    pub fecha: String,
+    pub operador: String,
+    pub entidad: String,
+    pub entidad_id: Option<i64>,
+    pub accion: String,
+    /// JSON {"campo": {"antes": .., "despues": ..}}
+    pub cambios: String,
+}
+
+fn valor_columna(fila: &SqliteRow, i: usize) -> Value {
+    let Ok(raw) = fila.try_get_raw(i) else { return Value::Null };
+    if raw.is_null() {
+        return Value::Null;
+    }
+
+    match raw.type_info().name() {
+        "INTEGER" | "BOOLEAN" => fila.try_get::<i64, _>(i).map(Value::from).unwrap_or(Value::Null),
+        "REAL" => fila.try_get::<f64, _>(i).map(Value::from).unwrap_or(Value::Null),
+        // Binarios (firma): tamaño y hash, no el contenido
+        "BLOB" => fila
+            .try_get::<Vec<u8>, _>(i)
+            .map(|bytes| {
+                let hash: String = Sha256::digest(&bytes).iter().take(6).map(|b| format!("{:02x}", b)).collect();
+                Value::from(format!("<{} bytes sha256:{}>", bytes.len(), hash))
+            })
+            .unwrap_or(Value::Null),
+        _ => fila.try_get::<String, _>(i).map(Value::from).unwrap_or(Value::Null),
+    }
+}
+
+/// Fila completa de la entidad como JSON (`None` si no existe). OT e informes
+/// incluyen además los IDs de sus requerimientos.
+pub async fn instantanea(
+    conn: &mut SqliteConnection,
+    entidad: Entidad,
+    id: i64,
+) -> Result<Option<Value>, sqlx::Error> {
+    let Some(tabla) = entidad.tabla() else { return Ok(None) };
+
+    let Some(fila) = sqlx::query(&format!("SELECT * FROM {} WHERE id = ?", tabla))
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    let mut datos: Map<String, Value> = fila
+        .columns()
+        .iter()
+        .map(|c| (c.name().to_string(), valor_columna(&fila, c.ordinal())))
+        .collect();
+
+    let vinculo = match entidad {
+        Entidad::OrdenTrabajo => Some("ot_id"),
+        Entidad::InformePago => Some("informe_pago_id"),
+        _ => None,
+    };
+    if let Some(columna) = vinculo {
+        let ids: Vec<i64> = sqlx::query_scalar(&format!("SELECT id FROM requerimientos WHERE {} = ? ORDER BY id", columna))
+            .bind(id)
+            .fetch_all(&mut *conn)
+            .await?;
+        datos.insert("requerimientos".to_string(), Value::from(ids));
+    }
+
+    Ok(Some(Value::Object(datos)))
+}
+
+/// Campos distintos entre dos instantáneas (creación: `antes` = None; eliminación: `despues` = None)
+pub fn diferencias(antes: Option<&Value>, despues: Option<&Value>) -> Map<String, Value> {
+    let vacio = Map::new();
+    let antes = antes.and_then(|v| v.as_object()).unwrap_or(&vacio);
+    let despues = despues.and_then(|v| v.as_object()).unwrap_or(&vacio);
+
+    let mut campos: Vec<&String> = antes.keys().chain(despues.keys()).collect();
+    campos.sort();
+    campos.dedup();
+
+    campos
+        .into_iter()
+        .filter(|campo| !IGNORADAS.contains(&campo.as_str()))
+        .filter_map(|campo| {
+            let a = antes.get(campo).unwrap_or(&Value::Null);
+            let d = despues.get(campo).unwrap_or(&Value::Null);
+            (a != d).then(|| (campo.clone(), serde_json::json!({ "antes": a, "despues": d })))
+        })
+        .collect()
+}
+
+pub async fn registrar(
+    conn: &mut SqliteConnection,
+    operador: &str,
+    entidad: Entidad,
+    entidad_id: Option<i64>,
+    accion: &str,
+    cambios: Value,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO auditoria (operador, entidad, entidad_id, accion, cambios) VALUES (?, ?, ?, ?, ?)"
+    )
+    .bind(operador)
+    .bind(entidad.as_str())
+    .bind(entidad_id)
+    .bind(accion)
+    .bind(cambios.to_string())
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Compara `antes` con el estado actual de la entidad y registra lo que cambió.
+/// No registra nada si no hubo cambios.
+pub async fn registrar_cambio(
+    conn: &mut SqliteConnection,
+    operador: &str,
+    entidad: Entidad,
+    id: i64,
+    accion: &str,
+    antes: Option<Value>,
+) -> Result<(), sqlx::Error> {
+    let despues = instantanea(&mut *conn, entidad, id).await?;
+    let cambios = diferencias(antes.as_ref(), despues.as_ref());
+    if cambios.is_empty() {
+        return Ok(());
+    }
+    registrar(conn, operador, entidad, Some(id), accion, Value::Object(cambios)).await
+}
+
+/// Instantáneas de varias filas, para registrar después con `registrar_cambios`
+pub async fn instantaneas(
+    conn: &mut SqliteConnection,
+    entidad: Entidad,
+    ids: &[i64],
+) -> Result<Vec<(i64, Option<Value>)>, sqlx::Error> {
+    let mut filas = Vec::with_capacity(ids.len());
+    for id in ids {
+        filas.push((*id, instantanea(&mut *conn, entidad, *id).await?));
+    }
+    Ok(filas)
+}
+
+/// `registrar_cambio` de cada fila de `instantaneas`
+pub async fn registrar_cambios(
+    conn: &mut SqliteConnection,
+    operador: &str,
+    entidad: Entidad,
+    accion: &str,
+    antes: Vec<(i64, Option<Value>)>,
+) -> Result<(), sqlx::Error> {
+    for (id, antes) in antes {
+        registrar_cambio(&mut *conn, operador, entidad, id, accion, antes).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,198 @@
+use crate::adjuntos;
+use crate::db::{self, DbState};
+use crate::errores::ErrorComando;
+use sqlx::{ConnectOptions, Connection, Pool, Sqlite};
+use std::path::{Path, PathBuf};
+
+// ========== ARCHIVOS DE BASE DE DATOS ==========
+//
+// La aplicación trabaja sobre un archivo .db a la vez: por defecto
+// `database.db` en la carpeta de datos, o el que el usuario abra (los
+// inspectores intercambian un archivo por región). Abrir, crear o "guardar
+// como" reemplaza el pool de `DbState` sin reiniciar la aplicación.
+//
+// Respaldos y adjuntos viven en carpetas propias de cada archivo, junto a él:
+// `<base>.respaldos` (ver commands_respaldo.rs) y `<base>.adjuntos` (ver
+// adjuntos.rs). Las bases usadas se recuerdan en `recientes.json` y la última
+// se abre al iniciar.
+
+const MAX_RECIENTES: usize = 10;
+const ARCHIVO_RECIENTES: &str = "recientes.json";
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct BaseReciente {
+    pub ruta: String,
+    pub abierta_en: String,
+    /// El archivo sigue en su lugar (se calcula al listar)
+    #[serde(default)]
+    pub existe: bool,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct BaseActual {
+    pub ruta: String,
+    pub nombre: String,
+    /// Es la base por defecto de la carpeta de datos
+    pub predeterminada: bool,
+}
+
+pub fn actual(db: &DbState) -> BaseActual {
+    let ruta = db.db_path();
+    BaseActual {
+        nombre: ruta.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+        predeterminada: ruta == db::dir_app().join("database.db"),
+        ruta: ruta.to_string_lossy().to_string(),
+    }
+}
+
+// ========== RECIENTES ==========
+
+fn leer_recientes(app_dir: &Path) -> Vec<BaseReciente> {
+    std::fs::read_to_string(app_dir.join(ARCHIVO_RECIENTES))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn escribir_recientes(app_dir: &Path, recientes: &[BaseReciente]) -> Result<(), ErrorComando> {
+    let json = serde_json::to_string_pretty(recientes).map_err(|e| ErrorComando::Internal { mensaje: e.to_string() })?;
+    std::fs::create_dir_all(app_dir)?;
+    std::fs::write(app_dir.join(ARCHIVO_RECIENTES), json)?;
+    Ok(())
+}
+
+/// Bases abiertas, de la más reciente a la más antigua
+pub fn recientes(app_dir: &Path) -> Vec<BaseReciente> {
+    let mut lista = leer_recientes(app_dir);
+    for base in &mut lista {
+        base.existe = Path::new(&base.ruta).exists();
+    }
+    lista
+}
+
+pub fn ultima(app_dir: &Path) -> Option<PathBuf> {
+    leer_recientes(app_dir).into_iter().next().map(|base| PathBuf::from(base.ruta))
+}
+
+fn registrar_reciente(app_dir: &Path, ruta: &Path) -> Result<(), ErrorComando> {
+    let ruta = ruta.to_string_lossy().to_string();
+    let mut lista = leer_recientes(app_dir);
+    lista.retain(|base| base.ruta != ruta);
+    lista.insert(0, BaseReciente {
+        ruta,
+        abierta_en: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        existe: true,
+    });
+    lista.truncate(MAX_RECIENTES);
+    escribir_recientes(app_dir, &lista)
+}
+
+pub fn quitar_reciente(app_dir: &Path, ruta: &str) -> Result<(), ErrorComando> {
+    let mut lista = leer_recientes(app_dir);
+    lista.retain(|base| base.ruta != ruta);
+    escribir_recientes(app_dir, &lista)
+}
+
+// ========== ABRIR / CREAR / GUARDAR COMO ==========
+
+/// Rutas relativas o con `~` no tienen sentido para el proceso: solo absolutas, terminadas en .db
+fn validar_ruta(ruta: &str) -> Result<PathBuf, ErrorComando> {
+    let ruta = PathBuf::from(ruta.trim());
+    if !ruta.is_absolute() {
+        return Err(ErrorComando::validacion("ruta", "La ruta del archivo debe ser absoluta"));
+    }
+    if ruta.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase()).as_deref() != Some("db") {
+        return Err(ErrorComando::validacion("ruta", "El archivo debe tener extensión .db"));
+    }
+    Ok(ruta)
+}
+
+/// Un archivo de SSOL tiene el control de migraciones o, si es anterior a
+/// él, las tablas del schema original
+async fn verificar_formato(ruta: &Path) -> Result<(), ErrorComando> {
+    let mut conn = sqlx::sqlite::SqliteConnectOptions::new()
+        .filename(ruta)
+        .read_only(true)
+        .connect()
+        .await
+        .map_err(|e| ErrorComando::validacion("ruta", format!("No se pudo abrir {}: {}", ruta.display(), e)))?;
+    let tablas: Result<i64, sqlx::Error> = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM sqlite_master
+         WHERE type = 'table' AND name IN ('schema_migrations', 'jardines', 'requerimientos')"
+    )
+    .fetch_one(&mut conn)
+    .await;
+    conn.close().await.ok();
+    match tablas {
+        Ok(n) if n > 0 => Ok(()),
+        _ => Err(ErrorComando::validacion(
+            "ruta",
+            format!("{} no es una base de datos de SSOL", ruta.display()),
+        )),
+    }
+}
+
+/// Deja `pool` como base activa, cierra la anterior y la recuerda en recientes
+async fn activar(db: &DbState, pool: Pool<Sqlite>, ruta: PathBuf) -> Result<BaseActual, ErrorComando> {
+    let anterior = db.reemplazar(pool, ruta.clone());
+    // Espera a que terminen las operaciones que todavía la usan
+    anterior.close().await;
+    registrar_reciente(&db::dir_app(), &ruta)?;
+    println!("📂 Base activa: {}", ruta.display());
+    Ok(actual(db))
+}
+
+pub async fn abrir(db: &DbState, ruta: &str) -> Result<BaseActual, ErrorComando> {
+    let ruta = validar_ruta(ruta)?;
+    if !ruta.is_file() {
+        return Err(ErrorComando::validacion("ruta", format!("No existe el archivo {}", ruta.display())));
+    }
+    if ruta == db.db_path() {
+        registrar_reciente(&db::dir_app(), &ruta)?;
+        return Ok(actual(db));
+    }
+    verificar_formato(&ruta).await?;
+
+    let pool = db::conectar(&ruta, false).await?;
+    activar(db, pool, ruta).await
+}
+
+/// Base nueva y vacía (con el contrato inicial de las migraciones)
+pub async fn crear(db: &DbState, ruta: &str) -> Result<BaseActual, ErrorComando> {
+    let ruta = validar_ruta(ruta)?;
+    if ruta.exists() {
+        return Err(ErrorComando::validacion("ruta", format!("Ya existe el archivo {}", ruta.display())));
+    }
+
+    let pool = db::conectar(&ruta, true).await?;
+    activar(db, pool, ruta).await
+}
+
+/// Copia la base activa (y el contenido de sus adjuntos) a `ruta` y sigue
+/// trabajando sobre la copia
+pub async fn guardar_como(db: &DbState, ruta: &str) -> Result<BaseActual, ErrorComando> {
+    let ruta = validar_ruta(ruta)?;
+    if ruta.exists() {
+        return Err(ErrorComando::validacion("ruta", format!("Ya existe el archivo {}", ruta.display())));
+    }
+
+    let pool = db.pool();
+    sqlx::query("VACUUM INTO ?")
+        .bind(ruta.to_string_lossy().to_string())
+        .execute(&pool)
+        .await?;
+
+    let origen = adjuntos::directorio(&db.db_path());
+    let destino = adjuntos::directorio(&ruta);
+    if origen != destino {
+        let hashes: Vec<String> = sqlx::query_scalar("SELECT DISTINCT sha256 FROM adjuntos")
+            .fetch_all(&pool)
+            .await?;
+        for sha256 in hashes {
+            adjuntos::guardar(&destino, &adjuntos::leer(&origen, &sha256)?)?;
+        }
+    }
+
+    let pool = db::conectar(&ruta, false).await?;
+    activar(db, pool, ruta).await
+}
@@ -0,0 +1,44 @@
+// ========== CÁLCULOS DE INFORMES DE PAGO ==========
+//
+// neto              = suma de a_pago de las líneas (afectas + exentas)
+// gastos_generales  = neto × % GG
+// utilidades        = neto × % utilidades
+// iva               = (neto_afecto × (1 + % GG + % utilidades)) × % IVA
+// total_final       = neto + gastos_generales + utilidades + iva
+
+/// Tasas en porcentaje (ej: 19.0 = 19%)
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize, sqlx::FromRow)]
+pub struct TasasInforme {
+    pub porcentaje_gastos_generales: f64,
+    pub porcentaje_utilidades: f64,
+    pub porcentaje_iva: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct TotalesInforme {
+    pub neto: f64,
+    pub neto_exento: f64,
+    pub gastos_generales: f64,
+    pub utilidades: f64,
+    pub iva: f64,
+    pub total_final: f64,
+}
+
+pub fn calcular_totales_informe(neto_afecto: f64, neto_exento: f64, tasas: &TasasInforme) -> TotalesInforme {
+    let gg = tasas.porcentaje_gastos_generales / 100.0;
+    let util = tasas.porcentaje_utilidades / 100.0;
+
+    let neto = neto_afecto + neto_exento;
+    let gastos_generales = neto * gg;
+    let utilidades = neto * util;
+    let iva = neto_afecto * (1.0 + gg + util) * (tasas.porcentaje_iva / 100.0);
+
+    TotalesInforme {
+        neto,
+        neto_exento,
+        gastos_generales,
+        utilidades,
+        iva,
+        total_final: neto + gastos_generales + utilidades + iva,
+    }
+}
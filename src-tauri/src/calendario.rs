@@ -0,0 +1,214 @@
+use crate::errores::ErrorComando;
+use chrono::{Datelike, Duration, NaiveDate};
+use sqlx::SqliteConnection;
+use std::collections::HashSet;
+
+// ========== CALENDARIO ==========
+//
+// Cálculo de plazos en días corridos o hábiles según el contrato.
+// - corridos: fecha_limite = fecha_inicio + N días
+// - habiles:  fecha_limite = N-ésimo día hábil después de fecha_inicio,
+//             saltando los días no hábiles de la semana y los feriados.
+// Los días de atraso se cuentan con el mismo criterio.
+
+pub const TIPOS_PLAZO: &[&str] = &["corridos", "habiles"];
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, sqlx::FromRow)]
+pub struct ConfiguracionCalendario {
+    pub tipo_plazo: String,
+    /// Días de la semana no hábiles, ISO 8601 separados por coma (1 = lunes ... 7 = domingo)
+    pub dias_no_habiles: String,
+}
+
+impl ConfiguracionCalendario {
+    pub fn validar(&self) -> Result<(), ErrorComando> {
+        if !TIPOS_PLAZO.contains(&self.tipo_plazo.as_str()) {
+            return Err(ErrorComando::validacion("tipo_plazo", format!("Tipo de plazo no válido: {}", self.tipo_plazo)));
+        }
+        let dias = parse_dias_semana(&self.dias_no_habiles).map_err(|m| ErrorComando::validacion("dias_no_habiles", m))?;
+        if dias.len() >= 7 {
+            return Err(ErrorComando::validacion("dias_no_habiles", "La semana debe tener al menos un día hábil"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, sqlx::FromRow)]
+pub struct Feriado {
+    pub fecha: String,
+    pub nombre: String,
+}
+
+pub struct Calendario {
+    habiles: bool,
+    dias_no_habiles: Vec<u32>,
+    feriados: HashSet<NaiveDate>,
+}
+
+pub fn parse_dias_semana(texto: &str) -> Result<Vec<u32>, String> {
+    texto
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| match s.parse::<u32>() {
+            Ok(n) if (1..=7).contains(&n) => Ok(n),
+            _ => Err(format!("Día de la semana no válido: '{}' (use 1 = lunes ... 7 = domingo)", s)),
+        })
+        .collect()
+}
+
+/// Acepta 'YYYY-MM-DD' (opcionalmente con hora), 'DD-MM-YYYY' y 'DD/MM/YYYY'
+pub fn parse_fecha(fecha: &str) -> Option<NaiveDate> {
+    let fecha = fecha.trim();
+    if let Some(iso) = fecha.get(..10).and_then(|f| NaiveDate::parse_from_str(f, "%Y-%m-%d").ok()) {
+        return Some(iso);
+    }
+    NaiveDate::parse_from_str(fecha, "%d-%m-%Y")
+        .or_else(|_| NaiveDate::parse_from_str(fecha, "%d/%m/%Y"))
+        .ok()
+}
+
+impl Calendario {
+    pub fn new(config: &ConfiguracionCalendario, feriados: HashSet<NaiveDate>) -> Self {
+        Calendario {
+            habiles: config.tipo_plazo == "habiles",
+            dias_no_habiles: parse_dias_semana(&config.dias_no_habiles).unwrap_or_default(),
+            feriados,
+        }
+    }
+
+    pub fn tipo_plazo(&self) -> &'static str {
+        if self.habiles { "habiles" } else { "corridos" }
+    }
+
+    pub fn es_habil(&self, fecha: NaiveDate) -> bool {
+        !self.dias_no_habiles.contains(&fecha.weekday().number_from_monday())
+            && !self.feriados.contains(&fecha)
+    }
+
+    /// `None` si no hay plazo (plazo_total = 0), igual que antes
+    pub fn fecha_limite(&self, inicio: NaiveDate, plazo_total: i64) -> Option<NaiveDate> {
+        if plazo_total <= 0 {
+            return None;
+        }
+        if !self.habiles {
+            return Some(inicio + Duration::days(plazo_total));
+        }
+
+        let mut fecha = inicio;
+        let mut contados = 0;
+        while contados < plazo_total {
+            fecha += Duration::days(1);
+            if self.es_habil(fecha) {
+                contados += 1;
+            }
+        }
+        Some(fecha)
+    }
+
+    /// Días (corridos o hábiles) entre la fecha límite y la recepción
+    pub fn dias_atraso(&self, limite: NaiveDate, recepcion: NaiveDate) -> i64 {
+        if recepcion <= limite {
+            return 0;
+        }
+        if !self.habiles {
+            return (recepcion - limite).num_days();
+        }
+
+        limite
+            .iter_days()
+            .skip(1)
+            .take_while(|d| *d <= recepcion)
+            .filter(|d| self.es_habil(*d))
+            .count() as i64
+    }
+}
+
+pub async fn cargar_configuracion(conn: &mut SqliteConnection) -> Result<ConfiguracionCalendario, sqlx::Error> {
+    sqlx::query_as::<_, ConfiguracionCalendario>(
+        "SELECT tipo_plazo, dias_no_habiles FROM contrato_activo"
+    )
+    .fetch_one(conn)
+    .await
+}
+
+pub async fn cargar(conn: &mut SqliteConnection) -> Result<Calendario, sqlx::Error> {
+    let config = cargar_configuracion(&mut *conn).await?;
+
+    let feriados: Vec<String> = sqlx::query_scalar("SELECT fecha FROM feriados")
+        .fetch_all(&mut *conn)
+        .await?;
+
+    Ok(Calendario::new(
+        &config,
+        feriados.iter().filter_map(|f| parse_fecha(f)).collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fecha(texto: &str) -> NaiveDate {
+        parse_fecha(texto).unwrap()
+    }
+
+    fn calendario(tipo_plazo: &str, feriados: &[&str]) -> Calendario {
+        let config = ConfiguracionCalendario { tipo_plazo: tipo_plazo.to_string(), dias_no_habiles: "6,7".to_string() };
+        Calendario::new(&config, feriados.iter().map(|f| fecha(f)).collect())
+    }
+
+    // 2025-01-01 es miércoles; 04 y 05 son sábado y domingo
+    #[test]
+    fn fecha_limite_corridos_y_habiles() {
+        let inicio = fecha("2025-01-01");
+        let casos = [
+            ("corridos", &[][..], 3, Some("2025-01-04")),
+            ("corridos", &[][..], 10, Some("2025-01-11")),
+            ("habiles", &[][..], 2, Some("2025-01-03")),
+            // El tercer día hábil salta el fin de semana
+            ("habiles", &[][..], 3, Some("2025-01-06")),
+            // ... y el feriado del lunes
+            ("habiles", &["2025-01-06"][..], 3, Some("2025-01-07")),
+            ("corridos", &["2025-01-04"][..], 3, Some("2025-01-04")),
+            ("corridos", &[][..], 0, None),
+            ("habiles", &[][..], 0, None),
+        ];
+        for (tipo, feriados, plazo, esperado) in casos {
+            assert_eq!(
+                calendario(tipo, feriados).fecha_limite(inicio, plazo),
+                esperado.map(fecha),
+                "{} {:?} plazo {}",
+                tipo,
+                feriados,
+                plazo
+            );
+        }
+    }
+
+    #[test]
+    fn dias_atraso_corridos_y_habiles() {
+        let limite = fecha("2025-01-03");
+        let casos = [
+            ("corridos", &[][..], "2025-01-03", 0),
+            ("corridos", &[][..], "2025-01-02", 0),
+            ("corridos", &[][..], "2025-01-06", 3),
+            ("habiles", &[][..], "2025-01-06", 1),
+            ("habiles", &["2025-01-06"][..], "2025-01-07", 1),
+            ("habiles", &[][..], "2025-01-05", 0),
+        ];
+        for (tipo, feriados, recepcion, esperado) in casos {
+            assert_eq!(calendario(tipo, feriados).dias_atraso(limite, fecha(recepcion)), esperado, "{} {}", tipo, recepcion);
+        }
+    }
+
+    #[test]
+    fn dias_no_habiles_configurables() {
+        let config = ConfiguracionCalendario { tipo_plazo: "habiles".to_string(), dias_no_habiles: "7".to_string() };
+        let calendario = Calendario::new(&config, HashSet::new());
+        // Solo el domingo es inhábil: viernes, sábado y lunes
+        assert_eq!(calendario.fecha_limite(fecha("2025-01-02"), 3), Some(fecha("2025-01-06")));
+        assert!(ConfiguracionCalendario { tipo_plazo: "habiles".to_string(), dias_no_habiles: "1,2,3,4,5,6,7".to_string() }.validar().is_err());
+        assert!(parse_dias_semana("1, 8").is_err());
+    }
+}
@@ -0,0 +1,922 @@
+use crate::errores::ErrorComando;
+use crate::precios;
+use calamine::{Data, Range, Reader, Xlsx};
+use serde_json::Value;
+use sqlx::SqliteConnection;
+use std::collections::{BTreeMap, HashSet};
+
+// ========== IMPORTACIÓN DE CATÁLOGOS ==========
+//
+// Jardines, partidas y recintos se importan en dos pasos:
+// 1. `leer_*` convierte el archivo (JSON, CSV o Excel) en filas validadas y
+//    `previsualizar` las compara con el catálogo del contrato activo: nuevas,
+//    cambiadas (valor anterior y nuevo de cada campo), ausentes del archivo e
+//    inválidas (hoja, fila y columna). No escribe nada.
+// 2. `aplicar` inserta y actualiza exactamente lo que trae ese diff, después de
+//    verificar que el catálogo no cambió desde la vista previa.
+//
+// Las filas del catálogo que no vienen en el archivo (`eliminados`) nunca se
+// borran: se conservan o, si se pide `desactivar_ausentes`, quedan inactivas
+// (siguen sirviendo a los requerimientos, OTs e informes que las usan) y se
+// reactivan si vuelven en una importación posterior. Las importaciones
+// directas (sin vista previa) hacen los dos pasos de una vez y se niegan si
+// hay filas inválidas.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tabla {
+    Jardines,
+    Partidas,
+    Recintos,
+}
+
+impl Tabla {
+    pub fn desde(tipo: &str) -> Result<Self, ErrorComando> {
+        match tipo {
+            "jardines" => Ok(Tabla::Jardines),
+            "partidas" => Ok(Tabla::Partidas),
+            "recintos" => Ok(Tabla::Recintos),
+            _ => Err(ErrorComando::validacion("tipo", "Tipo de importación no válido")),
+        }
+    }
+
+    /// Nombre de la tabla, de la hoja Excel y de la clave JSON
+    pub fn nombre(self) -> &'static str {
+        match self {
+            Tabla::Jardines => "jardines",
+            Tabla::Partidas => "partidas",
+            Tabla::Recintos => "recintos",
+        }
+    }
+
+    /// Columnas en el orden del CSV y de la hoja Excel
+    fn columnas(self) -> &'static [&'static str] {
+        match self {
+            Tabla::Jardines => &["codigo", "nombre"],
+            Tabla::Partidas => &["item", "partida", "unidad", "precio_unitario", "exenta_iva"],
+            Tabla::Recintos => &["jardin_codigo", "nombre"],
+        }
+    }
+}
+
+// ========== FILAS ==========
+
+fn activa() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, sqlx::FromRow)]
+pub struct JardinCatalogo {
+    pub codigo: String,
+    pub nombre: String,
+    /// Las filas leídas del archivo siempre vienen activas
+    #[serde(default = "activa")]
+    pub activo: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, sqlx::FromRow)]
+pub struct PartidaCatalogo {
+    pub item: String,
+    pub partida: String,
+    pub unidad: Option<String>,
+    pub precio_unitario: f64,
+    /// None = el archivo no trae la columna (se conserva la del catálogo)
+    pub exenta_iva: Option<bool>,
+    #[serde(default = "activa")]
+    pub activo: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, sqlx::FromRow)]
+pub struct RecintoCatalogo {
+    pub jardin_codigo: String,
+    pub nombre: String,
+    #[serde(default = "activa")]
+    pub activo: bool,
+}
+
+/// Hoja `configuracion` del Excel: título, prefijo y contratista del contrato activo
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize, sqlx::FromRow)]
+pub struct ConfiguracionCatalogo {
+    pub titulo: String,
+    pub prefijo_correlativo: String,
+    pub contratista: String,
+}
+
+/// Clave y campos comparables de una fila de catálogo
+trait Fila: Clone {
+    fn clave(&self) -> String;
+    fn campos(&self) -> Vec<(&'static str, Value)>;
+    fn activo(&self) -> bool {
+        true
+    }
+}
+
+impl Fila for JardinCatalogo {
+    fn clave(&self) -> String {
+        self.codigo.clone()
+    }
+    fn campos(&self) -> Vec<(&'static str, Value)> {
+        vec![("nombre", self.nombre.clone().into()), ("activo", self.activo.into())]
+    }
+    fn activo(&self) -> bool {
+        self.activo
+    }
+}
+
+impl Fila for PartidaCatalogo {
+    fn clave(&self) -> String {
+        self.item.clone()
+    }
+    fn campos(&self) -> Vec<(&'static str, Value)> {
+        let mut campos = vec![
+            ("partida", self.partida.clone().into()),
+            ("unidad", self.unidad.clone().into()),
+            ("precio_unitario", self.precio_unitario.into()),
+        ];
+        if let Some(exenta) = self.exenta_iva {
+            campos.push(("exenta_iva", exenta.into()));
+        }
+        campos.push(("activo", self.activo.into()));
+        campos
+    }
+    fn activo(&self) -> bool {
+        self.activo
+    }
+}
+
+/// Un recinto es solo su nombre dentro del jardín: puede ser nuevo, faltar o
+/// volver a activarse, no cambiar
+impl Fila for RecintoCatalogo {
+    fn clave(&self) -> String {
+        format!("{} / {}", self.jardin_codigo, self.nombre)
+    }
+    fn campos(&self) -> Vec<(&'static str, Value)> {
+        vec![("activo", self.activo.into())]
+    }
+    fn activo(&self) -> bool {
+        self.activo
+    }
+}
+
+impl Fila for ConfiguracionCatalogo {
+    fn clave(&self) -> String {
+        "contrato".to_string()
+    }
+    fn campos(&self) -> Vec<(&'static str, Value)> {
+        vec![
+            ("titulo", self.titulo.clone().into()),
+            ("prefijo_correlativo", self.prefijo_correlativo.clone().into()),
+            ("contratista", self.contratista.clone().into()),
+        ]
+    }
+}
+
+// ========== LECTURA DE ARCHIVOS ==========
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct FilaInvalida {
+    pub hoja: Option<String>,
+    /// Cuenta desde 1 como en Excel (en JSON, posición en la lista)
+    pub fila: usize,
+    pub columna: Option<String>,
+    pub mensaje: String,
+}
+
+impl FilaInvalida {
+    pub fn error(&self) -> ErrorComando {
+        let mensaje = match &self.columna {
+            Some(columna) => format!("columna {}: {}", columna, self.mensaje),
+            None => self.mensaje.clone(),
+        };
+        ErrorComando::importacion(self.hoja.as_deref(), Some(self.fila), mensaje)
+    }
+}
+
+#[derive(Debug)]
+struct Filas<T> {
+    hoja: Option<String>,
+    filas: Vec<(usize, T)>,
+}
+
+/// Filas válidas del archivo, por tabla. Una tabla en `None` no viene en el archivo.
+#[derive(Debug, Default)]
+pub struct CatalogoLeido {
+    jardines: Option<Filas<JardinCatalogo>>,
+    partidas: Option<Filas<PartidaCatalogo>>,
+    recintos: Option<Filas<RecintoCatalogo>>,
+    configuracion: Option<ConfiguracionCatalogo>,
+    pub invalidas: Vec<FilaInvalida>,
+}
+
+impl CatalogoLeido {
+    /// Marca la tabla como presente aunque no traiga filas
+    fn abrir(&mut self, tabla: Tabla, hoja: Option<&str>) {
+        let hoja = hoja.map(str::to_string);
+        match tabla {
+            Tabla::Jardines => { self.jardines.get_or_insert(Filas { hoja, filas: Vec::new() }); }
+            Tabla::Partidas => { self.partidas.get_or_insert(Filas { hoja, filas: Vec::new() }); }
+            Tabla::Recintos => { self.recintos.get_or_insert(Filas { hoja, filas: Vec::new() }); }
+        }
+    }
+
+    fn invalida(&mut self, hoja: Option<&str>, fila: usize, columna: &str, mensaje: impl Into<String>) {
+        self.invalidas.push(FilaInvalida {
+            hoja: hoja.map(str::to_string),
+            fila,
+            columna: Some(columna.to_string()),
+            mensaje: mensaje.into(),
+        });
+    }
+
+    /// Valida una fila (celdas en el orden de `Tabla::columnas`). Las filas vacías se ignoran.
+    fn agregar(&mut self, tabla: Tabla, hoja: Option<&str>, fila: usize, celdas: &[String]) {
+        let celda = |i: usize| celdas.get(i).map(|c| c.trim()).unwrap_or_default().to_string();
+        if celdas.iter().all(|c| c.trim().is_empty()) {
+            return;
+        }
+
+        let columnas = tabla.columnas();
+        for (i, nombre) in columnas.iter().enumerate().take(2) {
+            if celda(i).is_empty() {
+                self.invalida(hoja, fila, nombre, "Valor obligatorio");
+                return;
+            }
+        }
+
+        match tabla {
+            Tabla::Jardines => {
+                let jardin = JardinCatalogo { codigo: celda(0), nombre: celda(1), activo: true };
+                agregar_unica(&mut self.jardines, &mut self.invalidas, columnas[0], fila, jardin);
+            }
+            Tabla::Partidas => {
+                let texto_precio = celda(3);
+                let precio_unitario = if texto_precio.is_empty() {
+                    0.0
+                } else {
+                    match texto_precio.parse::<f64>() {
+                        Ok(precio) if precio.is_finite() && precio >= 0.0 => precio,
+                        _ => {
+                            self.invalida(hoja, fila, columnas[3], format!("Precio no válido: '{}'", texto_precio));
+                            return;
+                        }
+                    }
+                };
+                let exenta_iva = match celda(4).to_lowercase().as_str() {
+                    "" => None,
+                    "si" | "sí" | "s" | "x" | "1" | "true" => Some(true),
+                    "no" | "n" | "0" | "false" => Some(false),
+                    otro => {
+                        self.invalida(hoja, fila, columnas[4], format!("Se espera sí o no: '{}'", otro));
+                        return;
+                    }
+                };
+                let unidad = Some(celda(2)).filter(|u| !u.is_empty());
+                let partida = PartidaCatalogo { item: celda(0), partida: celda(1), unidad, precio_unitario, exenta_iva, activo: true };
+                agregar_unica(&mut self.partidas, &mut self.invalidas, columnas[0], fila, partida);
+            }
+            Tabla::Recintos => {
+                let recinto = RecintoCatalogo { jardin_codigo: celda(0), nombre: celda(1), activo: true };
+                agregar_unica(&mut self.recintos, &mut self.invalidas, columnas[1], fila, recinto);
+            }
+        }
+    }
+
+    fn agregar_hoja(&mut self, tabla: Tabla, hoja: &str, range: &Range<Data>) {
+        self.abrir(tabla, Some(hoja));
+        // Fila 1 = encabezado
+        for (i, row) in range.rows().enumerate().skip(1) {
+            let celdas: Vec<String> = row.iter().map(|c| c.to_string()).collect();
+            self.agregar(tabla, Some(hoja), i + 1, &celdas);
+        }
+    }
+}
+
+/// Una clave repetida dentro del archivo es inválida
+fn agregar_unica<T: Fila>(filas: &mut Option<Filas<T>>, invalidas: &mut Vec<FilaInvalida>, columna: &str, fila: usize, valor: T) {
+    let Some(filas) = filas else { return };
+    if let Some((anterior, _)) = filas.filas.iter().find(|(_, otro)| otro.clave() == valor.clave()) {
+        invalidas.push(FilaInvalida {
+            hoja: filas.hoja.clone(),
+            fila,
+            columna: Some(columna.to_string()),
+            mensaje: format!("'{}' repetido (ya viene en la fila {})", valor.clave(), anterior),
+        });
+        return;
+    }
+    filas.filas.push((fila, valor));
+}
+
+fn texto_json(valor: Option<&Value>) -> String {
+    match valor {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Null) | None => String::new(),
+        Some(otro) => otro.to_string(),
+    }
+}
+
+/// `{"jardines": [...], "partidas": [...], "recintos": [...]}` con las columnas como claves
+pub fn leer_json(json_str: &str) -> Result<CatalogoLeido, ErrorComando> {
+    let datos: Value = serde_json::from_str(json_str)
+        .map_err(|e| ErrorComando::importacion(None, None, format!("Error parseando JSON: {}", e)))?;
+
+    let mut leido = CatalogoLeido::default();
+    for tabla in [Tabla::Jardines, Tabla::Partidas, Tabla::Recintos] {
+        let Some(filas) = datos.get(tabla.nombre()).and_then(|v| v.as_array()) else { continue };
+        leido.abrir(tabla, Some(tabla.nombre()));
+        for (i, fila) in filas.iter().enumerate() {
+            let celdas: Vec<String> = tabla.columnas().iter().map(|c| texto_json(fila.get(*c))).collect();
+            leido.agregar(tabla, Some(tabla.nombre()), i + 1, &celdas);
+        }
+    }
+    Ok(leido)
+}
+
+pub fn leer_csv(csv_str: &str, tabla: Tabla) -> CatalogoLeido {
+    let mut rdr = csv::ReaderBuilder::new().flexible(true).from_reader(csv_str.as_bytes());
+    let mut leido = CatalogoLeido::default();
+    leido.abrir(tabla, None);
+    for (i, result) in rdr.records().enumerate() {
+        // Fila 1 = encabezado
+        match result {
+            Ok(record) => {
+                let celdas: Vec<String> = record.iter().map(str::to_string).collect();
+                leido.agregar(tabla, None, i + 2, &celdas);
+            }
+            Err(e) => leido.invalidas.push(FilaInvalida { hoja: None, fila: i + 2, columna: None, mensaje: e.to_string() }),
+        }
+    }
+    leido
+}
+
+/// Una hoja de un archivo Excel en disco con las filas de `tabla`
+pub fn leer_xlsx_hoja(file_path: &str, sheet_name: &str, tabla: Tabla) -> Result<CatalogoLeido, ErrorComando> {
+    let mut workbook: Xlsx<_> = calamine::open_workbook(file_path)
+        .map_err(|e| ErrorComando::importacion(None, None, format!("Error abriendo Excel: {}", e)))?;
+    let range = workbook
+        .worksheet_range(sheet_name)
+        .map_err(|e| ErrorComando::importacion(Some(sheet_name), None, format!("Error leyendo hoja: {}", e)))?;
+
+    let mut leido = CatalogoLeido::default();
+    leido.agregar_hoja(tabla, sheet_name, &range);
+    Ok(leido)
+}
+
+/// Libro con las hojas `jardines`, `partidas`, `recintos` y `configuracion` (todas opcionales)
+pub fn leer_xlsx(file_bytes: Vec<u8>) -> Result<CatalogoLeido, ErrorComando> {
+    let mut workbook: Xlsx<_> = calamine::open_workbook_from_rs(std::io::Cursor::new(file_bytes))
+        .map_err(|e| ErrorComando::importacion(None, None, format!("Error abriendo Excel: {}", e)))?;
+
+    let mut leido = CatalogoLeido::default();
+    for tabla in [Tabla::Jardines, Tabla::Partidas, Tabla::Recintos] {
+        if let Ok(range) = workbook.worksheet_range(tabla.nombre()) {
+            leido.agregar_hoja(tabla, tabla.nombre(), &range);
+        }
+    }
+
+    if let Ok(range) = workbook.worksheet_range("configuracion") {
+        if let Some(row) = range.rows().nth(1).filter(|row| row.len() >= 3) {
+            let celda = |i: usize| row[i].to_string().trim().to_string();
+            if !celda(0).is_empty() {
+                leido.configuracion = Some(ConfiguracionCatalogo {
+                    titulo: celda(0),
+                    prefijo_correlativo: celda(1),
+                    contratista: celda(2),
+                });
+            }
+        }
+    }
+    Ok(leido)
+}
+
+// ========== DIFERENCIAS ==========
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CampoCambiado {
+    pub campo: String,
+    pub anterior: Value,
+    pub nuevo: Value,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Cambio<T> {
+    pub clave: String,
+    pub campos: Vec<CampoCambiado>,
+    pub nuevo: T,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct DiffTabla<T> {
+    pub nuevos: Vec<T>,
+    pub cambiados: Vec<Cambio<T>>,
+    /// Activos en el catálogo pero no en el archivo (no se borran)
+    pub eliminados: Vec<T>,
+    pub sin_cambios: usize,
+}
+
+/// Resultado de la vista previa; `aplicar` recibe este mismo valor
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct DiffCatalogo {
+    /// None = el archivo no trae la tabla
+    pub jardines: Option<DiffTabla<JardinCatalogo>>,
+    pub partidas: Option<DiffTabla<PartidaCatalogo>>,
+    pub recintos: Option<DiffTabla<RecintoCatalogo>>,
+    pub configuracion: Option<Cambio<ConfiguracionCatalogo>>,
+    pub invalidas: Vec<FilaInvalida>,
+}
+
+fn diferencias<T: Fila>(actual: &T, nuevo: &T) -> Vec<CampoCambiado> {
+    let anteriores: BTreeMap<&str, Value> = actual.campos().into_iter().collect();
+    nuevo
+        .campos()
+        .into_iter()
+        .filter_map(|(campo, valor)| {
+            let anterior = anteriores.get(campo).cloned().unwrap_or(Value::Null);
+            (anterior != valor).then(|| CampoCambiado { campo: campo.to_string(), anterior, nuevo: valor })
+        })
+        .collect()
+}
+
+fn comparar<T: Fila>(actuales: Vec<T>, archivo: impl IntoIterator<Item = T>) -> DiffTabla<T> {
+    let mut por_clave: BTreeMap<String, T> = actuales.into_iter().map(|a| (a.clave(), a)).collect();
+    let mut diff = DiffTabla { nuevos: Vec::new(), cambiados: Vec::new(), eliminados: Vec::new(), sin_cambios: 0 };
+    for nuevo in archivo {
+        match por_clave.remove(&nuevo.clave()) {
+            None => diff.nuevos.push(nuevo),
+            Some(actual) => {
+                let campos = diferencias(&actual, &nuevo);
+                if campos.is_empty() {
+                    diff.sin_cambios += 1;
+                } else {
+                    diff.cambiados.push(Cambio { clave: nuevo.clave(), campos, nuevo });
+                }
+            }
+        }
+    }
+    diff.eliminados = por_clave.into_values().filter(Fila::activo).collect();
+    diff
+}
+
+async fn cargar_jardines(conn: &mut SqliteConnection) -> Result<Vec<JardinCatalogo>, sqlx::Error> {
+    sqlx::query_as::<_, JardinCatalogo>(
+        "SELECT codigo, nombre, activo FROM jardines WHERE contrato_id = (SELECT id FROM contrato_activo) ORDER BY codigo"
+    )
+    .fetch_all(&mut *conn)
+    .await
+}
+
+async fn cargar_partidas(conn: &mut SqliteConnection) -> Result<Vec<PartidaCatalogo>, sqlx::Error> {
+    sqlx::query_as::<_, PartidaCatalogo>(
+        "SELECT item, partida, unidad, COALESCE(precio_unitario, 0) as precio_unitario, exenta_iva, activo
+         FROM partidas WHERE contrato_id = (SELECT id FROM contrato_activo) ORDER BY item"
+    )
+    .fetch_all(&mut *conn)
+    .await
+}
+
+async fn cargar_recintos(conn: &mut SqliteConnection) -> Result<Vec<RecintoCatalogo>, sqlx::Error> {
+    sqlx::query_as::<_, RecintoCatalogo>(
+        "SELECT jardin_codigo, nombre, activo FROM recintos WHERE contrato_id = (SELECT id FROM contrato_activo) ORDER BY jardin_codigo, nombre"
+    )
+    .fetch_all(&mut *conn)
+    .await
+}
+
+async fn cargar_configuracion(conn: &mut SqliteConnection) -> Result<ConfiguracionCatalogo, sqlx::Error> {
+    sqlx::query_as::<_, ConfiguracionCatalogo>("SELECT titulo, prefijo_correlativo, contratista FROM contrato_activo")
+        .fetch_one(&mut *conn)
+        .await
+}
+
+/// Compara el archivo con el catálogo del contrato activo sin modificar nada
+pub async fn previsualizar(conn: &mut SqliteConnection, leido: CatalogoLeido) -> Result<DiffCatalogo, ErrorComando> {
+    let mut diff = DiffCatalogo { invalidas: leido.invalidas, ..Default::default() };
+
+    let actuales = cargar_jardines(conn).await?;
+    // Los recintos pueden apuntar a jardines del catálogo o del mismo archivo
+    let mut codigos: HashSet<String> = actuales.iter().map(|j| j.codigo.clone()).collect();
+    if let Some(jardines) = leido.jardines {
+        codigos.extend(jardines.filas.iter().map(|(_, j)| j.codigo.clone()));
+        diff.jardines = Some(comparar(actuales, jardines.filas.into_iter().map(|(_, j)| j)));
+    }
+
+    if let Some(partidas) = leido.partidas {
+        diff.partidas = Some(comparar(cargar_partidas(conn).await?, partidas.filas.into_iter().map(|(_, p)| p)));
+    }
+
+    if let Some(recintos) = leido.recintos {
+        let mut validos = Vec::new();
+        for (fila, recinto) in recintos.filas {
+            if codigos.contains(&recinto.jardin_codigo) {
+                validos.push(recinto);
+            } else {
+                diff.invalidas.push(FilaInvalida {
+                    hoja: recintos.hoja.clone(),
+                    fila,
+                    columna: Some("jardin_codigo".to_string()),
+                    mensaje: format!("El jardín {} no existe", recinto.jardin_codigo),
+                });
+            }
+        }
+        diff.recintos = Some(comparar(cargar_recintos(conn).await?, validos));
+    }
+
+    if let Some(configuracion) = leido.configuracion {
+        let campos = diferencias(&cargar_configuracion(conn).await?, &configuracion);
+        if !campos.is_empty() {
+            diff.configuracion = Some(Cambio { clave: configuracion.clave(), campos, nuevo: configuracion });
+        }
+    }
+
+    diff.invalidas.sort_by(|a, b| (&a.hoja, a.fila).cmp(&(&b.hoja, b.fila)));
+    Ok(diff)
+}
+
+/// Los requerimientos nuevos solo usan jardines y partidas activos
+pub async fn verificar_activos(conn: &mut SqliteConnection, jardin_codigo: &str, partida_item: &str) -> Result<(), ErrorComando> {
+    let jardin: Option<bool> = sqlx::query_scalar(
+        "SELECT activo FROM jardines WHERE contrato_id = (SELECT id FROM contrato_activo) AND codigo = ?"
+    )
+    .bind(jardin_codigo)
+    .fetch_optional(&mut *conn)
+    .await?;
+    if jardin == Some(false) {
+        return Err(ErrorComando::validacion("jardin_codigo", format!("El jardín {} está inactivo", jardin_codigo)));
+    }
+    let partida: Option<bool> = sqlx::query_scalar(
+        "SELECT activo FROM partidas WHERE contrato_id = (SELECT id FROM contrato_activo) AND item = ?"
+    )
+    .bind(partida_item)
+    .fetch_optional(&mut *conn)
+    .await?;
+    if partida == Some(false) {
+        return Err(ErrorComando::validacion("partida_item", format!("La partida {} está inactiva", partida_item)));
+    }
+    Ok(())
+}
+
+// ========== APLICACIÓN ==========
+
+/// Filas insertadas o actualizadas por tabla
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct ResumenImportacion {
+    pub jardines: usize,
+    pub partidas: usize,
+    pub recintos: usize,
+    /// Filas ausentes del archivo que quedaron inactivas
+    pub desactivados: usize,
+    /// Se actualizó título, prefijo o contratista
+    pub contrato: bool,
+}
+
+impl ResumenImportacion {
+    pub fn registros(&self) -> usize {
+        self.jardines + self.partidas + self.recintos
+    }
+}
+
+/// Los nuevos no deben existir y los cambiados deben seguir con los valores
+/// anteriores que mostró la vista previa
+fn verificar<T: Fila>(entidad: &str, actuales: &BTreeMap<String, T>, diff: &DiffTabla<T>) -> Result<(), ErrorComando> {
+    let desactualizado = |clave: &str| {
+        ErrorComando::validacion(
+            "diff",
+            format!("{} {} cambió después de la vista previa; vuelva a revisar el archivo", entidad, clave),
+        )
+    };
+    for nuevo in &diff.nuevos {
+        if actuales.contains_key(&nuevo.clave()) {
+            return Err(desactualizado(&nuevo.clave()));
+        }
+    }
+    for cambio in &diff.cambiados {
+        let clave = cambio.nuevo.clave();
+        let actual = actuales.get(&clave).ok_or_else(|| ErrorComando::no_encontrado(entidad, &clave))?;
+        if diferencias(actual, &cambio.nuevo) != cambio.campos {
+            return Err(desactualizado(&clave));
+        }
+    }
+    Ok(())
+}
+
+fn por_clave<T: Fila>(filas: Vec<T>) -> BTreeMap<String, T> {
+    filas.into_iter().map(|f| (f.clave(), f)).collect()
+}
+
+/// Marca inactivas las filas ausentes del archivo que siguen activas
+async fn desactivar<T: Fila>(
+    conn: &mut SqliteConnection,
+    tabla: Tabla,
+    contrato_id: i64,
+    eliminados: &[T],
+) -> Result<usize, sqlx::Error> {
+    let clave = match tabla {
+        Tabla::Jardines => "codigo",
+        Tabla::Partidas => "item",
+        Tabla::Recintos => "jardin_codigo || ' / ' || nombre",
+    };
+    let mut desactivados = 0;
+    for fila in eliminados {
+        let resultado = sqlx::query(&format!(
+            "UPDATE {} SET activo = 0 WHERE contrato_id = ? AND {} = ? AND activo = 1",
+            tabla.nombre(),
+            clave
+        ))
+        .bind(contrato_id)
+        .bind(fila.clave())
+        .execute(&mut *conn)
+        .await?;
+        desactivados += resultado.rows_affected() as usize;
+    }
+    Ok(desactivados)
+}
+
+/// Inserta los nuevos y actualiza (o reactiva) los cambiados del diff en el
+/// contrato activo. Con `desactivar_ausentes`, los `eliminados` quedan inactivos.
+pub async fn aplicar(
+    conn: &mut SqliteConnection,
+    diff: &DiffCatalogo,
+    desactivar_ausentes: bool,
+) -> Result<ResumenImportacion, ErrorComando> {
+    let contrato_id = crate::contratos::activo(&mut *conn).await?;
+    let mut resumen = ResumenImportacion::default();
+
+    if let Some(jardines) = &diff.jardines {
+        verificar("Jardín", &por_clave(cargar_jardines(conn).await?), jardines)?;
+        for jardin in &jardines.nuevos {
+            sqlx::query("INSERT INTO jardines (contrato_id, codigo, nombre) VALUES (?, ?, ?)")
+                .bind(contrato_id)
+                .bind(&jardin.codigo)
+                .bind(&jardin.nombre)
+                .execute(&mut *conn)
+                .await?;
+        }
+        for cambio in &jardines.cambiados {
+            sqlx::query("UPDATE jardines SET nombre = ?, activo = ? WHERE contrato_id = ? AND codigo = ?")
+                .bind(&cambio.nuevo.nombre)
+                .bind(cambio.nuevo.activo)
+                .bind(contrato_id)
+                .bind(&cambio.nuevo.codigo)
+                .execute(&mut *conn)
+                .await?;
+        }
+        resumen.jardines = jardines.nuevos.len() + jardines.cambiados.len();
+        if desactivar_ausentes {
+            resumen.desactivados += desactivar(conn, Tabla::Jardines, contrato_id, &jardines.eliminados).await?;
+        }
+    }
+
+    if let Some(partidas) = &diff.partidas {
+        verificar("Partida", &por_clave(cargar_partidas(conn).await?), partidas)?;
+        for partida in &partidas.nuevos {
+            sqlx::query(
+                "INSERT INTO partidas (contrato_id, item, partida, unidad, precio_unitario, exenta_iva) VALUES (?, ?, ?, ?, ?, ?)"
+            )
+            .bind(contrato_id)
+            .bind(&partida.item)
+            .bind(&partida.partida)
+            .bind(&partida.unidad)
+            .bind(partida.precio_unitario)
+            .bind(partida.exenta_iva.unwrap_or(false))
+            .execute(&mut *conn)
+            .await?;
+        }
+        for cambio in &partidas.cambiados {
+            let partida = &cambio.nuevo;
+            let id: i64 = sqlx::query_scalar(
+                "UPDATE partidas SET partida = ?, unidad = ?, precio_unitario = ?, exenta_iva = COALESCE(?, exenta_iva), activo = ?
+                 WHERE contrato_id = ? AND item = ?
+                 RETURNING id"
+            )
+            .bind(&partida.partida)
+            .bind(&partida.unidad)
+            .bind(partida.precio_unitario)
+            .bind(partida.exenta_iva)
+            .bind(partida.activo)
+            .bind(contrato_id)
+            .bind(&partida.item)
+            .fetch_one(&mut *conn)
+            .await?;
+            if cambio.campos.iter().any(|c| c.campo == "precio_unitario") {
+                precios::registrar_cambio_catalogo(conn, id, partida.precio_unitario).await?;
+            }
+        }
+        precios::registrar_iniciales(conn, contrato_id).await?;
+        resumen.partidas = partidas.nuevos.len() + partidas.cambiados.len();
+        if desactivar_ausentes {
+            resumen.desactivados += desactivar(conn, Tabla::Partidas, contrato_id, &partidas.eliminados).await?;
+        }
+    }
+
+    if let Some(recintos) = &diff.recintos {
+        verificar("Recinto", &por_clave(cargar_recintos(conn).await?), recintos)?;
+        for recinto in &recintos.nuevos {
+            sqlx::query("INSERT INTO recintos (contrato_id, jardin_codigo, nombre) VALUES (?, ?, ?)")
+                .bind(contrato_id)
+                .bind(&recinto.jardin_codigo)
+                .bind(&recinto.nombre)
+                .execute(&mut *conn)
+                .await?;
+        }
+        for cambio in &recintos.cambiados {
+            sqlx::query("UPDATE recintos SET activo = ? WHERE contrato_id = ? AND jardin_codigo = ? AND nombre = ?")
+                .bind(cambio.nuevo.activo)
+                .bind(contrato_id)
+                .bind(&cambio.nuevo.jardin_codigo)
+                .bind(&cambio.nuevo.nombre)
+                .execute(&mut *conn)
+                .await?;
+        }
+        resumen.recintos = recintos.nuevos.len() + recintos.cambiados.len();
+        if desactivar_ausentes {
+            resumen.desactivados += desactivar(conn, Tabla::Recintos, contrato_id, &recintos.eliminados).await?;
+        }
+    }
+
+    if let Some(cambio) = &diff.configuracion {
+        if diferencias(&cargar_configuracion(conn).await?, &cambio.nuevo) != cambio.campos {
+            return Err(ErrorComando::validacion(
+                "diff",
+                "La configuración del contrato cambió después de la vista previa; vuelva a revisar el archivo",
+            ));
+        }
+        sqlx::query(
+            "UPDATE contratos
+             SET titulo = ?, prefijo_correlativo = ?, contratista = ?, updated_at = datetime('now')
+             WHERE id = ?"
+        )
+        .bind(&cambio.nuevo.titulo)
+        .bind(&cambio.nuevo.prefijo_correlativo)
+        .bind(&cambio.nuevo.contratista)
+        .bind(contrato_id)
+        .execute(&mut *conn)
+        .await?;
+        resumen.contrato = true;
+    }
+
+    Ok(resumen)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+    use sqlx::{Pool, Sqlite};
+
+    async fn pool_memoria() -> Pool<Sqlite> {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(SqliteConnectOptions::new().in_memory(true))
+            .await
+            .unwrap();
+        crate::migraciones::migrar(&pool).await.unwrap();
+        for sql in [
+            "UPDATE contratos SET fecha_inicio = '2025-01-01' WHERE id = 1",
+            "INSERT INTO jardines (contrato_id, codigo, nombre) VALUES (1, 'J01', 'Jardín Uno')",
+            "INSERT INTO partidas (contrato_id, item, partida, unidad, precio_unitario, exenta_iva) VALUES (1, '1.1', 'Pintura', 'm2', 1000, 0)",
+            "INSERT INTO recintos (contrato_id, jardin_codigo, nombre) VALUES (1, 'J01', 'Sala Cuna')",
+        ] {
+            sqlx::query(sql).execute(&pool).await.unwrap();
+        }
+        let mut conn = pool.acquire().await.unwrap();
+        precios::registrar_iniciales(&mut conn, 1).await.unwrap();
+        drop(conn);
+        pool
+    }
+
+    async fn vista_previa(conn: &mut SqliteConnection, json: &str) -> DiffCatalogo {
+        previsualizar(conn, leer_json(json).unwrap()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn vista_previa_desactualizada_se_rechaza() {
+        let pool = pool_memoria().await;
+        let mut conn = pool.acquire().await.unwrap();
+        let diff = vista_previa(
+            &mut conn,
+            r#"{"jardines": [{"codigo": "J01", "nombre": "Jardín Renombrado"}, {"codigo": "J02", "nombre": "Jardín Dos"}]}"#,
+        )
+        .await;
+        assert_eq!(diff.jardines.as_ref().unwrap().cambiados.len(), 1);
+
+        // Otro cambio llega entre la vista previa y la aplicación
+        sqlx::query("UPDATE jardines SET nombre = 'Editado a mano' WHERE codigo = 'J01'")
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        match aplicar(&mut conn, &diff, false).await {
+            Err(ErrorComando::Validation { campo, mensaje }) => {
+                assert_eq!(campo, "diff");
+                assert!(mensaje.contains("J01"), "{}", mensaje);
+            }
+            otro => panic!("se esperaba vista previa desactualizada, llegó {:?}", otro),
+        }
+
+        // Un "nuevo" que ya existe también está desactualizado
+        sqlx::query("UPDATE jardines SET nombre = 'Jardín Uno' WHERE codigo = 'J01'")
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO jardines (contrato_id, codigo, nombre) VALUES (1, 'J02', 'Jardín Dos')")
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        assert!(matches!(aplicar(&mut conn, &diff, false).await, Err(ErrorComando::Validation { .. })));
+        let nombre: String = sqlx::query_scalar("SELECT nombre FROM jardines WHERE codigo = 'J01'")
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+        assert_eq!(nombre, "Jardín Uno");
+    }
+
+    #[tokio::test]
+    async fn claves_repetidas_y_recintos_sin_jardin_son_invalidos() {
+        let pool = pool_memoria().await;
+        let mut conn = pool.acquire().await.unwrap();
+        let diff = vista_previa(
+            &mut conn,
+            r#"{
+                "jardines": [{"codigo": "J02", "nombre": "Jardín Dos"}, {"codigo": "J02", "nombre": "Otra vez"}],
+                "recintos": [
+                    {"jardin_codigo": "J01", "nombre": "Patio"},
+                    {"jardin_codigo": "J02", "nombre": "Patio"},
+                    {"jardin_codigo": "J99", "nombre": "Bodega"},
+                    {"jardin_codigo": "J01", "nombre": "Patio"}
+                ]
+            }"#,
+        )
+        .await;
+
+        let invalidas: Vec<(Option<&str>, usize, Option<&str>)> = diff
+            .invalidas
+            .iter()
+            .map(|i| (i.hoja.as_deref(), i.fila, i.columna.as_deref()))
+            .collect();
+        assert_eq!(
+            invalidas,
+            vec![
+                (Some("jardines"), 2, Some("codigo")),
+                (Some("recintos"), 3, Some("jardin_codigo")),
+                (Some("recintos"), 4, Some("nombre")),
+            ]
+        );
+        assert!(diff.invalidas[0].mensaje.contains("repetido (ya viene en la fila 1)"));
+        assert!(diff.invalidas[1].mensaje.contains("J99"));
+        // La primera aparición sigue valiendo; J02 viene en el mismo archivo
+        assert_eq!(diff.jardines.unwrap().nuevos.len(), 1);
+        assert_eq!(diff.recintos.unwrap().nuevos.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn cambio_de_precio_crea_una_version_nueva() {
+        let pool = pool_memoria().await;
+        let mut conn = pool.acquire().await.unwrap();
+        let diff = vista_previa(
+            &mut conn,
+            r#"{"partidas": [{"item": "1.1", "partida": "Pintura", "unidad": "m2", "precio_unitario": 1200}]}"#,
+        )
+        .await;
+        let campos: Vec<&str> = diff.partidas.as_ref().unwrap().cambiados[0].campos.iter().map(|c| c.campo.as_str()).collect();
+        assert_eq!(campos, vec!["precio_unitario"]);
+        aplicar(&mut conn, &diff, false).await.unwrap();
+
+        let versiones: Vec<(String, f64, Option<i64>)> = sqlx::query_as(
+            "SELECT vigente_desde, precio_unitario, reajuste_id FROM precios_partida ORDER BY vigente_desde"
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .unwrap();
+        let hoy = chrono::Local::now().format("%Y-%m-%d").to_string();
+        assert_eq!(versiones, vec![("2025-01-01".to_string(), 1000.0, None), (hoy, 1200.0, None)]);
+    }
+
+    #[tokio::test]
+    async fn desactivar_ausentes_no_borra() {
+        let pool = pool_memoria().await;
+        let mut conn = pool.acquire().await.unwrap();
+        let diff = vista_previa(&mut conn, r#"{"jardines": [{"codigo": "J02", "nombre": "Jardín Dos"}], "partidas": []}"#).await;
+        assert_eq!(diff.jardines.as_ref().unwrap().eliminados.len(), 1);
+        assert_eq!(diff.partidas.as_ref().unwrap().eliminados.len(), 1);
+
+        // Sin la opción, lo ausente queda como estaba
+        let resumen = aplicar(&mut conn, &diff, false).await.unwrap();
+        assert_eq!(resumen.desactivados, 0);
+
+        let diff = vista_previa(&mut conn, r#"{"jardines": [{"codigo": "J02", "nombre": "Jardín Dos"}], "partidas": []}"#).await;
+        let resumen = aplicar(&mut conn, &diff, true).await.unwrap();
+        assert_eq!(resumen.desactivados, 2);
+
+        let jardines: Vec<(String, bool)> = sqlx::query_as("SELECT codigo, activo FROM jardines ORDER BY codigo")
+            .fetch_all(&mut *conn)
+            .await
+            .unwrap();
+        assert_eq!(jardines, vec![("J01".to_string(), false), ("J02".to_string(), true)]);
+        let partidas: Vec<(String, bool)> = sqlx::query_as("SELECT item, activo FROM partidas")
+            .fetch_all(&mut *conn)
+            .await
+            .unwrap();
+        assert_eq!(partidas, vec![("1.1".to_string(), false)]);
+        // Su recinto y su historial de precios siguen ahí
+        let recintos: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM recintos").fetch_one(&mut *conn).await.unwrap();
+        let versiones: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM precios_partida").fetch_one(&mut *conn).await.unwrap();
+        assert_eq!((recintos, versiones), (1, 1));
+    }
+}
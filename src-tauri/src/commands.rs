@@ -1,27 +1,46 @@
-use crate::db::{DbState, Jardin, Partida, RequerimientoEnriquecido, Configuracion, Recinto, OrdenTrabajo, InformePagoEnriquecido};
-use sqlx::Row;
+use crate::adjuntos;
+use crate::auditoria::{self, Entidad};
+use crate::calendario::{self, ConfiguracionCalendario, Feriado};
+use crate::catalogo::{self, CatalogoLeido, DiffCatalogo, ResumenImportacion, Tabla};
+use crate::commands_respaldo::crear_respaldo;
+use crate::contratos;
+use crate::consultas::{self, FiltroRequerimientos, PaginaRequerimientos, Vista};
+use crate::errores::ErrorComando;
+use crate::estados::{self, ErrorEstado, EstadoInforme, EstadoRequerimiento};
+use crate::informes::{self, ConciliacionInformes, LineaInforme};
+use crate::multas::{self, DetalleMulta, ReglaMulta};
+use crate::precios;
+use crate::presupuesto::DatosContrato;
+use crate::recepciones;
+use crate::db::{DbState, Jardin, Partida, RequerimientoEnriquecido, Configuracion, HistorialTasas, Recinto, OrdenTrabajo, InformePagoEnriquecido};
+use sqlx::{Pool, Row, Sqlite, Transaction};
+use std::path::Path;
 use tauri::State;
 
 // ========== JARDINES ==========
 
-#[tauri::command]
-pub async fn get_jardines(db: State<'_, DbState>) -> Result<Vec<Jardin>, String> {
-    sqlx::query_as::<_, Jardin>("SELECT * FROM jardines ORDER BY nombre")
-        .fetch_all(&*db.pool)
-        .await
-        .map_err(|e| e.to_string())
+/// Solo los activos, salvo `incluir_inactivos` (para administrar el catálogo)
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_jardines(db: State<'_, DbState>, incluir_inactivos: Option<bool>) -> Result<Vec<Jardin>, ErrorComando> {
+    sqlx::query_as::<_, Jardin>(
+        "SELECT * FROM jardines WHERE contrato_id = (SELECT id FROM contrato_activo) AND (activo = 1 OR ?) ORDER BY nombre"
+    )
+    .bind(incluir_inactivos.unwrap_or(false))
+    .fetch_all(&db.pool())
+    .await
+    .map_err(ErrorComando::from)
 }
 
 #[tauri::command]
 pub async fn get_jardin_by_codigo(
     db: State<'_, DbState>,
     codigo: String,
-) -> Result<Option<Jardin>, String> {
-    sqlx::query_as::<_, Jardin>("SELECT * FROM jardines WHERE codigo = ?")
+) -> Result<Option<Jardin>, ErrorComando> {
+    sqlx::query_as::<_, Jardin>("SELECT * FROM jardines WHERE contrato_id = (SELECT id FROM contrato_activo) AND codigo = ?")
         .bind(codigo)
-        .fetch_optional(&*db.pool)
+        .fetch_optional(&db.pool())
         .await
-        .map_err(|e| e.to_string())
+        .map_err(ErrorComando::from)
 }
 
 #[tauri::command]
@@ -29,27 +48,35 @@ pub async fn add_jardin(
     db: State<'_, DbState>,
     codigo: String,
     nombre: String,
-) -> Result<i64, String> {
+) -> Result<i64, ErrorComando> {
+    let mut tx = db.pool().begin().await?;
+    
     let result = sqlx::query(
-        "INSERT INTO jardines (codigo, nombre) VALUES (?, ?)"
+        "INSERT INTO jardines (contrato_id, codigo, nombre) VALUES ((SELECT id FROM contrato_activo), ?, ?)"
     )
     .bind(&codigo)
     .bind(&nombre)
-    .execute(&*db.pool)
-    .await
-    .map_err(|e| e.to_string())?;
+    .execute(&mut *tx)
+    .await?;
     
-    Ok(result.last_insert_rowid())
+    let id = result.last_insert_rowid();
+    auditoria::registrar_cambio(&mut tx, &db.operador(), Entidad::Jardin, id, "crear", None).await?;
+    
+    tx.commit().await?;
+    Ok(id)
 }
 
 // ========== PARTIDAS ==========
 
-#[tauri::command]
-pub async fn get_partidas(db: State<'_, DbState>) -> Result<Vec<Partida>, String> {
-    sqlx::query_as::<_, Partida>("SELECT * FROM partidas ORDER BY item")
-        .fetch_all(&*db.pool)
-        .await
-        .map_err(|e| e.to_string())
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_partidas(db: State<'_, DbState>, incluir_inactivos: Option<bool>) -> Result<Vec<Partida>, ErrorComando> {
+    sqlx::query_as::<_, Partida>(
+        "SELECT * FROM partidas WHERE contrato_id = (SELECT id FROM contrato_activo) AND (activo = 1 OR ?) ORDER BY item"
+    )
+    .bind(incluir_inactivos.unwrap_or(false))
+    .fetch_all(&db.pool())
+    .await
+    .map_err(ErrorComando::from)
 }
 
 #[tauri::command(rename_all = "snake_case")]
@@ -59,74 +86,59 @@ pub async fn add_partida(
     partida: String,
     unidad: Option<String>,
     precio_unitario: f64,
-) -> Result<i64, String> {
+    exenta_iva: Option<bool>,
+) -> Result<i64, ErrorComando> {
+    let mut tx = db.pool().begin().await?;
+    
     let result = sqlx::query(
-        "INSERT INTO partidas (item, partida, unidad, precio_unitario) VALUES (?, ?, ?, ?)"
+        "INSERT INTO partidas (contrato_id, item, partida, unidad, precio_unitario, exenta_iva) 
+         VALUES ((SELECT id FROM contrato_activo), ?, ?, ?, ?, ?)"
     )
     .bind(&item)
     .bind(&partida)
     .bind(&unidad)
     .bind(precio_unitario)
-    .execute(&*db.pool)
-    .await
-    .map_err(|e| e.to_string())?;
+    .bind(exenta_iva.unwrap_or(false))
+    .execute(&mut *tx)
+    .await?;
+    
+    let id = result.last_insert_rowid();
+    let contrato_id = contratos::activo(&mut tx).await?;
+    precios::registrar_iniciales(&mut tx, contrato_id).await?;
+    auditoria::registrar_cambio(&mut tx, &db.operador(), Entidad::Partida, id, "crear", None).await?;
     
-    Ok(result.last_insert_rowid())
+    tx.commit().await?;
+    Ok(id)
 }
 
 // ========== REQUERIMIENTOS ==========
 
 #[tauri::command]
-pub async fn get_requerimientos(db: State<'_, DbState>) -> Result<Vec<RequerimientoEnriquecido>, String> {
-    sqlx::query_as::<_, RequerimientoEnriquecido>(
-        "SELECT 
-            r.id,
-            r.jardin_codigo,
-            r.recinto,
-            r.partida_item,
-            p.partida as partida_nombre,
-            p.unidad as partida_unidad,
-            r.precio_unitario,
-            r.cantidad,
-            r.precio_total,
-            r.fecha_inicio,
-            r.plazo_dias,
-            r.plazo_adicional,
-            (r.plazo_dias + r.plazo_adicional) as plazo_total,
-            CASE 
-                WHEN (r.plazo_dias + r.plazo_adicional) > 0 
-                THEN date(r.fecha_inicio, '+' || (r.plazo_dias + r.plazo_adicional) || ' days')
-                ELSE NULL
-            END as fecha_limite,
-            r.fecha_registro,
-            r.fecha_recepcion,
-            CASE 
-                WHEN r.fecha_recepcion IS NOT NULL 
-                     AND (r.plazo_dias + r.plazo_adicional) > 0
-                     AND date(r.fecha_inicio, '+' || (r.plazo_dias + r.plazo_adicional) || ' days') < r.fecha_recepcion
-                THEN CAST(julianday(r.fecha_recepcion) - julianday(date(r.fecha_inicio, '+' || (r.plazo_dias + r.plazo_adicional) || ' days')) AS INTEGER)
-                ELSE 0
-            END as dias_atraso,
-            r.multa,
-            (r.precio_total - COALESCE(r.multa, 0)) as a_pago,
-            r.descripcion,
-            r.observaciones,
-            r.estado,
-            r.ot_id,
-            ot.codigo as ot_codigo,
-            r.informe_pago_id,
-            ip.codigo as informe_pago_codigo,
-            r.created_at,
-            r.updated_at
-        FROM requerimientos r
-        LEFT JOIN partidas p ON r.partida_item = p.item
-        LEFT JOIN ordenes_trabajo ot ON r.ot_id = ot.id
-        LEFT JOIN informes_pago ip ON r.informe_pago_id = ip.id
-        ORDER BY r.fecha_inicio DESC"
+pub async fn get_requerimientos(db: State<'_, DbState>) -> Result<Vec<RequerimientoEnriquecido>, ErrorComando> {
+    let mut conn = db.pool().acquire().await?;
+    consultas::listar(&mut conn, Vista::Todos).await
+}
+
+/// Requerimientos filtrados, ordenados y paginados, con el total de filas que cumplen el filtro
+#[tauri::command(rename_all = "snake_case")]
+pub async fn consultar_requerimientos(
+    db: State<'_, DbState>,
+    filtro: Option<FiltroRequerimientos>,
+    orden: Option<String>,
+    descendente: Option<bool>,
+    limite: Option<i64>,
+    offset: Option<i64>,
+) -> Result<PaginaRequerimientos, ErrorComando> {
+    let mut conn = db.pool().acquire().await?;
+    consultas::consultar(
+        &mut conn,
+        &filtro.unwrap_or_default(),
+        orden.as_deref(),
+        descendente.unwrap_or(false),
+        limite,
+        offset.unwrap_or(0),
     )
-    .fetch_all(&*db.pool)
     .await
-    .map_err(|e| e.to_string())
 }
 
 #[tauri::command(rename_all = "snake_case")]
@@ -136,35 +148,53 @@ pub async fn add_requerimiento(
     recinto: Option<String>,
     partida_item: String,
     cantidad: f64,
-    precio_unitario: f64,
+    precio_unitario: Option<f64>,
     fecha_inicio: String,
     fecha_registro: String,
     plazo_dias: i32,
     descripcion: Option<String>,
-) -> Result<i64, String> {
+) -> Result<i64, ErrorComando> {
+    let mut tx = db.pool().begin().await?;
+    catalogo::verificar_activos(&mut tx, &jardin_codigo, &partida_item).await?;
+    
+    // Sin precio manual se usa la lista de precios vigente al inicio
+    let (precio_unitario, precio_partida_id) = match precio_unitario {
+        Some(precio) => (precio, None),
+        None => {
+            let precio = precios::vigente(&mut tx, &partida_item, &fecha_inicio)
+                .await?
+                .ok_or_else(|| ErrorComando::no_encontrado("Partida", &partida_item))?;
+            (precio.precio_unitario, Some(precio.id))
+        }
+    };
     let precio_total = cantidad * precio_unitario;
     
     let result = sqlx::query(
         "INSERT INTO requerimientos 
-         (jardin_codigo, recinto, partida_item, cantidad, precio_unitario, precio_total, 
+         (contrato_id, jardin_codigo, recinto, partida_item, cantidad, precio_unitario, precio_partida_id, precio_total, 
           fecha_inicio, fecha_registro, plazo_dias, descripcion, estado) 
-         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 'pendiente')"
+         VALUES ((SELECT id FROM contrato_activo), ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 'pendiente')"
     )
     .bind(&jardin_codigo)
     .bind(&recinto)
     .bind(&partida_item)
     .bind(cantidad)
     .bind(precio_unitario)
+    .bind(precio_partida_id)
     .bind(precio_total)
     .bind(&fecha_inicio)
     .bind(&fecha_registro)
     .bind(plazo_dias)
     .bind(&descripcion)
-    .execute(&*db.pool)
-    .await
-    .map_err(|e| e.to_string())?;
+    .execute(&mut *tx)
+    .await?;
     
-    Ok(result.last_insert_rowid())
+    let id = result.last_insert_rowid();
+    multas::recalcular_requerimiento(&mut tx, id).await?;
+    auditoria::registrar_cambio(&mut tx, &db.operador(), Entidad::Requerimiento, id, "crear", None).await?;
+    
+    tx.commit().await?;
+    Ok(id)
 }
 
 #[tauri::command(rename_all = "snake_case")]
@@ -180,27 +210,57 @@ pub async fn update_requerimiento(
     plazo_adicional: Option<i32>,
     fecha_recepcion: Option<String>,
     partida_item: Option<String>,
-) -> Result<(), String> {
+) -> Result<(), ErrorComando> {
     
     println!("🔧 update_requerimiento ID={} plazo_dias={:?} plazo_adicional={:?}", id, plazo_dias, plazo_adicional);
     
+    let mut tx = db.pool().begin().await?;
+    let antes = auditoria::instantanea(&mut tx, Entidad::Requerimiento, id).await?;
+    
+    // Descripción y observaciones se pueden corregir siempre; el resto solo antes del informe
+    let edita_datos = cantidad.is_some() || precio_unitario.is_some() || fecha_inicio.is_some()
+        || plazo_dias.is_some() || plazo_adicional.is_some() || fecha_recepcion.is_some() || partida_item.is_some();
+    if edita_datos {
+        estados::verificar_accion(&mut tx, id, "editar", EstadoRequerimiento::permite_editar).await?;
+    }
+    // Cambiar a otra partida exige, como al crear, un catálogo activo
+    if let Some(ref item) = partida_item {
+        let jardin_codigo: String = sqlx::query_scalar("SELECT jardin_codigo FROM requerimientos WHERE id = ?")
+            .bind(id)
+            .fetch_one(&mut *tx)
+            .await?;
+        catalogo::verificar_activos(&mut tx, &jardin_codigo, item).await?;
+    }
+    if let Some(cantidad) = cantidad {
+        let recibida: f64 = sqlx::query_scalar("SELECT cantidad_recibida FROM requerimientos WHERE id = ?")
+            .bind(id)
+            .fetch_one(&mut *tx)
+            .await?;
+        if cantidad < recibida {
+            return Err(ErrorComando::validacion(
+                "cantidad",
+                format!("La cantidad no puede ser menor que lo ya recibido ({})", recibida),
+            ));
+        }
+    }
+    
     let mut set_parts = vec![];
     
     if descripcion.is_some() { set_parts.push("descripcion = ?"); }
     if observaciones.is_some() { set_parts.push("observaciones = ?"); }
     if partida_item.is_some() { set_parts.push("partida_item = ?"); }
     if cantidad.is_some() { set_parts.push("cantidad = ?"); }
-    if precio_unitario.is_some() { set_parts.push("precio_unitario = ?"); }
+    // Un precio manual desliga el requerimiento de la lista de precios
+    if precio_unitario.is_some() { set_parts.push("precio_unitario = ?"); set_parts.push("precio_partida_id = NULL"); }
     if fecha_inicio.is_some() { set_parts.push("fecha_inicio = ?"); }
     if plazo_dias.is_some() { set_parts.push("plazo_dias = ?"); }
     if plazo_adicional.is_some() { set_parts.push("plazo_adicional = ?"); }
-    if fecha_recepcion.is_some() { set_parts.push("fecha_recepcion = ?"); }
     
     if cantidad.is_some() || precio_unitario.is_some() {
         set_parts.push("precio_total = cantidad * precio_unitario");
     }
     
-    if set_parts.is_empty() {
+    if set_parts.is_empty() && fecha_recepcion.is_none() {
         return Ok(());
     }
     
@@ -215,66 +275,85 @@ pub async fn update_requerimiento(
     
     if let Some(v) = descripcion { query = query.bind(v); }
     if let Some(v) = observaciones { query = query.bind(v); }
+    let partida_item_cambia = partida_item.is_some();
     if let Some(v) = partida_item { query = query.bind(v); }
     if let Some(v) = cantidad { query = query.bind(v); }
     if let Some(v) = precio_unitario { query = query.bind(v); }
     if let Some(ref v) = fecha_inicio { query = query.bind(v); }
     if let Some(ref v) = plazo_dias { query = query.bind(v); }
     if let Some(ref v) = plazo_adicional { query = query.bind(v); }
-    if let Some(v) = fecha_recepcion { query = query.bind(v); }
     
     query.bind(id)
-        .execute(&*db.pool)
-        .await
-        .map_err(|e| e.to_string())?;
+        .execute(&mut *tx)
+        .await?;
+    
+    // Otra partida o fecha de inicio puede corresponder a otra versión de precio
+    if precio_unitario.is_none() && (partida_item_cambia || fecha_inicio.is_some()) {
+        precios::revalorizar(&mut tx, &db.operador(), id).await?;
+    }
+    
+    // La fecha de recepción se registra como entrega del saldo; un cambio de
+    // cantidad puede completar o reabrir la recepción
+    if let Some(fecha) = fecha_recepcion {
+        recepciones::recibir_saldo(&mut tx, id, &fecha).await?;
+    } else if edita_datos {
+        recepciones::sincronizar(&mut tx, id).await?;
+    } else {
+        multas::recalcular_requerimiento(&mut tx, id).await?;
+    }
+    auditoria::registrar_cambio(&mut tx, &db.operador(), Entidad::Requerimiento, id, "actualizar", antes).await?;
+    tx.commit().await?;
     
     println!("✅ update_requerimiento ID={}", id);
     Ok(())
 }
 
+/// Recibe de una vez todo lo que falta (o corrige la fecha si ya estaba recepcionado).
+/// Las entregas parciales se registran con `agregar_recepcion`.
 #[tauri::command(rename_all = "snake_case")]
 pub async fn actualizar_fecha_recepcion(
     db: State<'_, DbState>,
     id: i64,
     fecha_recepcion: String,
-) -> Result<(), String> {
+) -> Result<(), ErrorComando> {
     println!("📝 actualizar_fecha_recepcion - ID: {}, fecha: {}", id, fecha_recepcion);
     
-    let result = sqlx::query(
-        "UPDATE requerimientos SET fecha_recepcion = ?, updated_at = datetime('now') WHERE id = ?"
-    )
-    .bind(&fecha_recepcion)
-    .bind(id)
-    .execute(&*db.pool)
-    .await
-    .map_err(|e| {
+    let mut tx = db.pool().begin().await?;
+    let antes = auditoria::instantanea(&mut tx, Entidad::Requerimiento, id).await?;
+    
+    recepciones::recibir_saldo(&mut tx, id, &fecha_recepcion).await.map_err(|e| {
         println!("❌ ERROR actualizar_fecha_recepcion: {}", e);
-        e.to_string()
+        e
     })?;
     
-    println!("✅ actualizar_fecha_recepcion: {} fila(s) afectada(s)", result.rows_affected());
+    auditoria::registrar_cambio(&mut tx, &db.operador(), Entidad::Requerimiento, id, "recepcionar", antes).await?;
+    tx.commit().await?;
+    
+    println!("✅ actualizar_fecha_recepcion ID={}", id);
     Ok(())
 }
 
+/// Quita todas las recepciones (parciales o total) y devuelve el requerimiento
+/// a en_ot (a pendiente si no tiene OT)
 #[tauri::command]
 pub async fn eliminar_fecha_recepcion(
     db: State<'_, DbState>,
     id: i64,
-) -> Result<(), String> {
+) -> Result<(), ErrorComando> {
     println!("🗑️ eliminar_fecha_recepcion - ID: {}", id);
     
-    let result = sqlx::query(
-        "UPDATE requerimientos SET fecha_recepcion = NULL, updated_at = datetime('now') WHERE id = ?"
-    )
-    .bind(id)
-    .execute(&*db.pool)
-    .await
-    .map_err(|e| {
+    let mut tx = db.pool().begin().await?;
+    let antes = auditoria::instantanea(&mut tx, Entidad::Requerimiento, id).await?;
+    
+    recepciones::quitar_todas(&mut tx, id).await.map_err(|e| {
         println!("❌ ERROR eliminar_fecha_recepcion: {}", e);
-        e.to_string()
+        e
     })?;
     
-    println!("✅ eliminar_fecha_recepcion: {} fila(s) afectada(s)", result.rows_affected());
+    auditoria::registrar_cambio(&mut tx, &db.operador(), Entidad::Requerimiento, id, "quitar_recepcion", antes).await?;
+    tx.commit().await?;
+    
+    println!("✅ eliminar_fecha_recepcion ID={}", id);
     Ok(())
 }
 
@@ -282,39 +361,94 @@ pub async fn eliminar_fecha_recepcion(
 pub async fn delete_requerimiento(
     db: State<'_, DbState>,
     id: i64,
-) -> Result<(), String> {
+) -> Result<(), ErrorComando> {
+    let mut tx = db.pool().begin().await?;
+    
+    estados::verificar_accion(&mut tx, id, "eliminar", EstadoRequerimiento::permite_eliminar).await?;
+    let antes = auditoria::instantanea(&mut tx, Entidad::Requerimiento, id).await?;
+    
     sqlx::query("DELETE FROM requerimientos WHERE id = ?")
         .bind(id)
-        .execute(&*db.pool)
-        .await
-        .map_err(|e| e.to_string())?;
+        .execute(&mut *tx)
+        .await?;
+    
+    auditoria::registrar_cambio(&mut tx, &db.operador(), Entidad::Requerimiento, id, "eliminar", antes).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Cambios de estado manuales: anular y reactivar un anulado. El resto de las
+/// transiciones ocurre al vincular OT, recepción e informe; el pago solo llega
+/// al pagar su informe.
+#[tauri::command]
+pub async fn cambiar_estado_requerimiento(
+    db: State<'_, DbState>,
+    id: i64,
+    estado: EstadoRequerimiento,
+) -> Result<(), ErrorComando> {
+    use EstadoRequerimiento::*;
+    
+    let mut tx = db.pool().begin().await?;
+    
+    let desde = estados::estado_actual(&mut tx, id).await?;
+    let manual = matches!(
+        (desde, estado),
+        (Pendiente | EnOt, Anulado) | (Anulado, Pendiente)
+    );
+    if !manual {
+        return Err(ErrorEstado::Transicion { id, desde, hacia: estado }.into());
+    }
+    // Anular saca de la OT: como al desvincular, no con entregas parciales
+    if desde == EnOt {
+        recepciones::verificar_sin_entregas(&mut tx, id).await?;
+    }
+    let antes = auditoria::instantanea(&mut tx, Entidad::Requerimiento, id).await?;
+    
+    // Un requerimiento anulado sale de su OT
+    sqlx::query(
+        "UPDATE requerimientos 
+         SET estado = ?, ot_id = CASE WHEN ? THEN NULL ELSE ot_id END, updated_at = datetime('now') 
+         WHERE id = ?"
+    )
+    .bind(estado.as_str())
+    .bind(estado == Anulado)
+    .bind(id)
+    .execute(&mut *tx)
+    .await?;
     
+    auditoria::registrar_cambio(&mut tx, &db.operador(), Entidad::Requerimiento, id, "cambiar_estado", antes).await?;
+    tx.commit().await?;
     Ok(())
 }
 
 
 // ========== RECINTOS ==========
 
-#[tauri::command]
-pub async fn get_recintos(db: State<'_, DbState>) -> Result<Vec<Recinto>, String> {
-    sqlx::query_as::<_, Recinto>("SELECT * FROM recintos ORDER BY nombre")
-        .fetch_all(&*db.pool)
-        .await
-        .map_err(|e| e.to_string())
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_recintos(db: State<'_, DbState>, incluir_inactivos: Option<bool>) -> Result<Vec<Recinto>, ErrorComando> {
+    sqlx::query_as::<_, Recinto>(
+        "SELECT * FROM recintos WHERE contrato_id = (SELECT id FROM contrato_activo) AND (activo = 1 OR ?) ORDER BY nombre"
+    )
+    .bind(incluir_inactivos.unwrap_or(false))
+    .fetch_all(&db.pool())
+    .await
+    .map_err(ErrorComando::from)
 }
 
 #[tauri::command(rename_all = "snake_case")]
 pub async fn get_recintos_by_jardin(
     db: State<'_, DbState>,
     jardin_codigo: String,
-) -> Result<Vec<Recinto>, String> {
+) -> Result<Vec<Recinto>, ErrorComando> {
     sqlx::query_as::<_, Recinto>(
-        "SELECT * FROM recintos WHERE jardin_codigo = ? ORDER BY nombre"
+        "SELECT * FROM recintos
+         WHERE contrato_id = (SELECT id FROM contrato_activo) AND jardin_codigo = ? AND activo = 1
+         ORDER BY nombre"
     )
     .bind(jardin_codigo)
-    .fetch_all(&*db.pool)
+    .fetch_all(&db.pool())
     .await
-    .map_err(|e| e.to_string())
+    .map_err(ErrorComando::from)
 }
 
 #[tauri::command(rename_all = "snake_case")]
@@ -322,88 +456,44 @@ pub async fn add_recinto(
     db: State<'_, DbState>,
     jardin_codigo: String,
     nombre: String,
-) -> Result<i64, String> {
+) -> Result<i64, ErrorComando> {
+    let mut tx = db.pool().begin().await?;
+    
     let result = sqlx::query(
-        "INSERT INTO recintos (jardin_codigo, nombre) VALUES (?, ?)"
+        "INSERT INTO recintos (contrato_id, jardin_codigo, nombre) VALUES ((SELECT id FROM contrato_activo), ?, ?)"
     )
     .bind(&jardin_codigo)
     .bind(&nombre)
-    .execute(&*db.pool)
-    .await
-    .map_err(|e| e.to_string())?;
+    .execute(&mut *tx)
+    .await?;
+    
+    let id = result.last_insert_rowid();
+    auditoria::registrar_cambio(&mut tx, &db.operador(), Entidad::Recinto, id, "crear", None).await?;
     
-    Ok(result.last_insert_rowid())
+    tx.commit().await?;
+    Ok(id)
 }
 
 
 // ========== ÓRDENES DE TRABAJO ==========
 
 #[tauri::command]
-pub async fn get_ordenes_trabajo(db: State<'_, DbState>) -> Result<Vec<OrdenTrabajo>, String> {
+pub async fn get_ordenes_trabajo(db: State<'_, DbState>) -> Result<Vec<OrdenTrabajo>, ErrorComando> {
     sqlx::query_as::<_, OrdenTrabajo>(
-        "SELECT * FROM ordenes_trabajo ORDER BY fecha_creacion DESC"
+        "SELECT * FROM ordenes_trabajo WHERE contrato_id = (SELECT id FROM contrato_activo) ORDER BY fecha_creacion DESC"
     )
-    .fetch_all(&*db.pool)
+    .fetch_all(&db.pool())
     .await
-    .map_err(|e| e.to_string())
+    .map_err(ErrorComando::from)
 }
 
 #[tauri::command(rename_all = "snake_case")]
 pub async fn get_orden_trabajo_detalle(
     db: State<'_, DbState>,
     ot_id: i64,
-) -> Result<Vec<RequerimientoEnriquecido>, String> {
-    sqlx::query_as::<_, RequerimientoEnriquecido>(
-        "SELECT 
-            r.id,
-            r.jardin_codigo,
-            r.recinto,
-            r.partida_item,
-            p.partida as partida_nombre,
-            p.unidad as partida_unidad,
-            r.precio_unitario,
-            r.cantidad,
-            r.precio_total,
-            r.fecha_inicio,
-            r.plazo_dias,
-            r.plazo_adicional,
-            (r.plazo_dias + r.plazo_adicional) as plazo_total,
-            CASE 
-                WHEN (r.plazo_dias + r.plazo_adicional) > 0 
-                THEN date(r.fecha_inicio, '+' || (r.plazo_dias + r.plazo_adicional) || ' days')
-                ELSE NULL
-            END as fecha_limite,
-            r.fecha_registro,
-            r.fecha_recepcion,
-            CASE 
-                WHEN r.fecha_recepcion IS NOT NULL 
-                     AND (r.plazo_dias + r.plazo_adicional) > 0
-                     AND date(r.fecha_inicio, '+' || (r.plazo_dias + r.plazo_adicional) || ' days') < r.fecha_recepcion
-                THEN CAST(julianday(r.fecha_recepcion) - julianday(date(r.fecha_inicio, '+' || (r.plazo_dias + r.plazo_adicional) || ' days')) AS INTEGER)
-                ELSE 0
-            END as dias_atraso,
-            r.multa,
-            (r.precio_total - COALESCE(r.multa, 0)) as a_pago,
-            r.descripcion,
-            r.observaciones,
-            r.estado,
-            r.ot_id,
-            ot.codigo as ot_codigo,
-            r.informe_pago_id,
-            ip.codigo as informe_pago_codigo,
-            r.created_at,
-            r.updated_at
-        FROM requerimientos r
-        LEFT JOIN partidas p ON r.partida_item = p.item
-        LEFT JOIN ordenes_trabajo ot ON r.ot_id = ot.id
-        LEFT JOIN informes_pago ip ON r.informe_pago_id = ip.id
-        WHERE r.ot_id = ?
-        ORDER BY r.fecha_inicio DESC"
-    )
-    .bind(ot_id)
-    .fetch_all(&*db.pool)
-    .await
-    .map_err(|e| e.to_string())
+) -> Result<Vec<RequerimientoEnriquecido>, ErrorComando> {
+    let mut conn = db.pool().acquire().await?;
+    consultas::listar(&mut conn, Vista::OrdenTrabajo(ot_id)).await
 }
 
 #[tauri::command(rename_all = "snake_case")]
@@ -413,27 +503,26 @@ pub async fn crear_orden_trabajo(
     fecha_creacion: String,
     observaciones: Option<String>,
     requerimiento_ids: Vec<i64>,
-) -> Result<i64, String> {
-    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+) -> Result<i64, ErrorComando> {
+    let mut tx = db.pool().begin().await?;
     
     // Obtener configuración para prefijo
-    let config_row = sqlx::query("SELECT prefijo_correlativo FROM configuracion_contrato WHERE id = 1")
+    let config_row = sqlx::query("SELECT prefijo_correlativo FROM contrato_activo")
         .fetch_one(&mut *tx)
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
     
     let prefijo: String = config_row.get("prefijo_correlativo");
     
     // Obtener último número de OT para este jardín específico
     let pattern = format!("OT-{}-{}", jardin_codigo, prefijo);
     let last_ot: Option<i64> = sqlx::query_scalar(
-        "SELECT MAX(CAST(SUBSTR(codigo, LENGTH(?) + 1) AS INTEGER)) FROM ordenes_trabajo WHERE codigo LIKE ? || '%'"
+        "SELECT MAX(CAST(SUBSTR(codigo, LENGTH(?) + 1) AS INTEGER)) FROM ordenes_trabajo 
+         WHERE contrato_id = (SELECT id FROM contrato_activo) AND codigo LIKE ? || '%'"
     )
     .bind(&pattern)
     .bind(&pattern)
     .fetch_optional(&mut *tx)
-    .await
-    .map_err(|e| e.to_string())?
+    .await?
     .flatten();
     
     let next_num = last_ot.unwrap_or(0) + 1;
@@ -441,30 +530,34 @@ pub async fn crear_orden_trabajo(
     
     // Crear OT
     let result = sqlx::query(
-        "INSERT INTO ordenes_trabajo (codigo, jardin_codigo, fecha_creacion, observaciones) 
-         VALUES (?, ?, ?, ?)"
+        "INSERT INTO ordenes_trabajo (contrato_id, codigo, jardin_codigo, fecha_creacion, observaciones) 
+         VALUES ((SELECT id FROM contrato_activo), ?, ?, ?, ?)"
     )
     .bind(&codigo)
     .bind(&jardin_codigo)
     .bind(&fecha_creacion)
     .bind(&observaciones)
     .execute(&mut *tx)
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
     
     let ot_id = result.last_insert_rowid();
     
     // Vincular requerimientos
+    let antes_requerimientos = auditoria::instantaneas(&mut tx, Entidad::Requerimiento, &requerimiento_ids).await?;
     for req_id in requerimiento_ids {
+        verificar_vinculable(&mut tx, req_id, &jardin_codigo, "la OT").await?;
+        estados::verificar_transicion(&mut tx, req_id, EstadoRequerimiento::Pendiente, EstadoRequerimiento::EnOt).await?;
         sqlx::query("UPDATE requerimientos SET ot_id = ?, estado = 'en_ot', updated_at = datetime('now') WHERE id = ?")
             .bind(ot_id)
             .bind(req_id)
             .execute(&mut *tx)
-            .await
-            .map_err(|e| e.to_string())?;
+            .await?;
     }
     
-    tx.commit().await.map_err(|e| e.to_string())?;
+    auditoria::registrar_cambio(&mut tx, &db.operador(), Entidad::OrdenTrabajo, ot_id, "crear", None).await?;
+    auditoria::registrar_cambios(&mut tx, &db.operador(), Entidad::Requerimiento, "agregar_a_ot", antes_requerimientos).await?;
+    
+    tx.commit().await?;
     Ok(ot_id)
 }
 
@@ -472,24 +565,33 @@ pub async fn crear_orden_trabajo(
 pub async fn eliminar_orden_trabajo(
     db: State<'_, DbState>,
     ot_id: i64,
-) -> Result<(), String> {
-    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+) -> Result<(), ErrorComando> {
+    let mut tx = db.pool().begin().await?;
+    let antes = auditoria::instantanea(&mut tx, Entidad::OrdenTrabajo, ot_id).await?;
+    
+    // Solo se desarma una OT sin requerimientos recepcionados
+    let vinculados = ids_vinculados(&mut tx, "ot_id", ot_id).await?;
+    for req_id in &vinculados {
+        estados::verificar_transicion(&mut tx, *req_id, EstadoRequerimiento::EnOt, EstadoRequerimiento::Pendiente).await?;
+        recepciones::verificar_sin_entregas(&mut tx, *req_id).await?;
+    }
+    let antes_requerimientos = auditoria::instantaneas(&mut tx, Entidad::Requerimiento, &vinculados).await?;
     
     // Desvincular requerimientos
     sqlx::query("UPDATE requerimientos SET ot_id = NULL, estado = 'pendiente', updated_at = datetime('now') WHERE ot_id = ?")
         .bind(ot_id)
         .execute(&mut *tx)
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
     
     // Eliminar OT
     sqlx::query("DELETE FROM ordenes_trabajo WHERE id = ?")
         .bind(ot_id)
         .execute(&mut *tx)
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
     
-    tx.commit().await.map_err(|e| e.to_string())?;
+    auditoria::registrar_cambio(&mut tx, &db.operador(), Entidad::OrdenTrabajo, ot_id, "eliminar", antes).await?;
+    auditoria::registrar_cambios(&mut tx, &db.operador(), Entidad::Requerimiento, "quitar_de_ot", antes_requerimientos).await?;
+    tx.commit().await?;
     Ok(())
 }
 
@@ -497,7 +599,7 @@ pub async fn eliminar_orden_trabajo(
 // ========== INFORMES DE PAGO ==========
 
 #[tauri::command]
-pub async fn get_informes_pago(db: State<'_, DbState>) -> Result<Vec<InformePagoEnriquecido>, String> {
+pub async fn get_informes_pago(db: State<'_, DbState>) -> Result<Vec<InformePagoEnriquecido>, ErrorComando> {
     sqlx::query_as::<_, InformePagoEnriquecido>(
         "SELECT 
             ip.id,
@@ -506,140 +608,66 @@ pub async fn get_informes_pago(db: State<'_, DbState>) -> Result<Vec<InformePago
             j.nombre as jardin_nombre,
             ip.fecha_creacion,
             ip.neto,
+            ip.neto_exento,
+            ip.gastos_generales,
             ip.utilidades,
             ip.iva,
             ip.total_final,
-            COUNT(DISTINCT r.id) as cantidad_requerimientos,
+            ip.porcentaje_gastos_generales,
+            ip.porcentaje_utilidades,
+            ip.porcentaje_iva,
+            CASE WHEN ip.estado = 'anulado' THEN COALESCE(json_array_length(ip.detalle_anulado), 0)
+                 ELSE COUNT(DISTINCT r.id) END as cantidad_requerimientos,
             ip.observaciones,
+            ip.estado,
+            ip.fecha_emision,
+            ip.fecha_aprobacion,
+            ip.fecha_pago,
+            ip.fecha_anulacion,
+            ip.motivo_anulacion,
             ip.created_at,
             ip.updated_at
         FROM informes_pago ip
-        LEFT JOIN jardines j ON ip.jardin_codigo = j.codigo
+        LEFT JOIN jardines j ON ip.contrato_id = j.contrato_id AND ip.jardin_codigo = j.codigo
         LEFT JOIN requerimientos r ON ip.id = r.informe_pago_id
+        WHERE ip.contrato_id = (SELECT id FROM contrato_activo)
         GROUP BY ip.id
         ORDER BY ip.fecha_creacion DESC"
     )
-    .fetch_all(&*db.pool)
+    .fetch_all(&db.pool())
     .await
-    .map_err(|e| e.to_string())
+    .map_err(ErrorComando::from)
 }
 
+/// Líneas del informe. Un informe anulado devuelve las líneas que tenía al anularse.
 #[tauri::command(rename_all = "snake_case")]
 pub async fn get_informe_pago_detalle(
     db: State<'_, DbState>,
     informe_id: i64,
-) -> Result<Vec<RequerimientoEnriquecido>, String> {
-    sqlx::query_as::<_, RequerimientoEnriquecido>(
-        "SELECT 
-            r.id,
-            r.jardin_codigo,
-            r.recinto,
-            r.partida_item,
-            p.partida as partida_nombre,
-            p.unidad as partida_unidad,
-            r.precio_unitario,
-            r.cantidad,
-            r.precio_total,
-            r.fecha_inicio,
-            r.plazo_dias,
-            r.plazo_adicional,
-            (r.plazo_dias + r.plazo_adicional) as plazo_total,
-            CASE 
-                WHEN (r.plazo_dias + r.plazo_adicional) > 0 
-                THEN date(r.fecha_inicio, '+' || (r.plazo_dias + r.plazo_adicional) || ' days')
-                ELSE NULL
-            END as fecha_limite,
-            r.fecha_registro,
-            r.fecha_recepcion,
-            CASE 
-                WHEN r.fecha_recepcion IS NOT NULL 
-                     AND (r.plazo_dias + r.plazo_adicional) > 0
-                     AND date(r.fecha_inicio, '+' || (r.plazo_dias + r.plazo_adicional) || ' days') < r.fecha_recepcion
-                THEN CAST(julianday(r.fecha_recepcion) - julianday(date(r.fecha_inicio, '+' || (r.plazo_dias + r.plazo_adicional) || ' days')) AS INTEGER)
-                ELSE 0
-            END as dias_atraso,
-            r.multa,
-            (r.precio_total - COALESCE(r.multa, 0)) as a_pago,
-            r.descripcion,
-            r.observaciones,
-            r.estado,
-            r.ot_id,
-            ot.codigo as ot_codigo,
-            r.informe_pago_id,
-            ip.codigo as informe_pago_codigo,
-            r.created_at,
-            r.updated_at
-        FROM requerimientos r
-        LEFT JOIN partidas p ON r.partida_item = p.item
-        LEFT JOIN ordenes_trabajo ot ON r.ot_id = ot.id
-        LEFT JOIN informes_pago ip ON r.informe_pago_id = ip.id
-        WHERE r.informe_pago_id = ?
-        ORDER BY r.fecha_inicio DESC"
-    )
-    .bind(informe_id)
-    .fetch_all(&*db.pool)
-    .await
-    .map_err(|e| e.to_string())
+) -> Result<Vec<RequerimientoEnriquecido>, ErrorComando> {
+    let mut conn = db.pool().acquire().await?;
+    if estados::estado_informe(&mut conn, informe_id).await? == EstadoInforme::Anulado {
+        let detalle: Option<String> = sqlx::query_scalar("SELECT detalle_anulado FROM informes_pago WHERE id = ?")
+            .bind(informe_id)
+            .fetch_one(&mut *conn)
+            .await?;
+        return match detalle {
+            Some(json) => serde_json::from_str(&json).map_err(|e| ErrorComando::Internal {
+                mensaje: format!("Detalle del informe anulado {} ilegible: {}", informe_id, e),
+            }),
+            None => Ok(Vec::new()),
+        };
+    }
+    consultas::listar(&mut conn, Vista::InformePago(informe_id)).await
 }
 
 #[tauri::command(rename_all = "snake_case")]
 pub async fn get_requerimientos_para_informe(
     db: State<'_, DbState>,
     jardin_codigo: String,
-) -> Result<Vec<RequerimientoEnriquecido>, String> {
-    sqlx::query_as::<_, RequerimientoEnriquecido>(
-        "SELECT 
-            r.id,
-            r.jardin_codigo,
-            r.recinto,
-            r.partida_item,
-            p.partida as partida_nombre,
-            p.unidad as partida_unidad,
-            p.precio_unitario,
-            r.cantidad,
-            r.precio_total,
-            r.fecha_inicio,
-            r.plazo_dias,
-            r.plazo_adicional,
-            (r.plazo_dias + r.plazo_adicional) as plazo_total,
-            CASE 
-                WHEN (r.plazo_dias + r.plazo_adicional) > 0 
-                THEN date(r.fecha_inicio, '+' || (r.plazo_dias + r.plazo_adicional) || ' days')
-                ELSE NULL
-            END as fecha_limite,
-            r.fecha_registro,
-            r.fecha_recepcion,
-            CASE 
-                WHEN r.fecha_recepcion IS NOT NULL 
-                     AND (r.plazo_dias + r.plazo_adicional) > 0
-                     AND date(r.fecha_inicio, '+' || (r.plazo_dias + r.plazo_adicional) || ' days') < r.fecha_recepcion
-                THEN CAST(julianday(r.fecha_recepcion) - julianday(date(r.fecha_inicio, '+' || (r.plazo_dias + r.plazo_adicional) || ' days')) AS INTEGER)
-                ELSE 0
-            END as dias_atraso,
-            r.multa,
-            (r.precio_total - r.multa) as a_pago,
-            r.descripcion,
-            r.observaciones,
-            r.estado,
-            r.ot_id,
-            ot.codigo as ot_codigo,
-            r.informe_pago_id,
-            ip.codigo as informe_pago_codigo,
-            r.created_at,
-            r.updated_at
-        FROM requerimientos r
-        LEFT JOIN partidas p ON r.partida_item = p.item
-        LEFT JOIN ordenes_trabajo ot ON r.ot_id = ot.id
-        LEFT JOIN informes_pago ip ON r.informe_pago_id = ip.id
-        WHERE r.jardin_codigo = ? 
-        AND r.fecha_recepcion IS NOT NULL
-        AND r.informe_pago_id IS NULL
-        ORDER BY r.fecha_inicio DESC"
-    )
-    .bind(jardin_codigo)
-    .fetch_all(&*db.pool)
-    .await
-    .map_err(|e| e.to_string())
+) -> Result<Vec<RequerimientoEnriquecido>, ErrorComando> {
+    let mut conn = db.pool().acquire().await?;
+    consultas::listar(&mut conn, Vista::ParaInforme(jardin_codigo)).await
 }
 
 #[tauri::command(rename_all = "snake_case")]
@@ -648,116 +676,255 @@ pub async fn crear_informe_pago(
     jardin_codigo: String,
     fecha_creacion: String,
     observaciones: Option<String>,
-    requerimientos: Vec<serde_json::Value>,
-) -> Result<i64, String> {
-    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+    requerimientos: Vec<LineaInforme>,
+) -> Result<i64, ErrorComando> {
+    let mut tx = db.pool().begin().await?;
     
     // Obtener configuración para prefijo
-    let config_row = sqlx::query("SELECT prefijo_correlativo FROM configuracion_contrato WHERE id = 1")
+    let config_row = sqlx::query("SELECT prefijo_correlativo FROM contrato_activo")
         .fetch_one(&mut *tx)
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
     
     let prefijo: String = config_row.get("prefijo_correlativo");
     
     // Obtener último número de informe para este jardín específico
     let pattern = format!("IP-{}-{}", jardin_codigo, prefijo);
     let last_informe: Option<i64> = sqlx::query_scalar(
-        "SELECT MAX(CAST(SUBSTR(codigo, LENGTH(?) + 1) AS INTEGER)) FROM informes_pago WHERE codigo LIKE ? || '%'"
+        "SELECT MAX(CAST(SUBSTR(codigo, LENGTH(?) + 1) AS INTEGER)) FROM informes_pago 
+         WHERE contrato_id = (SELECT id FROM contrato_activo) AND codigo LIKE ? || '%'"
     )
     .bind(&pattern)
     .bind(&pattern)
     .fetch_optional(&mut *tx)
-    .await
-    .map_err(|e| e.to_string())?
+    .await?
     .flatten();
     
     let next_num = last_informe.unwrap_or(0) + 1;
     let codigo = format!("IP-{}-{}{:02}", jardin_codigo, prefijo, next_num);
     
-    // Calcular totales
-    let mut neto = 0.0;
-    for req in &requerimientos {
-        if let Some(monto) = req.get("monto").and_then(|m| m.as_f64()) {
-            neto += monto;
-        }
-    }
-    
-    let utilidades = neto * 0.10; // 10%
-    let subtotal = neto + utilidades;
-    let iva = subtotal * 0.19; // 19%
-    let total_final = subtotal + iva;
+    // Tasas vigentes a la fecha del informe (historial_tasas)
+    let tasas = contratos::tasas_vigentes(&mut tx, &fecha_creacion).await?;
     
-    // Crear informe
+    // Crear informe (guarda las tasas aplicadas; los totales, al final)
     let result = sqlx::query(
-        "INSERT INTO informes_pago (codigo, jardin_codigo, fecha_creacion, neto, utilidades, iva, total_final, observaciones) 
-         VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+        "INSERT INTO informes_pago 
+         (contrato_id, codigo, jardin_codigo, fecha_creacion,
+          porcentaje_gastos_generales, porcentaje_utilidades, porcentaje_iva, observaciones) 
+         VALUES ((SELECT id FROM contrato_activo), ?, ?, ?, ?, ?, ?, ?)"
     )
     .bind(&codigo)
     .bind(&jardin_codigo)
     .bind(&fecha_creacion)
-    .bind(neto)
-    .bind(utilidades)
-    .bind(iva)
-    .bind(total_final)
+    .bind(tasas.porcentaje_gastos_generales)
+    .bind(tasas.porcentaje_utilidades)
+    .bind(tasas.porcentaje_iva)
     .bind(&observaciones)
     .execute(&mut *tx)
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
     
     let informe_id = result.last_insert_rowid();
     
-    // Vincular requerimientos
-    for req in requerimientos {
-        if let Some(req_id) = req.get("id").and_then(|v| v.as_i64()) {
-            sqlx::query("UPDATE requerimientos SET informe_pago_id = ?, estado = 'en_informe', updated_at = datetime('now') WHERE id = ?")
-                .bind(informe_id)
-                .bind(req_id)
-                .execute(&mut *tx)
-                .await
-                .map_err(|e| e.to_string())?;
-        }
+    // Vincular requerimientos (los recibidos en parte se separan: entra solo lo recibido)
+    for linea in requerimientos {
+        verificar_vinculable(&mut tx, linea.id, &jardin_codigo, "el informe").await?;
+        let req_id = recepciones::separar_recibido(&mut tx, &db.operador(), linea.id).await?;
+        estados::verificar_transicion(&mut tx, req_id, EstadoRequerimiento::Recepcionado, EstadoRequerimiento::EnInforme).await?;
+        let antes_requerimiento = auditoria::instantanea(&mut tx, Entidad::Requerimiento, req_id).await?;
+        sqlx::query("UPDATE requerimientos SET informe_pago_id = ?, estado = 'en_informe', updated_at = datetime('now') WHERE id = ?")
+            .bind(informe_id)
+            .bind(req_id)
+            .execute(&mut *tx)
+            .await?;
+        auditoria::registrar_cambio(&mut tx, &db.operador(), Entidad::Requerimiento, req_id, "agregar_a_informe", antes_requerimiento).await?;
     }
     
-    tx.commit().await.map_err(|e| e.to_string())?;
+    // Totales desde las líneas ya vinculadas (ver informes.rs)
+    informes::recalcular(&mut tx, informe_id).await?;
+    
+    auditoria::registrar_cambio(&mut tx, &db.operador(), Entidad::InformePago, informe_id, "crear", None).await?;
+    
+    tx.commit().await?;
     Ok(informe_id)
 }
 
+/// Solo se vinculan requerimientos del contrato activo y del mismo jardín que
+/// la OT o el informe (`destino`, para el mensaje)
+async fn verificar_vinculable(
+    tx: &mut Transaction<'_, Sqlite>,
+    req_id: i64,
+    jardin_codigo: &str,
+    destino: &str,
+) -> Result<(), ErrorComando> {
+    let req_jardin: String = sqlx::query_scalar(
+        "SELECT jardin_codigo FROM requerimientos WHERE id = ? AND contrato_id = (SELECT id FROM contrato_activo)"
+    )
+    .bind(req_id)
+    .fetch_optional(&mut **tx)
+    .await?
+    .ok_or_else(|| ErrorComando::no_encontrado("Requerimiento", req_id))?;
+    if req_jardin != jardin_codigo {
+        return Err(ErrorComando::validacion(
+            "requerimiento_ids",
+            format!(
+                "El requerimiento {} pertenece al jardín '{}' pero {} es del jardín '{}'",
+                req_id, req_jardin, destino, jardin_codigo
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// IDs de los requerimientos vinculados a una OT (`ot_id`) o informe (`informe_pago_id`)
+async fn ids_vinculados(
+    tx: &mut Transaction<'_, Sqlite>,
+    columna: &str,
+    id: i64,
+) -> Result<Vec<i64>, ErrorComando> {
+    sqlx::query_scalar(&format!("SELECT id FROM requerimientos WHERE {} = ? ORDER BY id", columna))
+        .bind(id)
+        .fetch_all(&mut **tx)
+        .await
+        .map_err(ErrorComando::from)
+}
+
 #[tauri::command(rename_all = "snake_case")]
 pub async fn eliminar_informe_pago(
     db: State<'_, DbState>,
     informe_id: i64,
-) -> Result<(), String> {
-    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+) -> Result<(), ErrorComando> {
+    let mut tx = db.pool().begin().await?;
+    let antes = auditoria::instantanea(&mut tx, Entidad::InformePago, informe_id).await?;
+    
+    // Emitido en adelante se anula (queda registro); solo el borrador se elimina
+    estados::verificar_borrador(&mut tx, informe_id, "eliminar").await?;
+    
+    // Un informe con líneas pagadas no se puede eliminar
+    let vinculados = ids_vinculados(&mut tx, "informe_pago_id", informe_id).await?;
+    for req_id in &vinculados {
+        estados::verificar_transicion(&mut tx, *req_id, EstadoRequerimiento::EnInforme, EstadoRequerimiento::Recepcionado).await?;
+    }
+    let antes_requerimientos = auditoria::instantaneas(&mut tx, Entidad::Requerimiento, &vinculados).await?;
     
-    // Desvincular requerimientos (volver a pendiente)
-    sqlx::query("UPDATE requerimientos SET informe_pago_id = NULL, estado = 'pendiente', updated_at = datetime('now') WHERE informe_pago_id = ?")
+    // Desvincular requerimientos (vuelven a recepcionado)
+    sqlx::query("UPDATE requerimientos SET informe_pago_id = NULL, estado = 'recepcionado', updated_at = datetime('now') WHERE informe_pago_id = ?")
         .bind(informe_id)
         .execute(&mut *tx)
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
     
     // Eliminar informe
     sqlx::query("DELETE FROM informes_pago WHERE id = ?")
         .bind(informe_id)
         .execute(&mut *tx)
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
+    
+    auditoria::registrar_cambio(&mut tx, &db.operador(), Entidad::InformePago, informe_id, "eliminar", antes).await?;
+    auditoria::registrar_cambios(&mut tx, &db.operador(), Entidad::Requerimiento, "quitar_de_informe", antes_requerimientos).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Avanza el informe en su flujo (ver estados.rs). `fecha` (AAAA-MM-DD, por
+/// defecto hoy) queda como fecha de emisión, aprobación, pago o anulación;
+/// anular exige `motivo`.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn cambiar_estado_informe_pago(
+    db: State<'_, DbState>,
+    informe_id: i64,
+    estado: EstadoInforme,
+    fecha: Option<String>,
+    motivo: Option<String>,
+) -> Result<(), ErrorComando> {
+    let fecha = fecha.unwrap_or_else(|| chrono::Local::now().format("%Y-%m-%d").to_string());
+    if chrono::NaiveDate::parse_from_str(&fecha, "%Y-%m-%d").is_err() {
+        return Err(ErrorComando::validacion("fecha", format!("Fecha inválida '{}', se espera AAAA-MM-DD", fecha)));
+    }
+    let motivo = motivo.map(|m| m.trim().to_string()).filter(|m| !m.is_empty());
+    
+    let mut tx = db.pool().begin().await?;
+    estados::verificar_transicion_informe(&mut tx, informe_id, estado).await?;
+    let antes = auditoria::instantanea(&mut tx, Entidad::InformePago, informe_id).await?;
+    let lineas = ids_vinculados(&mut tx, "informe_pago_id", informe_id).await?;
+    let antes_lineas = auditoria::instantaneas(&mut tx, Entidad::Requerimiento, &lineas).await?;
+    
+    let (columna_fecha, accion) = match estado {
+        // Ningún estado vuelve a borrador (verificar_transicion_informe ya lo rechaza)
+        EstadoInforme::Borrador => {
+            return Err(ErrorComando::validacion("estado", "Un informe emitido no vuelve a borrador; anúlelo y cree otro"));
+        }
+        EstadoInforme::Emitido => {
+            if lineas.is_empty() {
+                return Err(ErrorComando::validacion("requerimientos", "No se puede emitir un informe sin requerimientos"));
+            }
+            ("fecha_emision", "emitir")
+        }
+        EstadoInforme::Aprobado => ("fecha_aprobacion", "aprobar"),
+        EstadoInforme::Pagado => {
+            for req_id in &lineas {
+                estados::verificar_transicion(&mut tx, *req_id, EstadoRequerimiento::EnInforme, EstadoRequerimiento::Pagado).await?;
+            }
+            sqlx::query("UPDATE requerimientos SET estado = 'pagado', updated_at = datetime('now') WHERE informe_pago_id = ?")
+                .bind(informe_id)
+                .execute(&mut *tx)
+                .await?;
+            ("fecha_pago", "pagar")
+        }
+        EstadoInforme::Anulado => {
+            let Some(motivo) = &motivo else {
+                return Err(ErrorComando::validacion("motivo", "Indique el motivo de la anulación"));
+            };
+            for req_id in &lineas {
+                estados::verificar_transicion(&mut tx, *req_id, EstadoRequerimiento::EnInforme, EstadoRequerimiento::Recepcionado).await?;
+            }
+            // Copia de las líneas tal como estaban, para auditoría
+            let detalle = consultas::listar(&mut tx, Vista::InformePago(informe_id)).await?;
+            let detalle = serde_json::to_string(&detalle).map_err(|e| ErrorComando::Internal { mensaje: e.to_string() })?;
+            sqlx::query("UPDATE informes_pago SET motivo_anulacion = ?, detalle_anulado = ? WHERE id = ?")
+                .bind(motivo)
+                .bind(detalle)
+                .bind(informe_id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("UPDATE requerimientos SET informe_pago_id = NULL, estado = 'recepcionado', updated_at = datetime('now') WHERE informe_pago_id = ?")
+                .bind(informe_id)
+                .execute(&mut *tx)
+                .await?;
+            ("fecha_anulacion", "anular")
+        }
+    };
+    
+    sqlx::query(&format!(
+        "UPDATE informes_pago SET estado = ?, {} = ?, updated_at = datetime('now') WHERE id = ?",
+        columna_fecha
+    ))
+    .bind(estado.as_str())
+    .bind(&fecha)
+    .bind(informe_id)
+    .execute(&mut *tx)
+    .await?;
+    
+    auditoria::registrar_cambio(&mut tx, &db.operador(), Entidad::InformePago, informe_id, accion, antes).await?;
+    // Solo pagar y anular cambian las líneas; las demás no dejan registro
+    auditoria::registrar_cambios(&mut tx, &db.operador(), Entidad::Requerimiento, &format!("informe_{}", accion), antes_lineas).await?;
+    tx.commit().await?;
     
-    tx.commit().await.map_err(|e| e.to_string())?;
+    println!("📋 Informe {} → {} ({})", informe_id, estado, fecha);
     Ok(())
 }
 
 // ========== CONFIGURACIÓN ==========
 
 #[tauri::command]
-pub async fn get_configuracion(db: State<'_, DbState>) -> Result<Configuracion, String> {
+pub async fn get_configuracion(db: State<'_, DbState>) -> Result<Configuracion, ErrorComando> {
     use base64::{Engine as _, engine::general_purpose};
     
-    let row = sqlx::query("SELECT * FROM configuracion_contrato WHERE id = 1")
-        .fetch_one(&*db.pool)
-        .await
-        .map_err(|e| e.to_string())?;
+    let mut conn = db.pool().acquire().await?;
+    let row = sqlx::query("SELECT * FROM contrato_activo")
+        .fetch_one(&mut *conn)
+        .await?;
+    // Las tasas que rigen hoy según el historial (las columnas del contrato
+    // son solo la base anterior a él)
+    let hoy = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let tasas = contratos::tasas_vigentes(&mut conn, &hoy).await?;
     
     // Obtener firma_png y convertir a base64 con data URI
     let firma_bytes: Option<Vec<u8>> = row.get("firma_png");
@@ -772,6 +939,9 @@ pub async fn get_configuracion(db: State<'_, DbState>) -> Result<Configuracion,
         prefijo_correlativo: row.get("prefijo_correlativo"),
         ito_nombre: row.get("ito_nombre"),
         ito_firma_base64,
+        porcentaje_gastos_generales: tasas.porcentaje_gastos_generales,
+        porcentaje_utilidades: tasas.porcentaje_utilidades,
+        porcentaje_iva: tasas.porcentaje_iva,
     })
 }
 
@@ -782,72 +952,236 @@ pub async fn update_configuracion(
     contratista: String,
     prefijo_correlativo: String,
     ito_nombre: Option<String>,
-) -> Result<(), String> {
+) -> Result<(), ErrorComando> {
+    let mut tx = db.pool().begin().await?;
+    let contrato_id = contratos::activo(&mut tx).await?;
+    let antes = auditoria::instantanea(&mut tx, Entidad::Configuracion, contrato_id).await?;
+    
     sqlx::query(
-        "UPDATE configuracion_contrato 
+        "UPDATE contratos 
          SET titulo = ?, contratista = ?, prefijo_correlativo = ?, ito_nombre = ?, updated_at = datetime('now') 
-         WHERE id = 1"
+         WHERE activo = 1"
     )
     .bind(&titulo)
     .bind(&contratista)
     .bind(&prefijo_correlativo)
     .bind(&ito_nombre)
-    .execute(&*db.pool)
-    .await
-    .map_err(|e| e.to_string())?;
+    .execute(&mut *tx)
+    .await?;
     
+    auditoria::registrar_cambio(&mut tx, &db.operador(), Entidad::Configuracion, contrato_id, "actualizar", antes).await?;
+    tx.commit().await?;
     Ok(())
 }
 
-// ========== UTILIDADES ==========
-
-#[tauri::command]
-pub async fn clear_all(db: State<'_, DbState>) -> Result<(), String> {
-    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+#[tauri::command(rename_all = "snake_case")]
+pub async fn update_tasas_contrato(
+    db: State<'_, DbState>,
+    porcentaje_gastos_generales: f64,
+    porcentaje_utilidades: f64,
+    porcentaje_iva: f64,
+    vigente_desde: Option<String>,
+) -> Result<(), ErrorComando> {
+    let vigente_desde = vigente_desde.unwrap_or_else(|| chrono::Local::now().format("%Y-%m-%d").to_string());
+    if chrono::NaiveDate::parse_from_str(&vigente_desde, "%Y-%m-%d").is_err() {
+        return Err(ErrorComando::validacion("vigente_desde", format!("Fecha inválida '{}', se espera AAAA-MM-DD", vigente_desde)));
+    }
+    for (campo, nombre, valor) in [
+        ("porcentaje_gastos_generales", "gastos generales", porcentaje_gastos_generales),
+        ("porcentaje_utilidades", "utilidades", porcentaje_utilidades),
+        ("porcentaje_iva", "IVA", porcentaje_iva),
+    ] {
+        if !(0.0..=100.0).contains(&valor) {
+            return Err(ErrorComando::validacion(
+                campo,
+                format!("Porcentaje de {} fuera de rango: {}", nombre, valor),
+            ));
+        }
+    }
     
-    sqlx::query("DELETE FROM informes_pago").execute(&mut *tx).await.map_err(|e| e.to_string())?;
-    sqlx::query("DELETE FROM requerimientos").execute(&mut *tx).await.map_err(|e| e.to_string())?;
-    sqlx::query("DELETE FROM ordenes_trabajo").execute(&mut *tx).await.map_err(|e| e.to_string())?;
-    sqlx::query("DELETE FROM recintos").execute(&mut *tx).await.map_err(|e| e.to_string())?;
-    sqlx::query("DELETE FROM partidas").execute(&mut *tx).await.map_err(|e| e.to_string())?;
-    sqlx::query("DELETE FROM jardines").execute(&mut *tx).await.map_err(|e| e.to_string())?;
+    let mut tx = db.pool().begin().await?;
+    let contrato_id = contratos::activo(&mut tx).await?;
     
-    tx.commit().await.map_err(|e| e.to_string())?;
+    // Solo el historial: las tasas de hoy y las de cada informe se derivan de
+    // él (contratos::tasas_vigentes), así una fecha futura rige al llegar y
+    // una retroactiva no pisa las posteriores
+    sqlx::query(
+        "INSERT INTO historial_tasas (contrato_id, vigente_desde, porcentaje_gastos_generales, porcentaje_utilidades, porcentaje_iva) 
+         VALUES (?, ?, ?, ?, ?)"
+    )
+    .bind(contrato_id)
+    .bind(&vigente_desde)
+    .bind(porcentaje_gastos_generales)
+    .bind(porcentaje_utilidades)
+    .bind(porcentaje_iva)
+    .execute(&mut *tx)
+    .await?;
+    
+    // La fila del contrato no cambia: se registra la entrada del historial
+    let cambios = serde_json::json!({
+        "vigente_desde": vigente_desde,
+        "porcentaje_gastos_generales": porcentaje_gastos_generales,
+        "porcentaje_utilidades": porcentaje_utilidades,
+        "porcentaje_iva": porcentaje_iva,
+    });
+    auditoria::registrar(&mut tx, &db.operador(), Entidad::Configuracion, Some(contrato_id), "actualizar_tasas", cambios).await?;
+    tx.commit().await?;
     Ok(())
 }
 
-// ========== IMPORTACIÓN ==========
+#[tauri::command]
+pub async fn get_historial_tasas(db: State<'_, DbState>) -> Result<Vec<HistorialTasas>, ErrorComando> {
+    sqlx::query_as::<_, HistorialTasas>(
+        "SELECT * FROM historial_tasas WHERE contrato_id = (SELECT id FROM contrato_activo) ORDER BY vigente_desde DESC, id DESC"
+    )
+    .fetch_all(&db.pool())
+    .await
+    .map_err(ErrorComando::from)
+}
 
-#[derive(serde::Deserialize)]
-struct CatalogoImport {
-    jardines: Option<Vec<serde_json::Value>>,
-    partidas: Option<Vec<serde_json::Value>>,
-    recintos: Option<Vec<serde_json::Value>>,
+// ========== MULTAS ==========
+
+#[tauri::command]
+pub async fn get_reglas_multa(db: State<'_, DbState>) -> Result<ReglaMulta, ErrorComando> {
+    let mut conn = db.pool().acquire().await?;
+    Ok(multas::cargar_regla(&mut conn).await?)
 }
 
-#[derive(serde::Deserialize)]
-struct BaseDatosCompleta {
-    jardines: Option<Vec<serde_json::Value>>,
-    partidas: Option<Vec<serde_json::Value>>,
-    recintos: Option<Vec<serde_json::Value>>,
-    requerimientos: Option<Vec<serde_json::Value>>,
+/// Guarda la regla del contrato y recalcula las multas de los requerimientos
+/// que aún no están en un informe de pago. Devuelve cuántos se recalcularon.
+#[tauri::command]
+pub async fn update_reglas_multa(
+    db: State<'_, DbState>,
+    regla: ReglaMulta,
+) -> Result<u64, ErrorComando> {
+    regla.validar()?;
+    
+    let mut tx = db.pool().begin().await?;
+    let contrato_id = contratos::activo(&mut tx).await?;
+    let antes = auditoria::instantanea(&mut tx, Entidad::Configuracion, contrato_id).await?;
+    
+    sqlx::query(
+        "UPDATE contratos 
+         SET multa_modo = ?, multa_monto_diario = ?, multa_utm_diaria = ?, valor_utm = ?,
+             multa_porcentaje_diario = ?, multa_tope_porcentaje = ?, multa_dias_gracia = ?,
+             updated_at = datetime('now') 
+         WHERE activo = 1"
+    )
+    .bind(&regla.multa_modo)
+    .bind(regla.multa_monto_diario)
+    .bind(regla.multa_utm_diaria)
+    .bind(regla.valor_utm)
+    .bind(regla.multa_porcentaje_diario)
+    .bind(regla.multa_tope_porcentaje)
+    .bind(regla.multa_dias_gracia)
+    .execute(&mut *tx)
+    .await?;
+    
+    let recalculados = multas::recalcular_pendientes(&mut tx).await?;
+    auditoria::registrar_cambio(&mut tx, &db.operador(), Entidad::Configuracion, contrato_id, "actualizar_reglas_multa", antes).await?;
+    
+    tx.commit().await?;
+    Ok(recalculados)
+}
+
+/// Detalle del cálculo de multa guardado para un requerimiento
+#[tauri::command]
+pub async fn get_detalle_multa(
+    db: State<'_, DbState>,
+    id: i64,
+) -> Result<DetalleMulta, ErrorComando> {
+    let mut conn = db.pool().acquire().await?;
+    
+    let guardado: Option<Option<String>> = sqlx::query_scalar(
+        "SELECT multa_detalle FROM requerimientos WHERE id = ?"
+    )
+    .bind(id)
+    .fetch_optional(&mut *conn)
+    .await?;
+    
+    match guardado {
+        None => Err(ErrorComando::no_encontrado("Requerimiento", id)),
+        Some(Some(json)) => serde_json::from_str(&json).map_err(|e| ErrorComando::Internal { mensaje: e.to_string() }),
+        // Filas anteriores a las reglas configurables: calcular con la regla actual
+        Some(None) => {
+            let regla = multas::cargar_regla(&mut conn).await?;
+            let calendario = calendario::cargar(&mut conn).await?;
+            Ok(multas::detalle_requerimiento(&mut conn, &regla, &calendario, id).await?)
+        }
+    }
+}
+
+// ========== UTILIDADES ==========
+
+#[tauri::command]
+pub async fn clear_all(db: State<'_, DbState>) -> Result<(), ErrorComando> {
+    crear_respaldo(&db.pool(), &db.db_path(), "clear_all").await?;
+    
+    let mut tx = db.pool().begin().await?;
+    borrar_datos(&mut tx).await?;
+    auditoria::registrar(&mut tx, &db.operador(), Entidad::BaseDatos, None, "clear_all", serde_json::json!({})).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Borra los datos del contrato activo; los demás contratos no se tocan
+async fn borrar_datos(tx: &mut Transaction<'_, Sqlite>) -> Result<(), ErrorComando> {
+    // Recepciones y adjuntos de requerimientos, OTs e informes se borran por trigger;
+    // las versiones de precio, en cascada con las partidas
+    for tabla in ["requerimientos", "informes_pago", "ordenes_trabajo", "presupuesto_jardines", "recintos", "partidas", "reajustes", "jardines"] {
+        sqlx::query(&format!("DELETE FROM {} WHERE contrato_id = (SELECT id FROM contrato_activo)", tabla))
+            .execute(&mut **tx)
+            .await?;
+    }
+    Ok(())
+}
+
+// ========== IMPORTACIÓN ==========
+
+#[derive(serde::Deserialize)]
+struct BaseDatosCompleta {
+    jardines: Option<Vec<serde_json::Value>>,
+    partidas: Option<Vec<serde_json::Value>>,
+    recintos: Option<Vec<serde_json::Value>>,
+    requerimientos: Option<Vec<serde_json::Value>>,
     ordenes_trabajo: Option<Vec<serde_json::Value>>,
     informes_pago: Option<Vec<serde_json::Value>>,
     configuracion: Option<serde_json::Value>,
+    historial_tasas: Option<Vec<serde_json::Value>>,
+    reajustes: Option<Vec<serde_json::Value>>,
+    precios_partida: Option<Vec<serde_json::Value>>,
+    feriados: Option<Vec<serde_json::Value>>,
+    presupuesto_jardines: Option<Vec<serde_json::Value>>,
+    recepciones: Option<Vec<serde_json::Value>>,
+    adjuntos: Option<Vec<serde_json::Value>>,
+    archivos_adjuntos: Option<std::collections::HashMap<String, String>>,
 }
 
 #[tauri::command(rename_all = "snake_case")]
 pub async fn importar_base_datos_completa(
     db: State<'_, DbState>,
     json_str: String,
-) -> Result<String, String> {
-    let datos: BaseDatosCompleta = serde_json::from_str(&json_str)
-        .map_err(|e| format!("Error parseando JSON: {}", e))?;
-    
-    // 1. BORRAR TODO
-    clear_all(db.clone()).await?;
+) -> Result<String, ErrorComando> {
+    crear_respaldo(&db.pool(), &db.db_path(), "importar_base_datos_completa").await?;
+    importar_base_datos(&db.pool(), &json_str, &db.operador(), &adjuntos::directorio(&db.db_path())).await
+}
+
+async fn importar_base_datos(
+    pool: &Pool<Sqlite>,
+    json_str: &str,
+    operador: &str,
+    dir_adjuntos: &Path,
+) -> Result<String, ErrorComando> {
+    let datos: BaseDatosCompleta = serde_json::from_str(json_str)
+        .map_err(|e| ErrorComando::importacion(None, None, format!("Error parseando JSON: {}", e)))?;
+    
+    let mut tx = pool.begin().await?;
+    // El respaldo se restaura en el contrato activo
+    let contrato_id = contratos::activo(&mut tx).await?;
+    
+    // 1. BORRAR TODO (en la misma transacción: si la importación falla, no se pierde nada)
+    borrar_datos(&mut tx).await?;
     
-    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
     let mut counts = (0, 0, 0, 0, 0, 0);
     
     // 2. IMPORTAR JARDINES
@@ -857,9 +1191,11 @@ pub async fn importar_base_datos_completa(
                 j.get("codigo").and_then(|v| v.as_str()),
                 j.get("nombre").and_then(|v| v.as_str())
             ) {
-                sqlx::query("INSERT OR IGNORE INTO jardines (codigo, nombre) VALUES (?, ?)")
-                    .bind(codigo).bind(nombre)
-                    .execute(&mut *tx).await.map_err(|e| e.to_string())?;
+                // Respaldos anteriores a la desactivación: todo activo
+                let activo = j.get("activo").and_then(|v| v.as_bool()).unwrap_or(true);
+                sqlx::query("INSERT OR IGNORE INTO jardines (contrato_id, codigo, nombre, activo) VALUES (?, ?, ?, ?)")
+                    .bind(contrato_id).bind(codigo).bind(nombre).bind(activo)
+                    .execute(&mut *tx).await?;
                 counts.0 += 1;
             }
         }
@@ -874,12 +1210,65 @@ pub async fn importar_base_datos_completa(
             ) {
                 let unidad = p.get("unidad").and_then(|v| v.as_str());
                 let precio = p.get("precioUnitario").or(p.get("precio_unitario")).and_then(|v| v.as_f64()).unwrap_or(0.0);
-                sqlx::query("INSERT OR IGNORE INTO partidas (item, partida, unidad, precio_unitario) VALUES (?, ?, ?, ?)")
-                    .bind(item).bind(partida).bind(unidad).bind(precio)
-                    .execute(&mut *tx).await.map_err(|e| e.to_string())?;
+                let exenta_iva = p.get("exentaIva").or(p.get("exenta_iva")).and_then(|v| v.as_bool()).unwrap_or(false);
+                let activo = p.get("activo").and_then(|v| v.as_bool()).unwrap_or(true);
+                sqlx::query("INSERT OR IGNORE INTO partidas (contrato_id, item, partida, unidad, precio_unitario, exenta_iva, activo) VALUES (?, ?, ?, ?, ?, ?, ?)")
+                    .bind(contrato_id).bind(item).bind(partida).bind(unidad).bind(precio).bind(exenta_iva).bind(activo)
+                    .execute(&mut *tx).await?;
                 counts.1 += 1;
             }
         }
+        // Respaldos anteriores al historial de precios: una versión inicial por partida
+        if datos.precios_partida.is_none() {
+            precios::registrar_iniciales(&mut tx, contrato_id).await?;
+        }
+    }
+    
+    // 3b. IMPORTAR REAJUSTES Y VERSIONES DE PRECIO (el reajuste se identifica por su vigencia)
+    for r in datos.reajustes.iter().flatten() {
+        let (Some(vigente_desde), Some(tipo), Some(porcentaje)) = (
+            r.get("vigente_desde").and_then(|v| v.as_str()),
+            r.get("tipo").and_then(|v| v.as_str()),
+            r.get("porcentaje").and_then(|v| v.as_f64()),
+        ) else {
+            continue;
+        };
+        sqlx::query(
+            "INSERT INTO reajustes (contrato_id, vigente_desde, tipo, porcentaje, indice_base, indice_nuevo, descripcion, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, COALESCE(?, datetime('now')))"
+        )
+        .bind(contrato_id)
+        .bind(vigente_desde)
+        .bind(tipo)
+        .bind(porcentaje)
+        .bind(r.get("indice_base").and_then(|v| v.as_f64()))
+        .bind(r.get("indice_nuevo").and_then(|v| v.as_f64()))
+        .bind(r.get("descripcion").and_then(|v| v.as_str()))
+        .bind(r.get("created_at").and_then(|v| v.as_str()))
+        .execute(&mut *tx).await?;
+    }
+    for pp in datos.precios_partida.iter().flatten() {
+        let (Some(partida_item), Some(vigente_desde), Some(precio_unitario)) = (
+            pp.get("partida_item").and_then(|v| v.as_str()),
+            pp.get("vigente_desde").and_then(|v| v.as_str()),
+            pp.get("precio_unitario").and_then(|v| v.as_f64()),
+        ) else {
+            continue;
+        };
+        sqlx::query(
+            "INSERT INTO precios_partida (partida_id, vigente_desde, precio_unitario, reajuste_id, created_at)
+             SELECT p.id, ?2, ?3,
+                    (SELECT id FROM reajustes WHERE contrato_id = ?1 AND vigente_desde = ?4),
+                    COALESCE(?5, datetime('now'))
+             FROM partidas p WHERE p.contrato_id = ?1 AND p.item = ?6"
+        )
+        .bind(contrato_id)
+        .bind(vigente_desde)
+        .bind(precio_unitario)
+        .bind(pp.get("reajuste_vigente_desde").and_then(|v| v.as_str()))
+        .bind(pp.get("created_at").and_then(|v| v.as_str()))
+        .bind(partida_item)
+        .execute(&mut *tx).await?;
     }
     
     // 4. IMPORTAR RECINTOS
@@ -889,9 +1278,10 @@ pub async fn importar_base_datos_completa(
                 r.get("jardinCodigo").or(r.get("jardin_codigo")).and_then(|v| v.as_str()),
                 r.get("nombre").and_then(|v| v.as_str())
             ) {
-                sqlx::query("INSERT INTO recintos (jardin_codigo, nombre) VALUES (?, ?)")
-                    .bind(jardin_codigo).bind(nombre)
-                    .execute(&mut *tx).await.map_err(|e| e.to_string())?;
+                let activo = r.get("activo").and_then(|v| v.as_bool()).unwrap_or(true);
+                sqlx::query("INSERT INTO recintos (contrato_id, jardin_codigo, nombre, activo) VALUES (?, ?, ?, ?)")
+                    .bind(contrato_id).bind(jardin_codigo).bind(nombre).bind(activo)
+                    .execute(&mut *tx).await?;
                 counts.2 += 1;
             }
         }
@@ -908,9 +1298,9 @@ pub async fn importar_base_datos_completa(
                 ot.get("fechaCreacion").or(ot.get("fecha_creacion")).and_then(|v| v.as_str())
             ) {
                 let observaciones = ot.get("observaciones").and_then(|v| v.as_str());
-                let result = sqlx::query("INSERT INTO ordenes_trabajo (codigo, jardin_codigo, fecha_creacion, observaciones) VALUES (?, ?, ?, ?)")
-                    .bind(codigo).bind(jardin_codigo).bind(fecha_creacion).bind(observaciones)
-                    .execute(&mut *tx).await.map_err(|e| e.to_string())?;
+                let result = sqlx::query("INSERT INTO ordenes_trabajo (contrato_id, codigo, jardin_codigo, fecha_creacion, observaciones) VALUES (?, ?, ?, ?, ?)")
+                    .bind(contrato_id).bind(codigo).bind(jardin_codigo).bind(fecha_creacion).bind(observaciones)
+                    .execute(&mut *tx).await?;
                 
                 // Guardar código → ID en el mapa
                 ot_map.insert(codigo.to_string(), result.last_insert_rowid());
@@ -922,6 +1312,7 @@ pub async fn importar_base_datos_completa(
     // 6. IMPORTAR INFORMES DE PAGO (ANTES de requerimientos)
     // ✅ Crear mapa de código → ID para resolver referencias
     let mut informe_map: std::collections::HashMap<String, i64> = std::collections::HashMap::new();
+    let mut informes_sin_estado: Vec<i64> = Vec::new();
     if let Some(informes) = datos.informes_pago {
         for inf in informes {
             if let (Some(codigo), Some(jardin_codigo), Some(fecha_creacion)) = (
@@ -933,15 +1324,36 @@ pub async fn importar_base_datos_completa(
                 let utilidades = inf.get("utilidades").and_then(|v| v.as_f64()).unwrap_or(0.0);
                 let iva = inf.get("iva").and_then(|v| v.as_f64()).unwrap_or(0.0);
                 let total_final = inf.get("totalFinal").or(inf.get("total_final")).and_then(|v| v.as_f64()).unwrap_or(0.0);
+                let neto_exento = inf.get("netoExento").or(inf.get("neto_exento")).and_then(|v| v.as_f64()).unwrap_or(0.0);
+                let gastos_generales = inf.get("gastosGenerales").or(inf.get("gastos_generales")).and_then(|v| v.as_f64()).unwrap_or(0.0);
+                // Respaldos anteriores a las tasas configurables: 0% GG, 10% utilidades, 19% IVA
+                let pct_gg = inf.get("porcentajeGastosGenerales").or(inf.get("porcentaje_gastos_generales")).and_then(|v| v.as_f64()).unwrap_or(0.0);
+                let pct_utilidades = inf.get("porcentajeUtilidades").or(inf.get("porcentaje_utilidades")).and_then(|v| v.as_f64()).unwrap_or(10.0);
+                let pct_iva = inf.get("porcentajeIva").or(inf.get("porcentaje_iva")).and_then(|v| v.as_f64()).unwrap_or(19.0);
                 let observaciones = inf.get("observaciones").and_then(|v| v.as_str());
+                let estado = inf.get("estado").and_then(|v| v.as_str()).map(EstadoInforme::parse).transpose()?;
+                let texto = |campo: &str| inf.get(campo).and_then(|v| v.as_str());
                 
-                let result = sqlx::query("INSERT INTO informes_pago (codigo, jardin_codigo, fecha_creacion, neto, utilidades, iva, total_final, observaciones) VALUES (?, ?, ?, ?, ?, ?, ?, ?)")
-                    .bind(codigo).bind(jardin_codigo).bind(fecha_creacion)
-                    .bind(neto).bind(utilidades).bind(iva).bind(total_final).bind(observaciones)
-                    .execute(&mut *tx).await.map_err(|e| e.to_string())?;
+                let result = sqlx::query(
+                    "INSERT INTO informes_pago 
+                     (contrato_id, codigo, jardin_codigo, fecha_creacion, neto, neto_exento, gastos_generales, utilidades, iva, total_final,
+                      porcentaje_gastos_generales, porcentaje_utilidades, porcentaje_iva, observaciones,
+                      estado, fecha_emision, fecha_aprobacion, fecha_pago, fecha_anulacion, motivo_anulacion, detalle_anulado) 
+                     VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+                )
+                    .bind(contrato_id).bind(codigo).bind(jardin_codigo).bind(fecha_creacion)
+                    .bind(neto).bind(neto_exento).bind(gastos_generales).bind(utilidades).bind(iva).bind(total_final)
+                    .bind(pct_gg).bind(pct_utilidades).bind(pct_iva).bind(observaciones)
+                    .bind(estado.unwrap_or(EstadoInforme::Borrador).as_str())
+                    .bind(texto("fecha_emision")).bind(texto("fecha_aprobacion")).bind(texto("fecha_pago"))
+                    .bind(texto("fecha_anulacion")).bind(texto("motivo_anulacion")).bind(texto("detalle_anulado"))
+                    .execute(&mut *tx).await?;
                 
                 // Guardar código → ID en el mapa
                 informe_map.insert(codigo.to_string(), result.last_insert_rowid());
+                if estado.is_none() {
+                    informes_sin_estado.push(result.last_insert_rowid());
+                }
                 counts.5 += 1;
             }
         }
@@ -949,8 +1361,12 @@ pub async fn importar_base_datos_completa(
     
     // 7. IMPORTAR REQUERIMIENTOS (AL FINAL, después de OTs e Informes)
     // ✅ Mapear códigos → IDs usando los mapas creados arriba
+    let mut informados_sin_multa: Vec<i64> = Vec::new();
+    // Posición en el respaldo → ID nuevo (los adjuntos referencian requerimientos por posición)
+    let mut requerimiento_ids: Vec<Option<i64>> = Vec::new();
     if let Some(requerimientos) = datos.requerimientos {
         for req in requerimientos {
+            requerimiento_ids.push(None);
             let jardin_codigo = req.get("jardinCodigo").or(req.get("jardin_codigo")).and_then(|v| v.as_str());
             let partida_item = req.get("partidaItem").or(req.get("partida_item")).and_then(|v| v.as_str());
             
@@ -961,7 +1377,6 @@ pub async fn importar_base_datos_completa(
                 let precio_total = req.get("precioTotal").or(req.get("precio_total")).and_then(|v| v.as_f64()).unwrap_or(cantidad * precio_unitario);
                 let fecha_inicio = req.get("fechaInicio").or(req.get("fecha_inicio")).and_then(|v| v.as_str()).unwrap_or("");
                 let fecha_registro = req.get("fechaRegistro").or(req.get("fecha_registro")).and_then(|v| v.as_str()).unwrap_or("");
-                let estado = req.get("estado").and_then(|v| v.as_str()).unwrap_or("pendiente");
                 
                 // ✅ Mapear código de OT → ID (SOLO códigos, ignorar IDs numéricos)
                 let ot_id = req.get("ot_codigo")
@@ -980,368 +1395,904 @@ pub async fn importar_base_datos_completa(
                 let descripcion = req.get("descripcion").and_then(|v| v.as_str());
                 let observaciones = req.get("observaciones").and_then(|v| v.as_str());
                 let fecha_recepcion = req.get("fechaRecepcion").or(req.get("fecha_recepcion")).and_then(|v| v.as_str());
+                let estado = match EstadoRequerimiento::parse(req.get("estado").and_then(|v| v.as_str()).unwrap_or("pendiente"))? {
+                    estado @ (EstadoRequerimiento::Recepcionado | EstadoRequerimiento::Pagado | EstadoRequerimiento::Anulado) => estado,
+                    // Respaldos anteriores a la máquina de estados: se deduce de los vínculos
+                    _ => EstadoRequerimiento::desde_vinculos(ot_id.is_some(), fecha_recepcion.is_some(), informe_pago_id.is_some()),
+                };
+                let fecha_limite_campo = req.get("fechaLimite").or(req.get("fecha_limite"));
+                let fecha_limite = fecha_limite_campo.and_then(|v| v.as_str());
+                let dias_atraso = req.get("diasAtraso").or(req.get("dias_atraso")).and_then(|v| v.as_i64()).unwrap_or(0);
+                let multa = req.get("multa").and_then(|v| v.as_f64());
+                let multa_detalle = req.get("multaDetalle").or(req.get("multa_detalle")).and_then(|v| v.as_str());
+                let cantidad_separada = req.get("cantidad_separada").and_then(|v| v.as_f64()).unwrap_or(0.0);
+                // Versión de precio de su partida, por vigencia (sin ella: precio manual o importado)
+                let precio_vigente_desde = req.get("precio_vigente_desde").and_then(|v| v.as_str());
                 
-                sqlx::query(
+                let result = sqlx::query(
                     "INSERT INTO requerimientos 
-                     (jardin_codigo, recinto, partida_item, cantidad, precio_unitario, precio_total,
-                      fecha_inicio, fecha_registro, estado, ot_id, informe_pago_id, plazo_dias, plazo_adicional, descripcion, observaciones, fecha_recepcion)
-                     VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+                     (contrato_id, jardin_codigo, recinto, partida_item, cantidad, precio_unitario, precio_total,
+                      fecha_inicio, fecha_registro, estado, ot_id, informe_pago_id, plazo_dias, plazo_adicional, descripcion, observaciones, fecha_recepcion,
+                      fecha_limite, dias_atraso, multa, multa_detalle, cantidad_separada, precio_partida_id)
+                     VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?,
+                             (SELECT pp.id FROM precios_partida pp JOIN partidas p ON p.id = pp.partida_id
+                              WHERE p.contrato_id = ? AND p.item = ? AND pp.vigente_desde = ?))"
                 )
-                .bind(jc).bind(recinto).bind(pi).bind(cantidad).bind(precio_unitario).bind(precio_total)
-                .bind(fecha_inicio).bind(fecha_registro).bind(estado).bind(ot_id).bind(informe_pago_id)
+                .bind(contrato_id).bind(jc).bind(recinto).bind(pi).bind(cantidad).bind(precio_unitario).bind(precio_total)
+                .bind(fecha_inicio).bind(fecha_registro).bind(estado.as_str()).bind(ot_id).bind(informe_pago_id)
                 .bind(plazo_dias).bind(plazo_adicional).bind(descripcion).bind(observaciones).bind(fecha_recepcion)
-                .execute(&mut *tx).await.map_err(|e| e.to_string())?;
+                .bind(fecha_limite).bind(dias_atraso).bind(multa.unwrap_or(0.0)).bind(multa_detalle).bind(cantidad_separada)
+                .bind(contrato_id).bind(pi).bind(precio_vigente_desde)
+                .execute(&mut *tx).await?;
+                if let Some(posicion) = requerimiento_ids.last_mut() {
+                    *posicion = Some(result.last_insert_rowid());
+                }
+                
+                // Los informados conservan la multa con que se pagaron; si el respaldo no la trae, se calcula
+                if informe_pago_id.is_some() && multa.is_none() {
+                    informados_sin_multa.push(result.last_insert_rowid());
+                } else if informe_pago_id.is_some() && fecha_limite_campo.is_none() {
+                    // Respaldos anteriores al calendario: la fecha límite era en días corridos
+                    sqlx::query(
+                        "UPDATE requerimientos 
+                         SET fecha_limite = date(fecha_inicio, '+' || plazo_total || ' days') 
+                         WHERE id = ? AND plazo_total > 0"
+                    )
+                    .bind(result.last_insert_rowid())
+                    .execute(&mut *tx).await?;
+                }
                 counts.3 += 1;
             }
         }
     }
     
-    // 8. IMPORTAR CONFIGURACIÓN
+    // Respaldos anteriores al estado de informes: pagado si todas sus líneas lo están
+    for informe_id in informes_sin_estado {
+        sqlx::query(
+            "UPDATE informes_pago SET estado = 'pagado'
+             WHERE id = ?1
+               AND EXISTS (SELECT 1 FROM requerimientos WHERE informe_pago_id = ?1)
+               AND NOT EXISTS (SELECT 1 FROM requerimientos WHERE informe_pago_id = ?1 AND estado <> 'pagado')"
+        )
+        .bind(informe_id)
+        .execute(&mut *tx).await?;
+    }
+    
+    // 8. IMPORTAR RECEPCIONES (referencian requerimientos por posición)
+    let mut recepciones_importadas = 0;
+    for r in datos.recepciones.iter().flatten() {
+        let requerimiento_id = r.get("requerimiento")
+            .and_then(|v| v.as_u64())
+            .and_then(|i| requerimiento_ids.get(i as usize).copied().flatten());
+        let (Some(requerimiento_id), Some(fecha), Some(cantidad)) = (
+            requerimiento_id,
+            r.get("fecha").and_then(|v| v.as_str()),
+            r.get("cantidad").and_then(|v| v.as_f64()),
+        ) else {
+            continue;
+        };
+        sqlx::query(
+            "INSERT INTO recepciones (requerimiento_id, fecha, cantidad, observaciones, dias_atraso, multa, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, COALESCE(?, datetime('now')))"
+        )
+        .bind(requerimiento_id)
+        .bind(fecha)
+        .bind(cantidad)
+        .bind(r.get("observaciones").and_then(|v| v.as_str()))
+        .bind(r.get("dias_atraso").and_then(|v| v.as_i64()).unwrap_or(0))
+        .bind(r.get("multa").and_then(|v| v.as_f64()).unwrap_or(0.0))
+        .bind(r.get("created_at").and_then(|v| v.as_str()))
+        .execute(&mut *tx).await?;
+        recepciones_importadas += 1;
+    }
+    // Respaldos anteriores a las recepciones parciales: la fecha de recepción es una entrega por el total
+    sqlx::query(
+        "INSERT INTO recepciones (requerimiento_id, fecha, cantidad, dias_atraso, multa)
+         SELECT id, fecha_recepcion, cantidad, dias_atraso, COALESCE(multa, 0) FROM requerimientos r
+         WHERE fecha_recepcion IS NOT NULL AND NOT EXISTS (SELECT 1 FROM recepciones WHERE requerimiento_id = r.id)"
+    )
+    .execute(&mut *tx).await?;
+    sqlx::query(
+        "UPDATE requerimientos 
+         SET cantidad_recibida = (SELECT COALESCE(SUM(cantidad), 0) FROM recepciones WHERE requerimiento_id = requerimientos.id)"
+    )
+    .execute(&mut *tx).await?;
+    
+    // 9. IMPORTAR CONFIGURACIÓN
     if let Some(config) = datos.configuracion {
         let titulo = config.get("titulo").and_then(|v| v.as_str()).unwrap_or("");
         let contratista = config.get("contratista").and_then(|v| v.as_str()).unwrap_or("");
         let prefijo = config.get("prefijo_correlativo").and_then(|v| v.as_str()).unwrap_or("");
-        let ito_nombre = config.get("ito_nombre").and_then(|v| v.as_str()).unwrap_or("");
+        let ito_nombre = config.get("ito_nombre").and_then(|v| v.as_str());
         
         sqlx::query(
-            "UPDATE configuracion_contrato 
+            "UPDATE contratos 
              SET titulo = ?, contratista = ?, prefijo_correlativo = ?, ito_nombre = ?, updated_at = datetime('now') 
-             WHERE id = 1"
+             WHERE activo = 1"
         )
         .bind(titulo)
         .bind(contratista)
         .bind(prefijo)
         .bind(ito_nombre)
-        .execute(&mut *tx).await.map_err(|e| e.to_string())?;
+        .execute(&mut *tx).await?;
+        
+        // Tasas del contrato (se mantienen las actuales si el respaldo no las trae)
+        sqlx::query(
+            "UPDATE contratos 
+             SET porcentaje_gastos_generales = COALESCE(?, porcentaje_gastos_generales),
+                 porcentaje_utilidades = COALESCE(?, porcentaje_utilidades),
+                 porcentaje_iva = COALESCE(?, porcentaje_iva)
+             WHERE activo = 1"
+        )
+        .bind(config.get("porcentaje_gastos_generales").and_then(|v| v.as_f64()))
+        .bind(config.get("porcentaje_utilidades").and_then(|v| v.as_f64()))
+        .bind(config.get("porcentaje_iva").and_then(|v| v.as_f64()))
+        .execute(&mut *tx).await?;
+        
+        // Los informes toman las tasas del historial: las importadas rigen desde hoy
+        // (si el respaldo trae su historial, el paso 10 lo reemplaza)
+        sqlx::query(
+            "INSERT INTO historial_tasas (contrato_id, vigente_desde, porcentaje_gastos_generales, porcentaje_utilidades, porcentaje_iva)
+             SELECT id, date('now'), porcentaje_gastos_generales, porcentaje_utilidades, porcentaje_iva FROM contratos WHERE id = ?"
+        )
+        .bind(contrato_id)
+        .execute(&mut *tx).await?;
         
-        // Importar firma PNG si existe
-        if let Some(firma_base64) = config.get("firma_png_base64").and_then(|v| v.as_str()) {
+        // Regla de multa (solo respaldos que la incluyen completa)
+        if let Ok(regla) = serde_json::from_value::<ReglaMulta>(config.clone()) {
+            regla.validar().map_err(|e| ErrorComando::importacion(None, None, e.to_string()))?;
+            sqlx::query(
+                "UPDATE contratos 
+                 SET multa_modo = ?, multa_monto_diario = ?, multa_utm_diaria = ?, valor_utm = ?,
+                     multa_porcentaje_diario = ?, multa_tope_porcentaje = ?, multa_dias_gracia = ?
+                 WHERE activo = 1"
+            )
+            .bind(&regla.multa_modo)
+            .bind(regla.multa_monto_diario)
+            .bind(regla.multa_utm_diaria)
+            .bind(regla.valor_utm)
+            .bind(regla.multa_porcentaje_diario)
+            .bind(regla.multa_tope_porcentaje)
+            .bind(regla.multa_dias_gracia)
+            .execute(&mut *tx).await?;
+        }
+        
+        // Calendario de plazos (solo respaldos que lo incluyen)
+        if let Ok(cal) = serde_json::from_value::<ConfiguracionCalendario>(config.clone()) {
+            cal.validar().map_err(|e| ErrorComando::importacion(None, None, e.to_string()))?;
+            sqlx::query("UPDATE contratos SET tipo_plazo = ?, dias_no_habiles = ? WHERE activo = 1")
+                .bind(&cal.tipo_plazo)
+                .bind(&cal.dias_no_habiles)
+                .execute(&mut *tx).await?;
+        }
+        
+        // Monto y vigencia del contrato (solo respaldos que los incluyen)
+        if let Ok(contrato) = serde_json::from_value::<DatosContrato>(config.clone()) {
+            contrato.validar().map_err(|e| ErrorComando::importacion(None, None, e.to_string()))?;
+            sqlx::query(
+                "UPDATE contratos 
+                 SET monto_contratado = ?, fecha_inicio = ?, fecha_termino = ?, umbral_alerta = ?
+                 WHERE activo = 1"
+            )
+            .bind(contrato.monto_contratado)
+            .bind(&contrato.fecha_inicio)
+            .bind(&contrato.fecha_termino)
+            .bind(contrato.umbral_alerta)
+            .execute(&mut *tx).await?;
+        }
+        
+        // Importar firma PNG si existe (null explícito = sin firma)
+        if let Some(firma) = config.get("firma_png_base64") {
             use base64::{Engine as _, engine::general_purpose};
-            if let Ok(firma_bytes) = general_purpose::STANDARD.decode(firma_base64) {
-                sqlx::query("UPDATE configuracion_contrato SET firma_png = ?, updated_at = datetime('now') WHERE id = 1")
-                    .bind(firma_bytes)
-                    .execute(&mut *tx).await.map_err(|e| e.to_string())?;
-            }
+            let firma_bytes = match firma.as_str() {
+                Some(firma_base64) => Some(
+                    general_purpose::STANDARD
+                        .decode(firma_base64)
+                        .map_err(|e| ErrorComando::importacion(None, None, format!("Error decodificando firma: {}", e)))?,
+                ),
+                None => None,
+            };
+            sqlx::query("UPDATE contratos SET firma_png = ?, updated_at = datetime('now') WHERE activo = 1")
+                .bind(firma_bytes)
+                .execute(&mut *tx).await?;
         }
     }
     
-    tx.commit().await.map_err(|e| e.to_string())?;
-    Ok(format!("✅ Importado: {} jardines, {} partidas, {} recintos, {} requerimientos, {} OTs, {} informes", 
-        counts.0, counts.1, counts.2, counts.3, counts.4, counts.5))
-}
-
-#[tauri::command(rename_all = "snake_case")]
-pub async fn importar_catalogo_json(
-    db: State<'_, DbState>,
-    json_str: String,
-) -> Result<String, String> {
-    let catalogo: CatalogoImport = serde_json::from_str(&json_str)
-        .map_err(|e| format!("Error parseando JSON: {}", e))?;
-    
-    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
-    let mut count = 0;
-    
-    // Importar jardines
-    if let Some(jardines) = catalogo.jardines {
-        for j in jardines {
-            if let (Some(codigo), Some(nombre)) = (
-                j.get("codigo").and_then(|v| v.as_str()),
-                j.get("nombre").and_then(|v| v.as_str())
+    // Presupuesto por jardín (jardines ya importados)
+    if let Some(presupuestos) = datos.presupuesto_jardines {
+        for p in presupuestos {
+            if let (Some(jardin_codigo), Some(monto)) = (
+                p.get("jardin_codigo").and_then(|v| v.as_str()),
+                p.get("monto").and_then(|v| v.as_f64()),
             ) {
-                sqlx::query("INSERT OR IGNORE INTO jardines (codigo, nombre) VALUES (?, ?)")
-                    .bind(codigo)
-                    .bind(nombre)
-                    .execute(&mut *tx)
-                    .await
-                    .map_err(|e| e.to_string())?;
-                count += 1;
+                sqlx::query(
+                    "INSERT OR REPLACE INTO presupuesto_jardines (contrato_id, jardin_codigo, monto) 
+                     SELECT contrato_id, codigo, ? FROM jardines WHERE contrato_id = ? AND codigo = ?"
+                )
+                .bind(monto)
+                .bind(contrato_id)
+                .bind(jardin_codigo)
+                .execute(&mut *tx).await?;
             }
         }
     }
     
-    // Importar partidas
-    if let Some(partidas) = catalogo.partidas {
-        for p in partidas {
-            if let (Some(item), Some(partida)) = (
-                p.get("item").and_then(|v| v.as_str()),
-                p.get("partida").and_then(|v| v.as_str())
-            ) {
-                let unidad = p.get("unidad").and_then(|v| v.as_str());
-                let precio = p.get("precio_unitario").and_then(|v| v.as_f64()).unwrap_or(0.0);
-                
-                sqlx::query("INSERT OR IGNORE INTO partidas (item, partida, unidad, precio_unitario) VALUES (?, ?, ?, ?)")
-                    .bind(item)
-                    .bind(partida)
-                    .bind(unidad)
-                    .bind(precio)
-                    .execute(&mut *tx)
-                    .await
-                    .map_err(|e| e.to_string())?;
-                count += 1;
+    // 10. IMPORTAR HISTORIAL DE TASAS
+    if let Some(historial) = datos.historial_tasas {
+        sqlx::query("DELETE FROM historial_tasas WHERE contrato_id = ?").bind(contrato_id).execute(&mut *tx).await?;
+        for h in historial {
+            if let Some(vigente_desde) = h.get("vigente_desde").and_then(|v| v.as_str()) {
+                sqlx::query(
+                    "INSERT INTO historial_tasas (contrato_id, vigente_desde, porcentaje_gastos_generales, porcentaje_utilidades, porcentaje_iva) 
+                     VALUES (?, ?, ?, ?, ?)"
+                )
+                .bind(contrato_id)
+                .bind(vigente_desde)
+                .bind(h.get("porcentaje_gastos_generales").and_then(|v| v.as_f64()).unwrap_or(0.0))
+                .bind(h.get("porcentaje_utilidades").and_then(|v| v.as_f64()).unwrap_or(10.0))
+                .bind(h.get("porcentaje_iva").and_then(|v| v.as_f64()).unwrap_or(19.0))
+                .execute(&mut *tx).await?;
             }
         }
     }
     
-    // Importar recintos
-    if let Some(recintos) = catalogo.recintos {
-        for r in recintos {
-            if let (Some(jardin_codigo), Some(nombre)) = (
-                r.get("jardin_codigo").and_then(|v| v.as_str()),
-                r.get("nombre").and_then(|v| v.as_str())
+    // 11. IMPORTAR FERIADOS
+    // La tabla es común a todos los contratos: se agregan o actualizan, nunca se
+    // borran los que el respaldo no trae. Solo se recalcula el contrato activo
+    // (paso 13); los demás se recalculan al activarlos (activar_contrato).
+    if let Some(feriados) = datos.feriados {
+        for f in feriados {
+            if let (Some(texto), Some(nombre)) = (
+                f.get("fecha").and_then(|v| v.as_str()),
+                f.get("nombre").and_then(|v| v.as_str()),
             ) {
-                sqlx::query("INSERT INTO recintos (jardin_codigo, nombre) VALUES (?, ?)")
-                    .bind(jardin_codigo)
+                let fecha = calendario::parse_fecha(texto)
+                    .ok_or_else(|| ErrorComando::importacion(None, None, format!("Feriado con fecha no válida: {}", texto)))?;
+                sqlx::query("INSERT OR REPLACE INTO feriados (fecha, nombre) VALUES (?, ?)")
+                    .bind(fecha.format("%Y-%m-%d").to_string())
                     .bind(nombre)
-                    .execute(&mut *tx)
-                    .await
-                    .map_err(|e| e.to_string())?;
-                count += 1;
+                    .execute(&mut *tx).await?;
             }
         }
     }
     
-    tx.commit().await.map_err(|e| e.to_string())?;
-    Ok(format!("{} registros importados", count))
-}
-
-#[tauri::command(rename_all = "snake_case")]
-pub async fn importar_catalogo_csv(
-    db: State<'_, DbState>,
-    csv_str: String,
-    tipo: String,
-) -> Result<String, String> {
-    let mut rdr = csv::Reader::from_reader(csv_str.as_bytes());
-    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
-    let mut count = 0;
-    
-    match tipo.as_str() {
-        "jardines" => {
-            for result in rdr.records() {
-                let record = result.map_err(|e| e.to_string())?;
-                if record.len() >= 2 {
-                    sqlx::query("INSERT OR IGNORE INTO jardines (codigo, nombre) VALUES (?, ?)")
-                        .bind(&record[0])
-                        .bind(&record[1])
-                        .execute(&mut *tx)
-                        .await
-                        .map_err(|e| e.to_string())?;
-                    count += 1;
-                }
+    // 12. IMPORTAR ADJUNTOS: primero el contenido al almacén, luego las filas
+    let mut adjuntos_importados = 0;
+    if let Some(archivos) = datos.archivos_adjuntos {
+        use base64::{Engine as _, engine::general_purpose};
+        for (sha256, contenido) in archivos {
+            if !adjuntos::hash_valido(&sha256) {
+                return Err(ErrorComando::importacion(None, None, format!("Hash de adjunto no válido: '{}'", sha256)));
+            }
+            let bytes = general_purpose::STANDARD
+                .decode(&contenido)
+                .map_err(|e| ErrorComando::importacion(None, None, format!("Error decodificando adjunto {}: {}", sha256, e)))?;
+            if adjuntos::hash(&bytes) != sha256 {
+                return Err(ErrorComando::importacion(None, None, format!("El contenido del adjunto {} está dañado", sha256)));
             }
+            adjuntos::guardar(dir_adjuntos, &bytes)?;
         }
-        "partidas" => {
-            for result in rdr.records() {
-                let record = result.map_err(|e| e.to_string())?;
-                if record.len() >= 2 {
-                    let unidad = record.get(2).map(|s| s.to_string());
-                    let precio = record.get(3).and_then(|s| s.parse::<f64>().ok()).unwrap_or(0.0);
-                    
-                    sqlx::query("INSERT OR IGNORE INTO partidas (item, partida, unidad, precio_unitario) VALUES (?, ?, ?, ?)")
-                        .bind(&record[0])
-                        .bind(&record[1])
-                        .bind(unidad)
-                        .bind(precio)
-                        .execute(&mut *tx)
-                        .await
-                        .map_err(|e| e.to_string())?;
-                    count += 1;
-                }
+    }
+    if let Some(lista) = datos.adjuntos {
+        for a in lista {
+            let entidad = a.get("entidad").and_then(|v| v.as_str()).unwrap_or("");
+            let entidad_id = match entidad {
+                "requerimiento" => a.get("requerimiento")
+                    .and_then(|v| v.as_u64())
+                    .and_then(|i| requerimiento_ids.get(i as usize).copied().flatten()),
+                "orden_trabajo" => a.get("codigo").and_then(|v| v.as_str()).and_then(|c| ot_map.get(c)).copied(),
+                "informe_pago" => a.get("codigo").and_then(|v| v.as_str()).and_then(|c| informe_map.get(c)).copied(),
+                _ => None,
+            };
+            let (Some(entidad_id), Some(nombre), Some(sha256)) = (
+                entidad_id,
+                a.get("nombre").and_then(|v| v.as_str()),
+                a.get("sha256").and_then(|v| v.as_str()),
+            ) else {
+                continue;
+            };
+            if !adjuntos::hash_valido(sha256) {
+                return Err(ErrorComando::importacion(
+                    None,
+                    None,
+                    format!("El adjunto '{}' trae un hash no válido: '{}'", nombre, sha256),
+                ));
             }
+            let contenido = adjuntos::leer(dir_adjuntos, sha256)
+                .map_err(|_| ErrorComando::importacion(None, None, format!("Falta el contenido del adjunto '{}'", nombre)))?;
+            sqlx::query(
+                "INSERT INTO adjuntos (entidad, entidad_id, nombre, tipo_mime, tamano, sha256, etapa, descripcion, created_at)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, COALESCE(?, datetime('now')))"
+            )
+            .bind(entidad)
+            .bind(entidad_id)
+            .bind(nombre)
+            .bind(a.get("tipo_mime").and_then(|v| v.as_str()).unwrap_or_else(|| adjuntos::tipo_mime(nombre)))
+            .bind(contenido.len() as i64)
+            .bind(sha256)
+            .bind(a.get("etapa").and_then(|v| v.as_str()))
+            .bind(a.get("descripcion").and_then(|v| v.as_str()))
+            .bind(a.get("created_at").and_then(|v| v.as_str()))
+            .execute(&mut *tx).await?;
+            adjuntos_importados += 1;
         }
-        _ => return Err("Tipo de importación no válido".to_string()),
     }
     
-    tx.commit().await.map_err(|e| e.to_string())?;
-    Ok(format!("{} registros importados", count))
+    // 13. RECALCULAR MULTAS con la regla y el calendario ya importados
+    multas::recalcular_pendientes(&mut tx).await?;
+    for id in informados_sin_multa {
+        multas::recalcular_requerimiento(&mut tx, id).await?;
+    }
+    
+    let resumen = format!("✅ Importado: {} jardines, {} partidas, {} recintos, {} requerimientos, {} recepciones, {} OTs, {} informes, {} adjuntos", 
+        counts.0, counts.1, counts.2, counts.3, recepciones_importadas, counts.4, counts.5, adjuntos_importados);
+    auditoria::registrar(&mut tx, operador, Entidad::BaseDatos, None, "importar_base_datos_completa", serde_json::json!({ "resumen": resumen })).await?;
+    
+    tx.commit().await?;
+    Ok(resumen)
 }
 
-#[tauri::command(rename_all = "snake_case")]
-pub async fn importar_catalogo_xlsx(
-    db: State<'_, DbState>,
-    file_path: String,
-    sheet_name: String,
+// ========== EXPORTACIÓN ==========
+
+// Formato de respaldo completo: mismo contrato que `BaseDatosCompleta`.
+// Las referencias a OT/informe van por código (no por ID) para que el
+// importador las resuelva en una base vacía.
+
+#[derive(serde::Serialize, sqlx::FromRow)]
+struct JardinExport {
+    codigo: String,
+    nombre: String,
+    activo: bool,
+}
+
+#[derive(serde::Serialize, sqlx::FromRow)]
+struct PartidaExport {
+    item: String,
+    partida: String,
+    unidad: Option<String>,
+    precio_unitario: f64,
+    exenta_iva: bool,
+    activo: bool,
+}
+
+#[derive(serde::Serialize, sqlx::FromRow)]
+struct RecintoExport {
+    jardin_codigo: String,
+    nombre: String,
+    activo: bool,
+}
+
+#[derive(serde::Serialize, sqlx::FromRow)]
+struct OrdenTrabajoExport {
+    codigo: String,
+    jardin_codigo: String,
+    fecha_creacion: String,
+    observaciones: Option<String>,
+}
+
+#[derive(serde::Serialize, sqlx::FromRow)]
+struct InformePagoExport {
+    codigo: String,
+    jardin_codigo: String,
+    fecha_creacion: String,
+    neto: f64,
+    neto_exento: f64,
+    gastos_generales: f64,
+    utilidades: f64,
+    iva: f64,
+    total_final: f64,
+    porcentaje_gastos_generales: f64,
+    porcentaje_utilidades: f64,
+    porcentaje_iva: f64,
+    observaciones: Option<String>,
+    estado: String,
+    fecha_emision: Option<String>,
+    fecha_aprobacion: Option<String>,
+    fecha_pago: Option<String>,
+    fecha_anulacion: Option<String>,
+    motivo_anulacion: Option<String>,
+    /// Líneas al momento de anular (JSON), solo en informes anulados
+    detalle_anulado: Option<String>,
+}
+
+#[derive(serde::Serialize, sqlx::FromRow)]
+struct RequerimientoExport {
+    jardin_codigo: String,
+    recinto: Option<String>,
+    partida_item: String,
+    cantidad: f64,
+    precio_unitario: f64,
+    precio_total: f64,
+    fecha_inicio: String,
+    fecha_registro: String,
+    estado: String,
+    ot_codigo: Option<String>,
+    informe_codigo: Option<String>,
+    fecha_recepcion: Option<String>,
+    plazo_dias: i32,
+    plazo_adicional: i32,
+    fecha_limite: Option<String>,
+    dias_atraso: i32,
+    multa: f64,
+    multa_detalle: Option<String>,
+    descripcion: Option<String>,
+    observaciones: Option<String>,
+    cantidad_separada: f64,
+    /// Vigencia de la versión de precio de su partida; None = precio manual o importado
+    precio_vigente_desde: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+struct ConfiguracionExport {
+    titulo: String,
+    contratista: String,
+    prefijo_correlativo: String,
+    ito_nombre: Option<String>,
+    firma_png_base64: Option<String>,
+    porcentaje_gastos_generales: f64,
+    porcentaje_utilidades: f64,
+    porcentaje_iva: f64,
+    #[serde(flatten)]
+    regla_multa: ReglaMulta,
+    #[serde(flatten)]
+    calendario: ConfiguracionCalendario,
+    #[serde(flatten)]
+    contrato: DatosContrato,
+}
+
+#[derive(serde::Serialize, sqlx::FromRow)]
+struct PresupuestoJardinExport {
+    jardin_codigo: String,
+    monto: f64,
+}
+
+#[derive(serde::Serialize, sqlx::FromRow)]
+struct HistorialTasasExport {
+    vigente_desde: String,
+    porcentaje_gastos_generales: f64,
+    porcentaje_utilidades: f64,
+    porcentaje_iva: f64,
+}
+
+#[derive(serde::Serialize, sqlx::FromRow)]
+struct ReajusteExport {
+    vigente_desde: String,
     tipo: String,
-) -> Result<String, String> {
-    use calamine::{Reader, open_workbook, Xlsx};
+    porcentaje: f64,
+    indice_base: Option<f64>,
+    indice_nuevo: Option<f64>,
+    descripcion: Option<String>,
+    created_at: String,
+}
+
+#[derive(serde::Serialize, sqlx::FromRow)]
+struct PrecioPartidaExport {
+    partida_item: String,
+    vigente_desde: String,
+    precio_unitario: f64,
+    /// Vigencia del reajuste que la generó; None = precio inicial o de catálogo
+    reajuste_vigente_desde: Option<String>,
+    created_at: String,
+}
+
+#[derive(serde::Serialize, sqlx::FromRow)]
+struct RecepcionExport {
+    /// Posición en `requerimientos`
+    #[sqlx(skip)]
+    requerimiento: usize,
+    #[serde(skip)]
+    requerimiento_id: i64,
+    fecha: String,
+    cantidad: f64,
+    observaciones: Option<String>,
+    dias_atraso: i64,
+    multa: f64,
+    created_at: String,
+}
+
+#[derive(serde::Serialize)]
+struct AdjuntoExport {
+    entidad: String,
+    /// Posición en `requerimientos` (los requerimientos no tienen código)
+    requerimiento: Option<usize>,
+    /// Código de la OT o del informe
+    codigo: Option<String>,
+    nombre: String,
+    tipo_mime: String,
+    sha256: String,
+    etapa: Option<String>,
+    descripcion: Option<String>,
+    created_at: String,
+}
+
+#[derive(serde::Serialize)]
+struct BaseDatosExport {
+    fecha_exportacion: String,
+    jardines: Vec<JardinExport>,
+    partidas: Vec<PartidaExport>,
+    recintos: Vec<RecintoExport>,
+    requerimientos: Vec<RequerimientoExport>,
+    ordenes_trabajo: Vec<OrdenTrabajoExport>,
+    informes_pago: Vec<InformePagoExport>,
+    configuracion: ConfiguracionExport,
+    historial_tasas: Vec<HistorialTasasExport>,
+    reajustes: Vec<ReajusteExport>,
+    precios_partida: Vec<PrecioPartidaExport>,
+    feriados: Vec<Feriado>,
+    presupuesto_jardines: Vec<PresupuestoJardinExport>,
+    recepciones: Vec<RecepcionExport>,
+    adjuntos: Vec<AdjuntoExport>,
+    /// Contenido de los adjuntos en base64, por SHA-256 (una vez por archivo)
+    archivos_adjuntos: std::collections::BTreeMap<String, String>,
+}
+
+#[tauri::command]
+pub async fn exportar_base_datos_completa(db: State<'_, DbState>) -> Result<String, ErrorComando> {
+    let datos = exportar_base_datos(&db.pool(), &adjuntos::directorio(&db.db_path())).await?;
+    serde_json::to_string_pretty(&datos).map_err(|e| ErrorComando::Internal { mensaje: e.to_string() })
+}
+
+async fn exportar_base_datos(pool: &Pool<Sqlite>, dir_adjuntos: &Path) -> Result<BaseDatosExport, ErrorComando> {
+    use base64::{Engine as _, engine::general_purpose};
     
-    let mut workbook: Xlsx<_> = open_workbook(&file_path)
-        .map_err(|e| format!("Error abriendo Excel: {}", e))?;
+    let jardines = sqlx::query_as::<_, JardinExport>(
+        "SELECT codigo, nombre, activo FROM jardines WHERE contrato_id = (SELECT id FROM contrato_activo) ORDER BY id"
+    )
+    .fetch_all(pool)
+    .await?;
     
-    let range = workbook
-        .worksheet_range(&sheet_name)
-        .map_err(|e| format!("Error leyendo hoja: {}", e))?;
+    let partidas = sqlx::query_as::<_, PartidaExport>(
+        "SELECT item, partida, unidad, COALESCE(precio_unitario, 0) as precio_unitario, exenta_iva, activo FROM partidas 
+         WHERE contrato_id = (SELECT id FROM contrato_activo) ORDER BY id"
+    )
+    .fetch_all(pool)
+    .await?;
     
-    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
-    let mut count = 0;
+    let recintos = sqlx::query_as::<_, RecintoExport>(
+        "SELECT jardin_codigo, nombre, activo FROM recintos WHERE contrato_id = (SELECT id FROM contrato_activo) ORDER BY id"
+    )
+    .fetch_all(pool)
+    .await?;
     
-    for row in range.rows().skip(1) {
-        match tipo.as_str() {
-            "jardines" if row.len() >= 2 => {
-                let codigo = row[0].to_string();
-                let nombre = row[1].to_string();
-                
-                sqlx::query("INSERT OR IGNORE INTO jardines (codigo, nombre) VALUES (?, ?)")
-                    .bind(&codigo)
-                    .bind(&nombre)
-                    .execute(&mut *tx)
-                    .await
-                    .map_err(|e| e.to_string())?;
-                count += 1;
-            }
-            "partidas" if row.len() >= 2 => {
-                let item = row[0].to_string();
-                let partida = row[1].to_string();
-                let unidad = if row.len() > 2 { Some(row[2].to_string()) } else { None };
-                let precio = if row.len() > 3 {
-                    row[3].to_string().parse::<f64>().unwrap_or(0.0)
-                } else { 0.0 };
-                
-                sqlx::query("INSERT OR IGNORE INTO partidas (item, partida, unidad, precio_unitario) VALUES (?, ?, ?, ?)")
-                    .bind(&item)
-                    .bind(&partida)
-                    .bind(unidad)
-                    .bind(precio)
-                    .execute(&mut *tx)
-                    .await
-                    .map_err(|e| e.to_string())?;
-                count += 1;
-            }
-            _ => {}
+    let ordenes_trabajo = sqlx::query_as::<_, OrdenTrabajoExport>(
+        "SELECT codigo, jardin_codigo, fecha_creacion, observaciones FROM ordenes_trabajo 
+         WHERE contrato_id = (SELECT id FROM contrato_activo) ORDER BY id"
+    )
+    .fetch_all(pool)
+    .await?;
+    
+    let informes_pago = sqlx::query_as::<_, InformePagoExport>(
+        "SELECT codigo, jardin_codigo, fecha_creacion, neto, neto_exento, gastos_generales, utilidades, iva, total_final,
+                porcentaje_gastos_generales, porcentaje_utilidades, porcentaje_iva, observaciones,
+                estado, fecha_emision, fecha_aprobacion, fecha_pago, fecha_anulacion, motivo_anulacion, detalle_anulado
+         FROM informes_pago WHERE contrato_id = (SELECT id FROM contrato_activo) ORDER BY id"
+    )
+    .fetch_all(pool)
+    .await?;
+    
+    let requerimientos = sqlx::query_as::<_, RequerimientoExport>(
+        "SELECT 
+            r.jardin_codigo,
+            r.recinto,
+            r.partida_item,
+            r.cantidad,
+            r.precio_unitario,
+            r.precio_total,
+            r.fecha_inicio,
+            r.fecha_registro,
+            r.estado,
+            ot.codigo as ot_codigo,
+            ip.codigo as informe_codigo,
+            r.fecha_recepcion,
+            COALESCE(r.plazo_dias, 0) as plazo_dias,
+            COALESCE(r.plazo_adicional, 0) as plazo_adicional,
+            r.fecha_limite,
+            r.dias_atraso,
+            COALESCE(r.multa, 0) as multa,
+            r.multa_detalle,
+            r.descripcion,
+            r.observaciones,
+            r.cantidad_separada,
+            pp.vigente_desde as precio_vigente_desde
+        FROM requerimientos r
+        LEFT JOIN precios_partida pp ON r.precio_partida_id = pp.id
+        LEFT JOIN ordenes_trabajo ot ON r.ot_id = ot.id
+        LEFT JOIN informes_pago ip ON r.informe_pago_id = ip.id
+        WHERE r.contrato_id = (SELECT id FROM contrato_activo)
+        ORDER BY r.id"
+    )
+    .fetch_all(pool)
+    .await?;
+    
+    let row = sqlx::query("SELECT * FROM contrato_activo")
+        .fetch_one(pool)
+        .await?;
+    
+    let firma_bytes: Option<Vec<u8>> = row.get("firma_png");
+    let configuracion = ConfiguracionExport {
+        titulo: row.get("titulo"),
+        contratista: row.get("contratista"),
+        prefijo_correlativo: row.get("prefijo_correlativo"),
+        ito_nombre: row.get("ito_nombre"),
+        firma_png_base64: firma_bytes.map(|bytes| general_purpose::STANDARD.encode(&bytes)),
+        porcentaje_gastos_generales: row.get("porcentaje_gastos_generales"),
+        porcentaje_utilidades: row.get("porcentaje_utilidades"),
+        porcentaje_iva: row.get("porcentaje_iva"),
+        regla_multa: ReglaMulta {
+            multa_modo: row.get("multa_modo"),
+            multa_monto_diario: row.get("multa_monto_diario"),
+            multa_utm_diaria: row.get("multa_utm_diaria"),
+            valor_utm: row.get("valor_utm"),
+            multa_porcentaje_diario: row.get("multa_porcentaje_diario"),
+            multa_tope_porcentaje: row.get("multa_tope_porcentaje"),
+            multa_dias_gracia: row.get("multa_dias_gracia"),
+        },
+        calendario: ConfiguracionCalendario {
+            tipo_plazo: row.get("tipo_plazo"),
+            dias_no_habiles: row.get("dias_no_habiles"),
+        },
+        contrato: DatosContrato {
+            monto_contratado: row.get("monto_contratado"),
+            fecha_inicio: row.get("fecha_inicio"),
+            fecha_termino: row.get("fecha_termino"),
+            umbral_alerta: row.get("umbral_alerta"),
+        },
+    };
+    
+    let historial_tasas = sqlx::query_as::<_, HistorialTasasExport>(
+        "SELECT vigente_desde, porcentaje_gastos_generales, porcentaje_utilidades, porcentaje_iva 
+         FROM historial_tasas WHERE contrato_id = (SELECT id FROM contrato_activo) ORDER BY id"
+    )
+    .fetch_all(pool)
+    .await?;
+    
+    let reajustes = sqlx::query_as::<_, ReajusteExport>(
+        "SELECT vigente_desde, tipo, porcentaje, indice_base, indice_nuevo, descripcion, created_at
+         FROM reajustes WHERE contrato_id = (SELECT id FROM contrato_activo) ORDER BY vigente_desde"
+    )
+    .fetch_all(pool)
+    .await?;
+    
+    let precios_partida = sqlx::query_as::<_, PrecioPartidaExport>(
+        "SELECT p.item as partida_item, pp.vigente_desde, pp.precio_unitario, r.vigente_desde as reajuste_vigente_desde, pp.created_at
+         FROM precios_partida pp
+         JOIN partidas p ON p.id = pp.partida_id
+         LEFT JOIN reajustes r ON r.id = pp.reajuste_id
+         WHERE p.contrato_id = (SELECT id FROM contrato_activo)
+         ORDER BY p.id, pp.vigente_desde"
+    )
+    .fetch_all(pool)
+    .await?;
+    
+    let feriados = sqlx::query_as::<_, Feriado>("SELECT fecha, nombre FROM feriados ORDER BY fecha")
+        .fetch_all(pool)
+        .await?;
+    
+    let presupuesto_jardines = sqlx::query_as::<_, PresupuestoJardinExport>(
+        "SELECT jardin_codigo, monto FROM presupuesto_jardines 
+         WHERE contrato_id = (SELECT id FROM contrato_activo) ORDER BY jardin_codigo"
+    )
+    .fetch_all(pool)
+    .await?;
+    
+    // Recepciones y adjuntos: requerimientos por posición en `requerimientos`, OT e informes por código
+    let posiciones: std::collections::HashMap<i64, usize> = sqlx::query_scalar::<_, i64>(
+        "SELECT id FROM requerimientos WHERE contrato_id = (SELECT id FROM contrato_activo) ORDER BY id"
+    )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .enumerate()
+        .map(|(posicion, id)| (id, posicion))
+        .collect();
+    let mut recepciones = sqlx::query_as::<_, RecepcionExport>(
+        "SELECT requerimiento_id, fecha, cantidad, observaciones, dias_atraso, multa, created_at FROM recepciones ORDER BY id"
+    )
+    .fetch_all(pool)
+    .await?;
+    recepciones.retain_mut(|r| match posiciones.get(&r.requerimiento_id) {
+        Some(&posicion) => {
+            r.requerimiento = posicion;
+            true
         }
+        None => false,
+    });
+    let filas = sqlx::query(
+        "SELECT a.entidad, a.entidad_id, COALESCE(ot.codigo, ip.codigo) as codigo,
+                a.nombre, a.tipo_mime, a.sha256, a.etapa, a.descripcion, a.created_at
+         FROM adjuntos a
+         LEFT JOIN ordenes_trabajo ot ON a.entidad = 'orden_trabajo' AND ot.id = a.entidad_id
+         LEFT JOIN informes_pago ip ON a.entidad = 'informe_pago' AND ip.id = a.entidad_id
+         LEFT JOIN requerimientos r ON a.entidad = 'requerimiento' AND r.id = a.entidad_id
+         WHERE COALESCE(ot.contrato_id, ip.contrato_id, r.contrato_id) = (SELECT id FROM contrato_activo)
+         ORDER BY a.id"
+    )
+    .fetch_all(pool)
+    .await?;
+    let mut adjuntos_export = Vec::new();
+    let mut archivos_adjuntos = std::collections::BTreeMap::new();
+    for fila in filas {
+        let entidad: String = fila.get("entidad");
+        let entidad_id: i64 = fila.get("entidad_id");
+        let sha256: String = fila.get("sha256");
+        if !archivos_adjuntos.contains_key(&sha256) {
+            let contenido = adjuntos::leer(dir_adjuntos, &sha256)?;
+            archivos_adjuntos.insert(sha256.clone(), general_purpose::STANDARD.encode(contenido));
+        }
+        adjuntos_export.push(AdjuntoExport {
+            requerimiento: if entidad == "requerimiento" { posiciones.get(&entidad_id).copied() } else { None },
+            entidad,
+            codigo: fila.get("codigo"),
+            nombre: fila.get("nombre"),
+            tipo_mime: fila.get("tipo_mime"),
+            sha256,
+            etapa: fila.get("etapa"),
+            descripcion: fila.get("descripcion"),
+            created_at: fila.get("created_at"),
+        });
     }
     
-    tx.commit().await.map_err(|e| e.to_string())?;
-    Ok(format!("{} registros importados", count))
+    Ok(BaseDatosExport {
+        fecha_exportacion: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        jardines,
+        partidas,
+        recintos,
+        requerimientos,
+        ordenes_trabajo,
+        informes_pago,
+        configuracion,
+        historial_tasas,
+        reajustes,
+        precios_partida,
+        feriados,
+        presupuesto_jardines,
+        recepciones,
+        adjuntos: adjuntos_export,
+        archivos_adjuntos,
+    })
 }
 
+// Catálogos: cada formato tiene su vista previa (`previsualizar_catalogo_*`,
+// no escribe) y su importación directa. La vista previa se confirma con
+// `aplicar_catalogo`, que recibe el mismo diff (ver catalogo.rs).
+
 #[tauri::command(rename_all = "snake_case")]
-pub async fn importar_catalogo_xlsx_bytes(
+pub async fn previsualizar_catalogo_json(db: State<'_, DbState>, json_str: String) -> Result<DiffCatalogo, ErrorComando> {
+    let leido = catalogo::leer_json(&json_str)?;
+    let mut conn = db.pool().acquire().await?;
+    catalogo::previsualizar(&mut conn, leido).await
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn previsualizar_catalogo_csv(
+    db: State<'_, DbState>,
+    csv_str: String,
+    tipo: String,
+) -> Result<DiffCatalogo, ErrorComando> {
+    let leido = catalogo::leer_csv(&csv_str, Tabla::desde(&tipo)?);
+    let mut conn = db.pool().acquire().await?;
+    catalogo::previsualizar(&mut conn, leido).await
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn previsualizar_catalogo_xlsx(
+    db: State<'_, DbState>,
+    file_path: String,
+    sheet_name: String,
+    tipo: String,
+) -> Result<DiffCatalogo, ErrorComando> {
+    let leido = catalogo::leer_xlsx_hoja(&file_path, &sheet_name, Tabla::desde(&tipo)?)?;
+    let mut conn = db.pool().acquire().await?;
+    catalogo::previsualizar(&mut conn, leido).await
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn previsualizar_catalogo_xlsx_bytes(
     db: State<'_, DbState>,
     file_bytes: Vec<u8>,
-) -> Result<serde_json::Value, String> {
-    use calamine::{Reader, Xlsx, open_workbook_from_rs};
-    use std::io::Cursor;
-    
-    let cursor = Cursor::new(file_bytes);
-    let mut workbook: Xlsx<_> = open_workbook_from_rs(cursor)
-        .map_err(|e| format!("Error abriendo Excel: {}", e))?;
-    
-    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
-    
-    // Borrar catálogos existentes antes de importar
-    sqlx::query("DELETE FROM recintos").execute(&mut *tx).await.map_err(|e| e.to_string())?;
-    sqlx::query("DELETE FROM partidas").execute(&mut *tx).await.map_err(|e| e.to_string())?;
-    sqlx::query("DELETE FROM jardines").execute(&mut *tx).await.map_err(|e| e.to_string())?;
-    
-    let mut jardines_count = 0;
-    let mut partidas_count = 0;
-    let mut recintos_count = 0;
-    let mut contrato_actualizado = false;
-    
-    // HOJA: jardines
-    if let Ok(range) = workbook.worksheet_range("jardines") {
-        for row in range.rows().skip(1) {
-            if row.len() >= 2 {
-                let codigo = row[0].to_string().trim().to_string();
-                let nombre = row[1].to_string().trim().to_string();
-                
-                if !codigo.is_empty() && !nombre.is_empty() {
-                    sqlx::query("INSERT INTO jardines (codigo, nombre) VALUES (?, ?)")
-                        .bind(&codigo)
-                        .bind(&nombre)
-                        .execute(&mut *tx)
-                        .await
-                        .map_err(|e| e.to_string())?;
-                    jardines_count += 1;
-                }
-            }
-        }
+) -> Result<DiffCatalogo, ErrorComando> {
+    let leido = catalogo::leer_xlsx(file_bytes)?;
+    let mut conn = db.pool().acquire().await?;
+    catalogo::previsualizar(&mut conn, leido).await
+}
+
+/// Aplica el diff de una vista previa. Falla sin cambiar nada si el catálogo
+/// se modificó entretanto. Con `desactivar_ausentes` el archivo reemplaza el
+/// catálogo: lo que no trae queda inactivo (nunca se borra).
+#[tauri::command(rename_all = "snake_case")]
+pub async fn aplicar_catalogo(
+    db: State<'_, DbState>,
+    diff: DiffCatalogo,
+    desactivar_ausentes: Option<bool>,
+) -> Result<ResumenImportacion, ErrorComando> {
+    crear_respaldo(&db.pool(), &db.db_path(), "aplicar_catalogo").await?;
+    
+    let mut tx = db.pool().begin().await?;
+    let resumen = catalogo::aplicar(&mut tx, &diff, desactivar_ausentes.unwrap_or(false)).await?;
+    auditoria::registrar(&mut tx, &db.operador(), Entidad::BaseDatos, None, "aplicar_catalogo", serde_json::json!(resumen)).await?;
+    tx.commit().await?;
+    
+    println!("📥 Catálogo aplicado: {} registros", resumen.registros());
+    Ok(resumen)
+}
+
+/// Importación directa: vista previa y aplicación en la misma transacción.
+/// Cualquier fila inválida cancela la importación.
+async fn importar_catalogo(
+    db: &DbState,
+    leido: CatalogoLeido,
+    desactivar_ausentes: bool,
+    accion: &str,
+    mut detalle: serde_json::Value,
+) -> Result<ResumenImportacion, ErrorComando> {
+    if let Some(invalida) = leido.invalidas.first() {
+        return Err(invalida.error());
     }
     
-    // HOJA: partidas
-    if let Ok(range) = workbook.worksheet_range("partidas") {
-        for row in range.rows().skip(1) {
-            if row.len() >= 2 {
-                let item = row[0].to_string().trim().to_string();
-                let partida = row[1].to_string().trim().to_string();
-                let unidad = if row.len() > 2 { 
-                    let u = row[2].to_string().trim().to_string();
-                    if u.is_empty() { None } else { Some(u) }
-                } else { None };
-                let precio = if row.len() > 3 {
-                    row[3].to_string().trim().parse::<f64>().unwrap_or(0.0)
-                } else { 0.0 };
-                
-                if !item.is_empty() && !partida.is_empty() {
-                    sqlx::query("INSERT INTO partidas (item, partida, unidad, precio_unitario) VALUES (?, ?, ?, ?)")
-                        .bind(&item)
-                        .bind(&partida)
-                        .bind(unidad)
-                        .bind(precio)
-                        .execute(&mut *tx)
-                        .await
-                        .map_err(|e| e.to_string())?;
-                    partidas_count += 1;
-                }
-            }
-        }
+    let mut tx = db.pool().begin().await?;
+    let diff = catalogo::previsualizar(&mut tx, leido).await?;
+    if let Some(invalida) = diff.invalidas.first() {
+        return Err(invalida.error());
     }
+    let resumen = catalogo::aplicar(&mut tx, &diff, desactivar_ausentes).await?;
     
-    // HOJA: recintos
-    if let Ok(range) = workbook.worksheet_range("recintos") {
-        for row in range.rows().skip(1) {
-            if row.len() >= 2 {
-                let jardin_codigo = row[0].to_string().trim().to_string();
-                let nombre = row[1].to_string().trim().to_string();
-                
-                if !jardin_codigo.is_empty() && !nombre.is_empty() {
-                    sqlx::query("INSERT INTO recintos (jardin_codigo, nombre) VALUES (?, ?)")
-                        .bind(&jardin_codigo)
-                        .bind(&nombre)
-                        .execute(&mut *tx)
-                        .await
-                        .map_err(|e| e.to_string())?;
-                    recintos_count += 1;
-                }
-            }
-        }
+    if let Some(detalle) = detalle.as_object_mut() {
+        detalle.insert("registros".to_string(), resumen.registros().into());
+        detalle.insert("desactivados".to_string(), resumen.desactivados.into());
     }
+    auditoria::registrar(&mut tx, &db.operador(), Entidad::BaseDatos, None, accion, detalle).await?;
     
-    // HOJA: configuracion
-    if let Ok(range) = workbook.worksheet_range("configuracion") {
-        if let Some(row) = range.rows().nth(1) {
-            if row.len() >= 3 {
-                let titulo = row[0].to_string().trim().to_string();
-                let prefijo = row[1].to_string().trim().to_string();
-                let contratista = row[2].to_string().trim().to_string();
-                
-                if !titulo.is_empty() {
-                    sqlx::query(
-                        "UPDATE configuracion_contrato 
-                         SET titulo = ?, prefijo_correlativo = ?, contratista = ?, updated_at = datetime('now')
-                         WHERE id = 1"
-                    )
-                    .bind(&titulo)
-                    .bind(&prefijo)
-                    .bind(&contratista)
-                    .execute(&mut *tx)
-                    .await
-                    .map_err(|e| e.to_string())?;
-                    
-                    contrato_actualizado = true;
-                }
-            }
-        }
-    }
+    tx.commit().await?;
+    Ok(resumen)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn importar_catalogo_json(
+    db: State<'_, DbState>,
+    json_str: String,
+) -> Result<String, ErrorComando> {
+    let leido = catalogo::leer_json(&json_str)?;
+    let resumen = importar_catalogo(&db, leido, false, "importar_catalogo_json", serde_json::json!({})).await?;
+    Ok(format!("{} registros importados", resumen.registros()))
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn importar_catalogo_csv(
+    db: State<'_, DbState>,
+    csv_str: String,
+    tipo: String,
+) -> Result<String, ErrorComando> {
+    let leido = catalogo::leer_csv(&csv_str, Tabla::desde(&tipo)?);
+    let resumen = importar_catalogo(&db, leido, false, "importar_catalogo_csv", serde_json::json!({ "tipo": tipo })).await?;
+    Ok(format!("{} registros importados", resumen.registros()))
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn importar_catalogo_xlsx(
+    db: State<'_, DbState>,
+    file_path: String,
+    sheet_name: String,
+    tipo: String,
+) -> Result<String, ErrorComando> {
+    let leido = catalogo::leer_xlsx_hoja(&file_path, &sheet_name, Tabla::desde(&tipo)?)?;
+    let detalle = serde_json::json!({ "hoja": sheet_name, "tipo": tipo });
+    let resumen = importar_catalogo(&db, leido, false, "importar_catalogo_xlsx", detalle).await?;
+    Ok(format!("{} registros importados", resumen.registros()))
+}
+
+/// Libro con hojas jardines, partidas, recintos y configuracion: reemplaza el
+/// catálogo de las hojas que trae. Inserta y actualiza por código/ítem; lo que
+/// falta en el archivo queda inactivo, sin tocar los movimientos que lo usan.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn importar_catalogo_xlsx_bytes(
+    db: State<'_, DbState>,
+    file_bytes: Vec<u8>,
+) -> Result<ResumenImportacion, ErrorComando> {
+    let leido = catalogo::leer_xlsx(file_bytes)?;
     
-    tx.commit().await.map_err(|e| e.to_string())?;
+    crear_respaldo(&db.pool(), &db.db_path(), "importar_catalogo_xlsx").await?;
     
-    Ok(serde_json::json!({
-        "jardines": jardines_count,
-        "partidas": partidas_count,
-        "recintos": recintos_count,
-        "contrato": contrato_actualizado
-    }))
+    importar_catalogo(&db, leido, true, "importar_catalogo_xlsx_bytes", serde_json::json!({})).await
 }
 
 #[tauri::command(rename_all = "snake_case")]
@@ -1350,33 +2301,20 @@ pub async fn update_orden_trabajo(
     ot_id: i64,
     requerimiento_ids: Vec<i64>,
     observaciones: Option<String>,
-) -> Result<(), String> {
-    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
+) -> Result<(), ErrorComando> {
+    let mut tx = db.pool().begin().await?;
+    let antes = auditoria::instantanea(&mut tx, Entidad::OrdenTrabajo, ot_id).await?;
     
     // Obtener el jardín de la OT
     let ot_jardin: (String,) = sqlx::query_as("SELECT jardin_codigo FROM ordenes_trabajo WHERE id = ?")
         .bind(ot_id)
-        .fetch_one(&mut *tx)
-        .await
-        .map_err(|e| format!("OT no encontrada: {}", e))?;
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| ErrorComando::no_encontrado("OT", ot_id))?;
     
     // Validar que todos los requerimientos pertenecen al mismo jardín
     for req_id in &requerimiento_ids {
-        let req_jardin: (String,) = sqlx::query_as(
-            "SELECT jardin_codigo FROM requerimientos WHERE id = ?"
-        )
-        .bind(req_id)
-        .fetch_optional(&mut *tx)
-        .await
-        .map_err(|e| format!("Error al verificar requerimiento {}: {}", req_id, e))?
-        .ok_or_else(|| format!("Requerimiento {} no existe", req_id))?;
-        
-        if req_jardin.0 != ot_jardin.0 {
-            return Err(format!(
-                "El requerimiento {} pertenece al jardín '{}' pero la OT es del jardín '{}'",
-                req_id, req_jardin.0, ot_jardin.0
-            ));
-        }
+        verificar_vinculable(&mut tx, *req_id, &ot_jardin.0, "la OT").await?;
     }
     
     // Actualizar observaciones si se proporcionan
@@ -1385,28 +2323,39 @@ pub async fn update_orden_trabajo(
             .bind(obs)
             .bind(ot_id)
             .execute(&mut *tx)
-            .await
-            .map_err(|e| e.to_string())?;
+            .await?;
     }
     
-    // Desvincular todos los requerimientos actuales
-    sqlx::query("UPDATE requerimientos SET ot_id = NULL, estado = 'pendiente', updated_at = datetime('now') WHERE ot_id = ?")
-        .bind(ot_id)
-        .execute(&mut *tx)
-        .await
-        .map_err(|e| e.to_string())?;
+    let actuales = ids_vinculados(&mut tx, "ot_id", ot_id).await?;
+    let salen: Vec<i64> = actuales.iter().copied().filter(|id| !requerimiento_ids.contains(id)).collect();
+    let entran: Vec<i64> = requerimiento_ids.iter().copied().filter(|id| !actuales.contains(id)).collect();
+    let antes_salen = auditoria::instantaneas(&mut tx, Entidad::Requerimiento, &salen).await?;
+    let antes_entran = auditoria::instantaneas(&mut tx, Entidad::Requerimiento, &entran).await?;
+    
+    // Desvincular los que salen de la OT (los que se mantienen conservan su estado)
+    for req_id in &salen {
+        estados::verificar_transicion(&mut tx, *req_id, EstadoRequerimiento::EnOt, EstadoRequerimiento::Pendiente).await?;
+        recepciones::verificar_sin_entregas(&mut tx, *req_id).await?;
+        sqlx::query("UPDATE requerimientos SET ot_id = NULL, estado = 'pendiente', updated_at = datetime('now') WHERE id = ?")
+            .bind(req_id)
+            .execute(&mut *tx)
+            .await?;
+    }
     
     // Vincular nuevos requerimientos
-    for req_id in requerimiento_ids {
+    for req_id in &entran {
+        estados::verificar_transicion(&mut tx, *req_id, EstadoRequerimiento::Pendiente, EstadoRequerimiento::EnOt).await?;
         sqlx::query("UPDATE requerimientos SET ot_id = ?, estado = 'en_ot', updated_at = datetime('now') WHERE id = ?")
             .bind(ot_id)
             .bind(req_id)
             .execute(&mut *tx)
-            .await
-            .map_err(|e| e.to_string())?;
+            .await?;
     }
     
-    tx.commit().await.map_err(|e| e.to_string())?;
+    auditoria::registrar_cambio(&mut tx, &db.operador(), Entidad::OrdenTrabajo, ot_id, "actualizar", antes).await?;
+    auditoria::registrar_cambios(&mut tx, &db.operador(), Entidad::Requerimiento, "quitar_de_ot", antes_salen).await?;
+    auditoria::registrar_cambios(&mut tx, &db.operador(), Entidad::Requerimiento, "agregar_a_ot", antes_entran).await?;
+    tx.commit().await?;
     Ok(())
 }
 
@@ -1414,59 +2363,274 @@ pub async fn update_orden_trabajo(
 pub async fn update_informe_pago(
     db: State<'_, DbState>,
     informe_id: i64,
-    requerimientos: Vec<serde_json::Value>,
+    requerimientos: Vec<LineaInforme>,
     observaciones: Option<String>,
-) -> Result<(), String> {
-    let mut tx = db.pool.begin().await.map_err(|e| e.to_string())?;
-    
-    // Calcular nuevos totales
-    let mut neto = 0.0;
-    for req in &requerimientos {
-        if let Some(monto) = req.get("monto").and_then(|m| m.as_f64()) {
-            neto += monto;
-        }
-    }
-    
-    let utilidades = neto * 0.10;
-    let subtotal = neto + utilidades;
-    let iva = subtotal * 0.19;
-    let total_final = subtotal + iva;
+) -> Result<(), ErrorComando> {
+    let mut tx = db.pool().begin().await?;
+    let antes = auditoria::instantanea(&mut tx, Entidad::InformePago, informe_id).await?;
     
-    // Actualizar informe
-    sqlx::query(
-        "UPDATE informes_pago 
-         SET neto = ?, utilidades = ?, iva = ?, total_final = ?, observaciones = ?, updated_at = datetime('now')
-         WHERE id = ?"
-    )
-    .bind(neto)
-    .bind(utilidades)
-    .bind(iva)
-    .bind(total_final)
-    .bind(&observaciones)
-    .bind(informe_id)
-    .execute(&mut *tx)
-    .await
-    .map_err(|e| e.to_string())?;
+    // Líneas y totales quedan fijos al emitir
+    estados::verificar_borrador(&mut tx, informe_id, "modificar").await?;
+    let informe_jardin: String = sqlx::query_scalar("SELECT jardin_codigo FROM informes_pago WHERE id = ?")
+        .bind(informe_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    for linea in &requerimientos {
+        verificar_vinculable(&mut tx, linea.id, &informe_jardin, "el informe").await?;
+    }
     
-    // Desvincular requerimientos viejos
-    sqlx::query("UPDATE requerimientos SET informe_pago_id = NULL, estado = 'pendiente', updated_at = datetime('now') WHERE informe_pago_id = ?")
+    sqlx::query("UPDATE informes_pago SET observaciones = ?, updated_at = datetime('now') WHERE id = ?")
+        .bind(&observaciones)
         .bind(informe_id)
         .execute(&mut *tx)
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
+    
+    let actuales = ids_vinculados(&mut tx, "informe_pago_id", informe_id).await?;
+    let nuevos: Vec<i64> = requerimientos.iter().map(|linea| linea.id).collect();
+    let salen: Vec<i64> = actuales.iter().copied().filter(|id| !nuevos.contains(id)).collect();
+    let entran: Vec<i64> = nuevos.iter().copied().filter(|id| !actuales.contains(id)).collect();
+    let antes_salen = auditoria::instantaneas(&mut tx, Entidad::Requerimiento, &salen).await?;
+    let antes_entran = auditoria::instantaneas(&mut tx, Entidad::Requerimiento, &entran).await?;
+    
+    // Desvincular los que salen del informe (vuelven a recepcionado)
+    for req_id in &salen {
+        estados::verificar_transicion(&mut tx, *req_id, EstadoRequerimiento::EnInforme, EstadoRequerimiento::Recepcionado).await?;
+        sqlx::query("UPDATE requerimientos SET informe_pago_id = NULL, estado = 'recepcionado', updated_at = datetime('now') WHERE id = ?")
+            .bind(req_id)
+            .execute(&mut *tx)
+            .await?;
+    }
     
     // Vincular nuevos requerimientos
-    for req in requerimientos {
-        if let Some(req_id) = req.get("id").and_then(|v| v.as_i64()) {
-            sqlx::query("UPDATE requerimientos SET informe_pago_id = ?, estado = 'en_informe', updated_at = datetime('now') WHERE id = ?")
-                .bind(informe_id)
-                .bind(req_id)
-                .execute(&mut *tx)
-                .await
-                .map_err(|e| e.to_string())?;
-        }
+    for req_id in &entran {
+        estados::verificar_transicion(&mut tx, *req_id, EstadoRequerimiento::Recepcionado, EstadoRequerimiento::EnInforme).await?;
+        sqlx::query("UPDATE requerimientos SET informe_pago_id = ?, estado = 'en_informe', updated_at = datetime('now') WHERE id = ?")
+            .bind(informe_id)
+            .bind(req_id)
+            .execute(&mut *tx)
+            .await?;
     }
     
-    tx.commit().await.map_err(|e| e.to_string())?;
+    // Recalcular con las tasas guardadas en el informe (no las vigentes)
+    informes::recalcular(&mut tx, informe_id).await?;
+    
+    auditoria::registrar_cambio(&mut tx, &db.operador(), Entidad::InformePago, informe_id, "actualizar", antes).await?;
+    auditoria::registrar_cambios(&mut tx, &db.operador(), Entidad::Requerimiento, "quitar_de_informe", antes_salen).await?;
+    auditoria::registrar_cambios(&mut tx, &db.operador(), Entidad::Requerimiento, "agregar_a_informe", antes_entran).await?;
+    tx.commit().await?;
     Ok(())
 }
+
+/// Compara los totales guardados de los informes del contrato activo con los
+/// de sus líneas. Con `corregir` guarda los calculados en los borradores
+/// (respaldo antes y auditoría de cada informe corregido).
+#[tauri::command]
+pub async fn conciliar_informes_pago(
+    db: State<'_, DbState>,
+    corregir: Option<bool>,
+) -> Result<ConciliacionInformes, ErrorComando> {
+    let corregir = corregir.unwrap_or(false);
+    if corregir {
+        crear_respaldo(&db.pool(), &db.db_path(), "conciliar_informes").await?;
+    }
+    
+    let mut tx = db.pool().begin().await?;
+    let contrato_id = contratos::activo(&mut tx).await?;
+    let descuadrados = informes::descuadrados(&mut tx, Some(contrato_id)).await?;
+    
+    let mut corregidos = Vec::new();
+    if corregir {
+        for informe in descuadrados.iter().filter(|i| i.reparable) {
+            let antes = auditoria::instantanea(&mut tx, Entidad::InformePago, informe.id).await?;
+            informes::corregir(&mut tx, informe).await?;
+            auditoria::registrar_cambio(&mut tx, &db.operador(), Entidad::InformePago, informe.id, "conciliar_totales", antes).await?;
+            corregidos.push(informe.id);
+        }
+    }
+    tx.commit().await?;
+    
+    println!("🧮 Informes descuadrados: {} ({} corregidos)", descuadrados.len(), corregidos.len());
+    Ok(ConciliacionInformes { descuadrados, corregidos })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+
+    async fn pool_memoria() -> Pool<Sqlite> {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(SqliteConnectOptions::new().in_memory(true))
+            .await
+            .unwrap();
+        crate::migraciones::migrar(&pool).await.unwrap();
+        pool
+    }
+
+    async fn poblar(pool: &Pool<Sqlite>, dir_adjuntos: &Path) {
+        for sql in [
+            "INSERT INTO jardines (contrato_id, codigo, nombre, activo) VALUES (1, 'J01', 'Jardín Uno', 1), (1, 'J02', 'Jardín Dos', 1), (1, 'J03', 'Jardín Cerrado', 0)",
+            "INSERT INTO partidas (contrato_id, item, partida, unidad, precio_unitario, exenta_iva, activo) VALUES (1, '1.1', 'Pintura', 'm2', 4500.5, 0, 1), (1, '1.2', 'Cerámica', NULL, 12000, 1, 1), (1, '1.3', 'Obsoleta', NULL, 100, 0, 0)",
+            "INSERT INTO recintos (contrato_id, jardin_codigo, nombre, activo) VALUES (1, 'J01', 'Sala Cuna', 1), (1, 'J01', 'Patio', 1), (1, 'J01', 'Bodega', 0)",
+            "INSERT INTO ordenes_trabajo (contrato_id, codigo, jardin_codigo, fecha_creacion, observaciones) VALUES (1, 'OT-J01-M001', 'J01', '2025-03-01', 'Urgente')",
+            "INSERT INTO informes_pago (contrato_id, codigo, jardin_codigo, fecha_creacion, neto, gastos_generales, utilidades, iva, total_final, porcentaje_gastos_generales, porcentaje_utilidades)
+             VALUES (1, 'IP-J01-M01', 'J01', '2025-04-01', 90000, 4500, 9000, 19665, 123165, 5, 10)",
+            "INSERT INTO historial_tasas (contrato_id, vigente_desde, porcentaje_gastos_generales, porcentaje_utilidades, porcentaje_iva) VALUES (1, '2025-06-01', 5, 12, 19)",
+            "INSERT INTO feriados (fecha, nombre) VALUES ('2025-02-14', 'Feriado de prueba')",
+            "INSERT INTO presupuesto_jardines (contrato_id, jardin_codigo, monto) VALUES (1, 'J01', 500000)",
+            "UPDATE contratos SET titulo = 'Contrato 2026', contratista = 'ACME', prefijo_correlativo = 'X', ito_nombre = NULL, firma_png = x'89504E47', porcentaje_gastos_generales = 5, porcentaje_utilidades = 12,
+                multa_modo = 'porcentaje', multa_porcentaje_diario = 1.5, multa_tope_porcentaje = 10, multa_dias_gracia = 2,
+                monto_contratado = 1000000, fecha_inicio = '2025-01-01', umbral_alerta = 75 WHERE id = 1",
+        ] {
+            sqlx::query(sql).execute(pool).await.unwrap();
+        }
+        sqlx::query(
+            "INSERT INTO requerimientos (contrato_id, jardin_codigo, recinto, partida_item, cantidad, precio_unitario, precio_total,
+                fecha_inicio, fecha_registro, estado, ot_id, informe_pago_id, plazo_dias, plazo_adicional, descripcion, fecha_recepcion)
+             VALUES (1, 'J01', 'Sala Cuna', '1.1', 20, 4500.5, 90010, '2025-02-01', '2025-02-01', 'en_informe', 1, 1, 10, 2, 'Muros', '2025-02-20'),
+                    (1, 'J01', NULL, '1.2', 1.5, 12000, 18000, '2025-02-10', '2025-02-11', 'en_ot', 1, NULL, 5, 0, NULL, NULL),
+                    (1, 'J02', NULL, '1.1', 3, 4500.5, 13501.5, '2025-03-01', '2025-03-01', 'pendiente', NULL, NULL, 0, 0, 'Sin plazo', NULL)"
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+        // Recepción total del informado y una entrega parcial del que sigue en la OT
+        sqlx::query(
+            "INSERT INTO recepciones (requerimiento_id, fecha, cantidad, observaciones, dias_atraso) 
+             VALUES (1, '2025-02-20', 20, NULL, 7), (2, '2025-02-18', 0.5, 'Primer paño', 0)"
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+        sqlx::query("UPDATE requerimientos SET cantidad_recibida = CASE id WHEN 1 THEN 20 WHEN 2 THEN 0.5 ELSE 0 END")
+            .execute(pool)
+            .await
+            .unwrap();
+        let mut conn = pool.acquire().await.unwrap();
+        multas::recalcular_pendientes(&mut conn).await.unwrap();
+
+        // Versiones de precio y un reajuste que revaloriza el requerimiento de J02
+        precios::registrar_iniciales(&mut conn, 1).await.unwrap();
+        sqlx::query(
+            "UPDATE requerimientos SET precio_partida_id = (
+                 SELECT pp.id FROM precios_partida pp JOIN partidas p ON p.id = pp.partida_id WHERE p.item = '1.1'
+             ) WHERE id = 3"
+        )
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+        let reajuste = precios::NuevoReajuste {
+            vigente_desde: "2025-03-01".to_string(),
+            porcentaje: Some(10.0),
+            indice_base: None,
+            indice_nuevo: None,
+            descripcion: Some("IPC".to_string()),
+        };
+        precios::reajustar(&mut conn, "prueba", &reajuste).await.unwrap();
+
+        // Mismo contenido en dos entidades: se exporta una sola vez
+        let sha256 = adjuntos::guardar(dir_adjuntos, b"foto").unwrap();
+        sqlx::query(
+            "INSERT INTO adjuntos (entidad, entidad_id, nombre, tipo_mime, tamano, sha256, etapa)
+             VALUES ('requerimiento', 2, 'antes.jpg', 'image/jpeg', 4, ?1, 'antes'), ('orden_trabajo', 1, 'acta.jpg', 'image/jpeg', 4, ?1, NULL)"
+        )
+        .bind(&sha256)
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+    }
+
+    fn dir_temporal(nombre: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("adjuntos-{}-{}", std::process::id(), nombre));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn sin_fecha(datos: &BaseDatosExport) -> serde_json::Value {
+        let mut valor = serde_json::to_value(datos).unwrap();
+        valor.as_object_mut().unwrap().remove("fecha_exportacion");
+        valor
+    }
+
+    #[tokio::test]
+    async fn exportar_importar_es_ida_y_vuelta_sin_perdida() {
+        let (dir_origen, dir_destino) = (dir_temporal("origen"), dir_temporal("destino"));
+        let origen = pool_memoria().await;
+        poblar(&origen, &dir_origen).await;
+        let exportado = exportar_base_datos(&origen, &dir_origen).await.unwrap();
+        let json = serde_json::to_string(&exportado).unwrap();
+
+        let destino = pool_memoria().await;
+        // Datos previos en el destino deben quedar reemplazados
+        sqlx::query("INSERT INTO jardines (contrato_id, codigo, nombre) VALUES (1, 'J99', 'Otro')")
+            .execute(&destino)
+            .await
+            .unwrap();
+        importar_base_datos(&destino, &json, "prueba", &dir_destino).await.unwrap();
+        let reexportado = exportar_base_datos(&destino, &dir_destino).await.unwrap();
+
+        assert_eq!(sin_fecha(&exportado), sin_fecha(&reexportado));
+        assert_eq!(reexportado.requerimientos[0].ot_codigo.as_deref(), Some("OT-J01-M001"));
+        assert_eq!(reexportado.requerimientos[0].informe_codigo.as_deref(), Some("IP-J01-M01"));
+        assert_eq!(reexportado.configuracion.firma_png_base64.as_deref(), Some("iVBORw=="));
+        assert_eq!(reexportado.configuracion.ito_nombre, None);
+        assert_eq!(reexportado.configuracion.contrato.monto_contratado, 1000000.0);
+        assert_eq!(reexportado.presupuesto_jardines[0].jardin_codigo, "J01");
+        assert_eq!(reexportado.recepciones.len(), 2);
+        assert_eq!(reexportado.recepciones[1].requerimiento, 1);
+        assert_eq!(reexportado.recepciones[1].observaciones.as_deref(), Some("Primer paño"));
+        assert_eq!(reexportado.adjuntos[0].requerimiento, Some(1));
+        assert_eq!(reexportado.adjuntos[1].codigo.as_deref(), Some("OT-J01-M001"));
+        assert_eq!(reexportado.archivos_adjuntos.len(), 1);
+        assert_eq!(reexportado.reajustes.len(), 1);
+        assert_eq!(reexportado.precios_partida.len(), 6);
+        assert_eq!(reexportado.precios_partida[1].reajuste_vigente_desde.as_deref(), Some("2025-03-01"));
+        assert_eq!(reexportado.requerimientos[2].precio_vigente_desde.as_deref(), Some("2025-03-01"));
+        assert_eq!(reexportado.requerimientos[2].precio_unitario, 4951.0);
+        assert!(!reexportado.jardines[2].activo);
+        assert!(!reexportado.partidas[2].activo);
+        assert!(!reexportado.recintos[2].activo);
+        assert!(reexportado.jardines[0].activo && reexportado.partidas[0].activo && reexportado.recintos[0].activo);
+    }
+
+    #[tokio::test]
+    async fn importar_feriados_no_borra_los_de_otros_contratos() {
+        let destino = pool_memoria().await;
+        sqlx::query("INSERT INTO feriados (fecha, nombre) VALUES ('2025-09-18', 'Fiestas Patrias'), ('2025-02-14', 'Antiguo')")
+            .execute(&destino)
+            .await
+            .unwrap();
+        let json = r#"{"feriados": [{"fecha": "14/02/2025", "nombre": "Renombrado"}, {"fecha": "2025-12-25", "nombre": "Navidad"}]}"#;
+        importar_base_datos(&destino, json, "prueba", &dir_temporal("feriados")).await.unwrap();
+
+        let feriados: Vec<(String, String)> = sqlx::query_as("SELECT fecha, nombre FROM feriados ORDER BY fecha")
+            .fetch_all(&destino)
+            .await
+            .unwrap();
+        let esperados = [("2025-02-14", "Renombrado"), ("2025-09-18", "Fiestas Patrias"), ("2025-12-25", "Navidad")];
+        assert_eq!(feriados, esperados.map(|(f, n)| (f.to_string(), n.to_string())));
+    }
+
+    #[tokio::test]
+    async fn importar_rechaza_hashes_de_adjunto_no_validos() {
+        let (dir_origen, dir_destino) = (dir_temporal("hash-origen"), dir_temporal("hash-destino"));
+        let origen = pool_memoria().await;
+        poblar(&origen, &dir_origen).await;
+        let exportado = serde_json::to_value(exportar_base_datos(&origen, &dir_origen).await.unwrap()).unwrap();
+
+        let destino = pool_memoria().await;
+        for hash in ["", "é", "../../x", &"A".repeat(64)] {
+            let mut datos = exportado.clone();
+            datos["adjuntos"][0]["sha256"] = serde_json::json!(hash);
+            let error = importar_base_datos(&destino, &datos.to_string(), "prueba", &dir_destino).await.unwrap_err();
+            assert!(matches!(error, ErrorComando::Import { .. }), "{}: {:?}", hash, error);
+
+            let mut datos = exportado.clone();
+            let contenido = datos["archivos_adjuntos"].as_object().unwrap().values().next().unwrap().clone();
+            datos["archivos_adjuntos"] = serde_json::json!({ hash: contenido });
+            let error = importar_base_datos(&destino, &datos.to_string(), "prueba", &dir_destino).await.unwrap_err();
+            assert!(matches!(error, ErrorComando::Import { .. }), "{}: {:?}", hash, error);
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use crate::adjuntos::{self, Adjunto};
+use crate::auditoria;
+use crate::db::DbState;
+use crate::errores::ErrorComando;
+use base64::{engine::general_purpose, Engine as _};
+use tauri::State;
+
+// ========== ADJUNTOS ==========
+//
+// Fotos y documentos por requerimiento, OT o informe. Los contenidos viajan
+// en base64 hacia el frontend, igual que la firma del ITO.
+
+/// Adjunta un archivo. `etapa` (antes / despues / recepcion) es opcional y
+/// solo tiene sentido para fotos.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn agregar_adjunto(
+    db: State<'_, DbState>,
+    entidad: String,
+    entidad_id: i64,
+    nombre: String,
+    contenido: Vec<u8>,
+    etapa: Option<String>,
+    descripcion: Option<String>,
+) -> Result<Adjunto, ErrorComando> {
+    let tipo = adjuntos::entidad(&entidad)?;
+    if contenido.is_empty() {
+        return Err(ErrorComando::validacion("contenido", "El archivo está vacío"));
+    }
+    if contenido.len() > adjuntos::TAMANO_MAXIMO {
+        return Err(ErrorComando::validacion(
+            "contenido",
+            format!("El archivo supera el máximo de {} MB", adjuntos::TAMANO_MAXIMO / (1024 * 1024)),
+        ));
+    }
+    if let Some(etapa) = etapa.as_deref().filter(|e| !adjuntos::ETAPAS.contains(e)) {
+        return Err(ErrorComando::validacion("etapa", format!("Etapa '{}' no válida", etapa)));
+    }
+
+    let mut tx = db.pool().begin().await?;
+    let tabla = tipo.tabla().unwrap_or_default();
+    let existe: Option<i64> = sqlx::query_scalar(&format!("SELECT id FROM {} WHERE id = ?", tabla))
+        .bind(entidad_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    if existe.is_none() {
+        return Err(ErrorComando::no_encontrado(tipo.as_str(), entidad_id));
+    }
+
+    // La fila primero: así la transacción toma el bloqueo de escritura antes de
+    // que el archivo exista (ver adjuntos.rs)
+    let sha256 = adjuntos::hash(&contenido);
+    let result = sqlx::query(
+        "INSERT INTO adjuntos (entidad, entidad_id, nombre, tipo_mime, tamano, sha256, etapa, descripcion)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(tipo.as_str())
+    .bind(entidad_id)
+    .bind(&nombre)
+    .bind(adjuntos::tipo_mime(&nombre))
+    .bind(contenido.len() as i64)
+    .bind(&sha256)
+    .bind(&etapa)
+    .bind(&descripcion)
+    .execute(&mut *tx)
+    .await?;
+    let id = result.last_insert_rowid();
+    adjuntos::escribir(&adjuntos::directorio(&db.db_path()), &sha256, &contenido)?;
+
+    auditoria::registrar(
+        &mut tx,
+        &db.operador(),
+        tipo,
+        Some(entidad_id),
+        "agregar_adjunto",
+        serde_json::json!({ "adjunto_id": id, "nombre": nombre, "sha256": sha256, "etapa": etapa }),
+    )
+    .await?;
+
+    let adjunto = sqlx::query_as::<_, Adjunto>("SELECT * FROM adjuntos WHERE id = ?")
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    println!("📎 Adjunto {} agregado a {} {}", adjunto.nombre, entidad, entidad_id);
+    Ok(adjunto)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_adjuntos(
+    db: State<'_, DbState>,
+    entidad: String,
+    entidad_id: i64,
+) -> Result<Vec<Adjunto>, ErrorComando> {
+    let tipo = adjuntos::entidad(&entidad)?;
+    sqlx::query_as::<_, Adjunto>(
+        "SELECT * FROM adjuntos WHERE entidad = ? AND entidad_id = ? ORDER BY created_at, id"
+    )
+    .bind(tipo.as_str())
+    .bind(entidad_id)
+    .fetch_all(&db.pool())
+    .await
+    .map_err(ErrorComando::from)
+}
+
+async fn buscar(db: &State<'_, DbState>, id: i64) -> Result<Adjunto, ErrorComando> {
+    sqlx::query_as::<_, Adjunto>("SELECT * FROM adjuntos WHERE id = ?")
+        .bind(id)
+        .fetch_optional(&db.pool())
+        .await?
+        .ok_or_else(|| ErrorComando::no_encontrado("Adjunto", id))
+}
+
+/// Contenido completo en base64
+#[tauri::command]
+pub async fn get_adjunto_contenido(db: State<'_, DbState>, id: i64) -> Result<String, ErrorComando> {
+    let adjunto = buscar(&db, id).await?;
+    let contenido = adjuntos::leer(&adjuntos::directorio(&db.db_path()), &adjunto.sha256)?;
+    Ok(general_purpose::STANDARD.encode(contenido))
+}
+
+/// Miniatura JPEG en base64; None si el adjunto no es una imagen
+#[tauri::command]
+pub async fn get_adjunto_miniatura(db: State<'_, DbState>, id: i64) -> Result<Option<String>, ErrorComando> {
+    let adjunto = buscar(&db, id).await?;
+    let miniatura = adjuntos::miniatura(&adjuntos::directorio(&db.db_path()), &adjunto.sha256, &adjunto.tipo_mime)?;
+    Ok(miniatura.map(|bytes| general_purpose::STANDARD.encode(bytes)))
+}
+
+/// Guarda una copia del adjunto en la ruta elegida
+#[tauri::command(rename_all = "snake_case")]
+pub async fn exportar_adjunto(
+    db: State<'_, DbState>,
+    id: i64,
+    file_path: String,
+) -> Result<(), ErrorComando> {
+    let adjunto = buscar(&db, id).await?;
+    let contenido = adjuntos::leer(&adjuntos::directorio(&db.db_path()), &adjunto.sha256)?;
+    std::fs::write(&file_path, contenido)?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn eliminar_adjunto(db: State<'_, DbState>, id: i64) -> Result<(), ErrorComando> {
+    let adjunto = buscar(&db, id).await?;
+    let tipo = adjuntos::entidad(&adjunto.entidad)?;
+
+    let mut tx = db.pool().begin().await?;
+    sqlx::query("DELETE FROM adjuntos WHERE id = ?").bind(id).execute(&mut *tx).await?;
+    auditoria::registrar(
+        &mut tx,
+        &db.operador(),
+        tipo,
+        Some(adjunto.entidad_id),
+        "eliminar_adjunto",
+        serde_json::json!({ "adjunto_id": id, "nombre": adjunto.nombre, "sha256": adjunto.sha256 }),
+    )
+    .await?;
+
+    // El archivo se borra solo si ningún otro adjunto usa el mismo contenido
+    let dir = adjuntos::directorio(&db.db_path());
+    let apartado = adjuntos::apartar(&mut tx, &dir, &adjunto.sha256).await?;
+    if let Err(e) = tx.commit().await {
+        if let Some(ruta) = &apartado {
+            let _ = adjuntos::restaurar(&dir, &adjunto.sha256, ruta);
+        }
+        return Err(e.into());
+    }
+    if let Some(ruta) = apartado {
+        let _ = std::fs::remove_file(ruta);
+    }
+    Ok(())
+}
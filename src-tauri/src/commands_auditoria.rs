@@ -0,0 +1,52 @@
+use crate::auditoria::RegistroAuditoria;
+use crate::db::DbState;
+use crate::errores::ErrorComando;
+use tauri::State;
+
+// ========== AUDITORÍA ==========
+
+const LIMITE_POR_DEFECTO: i64 = 200;
+
+/// Historial de una entidad, del más reciente al más antiguo. Sin `entidad_id`
+/// devuelve el historial de todas las filas de ese tipo (p. ej. `base_datos`).
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_auditoria(
+    db: State<'_, DbState>,
+    entidad: String,
+    entidad_id: Option<i64>,
+    limite: Option<i64>,
+) -> Result<Vec<RegistroAuditoria>, ErrorComando> {
+    sqlx::query_as::<_, RegistroAuditoria>(
+        "SELECT id, fecha, operador, entidad, entidad_id, accion, cambios
+         FROM auditoria
+         WHERE entidad = ? AND (? IS NULL OR entidad_id = ?)
+         ORDER BY id DESC
+         LIMIT ?"
+    )
+    .bind(&entidad)
+    .bind(entidad_id)
+    .bind(entidad_id)
+    .bind(limite.unwrap_or(LIMITE_POR_DEFECTO))
+    .fetch_all(&db.pool())
+    .await
+    .map_err(ErrorComando::from)
+}
+
+#[tauri::command]
+pub async fn get_operador(db: State<'_, DbState>) -> Result<String, ErrorComando> {
+    Ok(db.operador())
+}
+
+/// Nombre con que se firman los cambios de esta sesión
+#[tauri::command]
+pub async fn set_operador(
+    db: State<'_, DbState>,
+    nombre: String,
+) -> Result<(), ErrorComando> {
+    let nombre = nombre.trim();
+    if nombre.is_empty() {
+        return Err(ErrorComando::validacion("nombre", "El nombre del operador no puede estar vacío"));
+    }
+    *db.operador.write().map_err(|e| ErrorComando::Internal { mensaje: e.to_string() })? = nombre.to_string();
+    Ok(())
+}
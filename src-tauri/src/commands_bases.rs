@@ -0,0 +1,63 @@
+use crate::auditoria::{self, Entidad};
+use crate::bases::{self, BaseActual, BaseReciente};
+use crate::db::{self, DbState};
+use crate::errores::ErrorComando;
+use tauri::State;
+
+// ========== ARCHIVOS DE BASE DE DATOS ==========
+//
+// Abrir, crear y "guardar como". Después de cualquiera de ellos el frontend
+// debe recargar todo: catálogos, listados y configuración son de otra base.
+
+#[tauri::command]
+pub async fn get_base_actual(db: State<'_, DbState>) -> Result<BaseActual, ErrorComando> {
+    Ok(bases::actual(&db))
+}
+
+#[tauri::command]
+pub async fn get_bases_recientes() -> Result<Vec<BaseReciente>, ErrorComando> {
+    Ok(bases::recientes(&db::dir_app()))
+}
+
+#[tauri::command]
+pub async fn abrir_base_datos(db: State<'_, DbState>, ruta: String) -> Result<BaseActual, ErrorComando> {
+    let base = bases::abrir(&db, &ruta).await?;
+    registrar(&db, "abrir_base_datos").await?;
+    Ok(base)
+}
+
+#[tauri::command]
+pub async fn crear_base_datos(db: State<'_, DbState>, ruta: String) -> Result<BaseActual, ErrorComando> {
+    let base = bases::crear(&db, &ruta).await?;
+    registrar(&db, "crear_base_datos").await?;
+    Ok(base)
+}
+
+#[tauri::command]
+pub async fn guardar_base_datos_como(db: State<'_, DbState>, ruta: String) -> Result<BaseActual, ErrorComando> {
+    let origen = db.db_path();
+    let base = bases::guardar_como(&db, &ruta).await?;
+    let mut conn = db.pool().acquire().await?;
+    auditoria::registrar(
+        &mut conn,
+        &db.operador(),
+        Entidad::BaseDatos,
+        None,
+        "guardar_base_datos_como",
+        serde_json::json!({ "origen": origen.to_string_lossy() }),
+    )
+    .await?;
+    Ok(base)
+}
+
+#[tauri::command]
+pub async fn quitar_base_reciente(ruta: String) -> Result<(), ErrorComando> {
+    bases::quitar_reciente(&db::dir_app(), &ruta)
+}
+
+/// Queda en la auditoría de la base recién abierta
+async fn registrar(db: &DbState, accion: &str) -> Result<(), ErrorComando> {
+    let mut conn = db.pool().acquire().await?;
+    auditoria::registrar(&mut conn, &db.operador(), Entidad::BaseDatos, None, accion, serde_json::json!({})).await?;
+    Ok(())
+}
@@ -0,0 +1,203 @@
+use crate::auditoria::{self, Entidad};
+use crate::calendario::{self, ConfiguracionCalendario, Feriado};
+use crate::contratos;
+use crate::db::DbState;
+use crate::errores::ErrorComando;
+use crate::multas;
+use chrono::NaiveDate;
+use sqlx::{Sqlite, Transaction};
+use tauri::State;
+
+// ========== CALENDARIO ==========
+//
+// Feriados y tipo de plazo del contrato. Todo cambio recalcula fecha_limite,
+// días de atraso y multa de los requerimientos que aún no están en un informe.
+
+#[tauri::command]
+pub async fn get_configuracion_calendario(db: State<'_, DbState>) -> Result<ConfiguracionCalendario, ErrorComando> {
+    let mut conn = db.pool().acquire().await?;
+    Ok(calendario::cargar_configuracion(&mut conn).await?)
+}
+
+/// Devuelve cuántos requerimientos se recalcularon
+#[tauri::command]
+pub async fn update_configuracion_calendario(
+    db: State<'_, DbState>,
+    config: ConfiguracionCalendario,
+) -> Result<u64, ErrorComando> {
+    config.validar()?;
+
+    let mut tx = db.pool().begin().await?;
+    let contrato_id = contratos::activo(&mut tx).await?;
+    let antes = auditoria::instantanea(&mut tx, Entidad::Configuracion, contrato_id).await?;
+
+    sqlx::query(
+        "UPDATE contratos
+         SET tipo_plazo = ?, dias_no_habiles = ?, updated_at = datetime('now')
+         WHERE activo = 1"
+    )
+    .bind(&config.tipo_plazo)
+    .bind(&config.dias_no_habiles)
+    .execute(&mut *tx)
+    .await?;
+
+    let recalculados = multas::recalcular_pendientes(&mut tx).await?;
+    auditoria::registrar_cambio(&mut tx, &db.operador(), Entidad::Configuracion, contrato_id, "actualizar_calendario", antes).await?;
+
+    tx.commit().await?;
+    Ok(recalculados)
+}
+
+#[tauri::command]
+pub async fn get_feriados(db: State<'_, DbState>) -> Result<Vec<Feriado>, ErrorComando> {
+    sqlx::query_as::<_, Feriado>("SELECT fecha, nombre FROM feriados ORDER BY fecha")
+        .fetch_all(&db.pool())
+        .await
+        .map_err(ErrorComando::from)
+}
+
+#[tauri::command]
+pub async fn add_feriado(
+    db: State<'_, DbState>,
+    fecha: String,
+    nombre: String,
+) -> Result<(), ErrorComando> {
+    let fecha = calendario::parse_fecha(&fecha)
+        .ok_or_else(|| ErrorComando::validacion("fecha", format!("Fecha no válida: {}", fecha)))?;
+
+    let mut tx = db.pool().begin().await?;
+    let cambios = serde_json::json!({ "fecha": fecha.format("%Y-%m-%d").to_string(), "nombre": nombre });
+    guardar_feriados(&mut tx, vec![(fecha, nombre)]).await?;
+    multas::recalcular_pendientes(&mut tx).await?;
+    auditoria::registrar(&mut tx, &db.operador(), Entidad::Feriado, None, "crear", cambios).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn eliminar_feriado(
+    db: State<'_, DbState>,
+    fecha: String,
+) -> Result<(), ErrorComando> {
+    // Misma normalización que al agregar: "18/09/2025" es el feriado 2025-09-18
+    let fecha = calendario::parse_fecha(&fecha)
+        .ok_or_else(|| ErrorComando::validacion("fecha", format!("Fecha no válida: {}", fecha)))?
+        .format("%Y-%m-%d")
+        .to_string();
+    let mut tx = db.pool().begin().await?;
+
+    let nombre: Option<String> = sqlx::query_scalar("SELECT nombre FROM feriados WHERE fecha = ?")
+        .bind(&fecha)
+        .fetch_optional(&mut *tx)
+        .await?;
+    let Some(nombre) = nombre else { return Ok(()) };
+
+    sqlx::query("DELETE FROM feriados WHERE fecha = ?")
+        .bind(&fecha)
+        .execute(&mut *tx)
+        .await?;
+
+    multas::recalcular_pendientes(&mut tx).await?;
+    auditoria::registrar(&mut tx, &db.operador(), Entidad::Feriado, None, "eliminar", serde_json::json!({ "fecha": fecha, "nombre": nombre })).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// CSV con encabezado y columnas `fecha,nombre`. Fechas ya registradas se actualizan.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn importar_feriados_csv(
+    db: State<'_, DbState>,
+    csv_str: String,
+) -> Result<String, ErrorComando> {
+    let mut rdr = csv::Reader::from_reader(csv_str.as_bytes());
+    let mut feriados = Vec::new();
+
+    for (i, result) in rdr.records().enumerate() {
+        let record = result.map_err(|e| ErrorComando::importacion(None, Some(i + 2), e.to_string()))?;
+        let texto = record.get(0).unwrap_or("").trim();
+        if texto.is_empty() {
+            continue;
+        }
+        // Fila 1 = encabezado
+        let fecha = calendario::parse_fecha(texto)
+            .ok_or_else(|| ErrorComando::importacion(None, Some(i + 2), format!("fecha no válida '{}'", texto)))?;
+        let nombre = record.get(1).unwrap_or("").trim().to_string();
+        feriados.push((fecha, nombre));
+    }
+
+    let mut tx = db.pool().begin().await?;
+    let count = guardar_feriados(&mut tx, feriados).await?;
+    multas::recalcular_pendientes(&mut tx).await?;
+    auditoria::registrar(&mut tx, &db.operador(), Entidad::Feriado, None, "importar", serde_json::json!({ "feriados": count })).await?;
+    tx.commit().await?;
+
+    Ok(format!("{} feriados importados", count))
+}
+
+/// Primera hoja del libro, con encabezado y columnas fecha | nombre
+#[tauri::command(rename_all = "snake_case")]
+pub async fn importar_feriados_xlsx_bytes(
+    db: State<'_, DbState>,
+    file_bytes: Vec<u8>,
+) -> Result<String, ErrorComando> {
+    use calamine::{DataType, Reader, Xlsx, open_workbook_from_rs};
+    use std::io::Cursor;
+
+    let cursor = Cursor::new(file_bytes);
+    let mut workbook: Xlsx<_> = open_workbook_from_rs(cursor)
+        .map_err(|e| ErrorComando::importacion(None, None, format!("Error abriendo Excel: {}", e)))?;
+
+    let range = workbook
+        .worksheet_range_at(0)
+        .ok_or_else(|| ErrorComando::importacion(None, None, "El archivo no tiene hojas"))?
+        .map_err(|e| ErrorComando::importacion(None, None, format!("Error leyendo hoja: {}", e)))?;
+
+    let mut feriados = Vec::new();
+    for (i, row) in range.rows().enumerate().skip(1) {
+        let Some(celda) = row.first() else { continue };
+        if celda.is_empty() {
+            continue;
+        }
+        let fecha = fecha_celda(celda)
+            .ok_or_else(|| ErrorComando::importacion(None, Some(i + 1), format!("fecha no válida '{}'", celda)))?;
+        let nombre = row.get(1).map(|c| c.to_string().trim().to_string()).unwrap_or_default();
+        feriados.push((fecha, nombre));
+    }
+
+    let mut tx = db.pool().begin().await?;
+    let count = guardar_feriados(&mut tx, feriados).await?;
+    multas::recalcular_pendientes(&mut tx).await?;
+    auditoria::registrar(&mut tx, &db.operador(), Entidad::Feriado, None, "importar", serde_json::json!({ "feriados": count })).await?;
+    tx.commit().await?;
+
+    Ok(format!("{} feriados importados", count))
+}
+
+/// Celdas con formato fecha (número de serie de Excel) o texto
+fn fecha_celda(celda: &calamine::Data) -> Option<NaiveDate> {
+    use calamine::Data;
+
+    match celda {
+        Data::DateTime(dt) => {
+            NaiveDate::from_ymd_opt(1899, 12, 30)?
+                .checked_add_signed(chrono::Duration::days(dt.as_f64().trunc() as i64))
+        }
+        Data::DateTimeIso(texto) | Data::String(texto) => calendario::parse_fecha(texto),
+        _ => None,
+    }
+}
+
+async fn guardar_feriados(
+    tx: &mut Transaction<'_, Sqlite>,
+    feriados: Vec<(NaiveDate, String)>,
+) -> Result<usize, ErrorComando> {
+    let count = feriados.len();
+    for (fecha, nombre) in feriados {
+        sqlx::query("INSERT OR REPLACE INTO feriados (fecha, nombre) VALUES (?, ?)")
+            .bind(fecha.format("%Y-%m-%d").to_string())
+            .bind(nombre)
+            .execute(&mut **tx)
+            .await?;
+    }
+    Ok(count)
+}
@@ -0,0 +1,72 @@
+use crate::auditoria::{self, Entidad};
+use crate::contratos::{self, Contrato};
+use crate::db::DbState;
+use crate::errores::ErrorComando;
+use crate::multas;
+use tauri::State;
+
+// ========== CONTRATOS ==========
+//
+// Alta, cambio y baja de contratos. Todos los demás comandos trabajan sobre el
+// contrato activo (ver contratos.rs); al cambiarlo el frontend debe recargar
+// catálogos, listados y configuración.
+
+#[tauri::command]
+pub async fn get_contratos(db: State<'_, DbState>) -> Result<Vec<Contrato>, ErrorComando> {
+    let mut conn = db.pool().acquire().await?;
+    contratos::listar(&mut conn).await
+}
+
+/// Crea el contrato (inactivo) y devuelve su id. `copiar_de` copia la
+/// configuración y el catálogo de otro contrato.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn crear_contrato(
+    db: State<'_, DbState>,
+    titulo: String,
+    contratista: String,
+    prefijo_correlativo: String,
+    copiar_de: Option<i64>,
+) -> Result<i64, ErrorComando> {
+    let mut tx = db.pool().begin().await?;
+    let id = contratos::crear(&mut tx, &titulo, &contratista, &prefijo_correlativo, copiar_de).await?;
+    auditoria::registrar_cambio(&mut tx, &db.operador(), Entidad::Configuracion, id, "crear_contrato", None).await?;
+    tx.commit().await?;
+
+    println!("📑 Contrato {} creado: {}", id, titulo);
+    Ok(id)
+}
+
+/// Cambia el contrato activo. Recalcula las multas pendientes del contrato
+/// que se activa, por si cambiaron los feriados mientras estaba inactivo.
+#[tauri::command]
+pub async fn activar_contrato(db: State<'_, DbState>, id: i64) -> Result<(), ErrorComando> {
+    let mut tx = db.pool().begin().await?;
+    let anterior = contratos::activo(&mut tx).await?;
+    contratos::activar(&mut tx, id).await?;
+    multas::recalcular_pendientes(&mut tx).await?;
+    auditoria::registrar(
+        &mut tx,
+        &db.operador(),
+        Entidad::Configuracion,
+        Some(id),
+        "activar_contrato",
+        serde_json::json!({ "anterior": anterior }),
+    )
+    .await?;
+    tx.commit().await?;
+
+    println!("📑 Contrato activo: {} (antes {})", id, anterior);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn eliminar_contrato(db: State<'_, DbState>, id: i64) -> Result<(), ErrorComando> {
+    let mut tx = db.pool().begin().await?;
+    let antes = auditoria::instantanea(&mut tx, Entidad::Configuracion, id).await?;
+    contratos::eliminar(&mut tx, id).await?;
+    auditoria::registrar_cambio(&mut tx, &db.operador(), Entidad::Configuracion, id, "eliminar_contrato", antes).await?;
+    tx.commit().await?;
+
+    println!("🗑️ Contrato {} eliminado", id);
+    Ok(())
+}
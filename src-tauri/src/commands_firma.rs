@@ -1,4 +1,7 @@
+use crate::auditoria::{self, Entidad};
+use crate::contratos;
 use crate::db::DbState;
+use crate::errores::ErrorComando;
 use tauri::State;
 use sqlx::Row;
 
@@ -8,34 +11,38 @@ use sqlx::Row;
 pub async fn importar_firma(
     db: State<'_, DbState>,
     imagen_base64: String,
-) -> Result<(), String> {
+) -> Result<(), ErrorComando> {
     use base64::{Engine as _, engine::general_purpose};
     
     let imagen_bytes = general_purpose::STANDARD
         .decode(&imagen_base64)
-        .map_err(|e| format!("Error decodificando base64: {}", e))?;
+        .map_err(|e| ErrorComando::validacion("imagen_base64", format!("Error decodificando base64: {}", e)))?;
+    
+    let mut tx = db.pool().begin().await?;
+    let contrato_id = contratos::activo(&mut tx).await?;
+    let antes = auditoria::instantanea(&mut tx, Entidad::Configuracion, contrato_id).await?;
     
     sqlx::query(
-        "UPDATE configuracion_contrato 
+        "UPDATE contratos 
          SET firma_png = ?, updated_at = datetime('now')
-         WHERE id = 1"
+         WHERE activo = 1"
     )
     .bind(&imagen_bytes)
-    .execute(&*db.pool)
-    .await
-    .map_err(|e| e.to_string())?;
+    .execute(&mut *tx)
+    .await?;
     
+    auditoria::registrar_cambio(&mut tx, &db.operador(), Entidad::Configuracion, contrato_id, "importar_firma", antes).await?;
+    tx.commit().await?;
     Ok(())
 }
 
 #[tauri::command]
-pub async fn get_firma(db: State<'_, DbState>) -> Result<Option<String>, String> {
+pub async fn get_firma(db: State<'_, DbState>) -> Result<Option<String>, ErrorComando> {
     use base64::{Engine as _, engine::general_purpose};
     
-    let row = sqlx::query("SELECT firma_png FROM configuracion_contrato WHERE id = 1")
-        .fetch_one(&*db.pool)
-        .await
-        .map_err(|e| e.to_string())?;
+    let row = sqlx::query("SELECT firma_png FROM contrato_activo")
+        .fetch_one(&db.pool())
+        .await?;
     
     let firma_bytes: Option<Vec<u8>> = row.get("firma_png");
     
@@ -0,0 +1,31 @@
+use crate::auditoria::{self, Entidad};
+use crate::commands_respaldo::crear_respaldo;
+use crate::db::DbState;
+use crate::errores::ErrorComando;
+use crate::integridad::{self, ReporteIntegridad};
+use tauri::State;
+
+// ========== INTEGRIDAD ==========
+//
+// Diagnóstico de la base abierta (ver integridad.rs). Sin `reparar` no
+// escribe nada; con `reparar` toma un respaldo antes y deja la reparación en
+// la auditoría.
+
+#[tauri::command]
+pub async fn verificar_integridad(db: State<'_, DbState>, reparar: Option<bool>) -> Result<ReporteIntegridad, ErrorComando> {
+    let reparar = reparar.unwrap_or(false);
+    if reparar {
+        crear_respaldo(&db.pool(), &db.db_path(), "reparar_integridad").await?;
+    }
+
+    let mut tx = db.pool().begin().await?;
+    let reporte = integridad::verificar(&mut tx, reparar).await?;
+    if let Some(reparaciones) = &reporte.reparaciones {
+        auditoria::registrar(&mut tx, &db.operador(), Entidad::BaseDatos, None, "reparar_integridad", serde_json::json!(reparaciones))
+            .await?;
+    }
+    tx.commit().await?;
+
+    println!("🩺 Integridad verificada: {} problemas", reporte.problemas());
+    Ok(reporte)
+}
@@ -0,0 +1,514 @@
+use crate::commands;
+use crate::db::{Configuracion, DbState, InformePagoEnriquecido};
+use crate::errores::ErrorComando;
+use crate::pdf::{self, Alineacion, Documento, Fuente};
+use sqlx::Row;
+use tauri::State;
+
+// ========== DOCUMENTOS PDF ==========
+//
+// Documentos oficiales generados en Rust: el mismo registro produce siempre
+// el mismo archivo, sin depender del diálogo de impresión del webview. El
+// diseño sigue el de la vista de impresión (oficio horizontal).
+
+const MARGEN: f32 = 28.0;
+const TAMANO_TABLA: f32 = 8.0;
+const INTERLINEA: f32 = 10.0;
+const ALTO_PIE: f32 = 24.0;
+
+/// Formato es-CL sin decimales (1.234.567), como `formatearNumero` del frontend
+pub(crate) fn formatear_numero(valor: f64) -> String {
+    let entero = valor.round() as i64;
+    let digitos = entero.unsigned_abs().to_string();
+    let mut salida = String::new();
+    for (i, c) in digitos.chars().enumerate() {
+        if i > 0 && (digitos.len() - i).is_multiple_of(3) {
+            salida.push('.');
+        }
+        salida.push(c);
+    }
+    if entero < 0 {
+        salida.insert(0, '-');
+    }
+    salida
+}
+
+/// YYYY-MM-DD → DD/MM/AAAA, como `formatearFecha` del frontend
+pub(crate) fn formatear_fecha(fecha: &str) -> String {
+    match chrono::NaiveDate::parse_from_str(fecha, "%Y-%m-%d") {
+        Ok(f) => f.format("%d/%m/%Y").to_string(),
+        Err(_) => fecha.to_string(),
+    }
+}
+
+pub(crate) struct Columna {
+    pub titulo: &'static str,
+    pub ancho: f32,
+    pub alineacion: Alineacion,
+}
+
+/// Documento con un cursor vertical: agrega páginas cuando el contenido no cabe
+pub(crate) struct Hoja {
+    pub doc: Documento,
+    pub y: f32,
+}
+
+impl Hoja {
+    pub fn new() -> Self {
+        let mut doc = Documento::new(pdf::OFICIO_HORIZONTAL);
+        doc.nueva_pagina();
+        Hoja { doc, y: MARGEN }
+    }
+
+    pub fn ancho_util(&self) -> f32 {
+        self.doc.ancho() - 2.0 * MARGEN
+    }
+
+    fn limite(&self) -> f32 {
+        self.doc.alto() - MARGEN - ALTO_PIE
+    }
+
+    /// Salta de página si no quedan `alto` puntos. Devuelve true si saltó.
+    pub fn asegurar(&mut self, alto: f32) -> bool {
+        if self.y + alto <= self.limite() {
+            return false;
+        }
+        self.doc.nueva_pagina();
+        self.y = MARGEN;
+        true
+    }
+
+    pub fn titulo_centrado(&mut self, texto: &str, fuente: Fuente, tamano: f32) {
+        self.y += tamano;
+        self.doc.texto_alineado(MARGEN, self.ancho_util(), self.y, fuente, tamano, Alineacion::Centro, texto);
+        self.y += tamano * 0.4;
+    }
+
+    pub fn separador(&mut self, grosor: f32) {
+        self.y += 4.0;
+        self.doc.linea(MARGEN, self.y, MARGEN + self.ancho_util(), self.y, grosor);
+        self.y += 10.0;
+    }
+
+    /// Filas etiqueta: valor sobre fondo gris
+    pub fn datos(&mut self, filas: &[(&str, String)]) {
+        let alto = filas.len() as f32 * 16.0 + 8.0;
+        self.asegurar(alto);
+        self.doc.rectangulo(MARGEN, self.y, self.ancho_util(), alto, Some(0.95), false);
+        let mut y = self.y + 4.0;
+        for (i, (etiqueta, valor)) in filas.iter().enumerate() {
+            self.doc.texto(MARGEN + 8.0, y + 11.0, Fuente::Negrita, 10.0, etiqueta);
+            self.doc.texto(MARGEN + 130.0, y + 11.0, Fuente::Normal, 10.0, valor);
+            y += 16.0;
+            if i + 1 < filas.len() {
+                self.doc.linea(MARGEN + 4.0, y, MARGEN + self.ancho_util() - 4.0, y, 0.3);
+            }
+        }
+        self.y += alto + 14.0;
+    }
+
+    /// Texto corrido ajustado al ancho útil
+    pub fn parrafo(&mut self, texto: &str, tamano: f32) {
+        let lineas = pdf::ajustar(texto, self.ancho_util(), Fuente::Normal, tamano);
+        self.asegurar(lineas.len() as f32 * (tamano + 3.0) + 8.0);
+        for linea in &lineas {
+            self.y += tamano + 3.0;
+            self.doc.texto(MARGEN, self.y, Fuente::Normal, tamano, linea);
+        }
+        self.y += 10.0;
+    }
+
+    /// Barra de título de sección
+    pub fn seccion(&mut self, titulo: &str) {
+        self.asegurar(40.0);
+        self.doc.rectangulo(MARGEN, self.y, self.ancho_util(), 18.0, Some(0.88), false);
+        self.doc.linea(MARGEN, self.y + 18.0, MARGEN + self.ancho_util(), self.y + 18.0, 1.0);
+        self.doc.texto(MARGEN + 6.0, self.y + 13.0, Fuente::Negrita, 11.0, titulo);
+        self.y += 26.0;
+    }
+
+    fn encabezado_tabla(&mut self, columnas: &[Columna]) {
+        let alto = 16.0;
+        let mut x = MARGEN;
+        for col in columnas {
+            self.doc.rectangulo(x, self.y, col.ancho, alto, Some(0.8), true);
+            self.doc.texto_alineado(x + 3.0, col.ancho - 6.0, self.y + 11.0, Fuente::Negrita, TAMANO_TABLA, col.alineacion, col.titulo);
+            x += col.ancho;
+        }
+        self.y += alto;
+    }
+
+    /// Tabla con texto ajustado por celda; repite el encabezado en cada página
+    pub fn tabla(&mut self, columnas: &[Columna], filas: &[Vec<String>]) {
+        self.asegurar(40.0);
+        self.encabezado_tabla(columnas);
+
+        for fila in filas {
+            let lineas: Vec<Vec<String>> = columnas
+                .iter()
+                .zip(fila)
+                .map(|(col, texto)| pdf::ajustar(texto, col.ancho - 6.0, Fuente::Normal, TAMANO_TABLA))
+                .collect();
+            let n = lineas.iter().map(Vec::len).max().unwrap_or(1);
+            let alto = n as f32 * INTERLINEA + 6.0;
+
+            if self.asegurar(alto) {
+                self.encabezado_tabla(columnas);
+            }
+
+            let mut x = MARGEN;
+            for (col, celda) in columnas.iter().zip(&lineas) {
+                self.doc.rectangulo(x, self.y, col.ancho, alto, None, true);
+                for (i, linea) in celda.iter().enumerate() {
+                    let base = self.y + 3.0 + (i as f32 + 1.0) * INTERLINEA - 2.0;
+                    self.doc.texto_alineado(x + 3.0, col.ancho - 6.0, base, Fuente::Normal, TAMANO_TABLA, col.alineacion, linea);
+                }
+                x += col.ancho;
+            }
+            self.y += alto;
+        }
+        self.y += 10.0;
+    }
+
+    /// Línea "etiqueta  valor" alineada a la derecha
+    pub fn total(&mut self, etiqueta: &str, valor: &str, fuente: Fuente) {
+        self.asegurar(16.0);
+        self.y += 12.0;
+        let ancho = self.ancho_util();
+        self.doc.texto_alineado(MARGEN, ancho - 130.0, self.y, fuente, 10.0, Alineacion::Derecha, etiqueta);
+        self.doc.texto_alineado(MARGEN + ancho - 120.0, 120.0, self.y, fuente, 10.0, Alineacion::Derecha, valor);
+        self.y += 4.0;
+    }
+
+    /// Bloques de firma lado a lado: (imagen de firma, rótulo, nombre)
+    pub fn firmas(&mut self, bloques: &[(Option<usize>, &str, &str)]) {
+        let alto_firma = 60.0;
+        self.asegurar(alto_firma + 60.0);
+        self.y += 20.0;
+
+        let ancho_bloque = 220.0;
+        let espacio = (self.ancho_util() - ancho_bloque * bloques.len() as f32) / (bloques.len() as f32 + 1.0);
+        let mut x = MARGEN + espacio;
+        for (imagen, rotulo, nombre) in bloques {
+            let linea_y = self.y + alto_firma;
+            if let Some(img) = imagen {
+                let proporcion = self.doc.proporcion_imagen(*img);
+                let (mut ancho, mut alto) = (alto_firma * proporcion, alto_firma);
+                if ancho > ancho_bloque {
+                    ancho = ancho_bloque;
+                    alto = ancho_bloque / proporcion;
+                }
+                self.doc.imagen(*img, x + (ancho_bloque - ancho) / 2.0, linea_y - alto - 2.0, ancho, alto);
+            }
+            self.doc.linea(x, linea_y, x + ancho_bloque, linea_y, 0.8);
+            self.doc.texto_alineado(x, ancho_bloque, linea_y + 14.0, Fuente::Negrita, 10.0, Alineacion::Centro, rotulo);
+            self.doc.texto_alineado(x, ancho_bloque, linea_y + 28.0, Fuente::Normal, 10.0, Alineacion::Centro, nombre);
+            x += ancho_bloque + espacio;
+        }
+        self.y += alto_firma + 40.0;
+    }
+
+    /// Pie "código — Página i de n" en todas las páginas y bytes finales
+    pub fn terminar(mut self, codigo: &str) -> Vec<u8> {
+        let total = self.doc.cantidad_paginas();
+        let (ancho, y) = (self.ancho_util(), self.doc.alto() - MARGEN);
+        for i in 0..total {
+            let texto = format!("{} — Página {} de {}", codigo, i + 1, total);
+            let x = MARGEN + (ancho - pdf::ancho_texto(&texto, Fuente::Normal, 8.0)) / 2.0;
+            self.doc.texto_en(i, x, y, Fuente::Normal, 8.0, &texto);
+        }
+        self.doc.bytes()
+    }
+}
+
+/// Firma del ITO registrada en la configuración, lista para dibujar
+pub(crate) async fn cargar_firma(db: &State<'_, DbState>, doc: &mut Documento) -> Result<Option<usize>, ErrorComando> {
+    let firma: Option<Vec<u8>> = sqlx::query("SELECT firma_png FROM contrato_activo")
+        .fetch_one(&db.pool())
+        .await?
+        .get("firma_png");
+    match firma {
+        Some(bytes) => doc
+            .agregar_png(&bytes)
+            .map(Some)
+            .map_err(|e| ErrorComando::validacion("firma_png", e)),
+        None => Ok(None),
+    }
+}
+
+fn guardar(file_path: &str, bytes: &[u8]) -> Result<(), ErrorComando> {
+    std::fs::write(file_path, bytes)?;
+    println!("📄 PDF generado: {}", file_path);
+    Ok(())
+}
+
+/// Número de zona al inicio del recinto ("3 - Sala cuna" → "3")
+fn zona(recinto: Option<&str>) -> String {
+    let recinto = recinto.unwrap_or("");
+    let numero: String = recinto.chars().take_while(|c| c.is_ascii_digit()).collect();
+    if numero.is_empty() { recinto.to_string() } else { numero }
+}
+
+fn moneda(valor: f64) -> String {
+    format!("${}", formatear_numero(valor))
+}
+
+fn o_sin_especificar(valor: Option<&str>) -> String {
+    valor.filter(|v| !v.is_empty()).unwrap_or("Sin especificar").to_string()
+}
+
+pub(crate) async fn documento_orden_trabajo(
+    db: &State<'_, DbState>,
+    config: &Configuracion,
+    ot_id: i64,
+) -> Result<Vec<u8>, ErrorComando> {
+    let ot = commands::get_ordenes_trabajo(db.clone())
+        .await?
+        .into_iter()
+        .find(|ot| ot.id == ot_id)
+        .ok_or_else(|| ErrorComando::no_encontrado("OT", ot_id))?;
+    let jardin = commands::get_jardin_by_codigo(db.clone(), ot.jardin_codigo.clone()).await?;
+    let mut requerimientos = commands::get_orden_trabajo_detalle(db.clone(), ot_id).await?;
+    requerimientos.sort_by_key(|r| r.id);
+
+    let mut hoja = Hoja::new();
+    let firma = cargar_firma(db, &mut hoja.doc).await?;
+
+    hoja.titulo_centrado(&config.titulo, Fuente::Normal, 11.0);
+    hoja.titulo_centrado("ORDEN DE TRABAJO", Fuente::Negrita, 18.0);
+    hoja.titulo_centrado(&ot.codigo, Fuente::Negrita, 14.0);
+    hoja.separador(1.5);
+
+    let mut datos = vec![
+        ("Jardín:", format!("{} - {}", ot.jardin_codigo, jardin.map(|j| j.nombre).unwrap_or_else(|| "N/A".to_string()))),
+        ("Fecha Creación:", formatear_fecha(&ot.fecha_creacion)),
+        ("Contratista:", o_sin_especificar(Some(&config.contratista))),
+        ("ITO:", o_sin_especificar(config.ito_nombre.as_deref())),
+    ];
+    if let Some(obs) = ot.observaciones.as_ref().filter(|o| !o.is_empty()) {
+        datos.push(("Observaciones:", obs.clone()));
+    }
+    hoja.datos(&datos);
+
+    hoja.seccion("REQUERIMIENTOS INCLUIDOS");
+    let columnas = [
+        Columna { titulo: "#", ancho: 24.0, alineacion: Alineacion::Centro },
+        Columna { titulo: "Zona", ancho: 60.0, alineacion: Alineacion::Izquierda },
+        Columna { titulo: "Item - Partida", ancho: 230.0, alineacion: Alineacion::Izquierda },
+        Columna { titulo: "Cantidad", ancho: 70.0, alineacion: Alineacion::Derecha },
+        Columna { titulo: "F. Inicio", ancho: 62.0, alineacion: Alineacion::Centro },
+        Columna { titulo: "Plazo", ancho: 50.0, alineacion: Alineacion::Centro },
+        Columna { titulo: "Plazo Adic.", ancho: 55.0, alineacion: Alineacion::Centro },
+        Columna { titulo: "F. Límite", ancho: 62.0, alineacion: Alineacion::Centro },
+        Columna { titulo: "Descripción", ancho: 170.0, alineacion: Alineacion::Izquierda },
+        Columna { titulo: "Observaciones", ancho: 169.0, alineacion: Alineacion::Izquierda },
+    ];
+    let filas: Vec<Vec<String>> = requerimientos
+        .iter()
+        .enumerate()
+        .map(|(i, r)| {
+            vec![
+                (i + 1).to_string(),
+                zona(r.recinto.as_deref()),
+                format!("{} - {}", r.partida_item, r.partida_nombre.as_deref().unwrap_or("")),
+                format!("{} {}", formatear_numero(r.cantidad), r.partida_unidad.as_deref().unwrap_or("")),
+                formatear_fecha(&r.fecha_inicio),
+                format!("{} días", r.plazo_dias),
+                if r.plazo_adicional > 0 { format!("{} días", r.plazo_adicional) } else { "-".to_string() },
+                r.fecha_limite.as_deref().map(formatear_fecha).unwrap_or_else(|| "-".to_string()),
+                r.descripcion.clone().filter(|d| !d.is_empty()).unwrap_or_else(|| "-".to_string()),
+                r.observaciones.clone().filter(|o| !o.is_empty()).unwrap_or_else(|| "-".to_string()),
+            ]
+        })
+        .collect();
+    if filas.is_empty() {
+        hoja.total("No hay requerimientos asignados", "", Fuente::Normal);
+    } else {
+        hoja.tabla(&columnas, &filas);
+    }
+    hoja.total("Total Requerimientos:", &requerimientos.len().to_string(), Fuente::Negrita);
+
+    let ito = o_sin_especificar(config.ito_nombre.as_deref());
+    let contratista = o_sin_especificar(Some(&config.contratista));
+    hoja.firmas(&[(firma, "Firma ITO", &ito), (None, "Firma Contratista", &contratista)]);
+
+    Ok(hoja.terminar(&ot.codigo))
+}
+
+/// Estado con la fecha del último paso, p. ej. "Emitido el 05/03/2025"
+fn estado_informe(informe: &InformePagoEnriquecido) -> String {
+    let (nombre, fecha) = match informe.estado.as_str() {
+        "emitido" => ("Emitido", &informe.fecha_emision),
+        "aprobado" => ("Aprobado por mandante", &informe.fecha_aprobacion),
+        "pagado" => ("Pagado", &informe.fecha_pago),
+        "anulado" => ("ANULADO", &informe.fecha_anulacion),
+        _ => ("Borrador", &None),
+    };
+    let mut texto = match fecha {
+        Some(fecha) => format!("{} el {}", nombre, formatear_fecha(fecha)),
+        None => nombre.to_string(),
+    };
+    if let Some(motivo) = &informe.motivo_anulacion {
+        texto.push_str(&format!(" — {}", motivo));
+    }
+    texto
+}
+
+pub(crate) async fn documento_informe_pago(
+    db: &State<'_, DbState>,
+    config: &Configuracion,
+    informe: &InformePagoEnriquecido,
+) -> Result<Vec<u8>, ErrorComando> {
+    let mut requerimientos = commands::get_informe_pago_detalle(db.clone(), informe.id).await?;
+    requerimientos.sort_by(|a, b| a.ot_codigo.cmp(&b.ot_codigo).then(a.id.cmp(&b.id)));
+
+    let mut hoja = Hoja::new();
+    let firma = cargar_firma(db, &mut hoja.doc).await?;
+
+    hoja.titulo_centrado("INFORME DE PAGO", Fuente::Negrita, 18.0);
+    hoja.titulo_centrado(&config.titulo, Fuente::Normal, 11.0);
+    hoja.titulo_centrado(&informe.codigo, Fuente::Negrita, 14.0);
+    hoja.separador(1.5);
+
+    let mut datos = vec![
+        ("Jardín:", format!("{} - {}", informe.jardin_codigo, informe.jardin_nombre.as_deref().unwrap_or("N/A"))),
+        ("Fecha Creación:", formatear_fecha(&informe.fecha_creacion)),
+        ("Contratista:", o_sin_especificar(Some(&config.contratista))),
+        ("ITO:", o_sin_especificar(config.ito_nombre.as_deref())),
+        ("Estado:", estado_informe(informe)),
+    ];
+    if let Some(obs) = informe.observaciones.as_ref().filter(|o| !o.is_empty()) {
+        datos.push(("Observaciones:", obs.clone()));
+    }
+    hoja.datos(&datos);
+
+    hoja.seccion("REQUERIMIENTOS A PAGO");
+    hoja.parrafo(
+        "Mediante el presente solicito proceder al pago, al contratista, de los siguientes servicios con recepción conforme:",
+        10.0,
+    );
+    let columnas = [
+        Columna { titulo: "Zona", ancho: 36.0, alineacion: Alineacion::Centro },
+        Columna { titulo: "Item - Partida", ancho: 200.0, alineacion: Alineacion::Izquierda },
+        Columna { titulo: "Cant.- Un.", ancho: 76.0, alineacion: Alineacion::Derecha },
+        Columna { titulo: "P. Unit.", ancho: 70.0, alineacion: Alineacion::Derecha },
+        Columna { titulo: "P. Total", ancho: 80.0, alineacion: Alineacion::Derecha },
+        Columna { titulo: "F. Inicio", ancho: 62.0, alineacion: Alineacion::Centro },
+        Columna { titulo: "T. Plazo", ancho: 50.0, alineacion: Alineacion::Centro },
+        Columna { titulo: "F. Límite", ancho: 62.0, alineacion: Alineacion::Centro },
+        Columna { titulo: "F. Recepción", ancho: 68.0, alineacion: Alineacion::Centro },
+        Columna { titulo: "D. Atraso", ancho: 50.0, alineacion: Alineacion::Derecha },
+        Columna { titulo: "Multa", ancho: 90.0, alineacion: Alineacion::Derecha },
+        Columna { titulo: "A Pago", ancho: 108.0, alineacion: Alineacion::Derecha },
+    ];
+    let fecha = |f: Option<&str>| f.map(formatear_fecha).unwrap_or_else(|| "-".to_string());
+    let filas: Vec<Vec<String>> = requerimientos
+        .iter()
+        .map(|r| {
+            vec![
+                zona(r.recinto.as_deref()),
+                format!("{} - {}", r.partida_item, r.partida_nombre.as_deref().unwrap_or("")),
+                format!("{} {}", formatear_numero(r.cantidad), r.partida_unidad.as_deref().unwrap_or("")),
+                moneda(r.precio_unitario.unwrap_or(0.0)),
+                moneda(r.precio_total),
+                formatear_fecha(&r.fecha_inicio),
+                if r.plazo_total > 0 { r.plazo_total.to_string() } else { "-".to_string() },
+                fecha(r.fecha_limite.as_deref()),
+                fecha(r.fecha_recepcion.as_deref()),
+                r.dias_atraso.max(0).to_string(),
+                moneda(r.multa),
+                moneda(r.a_pago),
+            ]
+        })
+        .collect();
+    if filas.is_empty() {
+        hoja.total("No hay requerimientos asignados", "", Fuente::Normal);
+    } else {
+        hoja.tabla(&columnas, &filas);
+    }
+
+    // Resumen con los montos guardados en el informe (`neto` incluye lo exento)
+    hoja.total("Neto Afecto:", &moneda(informe.neto - informe.neto_exento), Fuente::Normal);
+    if informe.neto_exento != 0.0 {
+        hoja.total("Neto Exento:", &moneda(informe.neto_exento), Fuente::Normal);
+    }
+    if informe.gastos_generales != 0.0 {
+        let etiqueta = format!("Gastos Generales ({}%):", informe.porcentaje_gastos_generales);
+        hoja.total(&etiqueta, &moneda(informe.gastos_generales), Fuente::Normal);
+    }
+    hoja.total(&format!("Utilidades ({}%):", informe.porcentaje_utilidades), &moneda(informe.utilidades), Fuente::Normal);
+    hoja.total(&format!("IVA ({}%):", informe.porcentaje_iva), &moneda(informe.iva), Fuente::Normal);
+    hoja.total("Total:", &moneda(informe.total_final), Fuente::Negrita);
+
+    let ito = o_sin_especificar(config.ito_nombre.as_deref());
+    hoja.firmas(&[(firma, "Firma ITO", &ito)]);
+
+    Ok(hoja.terminar(&informe.codigo))
+}
+
+// ========== COMANDOS ==========
+
+/// PDF oficial de la OT con la firma del ITO
+#[tauri::command(rename_all = "snake_case")]
+pub async fn exportar_orden_trabajo_pdf(
+    db: State<'_, DbState>,
+    file_path: String,
+    ot_id: i64,
+) -> Result<(), ErrorComando> {
+    let config = commands::get_configuracion(db.clone()).await?;
+    let bytes = documento_orden_trabajo(&db, &config, ot_id).await?;
+    guardar(&file_path, &bytes)
+}
+
+/// PDF oficial del informe de pago con la firma del ITO
+#[tauri::command(rename_all = "snake_case")]
+pub async fn exportar_informe_pago_pdf(
+    db: State<'_, DbState>,
+    file_path: String,
+    informe_id: i64,
+) -> Result<(), ErrorComando> {
+    let config = commands::get_configuracion(db.clone()).await?;
+    let informe = commands::get_informes_pago(db.clone())
+        .await?
+        .into_iter()
+        .find(|ip| ip.id == informe_id)
+        .ok_or_else(|| ErrorComando::no_encontrado("Informe", informe_id))?;
+    let bytes = documento_informe_pago(&db, &config, &informe).await?;
+    guardar(&file_path, &bytes)
+}
+
+/// Los informes no anulados creados en el mes (`YYYY-MM`), uno por archivo
+/// `{codigo}.pdf` dentro de `carpeta`. Devuelve las rutas generadas.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn exportar_informes_pago_mes_pdf(
+    db: State<'_, DbState>,
+    carpeta: String,
+    mes: String,
+) -> Result<Vec<String>, ErrorComando> {
+    // Normalizado: "2025-1" no debe calzar también con octubre a diciembre
+    let mes = match chrono::NaiveDate::parse_from_str(&format!("{}-01", mes), "%Y-%m-%d") {
+        Ok(primer_dia) => primer_dia.format("%Y-%m").to_string(),
+        Err(_) => return Err(ErrorComando::validacion("mes", format!("Mes inválido '{}', se espera AAAA-MM", mes))),
+    };
+    let config = commands::get_configuracion(db.clone()).await?;
+    let mut informes: Vec<InformePagoEnriquecido> = commands::get_informes_pago(db.clone())
+        .await?
+        .into_iter()
+        .filter(|ip| ip.fecha_creacion.starts_with(&mes) && ip.estado != "anulado")
+        .collect();
+    informes.sort_by(|a, b| a.codigo.cmp(&b.codigo));
+
+    let carpeta = std::path::Path::new(&carpeta);
+    std::fs::create_dir_all(carpeta)?;
+
+    let mut rutas = Vec::new();
+    for informe in &informes {
+        let bytes = documento_informe_pago(&db, &config, informe).await?;
+        let ruta = carpeta.join(format!("{}.pdf", informe.codigo));
+        std::fs::write(&ruta, &bytes)?;
+        rutas.push(ruta.to_string_lossy().into_owned());
+    }
+
+    println!("📄 {} informes de pago de {} generados en {}", rutas.len(), mes, carpeta.display());
+    Ok(rutas)
+}
@@ -0,0 +1,67 @@
+use crate::auditoria::{self, Entidad};
+use crate::commands_respaldo::crear_respaldo;
+use crate::contratos;
+use crate::db::DbState;
+use crate::errores::ErrorComando;
+use crate::precios::{self, NuevoReajuste, PrecioPartida, Reajuste, ResultadoReajuste};
+use tauri::State;
+
+// ========== PRECIOS Y REAJUSTES ==========
+//
+// Historial de precios por partida y reajustes del itemizado (ver precios.rs).
+// Después de un reajuste el frontend debe recargar partidas y requerimientos.
+// Aplicar un reajuste toma antes un respaldo, igual que aplicar el catálogo.
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_precios_partida(db: State<'_, DbState>, partida_id: i64) -> Result<Vec<PrecioPartida>, ErrorComando> {
+    let mut conn = db.pool().acquire().await?;
+    precios::historial(&mut conn, partida_id).await
+}
+
+/// Precio con que se valorizaría un requerimiento de la partida que inicia en `fecha`
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_precio_vigente(
+    db: State<'_, DbState>,
+    partida_item: String,
+    fecha: String,
+) -> Result<Option<PrecioPartida>, ErrorComando> {
+    let mut conn = db.pool().acquire().await?;
+    precios::vigente(&mut conn, &partida_item, &fecha).await
+}
+
+#[tauri::command]
+pub async fn get_reajustes(db: State<'_, DbState>) -> Result<Vec<Reajuste>, ErrorComando> {
+    let mut conn = db.pool().acquire().await?;
+    precios::listar_reajustes(&mut conn).await
+}
+
+#[tauri::command]
+pub async fn aplicar_reajuste(db: State<'_, DbState>, reajuste: NuevoReajuste) -> Result<ResultadoReajuste, ErrorComando> {
+    crear_respaldo(&db.pool(), &db.db_path(), "aplicar_reajuste").await?;
+
+    let mut tx = db.pool().begin().await?;
+    let contrato_id = contratos::activo(&mut tx).await?;
+    let resultado = precios::reajustar(&mut tx, &db.operador(), &reajuste).await?;
+    auditoria::registrar(
+        &mut tx,
+        &db.operador(),
+        Entidad::Configuracion,
+        Some(contrato_id),
+        "aplicar_reajuste",
+        serde_json::json!({
+            "reajuste_id": resultado.id,
+            "vigente_desde": reajuste.vigente_desde,
+            "porcentaje": resultado.porcentaje,
+            "partidas": resultado.partidas,
+            "requerimientos": resultado.requerimientos,
+        }),
+    )
+    .await?;
+    tx.commit().await?;
+
+    println!(
+        "💲 Reajuste {:.2}% desde {}: {} partidas, {} requerimientos revalorizados",
+        resultado.porcentaje, reajuste.vigente_desde, resultado.partidas, resultado.requerimientos.len()
+    );
+    Ok(resultado)
+}
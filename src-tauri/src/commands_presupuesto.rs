@@ -0,0 +1,87 @@
+use crate::auditoria::{self, Entidad};
+use crate::contratos;
+use crate::db::DbState;
+use crate::errores::ErrorComando;
+use crate::presupuesto::{self, DatosContrato, ResumenPresupuesto};
+use tauri::State;
+
+// ========== PRESUPUESTO DEL CONTRATO ==========
+//
+// Monto contratado (neto), vigencia y asignación por jardín. `get_resumen_presupuesto`
+// entrega comprometido / recibido / facturado / disponible y las alertas para
+// avisar al mandante antes de agotar el contrato.
+
+#[tauri::command]
+pub async fn get_datos_contrato(db: State<'_, DbState>) -> Result<DatosContrato, ErrorComando> {
+    let mut conn = db.pool().acquire().await?;
+    presupuesto::cargar_contrato(&mut conn).await
+}
+
+#[tauri::command]
+pub async fn update_datos_contrato(db: State<'_, DbState>, datos: DatosContrato) -> Result<(), ErrorComando> {
+    datos.validar()?;
+
+    let mut tx = db.pool().begin().await?;
+    let contrato_id = contratos::activo(&mut tx).await?;
+    let antes = auditoria::instantanea(&mut tx, Entidad::Configuracion, contrato_id).await?;
+
+    sqlx::query(
+        "UPDATE contratos
+         SET monto_contratado = ?, fecha_inicio = ?, fecha_termino = ?, umbral_alerta = ?, updated_at = datetime('now')
+         WHERE activo = 1"
+    )
+    .bind(datos.monto_contratado)
+    .bind(&datos.fecha_inicio)
+    .bind(&datos.fecha_termino)
+    .bind(datos.umbral_alerta)
+    .execute(&mut *tx)
+    .await?;
+
+    auditoria::registrar_cambio(&mut tx, &db.operador(), Entidad::Configuracion, contrato_id, "actualizar_presupuesto", antes).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// `monto = None` quita la asignación del jardín
+#[tauri::command(rename_all = "snake_case")]
+pub async fn asignar_presupuesto_jardin(
+    db: State<'_, DbState>,
+    jardin_codigo: String,
+    monto: Option<f64>,
+) -> Result<(), ErrorComando> {
+    let mut tx = db.pool().begin().await?;
+    let antes: Option<f64> = sqlx::query_scalar(
+        "SELECT monto FROM presupuesto_jardines WHERE contrato_id = (SELECT id FROM contrato_activo) AND jardin_codigo = ?"
+    )
+        .bind(&jardin_codigo)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+    presupuesto::asignar(&mut tx, &jardin_codigo, monto).await?;
+
+    let jardin_id: i64 = sqlx::query_scalar(
+        "SELECT id FROM jardines WHERE contrato_id = (SELECT id FROM contrato_activo) AND codigo = ?"
+    )
+        .bind(&jardin_codigo)
+        .fetch_one(&mut *tx)
+        .await?;
+    auditoria::registrar(
+        &mut tx,
+        &db.operador(),
+        Entidad::Jardin,
+        Some(jardin_id),
+        "asignar_presupuesto",
+        serde_json::json!({ "jardin_codigo": jardin_codigo, "antes": antes, "despues": monto }),
+    )
+    .await?;
+    tx.commit().await?;
+
+    println!("💰 Presupuesto de {}: {:?}", jardin_codigo, monto);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_resumen_presupuesto(db: State<'_, DbState>) -> Result<ResumenPresupuesto, ErrorComando> {
+    let mut conn = db.pool().acquire().await?;
+    presupuesto::resumen(&mut conn, chrono::Local::now().date_naive()).await
+}
@@ -0,0 +1,61 @@
+use crate::auditoria::{self, Entidad};
+use crate::db::DbState;
+use crate::errores::ErrorComando;
+use crate::recepciones::{self, Recepcion};
+use tauri::State;
+
+// ========== RECEPCIONES ==========
+//
+// Entregas parciales de un requerimiento. `actualizar_fecha_recepcion` sigue
+// disponible para recibir de una vez todo el saldo.
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_recepciones(
+    db: State<'_, DbState>,
+    requerimiento_id: i64,
+) -> Result<Vec<Recepcion>, ErrorComando> {
+    let mut conn = db.pool().acquire().await?;
+    recepciones::listar(&mut conn, requerimiento_id).await
+}
+
+/// Registra una entrega parcial. Al completar la cantidad el requerimiento pasa a recepcionado.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn agregar_recepcion(
+    db: State<'_, DbState>,
+    requerimiento_id: i64,
+    fecha: String,
+    cantidad: f64,
+    observaciones: Option<String>,
+) -> Result<i64, ErrorComando> {
+    let mut tx = db.pool().begin().await?;
+    let antes = auditoria::instantanea(&mut tx, Entidad::Requerimiento, requerimiento_id).await?;
+
+    let id = recepciones::agregar(&mut tx, requerimiento_id, &fecha, cantidad, observaciones.as_deref()).await?;
+
+    auditoria::registrar_cambio(&mut tx, &db.operador(), Entidad::Requerimiento, requerimiento_id, "agregar_recepcion", antes)
+        .await?;
+    tx.commit().await?;
+
+    println!("📦 Recepción {} registrada: {} el {} (requerimiento {})", id, cantidad, fecha, requerimiento_id);
+    Ok(id)
+}
+
+#[tauri::command]
+pub async fn eliminar_recepcion(db: State<'_, DbState>, id: i64) -> Result<(), ErrorComando> {
+    let mut tx = db.pool().begin().await?;
+    let requerimiento_id: Option<i64> = sqlx::query_scalar("SELECT requerimiento_id FROM recepciones WHERE id = ?")
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    let requerimiento_id = requerimiento_id.ok_or_else(|| ErrorComando::no_encontrado("Recepción", id))?;
+    let antes = auditoria::instantanea(&mut tx, Entidad::Requerimiento, requerimiento_id).await?;
+
+    recepciones::eliminar(&mut tx, id).await?;
+
+    auditoria::registrar_cambio(&mut tx, &db.operador(), Entidad::Requerimiento, requerimiento_id, "eliminar_recepcion", antes)
+        .await?;
+    tx.commit().await?;
+
+    println!("🗑️ Recepción {} eliminada (requerimiento {})", id, requerimiento_id);
+    Ok(())
+}
@@ -0,0 +1,263 @@
+use crate::auditoria::{self, Entidad};
+use crate::db::DbState;
+use crate::errores::ErrorComando;
+use sqlx::{Connection, Sqlite, SqlitePool};
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+// ========== RESPALDOS ==========
+//
+// Copias de la base abierta con `VACUUM INTO` (snapshot consistente aun con
+// WAL) en `<base>.respaldos` junto al archivo, una carpeta por base para que
+// listar, restaurar y rotar no mezclen bases. Se toman al iniciar y antes de
+// cada comando destructivo; se conservan las últimas MAX_RESPALDOS generaciones.
+
+const MAX_RESPALDOS: usize = 20;
+const PREFIJO: &str = "respaldo_";
+
+#[derive(Debug, serde::Serialize)]
+pub struct Respaldo {
+    pub nombre: String,
+    pub fecha: String,
+    pub motivo: String,
+    pub tamano_bytes: u64,
+}
+
+pub fn dir_respaldos(db_path: &Path) -> PathBuf {
+    let base = db_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    db_path.parent().unwrap_or_else(|| Path::new(".")).join(format!("{}.respaldos", base))
+}
+
+/// Antes de que cada base tuviera su carpeta, los respaldos vivían en
+/// `respaldos/` junto a la base por defecto; pasan a ser suyos una sola vez.
+pub fn mover_respaldos_anteriores(db_path: &Path) {
+    let anterior = db_path.parent().unwrap_or_else(|| Path::new(".")).join("respaldos");
+    let nuevo = dir_respaldos(db_path);
+    if anterior.is_dir() && !nuevo.exists() {
+        match std::fs::rename(&anterior, &nuevo) {
+            Ok(()) => println!("💾 Respaldos movidos a {}", nuevo.display()),
+            Err(e) => println!("⚠️ No se pudieron mover los respaldos a {}: {}", nuevo.display(), e),
+        }
+    }
+}
+
+/// Toma un respaldo y rota los antiguos. `motivo` queda en el nombre del archivo.
+pub async fn crear_respaldo(pool: &SqlitePool, db_path: &Path, motivo: &str) -> Result<Respaldo, ErrorComando> {
+    let dir = dir_respaldos(db_path);
+    std::fs::create_dir_all(&dir).map_err(|e| ErrorComando::Io { mensaje: format!("Error creando carpeta de respaldos: {}", e) })?;
+
+    let motivo: String = motivo
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    let nombre = format!(
+        "{}{}_{}.db",
+        PREFIJO,
+        chrono::Local::now().format("%Y%m%d_%H%M%S_%3f"),
+        motivo
+    );
+    let destino = dir.join(&nombre);
+
+    sqlx::query("VACUUM INTO ?")
+        .bind(destino.to_string_lossy().to_string())
+        .execute(pool)
+        .await
+        .map_err(|e| ErrorComando::Io { mensaje: format!("Error creando respaldo: {}", e) })?;
+
+    println!("💾 Respaldo creado: {}", destino.display());
+
+    rotar_respaldos(&dir)?;
+
+    leer_respaldo(&destino).ok_or_else(|| ErrorComando::no_encontrado("Respaldo", nombre))
+}
+
+fn leer_respaldo(path: &Path) -> Option<Respaldo> {
+    let nombre = path.file_name()?.to_str()?.to_string();
+    let base = nombre.strip_prefix(PREFIJO)?.strip_suffix(".db")?;
+
+    // respaldo_YYYYMMDD_HHMMSS_mmm_motivo.db
+    let partes: Vec<&str> = base.splitn(4, '_').collect();
+    if partes.len() != 4 {
+        return None;
+    }
+    let fecha = chrono::NaiveDateTime::parse_from_str(
+        &format!("{}{}", partes[0], partes[1]),
+        "%Y%m%d%H%M%S",
+    )
+    .ok()?;
+    let motivo = partes[3].to_string();
+
+    Some(Respaldo {
+        nombre,
+        fecha: fecha.format("%Y-%m-%d %H:%M:%S").to_string(),
+        motivo,
+        tamano_bytes: std::fs::metadata(path).ok()?.len(),
+    })
+}
+
+/// Respaldos existentes, del más reciente al más antiguo
+fn listar(dir: &Path) -> Result<Vec<Respaldo>, ErrorComando> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut respaldos: Vec<Respaldo> = std::fs::read_dir(dir)
+        .map_err(|e| ErrorComando::Io { mensaje: format!("Error leyendo respaldos: {}", e) })?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| leer_respaldo(&entry.path()))
+        .collect();
+
+    // El nombre empieza con la marca de tiempo: orden lexicográfico = cronológico
+    respaldos.sort_by(|a, b| b.nombre.cmp(&a.nombre));
+    Ok(respaldos)
+}
+
+fn rotar_respaldos(dir: &Path) -> Result<(), ErrorComando> {
+    for viejo in listar(dir)?.into_iter().skip(MAX_RESPALDOS) {
+        std::fs::remove_file(dir.join(&viejo.nombre))
+            .map_err(|e| ErrorComando::Io { mensaje: format!("Error eliminando respaldo {}: {}", viejo.nombre, e) })?;
+        println!("🗑️ Respaldo rotado: {}", viejo.nombre);
+    }
+    Ok(())
+}
+
+/// Reemplaza el contenido de la base activa por el de un respaldo.
+///
+/// El respaldo se copia a un archivo temporal y se migra a la versión actual
+/// del schema antes de copiar sus tablas, de modo que respaldos de versiones
+/// anteriores también se pueden restaurar. La auditoría no se reemplaza: la
+/// restauración queda registrada a continuación del historial actual.
+pub async fn restaurar(pool: &SqlitePool, db_path: &Path, nombre: &str, operador: &str) -> Result<(), ErrorComando> {
+    let dir = dir_respaldos(db_path);
+    let origen = dir.join(nombre);
+    if nombre.contains(['/', '\\']) || leer_respaldo(&origen).is_none() {
+        return Err(ErrorComando::no_encontrado("Respaldo", nombre));
+    }
+
+    // Respaldo del estado actual: la restauración también se puede deshacer
+    crear_respaldo(pool, db_path, "antes-restaurar").await?;
+
+    let temporal = dir.join(".restaurando.db");
+    std::fs::copy(&origen, &temporal).map_err(|e| ErrorComando::Io { mensaje: format!("Error copiando respaldo: {}", e) })?;
+
+    let resultado = copiar_desde(pool, &temporal, nombre, operador).await;
+
+    for sufijo in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{}", temporal.display(), sufijo));
+    }
+
+    resultado?;
+    println!("♻️ Respaldo restaurado: {}", nombre);
+    Ok(())
+}
+
+async fn copiar_desde(pool: &SqlitePool, temporal: &Path, nombre: &str, operador: &str) -> Result<(), ErrorComando> {
+    let pool_respaldo = SqlitePool::connect_with(
+        sqlx::sqlite::SqliteConnectOptions::new().filename(temporal)
+    )
+    .await?;
+    let migrado = crate::migraciones::migrar(&pool_respaldo).await;
+    pool_respaldo.close().await;
+    migrado?;
+
+    // ATTACH es por conexión: toda la copia ocurre en una sola
+    let mut conn = pool.acquire().await?;
+    sqlx::query("ATTACH DATABASE ? AS respaldo")
+        .bind(temporal.to_string_lossy().to_string())
+        .execute(&mut *conn)
+        .await?;
+
+    // Sin claves foráneas durante la copia: el orden de las tablas no importa y
+    // los ON DELETE CASCADE no borran filas ya copiadas
+    sqlx::query("PRAGMA foreign_keys = OFF")
+        .execute(&mut *conn)
+        .await?;
+    let copia = copiar_tablas(&mut conn, nombre, operador).await;
+    sqlx::query("PRAGMA foreign_keys = ON")
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query("DETACH DATABASE respaldo")
+        .execute(&mut *conn)
+        .await?;
+
+    copia
+}
+
+async fn copiar_tablas(
+    conn: &mut sqlx::pool::PoolConnection<Sqlite>,
+    nombre: &str,
+    operador: &str,
+) -> Result<(), ErrorComando> {
+    // Los movimientos primero: los triggers no dejan borrar jardines ni
+    // partidas que todavía usan
+    let tablas: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM main.sqlite_master
+         WHERE type = 'table' AND name NOT LIKE 'sqlite_%'
+           AND name NOT IN ('schema_migrations', 'auditoria')
+         ORDER BY name NOT IN ('requerimientos', 'ordenes_trabajo', 'informes_pago')"
+    )
+    .fetch_all(&mut **conn)
+    .await?;
+
+    let mut tx = conn.begin().await?;
+
+    // Se vacían todas antes de copiar: los triggers de borrado de
+    // requerimientos, OTs e informes no deben alcanzar filas ya copiadas
+    for tabla in &tablas {
+        sqlx::query(&format!("DELETE FROM main.\"{}\"", tabla))
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    for tabla in &tablas {
+        let columnas: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info(?)")
+            .bind(tabla)
+            .fetch_all(&mut *tx)
+            .await?;
+        let columnas = columnas
+            .iter()
+            .map(|c| format!("\"{}\"", c))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        sqlx::query(&format!(
+            "INSERT INTO main.\"{0}\" ({1}) SELECT {1} FROM respaldo.\"{0}\"",
+            tabla, columnas
+        ))
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ErrorComando::Internal { mensaje: format!("Error restaurando tabla {}: {}", tabla, e) })?;
+    }
+
+    // Contadores AUTOINCREMENT
+    sqlx::query("DELETE FROM main.sqlite_sequence WHERE name != 'auditoria'")
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        "INSERT INTO main.sqlite_sequence (name, seq)
+         SELECT name, seq FROM respaldo.sqlite_sequence WHERE name != 'auditoria'"
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    auditoria::registrar(&mut tx, operador, Entidad::BaseDatos, None, "restaurar_respaldo", serde_json::json!({ "respaldo": nombre })).await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+// ========== COMANDOS ==========
+
+#[tauri::command]
+pub async fn listar_respaldos(db: State<'_, DbState>) -> Result<Vec<Respaldo>, ErrorComando> {
+    listar(&dir_respaldos(&db.db_path()))
+}
+
+#[tauri::command]
+pub async fn restaurar_respaldo(
+    db: State<'_, DbState>,
+    nombre: String,
+) -> Result<(), ErrorComando> {
+    restaurar(&db.pool(), &db.db_path(), &nombre, &db.operador()).await
+}
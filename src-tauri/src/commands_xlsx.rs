@@ -0,0 +1,418 @@
+use crate::commands;
+use crate::db::{DbState, RequerimientoEnriquecido};
+use crate::errores::ErrorComando;
+use rust_xlsxwriter::{Color, ExcelDateTime, Format, FormatAlign, FormatBorder, Workbook, Worksheet};
+use tauri::State;
+
+// ========== EXPORTAR XLSX ==========
+//
+// Planillas para adjuntar a cada estado de pago. Los montos se escriben como
+// números (formato $ sin decimales) y las fechas como fechas de Excel, para
+// que el mandante pueda sumar y filtrar sin reformatear.
+
+const FORMATO_MONEDA: &str = "$ #,##0";
+const FORMATO_FECHA: &str = "dd-mm-yyyy";
+
+struct Formatos {
+    titulo: Format,
+    etiqueta: Format,
+    encabezado: Format,
+    texto: Format,
+    numero: Format,
+    entero: Format,
+    moneda: Format,
+    fecha: Format,
+    total_etiqueta: Format,
+    total_moneda: Format,
+}
+
+impl Formatos {
+    fn new() -> Self {
+        let borde = Format::new().set_border(FormatBorder::Thin);
+        Formatos {
+            titulo: Format::new().set_bold().set_font_size(14),
+            etiqueta: Format::new().set_bold(),
+            encabezado: borde
+                .clone()
+                .set_bold()
+                .set_text_wrap()
+                .set_align(FormatAlign::Center)
+                .set_align(FormatAlign::VerticalCenter)
+                .set_background_color(Color::RGB(0xD9E1F2)),
+            texto: borde.clone(),
+            numero: borde.clone().set_num_format("#,##0.##"),
+            entero: borde.clone().set_num_format("0"),
+            moneda: borde.clone().set_num_format(FORMATO_MONEDA),
+            fecha: borde.clone().set_num_format(FORMATO_FECHA).set_align(FormatAlign::Center),
+            total_etiqueta: borde.clone().set_bold().set_align(FormatAlign::Right),
+            total_moneda: borde.set_bold().set_num_format(FORMATO_MONEDA),
+        }
+    }
+}
+
+enum Valor {
+    Texto(Option<String>),
+    Numero(f64),
+    Entero(i64),
+    Moneda(f64),
+    /// Fecha ISO (YYYY-MM-DD); si no se puede interpretar se escribe como texto
+    Fecha(Option<String>),
+}
+
+fn escribir_valor(
+    ws: &mut Worksheet,
+    fila: u32,
+    col: u16,
+    valor: &Valor,
+    f: &Formatos,
+) -> Result<(), ErrorComando> {
+    match valor {
+        Valor::Texto(texto) => {
+            ws.write_string_with_format(fila, col, texto.as_deref().unwrap_or(""), &f.texto)?;
+        }
+        Valor::Numero(n) => {
+            ws.write_number_with_format(fila, col, *n, &f.numero)?;
+        }
+        Valor::Entero(n) => {
+            ws.write_number_with_format(fila, col, *n as f64, &f.entero)?;
+        }
+        Valor::Moneda(n) => {
+            ws.write_number_with_format(fila, col, *n, &f.moneda)?;
+        }
+        Valor::Fecha(fecha) => {
+            let texto = fecha.as_deref().unwrap_or("");
+            match ExcelDateTime::parse_from_str(texto) {
+                Ok(dt) => ws.write_datetime_with_format(fila, col, &dt, &f.fecha)?,
+                Err(_) => ws.write_string_with_format(fila, col, texto, &f.texto)?,
+            };
+        }
+    }
+    Ok(())
+}
+
+/// Escribe encabezados y filas desde `fila_inicio`. Devuelve la fila siguiente a la tabla.
+fn escribir_tabla(
+    ws: &mut Worksheet,
+    fila_inicio: u32,
+    columnas: &[(&str, f64)],
+    filas: &[Vec<Valor>],
+    f: &Formatos,
+) -> Result<u32, ErrorComando> {
+    for (col, (titulo, ancho)) in columnas.iter().enumerate() {
+        ws.set_column_width(col as u16, *ancho)?;
+        ws.write_string_with_format(fila_inicio, col as u16, *titulo, &f.encabezado)?;
+    }
+
+    let mut fila = fila_inicio + 1;
+    for valores in filas {
+        for (col, valor) in valores.iter().enumerate() {
+            escribir_valor(ws, fila, col as u16, valor, f)?;
+        }
+        fila += 1;
+    }
+    Ok(fila)
+}
+
+/// Pares etiqueta / valor sobre la tabla (contrato, jardín, fecha...)
+fn escribir_cabecera(
+    ws: &mut Worksheet,
+    titulo: &str,
+    datos: &[(&str, String)],
+    f: &Formatos,
+) -> Result<u32, ErrorComando> {
+    ws.write_string_with_format(0, 0, titulo, &f.titulo)?;
+    let mut fila = 2;
+    for (etiqueta, valor) in datos {
+        ws.write_string_with_format(fila, 0, *etiqueta, &f.etiqueta)?;
+        ws.write_string(fila, 2, valor)?;
+        fila += 1;
+    }
+    Ok(fila + 1)
+}
+
+async fn nombre_jardin(db: &State<'_, DbState>, codigo: &str) -> Result<String, ErrorComando> {
+    let jardin = commands::get_jardin_by_codigo(db.clone(), codigo.to_string()).await?;
+    Ok(match jardin {
+        Some(j) => format!("{} - {}", j.codigo, j.nombre),
+        None => codigo.to_string(),
+    })
+}
+
+fn guardar(mut workbook: Workbook, file_path: &str) -> Result<(), ErrorComando> {
+    workbook.save(file_path)?;
+    println!("📊 XLSX exportado: {}", file_path);
+    Ok(())
+}
+
+// ========== COMANDOS ==========
+
+/// Listado de requerimientos. `ids` es la lista filtrada en pantalla (en ese
+/// orden); sin `ids` se exportan todos.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn exportar_requerimientos_xlsx(
+    db: State<'_, DbState>,
+    file_path: String,
+    ids: Option<Vec<i64>>,
+) -> Result<(), ErrorComando> {
+    let todos = commands::get_requerimientos(db.clone()).await?;
+    let requerimientos: Vec<&RequerimientoEnriquecido> = match &ids {
+        Some(ids) => ids.iter().filter_map(|id| todos.iter().find(|r| r.id == *id)).collect(),
+        None => todos.iter().collect(),
+    };
+
+    let columnas = [
+        ("ID", 6.0),
+        ("Jardín", 10.0),
+        ("Recinto", 18.0),
+        ("Item", 8.0),
+        ("Partida", 40.0),
+        ("Unidad", 8.0),
+        ("Precio unitario", 14.0),
+        ("Cantidad", 10.0),
+        ("Precio total", 14.0),
+        ("Fecha inicio", 12.0),
+        ("Plazo", 8.0),
+        ("Plazo adicional", 10.0),
+        ("Plazo total", 8.0),
+        ("Fecha límite", 12.0),
+        ("Fecha registro", 12.0),
+        ("Fecha recepción", 12.0),
+        ("Días atraso", 8.0),
+        ("Multa", 12.0),
+        ("A pago", 14.0),
+        ("Descripción", 30.0),
+        ("Observaciones", 30.0),
+        ("Estado", 13.0),
+        ("OT", 16.0),
+        ("Informe de pago", 16.0),
+    ];
+    let filas: Vec<Vec<Valor>> = requerimientos
+        .iter()
+        .map(|r| {
+            vec![
+                Valor::Entero(r.id),
+                Valor::Texto(Some(r.jardin_codigo.clone())),
+                Valor::Texto(r.recinto.clone()),
+                Valor::Texto(Some(r.partida_item.clone())),
+                Valor::Texto(r.partida_nombre.clone()),
+                Valor::Texto(r.partida_unidad.clone()),
+                Valor::Moneda(r.precio_unitario.unwrap_or(0.0)),
+                Valor::Numero(r.cantidad),
+                Valor::Moneda(r.precio_total),
+                Valor::Fecha(Some(r.fecha_inicio.clone())),
+                Valor::Entero(r.plazo_dias.into()),
+                Valor::Entero(r.plazo_adicional.into()),
+                Valor::Entero(r.plazo_total.into()),
+                Valor::Fecha(r.fecha_limite.clone()),
+                Valor::Fecha(Some(r.fecha_registro.clone())),
+                Valor::Fecha(r.fecha_recepcion.clone()),
+                Valor::Entero(r.dias_atraso.into()),
+                Valor::Moneda(r.multa),
+                Valor::Moneda(r.a_pago),
+                Valor::Texto(r.descripcion.clone()),
+                Valor::Texto(r.observaciones.clone()),
+                Valor::Texto(Some(r.estado.clone())),
+                Valor::Texto(r.ot_codigo.clone()),
+                Valor::Texto(r.informe_pago_codigo.clone()),
+            ]
+        })
+        .collect();
+
+    let f = Formatos::new();
+    let mut workbook = Workbook::new();
+    let ws = workbook.add_worksheet().set_name("Requerimientos")?;
+
+    let fin = escribir_tabla(ws, 0, &columnas, &filas, &f)?;
+    ws.set_freeze_panes(1, 0)?;
+    ws.autofilter(0, 0, fin - 1, columnas.len() as u16 - 1)?;
+
+    guardar(workbook, &file_path)
+}
+
+/// Detalle de una OT con sus requerimientos y el total
+#[tauri::command(rename_all = "snake_case")]
+pub async fn exportar_orden_trabajo_xlsx(
+    db: State<'_, DbState>,
+    file_path: String,
+    ot_id: i64,
+) -> Result<(), ErrorComando> {
+    let ot = commands::get_ordenes_trabajo(db.clone())
+        .await?
+        .into_iter()
+        .find(|ot| ot.id == ot_id)
+        .ok_or_else(|| ErrorComando::no_encontrado("OT", ot_id))?;
+    let config = commands::get_configuracion(db.clone()).await?;
+    let mut requerimientos = commands::get_orden_trabajo_detalle(db.clone(), ot_id).await?;
+    requerimientos.sort_by_key(|r| r.id);
+
+    let f = Formatos::new();
+    let mut workbook = Workbook::new();
+    let ws = workbook.add_worksheet().set_name(&ot.codigo)?;
+    ws.set_landscape().set_print_fit_to_pages(1, 0);
+
+    let fila = escribir_cabecera(
+        ws,
+        &config.titulo,
+        &[
+            ("Orden de trabajo", ot.codigo.clone()),
+            ("Jardín", nombre_jardin(&db, &ot.jardin_codigo).await?),
+            ("Fecha", ot.fecha_creacion.clone()),
+            ("Contratista", config.contratista.clone()),
+            ("ITO", config.ito_nombre.clone().unwrap_or_default()),
+            ("Observaciones", ot.observaciones.clone().unwrap_or_default()),
+        ],
+        &f,
+    )?;
+
+    let columnas = [
+        ("N°", 5.0),
+        ("Item", 8.0),
+        ("Partida", 40.0),
+        ("Recinto", 18.0),
+        ("Unidad", 8.0),
+        ("Cantidad", 10.0),
+        ("Precio unitario", 14.0),
+        ("Total", 14.0),
+        ("Fecha inicio", 12.0),
+        ("Plazo total", 8.0),
+        ("Fecha límite", 12.0),
+        ("Descripción", 30.0),
+    ];
+    let filas: Vec<Vec<Valor>> = requerimientos
+        .iter()
+        .enumerate()
+        .map(|(i, r)| {
+            vec![
+                Valor::Entero(i as i64 + 1),
+                Valor::Texto(Some(r.partida_item.clone())),
+                Valor::Texto(r.partida_nombre.clone()),
+                Valor::Texto(r.recinto.clone()),
+                Valor::Texto(r.partida_unidad.clone()),
+                Valor::Numero(r.cantidad),
+                Valor::Moneda(r.precio_unitario.unwrap_or(0.0)),
+                Valor::Moneda(r.precio_total),
+                Valor::Fecha(Some(r.fecha_inicio.clone())),
+                Valor::Entero(r.plazo_total.into()),
+                Valor::Fecha(r.fecha_limite.clone()),
+                Valor::Texto(r.descripcion.clone()),
+            ]
+        })
+        .collect();
+
+    let fin = escribir_tabla(ws, fila, &columnas, &filas, &f)?;
+    ws.set_repeat_rows(fila, fila)?;
+
+    let total: f64 = requerimientos.iter().map(|r| r.precio_total).sum();
+    ws.write_string_with_format(fin, 6, "Total", &f.total_etiqueta)?;
+    ws.write_number_with_format(fin, 7, total, &f.total_moneda)?;
+
+    guardar(workbook, &file_path)
+}
+
+/// Informe de pago: líneas con multa y monto a pago, y el resumen
+/// neto / gastos generales / utilidades / IVA / total con las tasas del informe
+#[tauri::command(rename_all = "snake_case")]
+pub async fn exportar_informe_pago_xlsx(
+    db: State<'_, DbState>,
+    file_path: String,
+    informe_id: i64,
+) -> Result<(), ErrorComando> {
+    let informe = commands::get_informes_pago(db.clone())
+        .await?
+        .into_iter()
+        .find(|ip| ip.id == informe_id)
+        .ok_or_else(|| ErrorComando::no_encontrado("Informe", informe_id))?;
+    let config = commands::get_configuracion(db.clone()).await?;
+    let mut requerimientos = commands::get_informe_pago_detalle(db.clone(), informe_id).await?;
+    requerimientos.sort_by(|a, b| a.ot_codigo.cmp(&b.ot_codigo).then(a.id.cmp(&b.id)));
+
+    let f = Formatos::new();
+    let mut workbook = Workbook::new();
+    let ws = workbook.add_worksheet().set_name(&informe.codigo)?;
+    ws.set_landscape().set_print_fit_to_pages(1, 0);
+
+    let jardin = match &informe.jardin_nombre {
+        Some(nombre) => format!("{} - {}", informe.jardin_codigo, nombre),
+        None => informe.jardin_codigo.clone(),
+    };
+    let fila = escribir_cabecera(
+        ws,
+        &config.titulo,
+        &[
+            ("Informe de pago", informe.codigo.clone()),
+            ("Jardín", jardin),
+            ("Fecha", informe.fecha_creacion.clone()),
+            ("Contratista", config.contratista.clone()),
+            ("ITO", config.ito_nombre.clone().unwrap_or_default()),
+            ("Observaciones", informe.observaciones.clone().unwrap_or_default()),
+        ],
+        &f,
+    )?;
+
+    let columnas = [
+        ("N°", 5.0),
+        ("OT", 16.0),
+        ("Item", 8.0),
+        ("Partida", 40.0),
+        ("Recinto", 18.0),
+        ("Unidad", 8.0),
+        ("Cantidad", 10.0),
+        ("Precio unitario", 14.0),
+        ("Total", 14.0),
+        ("Fecha límite", 12.0),
+        ("Fecha recepción", 12.0),
+        ("Días atraso", 8.0),
+        ("Multa", 12.0),
+        ("A pago", 14.0),
+    ];
+    let filas: Vec<Vec<Valor>> = requerimientos
+        .iter()
+        .enumerate()
+        .map(|(i, r)| {
+            vec![
+                Valor::Entero(i as i64 + 1),
+                Valor::Texto(r.ot_codigo.clone()),
+                Valor::Texto(Some(r.partida_item.clone())),
+                Valor::Texto(r.partida_nombre.clone()),
+                Valor::Texto(r.recinto.clone()),
+                Valor::Texto(r.partida_unidad.clone()),
+                Valor::Numero(r.cantidad),
+                Valor::Moneda(r.precio_unitario.unwrap_or(0.0)),
+                Valor::Moneda(r.precio_total),
+                Valor::Fecha(r.fecha_limite.clone()),
+                Valor::Fecha(r.fecha_recepcion.clone()),
+                Valor::Entero(r.dias_atraso.into()),
+                Valor::Moneda(r.multa),
+                Valor::Moneda(r.a_pago),
+            ]
+        })
+        .collect();
+
+    let fin = escribir_tabla(ws, fila, &columnas, &filas, &f)?;
+    ws.set_repeat_rows(fila, fila)?;
+
+    let multas: f64 = requerimientos.iter().map(|r| r.multa).sum();
+    let a_pago: f64 = requerimientos.iter().map(|r| r.a_pago).sum();
+    ws.write_string_with_format(fin, 11, "Totales", &f.total_etiqueta)?;
+    ws.write_number_with_format(fin, 12, multas, &f.total_moneda)?;
+    ws.write_number_with_format(fin, 13, a_pago, &f.total_moneda)?;
+
+    // Resumen con los montos guardados en el informe (`neto` incluye lo exento)
+    let mut resumen = vec![("Neto afecto".to_string(), informe.neto - informe.neto_exento)];
+    if informe.neto_exento != 0.0 {
+        resumen.push(("Neto exento".to_string(), informe.neto_exento));
+    }
+    resumen.extend([
+        (format!("Gastos generales ({}%)", informe.porcentaje_gastos_generales), informe.gastos_generales),
+        (format!("Utilidades ({}%)", informe.porcentaje_utilidades), informe.utilidades),
+        (format!("IVA ({}%)", informe.porcentaje_iva), informe.iva),
+        ("Total".to_string(), informe.total_final),
+    ]);
+
+    for (fila, (etiqueta, monto)) in (fin + 2..).zip(&resumen) {
+        ws.merge_range(fila, 10, fila, 12, etiqueta, &f.total_etiqueta)?;
+        ws.write_number_with_format(fila, 13, *monto, &f.total_moneda)?;
+    }
+
+    guardar(workbook, &file_path)
+}
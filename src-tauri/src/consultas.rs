@@ -0,0 +1,406 @@
+use crate::db::RequerimientoEnriquecido;
+use crate::errores::ErrorComando;
+use crate::estados::EstadoRequerimiento;
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection};
+
+// ========== CONSULTA DE REQUERIMIENTOS ==========
+//
+// Única definición del SELECT de `RequerimientoEnriquecido` (plazo_total,
+// a_pago, códigos de OT e informe). Todas las vistas —listado, detalle de OT,
+// detalle de informe, candidatos a informe y la consulta paginada— son un
+// `FiltroRequerimientos` aplicado sobre ese SELECT, así que las columnas
+// calculadas no pueden diferir entre comandos.
+//
+// La consulta paginada cuenta el total con los mismos filtros para que la
+// tabla pueda mostrar "1-50 de N".
+
+const SELECT_ENRIQUECIDO: &str = "SELECT
+    r.id,
+    r.jardin_codigo,
+    r.recinto,
+    r.partida_item,
+    p.partida as partida_nombre,
+    p.unidad as partida_unidad,
+    r.precio_unitario,
+    r.cantidad,
+    r.cantidad_recibida,
+    r.precio_total,
+    r.fecha_inicio,
+    r.plazo_dias,
+    r.plazo_adicional,
+    (r.plazo_dias + r.plazo_adicional) as plazo_total,
+    r.fecha_limite,
+    r.fecha_registro,
+    r.fecha_recepcion,
+    r.dias_atraso,
+    r.multa,
+    (r.precio_total - COALESCE(r.multa, 0)) as a_pago,
+    r.descripcion,
+    r.observaciones,
+    r.estado,
+    r.ot_id,
+    ot.codigo as ot_codigo,
+    r.informe_pago_id,
+    ip.codigo as informe_pago_codigo,
+    r.created_at,
+    r.updated_at
+FROM requerimientos r
+LEFT JOIN partidas p ON r.contrato_id = p.contrato_id AND r.partida_item = p.item
+LEFT JOIN ordenes_trabajo ot ON r.ot_id = ot.id
+LEFT JOIN informes_pago ip ON r.informe_pago_id = ip.id";
+
+/// Todos los campos son opcionales; los que vienen se combinan con AND.
+/// Las fechas son YYYY-MM-DD y los rangos incluyen ambos extremos.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default)]
+pub struct FiltroRequerimientos {
+    pub jardin_codigo: Option<String>,
+    pub recinto: Option<String>,
+    pub partida_item: Option<String>,
+    pub ot_id: Option<i64>,
+    pub informe_pago_id: Option<i64>,
+    pub estados: Option<Vec<String>>,
+    pub inicio_desde: Option<String>,
+    pub inicio_hasta: Option<String>,
+    pub limite_desde: Option<String>,
+    pub limite_hasta: Option<String>,
+    pub recepcion_desde: Option<String>,
+    pub recepcion_hasta: Option<String>,
+    /// true: solo con OT; false: solo sin OT
+    pub con_ot: Option<bool>,
+    /// true: solo en un informe de pago; false: solo sin informe
+    pub con_informe: Option<bool>,
+    /// true: con alguna cantidad recibida; false: sin recepciones
+    pub con_recepcion: Option<bool>,
+    /// Recepcionados con atraso, o sin recepción y con la fecha límite vencida
+    pub solo_atrasados: bool,
+    /// Texto libre buscado en la descripción
+    pub texto: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct PaginaRequerimientos {
+    pub filas: Vec<RequerimientoEnriquecido>,
+    /// Filas que cumplen el filtro, sin considerar límite ni offset
+    pub total: i64,
+}
+
+/// Columnas por las que se puede ordenar (nombre del frontend → expresión SQL)
+const COLUMNAS_ORDEN: &[(&str, &str)] = &[
+    ("id", "r.id"),
+    ("jardin_codigo", "r.jardin_codigo"),
+    ("recinto", "r.recinto"),
+    ("partida_item", "r.partida_item"),
+    ("partida_nombre", "p.partida"),
+    ("cantidad", "r.cantidad"),
+    ("cantidad_recibida", "r.cantidad_recibida"),
+    ("precio_total", "r.precio_total"),
+    ("fecha_inicio", "r.fecha_inicio"),
+    ("fecha_limite", "r.fecha_limite"),
+    ("fecha_registro", "r.fecha_registro"),
+    ("fecha_recepcion", "r.fecha_recepcion"),
+    ("plazo_total", "plazo_total"),
+    ("dias_atraso", "r.dias_atraso"),
+    ("multa", "r.multa"),
+    ("a_pago", "a_pago"),
+    ("estado", "r.estado"),
+    ("ot_codigo", "ot.codigo"),
+    ("informe_pago_codigo", "ip.codigo"),
+];
+
+impl FiltroRequerimientos {
+    pub fn validar(&self) -> Result<(), ErrorComando> {
+        for estado in self.estados.iter().flatten() {
+            EstadoRequerimiento::parse(estado)?;
+        }
+        let fechas = [
+            ("inicio_desde", &self.inicio_desde),
+            ("inicio_hasta", &self.inicio_hasta),
+            ("limite_desde", &self.limite_desde),
+            ("limite_hasta", &self.limite_hasta),
+            ("recepcion_desde", &self.recepcion_desde),
+            ("recepcion_hasta", &self.recepcion_hasta),
+        ];
+        for (campo, fecha) in fechas {
+            if let Some(fecha) = fecha {
+                chrono::NaiveDate::parse_from_str(fecha, "%Y-%m-%d").map_err(|_| {
+                    ErrorComando::validacion(campo, format!("Fecha inválida '{}', se espera AAAA-MM-DD", fecha))
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Agrega " WHERE ..." con el contrato activo y las condiciones del filtro
+    fn condiciones<'a>(&'a self, qb: &mut QueryBuilder<'a, Sqlite>, hoy: &'a str) {
+        qb.push(" WHERE r.contrato_id = (SELECT id FROM contrato_activo)");
+        let y = |qb: &mut QueryBuilder<'a, Sqlite>| {
+            qb.push(" AND ");
+        };
+
+        if let Some(jardin) = &self.jardin_codigo {
+            y(qb);
+            qb.push("r.jardin_codigo = ").push_bind(jardin);
+        }
+        if let Some(recinto) = &self.recinto {
+            y(qb);
+            qb.push("r.recinto = ").push_bind(recinto);
+        }
+        if let Some(item) = &self.partida_item {
+            y(qb);
+            qb.push("r.partida_item = ").push_bind(item);
+        }
+        if let Some(ot_id) = self.ot_id {
+            y(qb);
+            qb.push("r.ot_id = ").push_bind(ot_id);
+        }
+        if let Some(informe_id) = self.informe_pago_id {
+            y(qb);
+            qb.push("r.informe_pago_id = ").push_bind(informe_id);
+        }
+        if let Some(estados) = &self.estados {
+            y(qb);
+            if estados.is_empty() {
+                qb.push("0");
+            } else {
+                qb.push("r.estado IN (");
+                let mut lista = qb.separated(", ");
+                for estado in estados {
+                    lista.push_bind(estado);
+                }
+                qb.push(")");
+            }
+        }
+
+        let rangos = [
+            ("r.fecha_inicio", &self.inicio_desde, &self.inicio_hasta),
+            ("r.fecha_limite", &self.limite_desde, &self.limite_hasta),
+            ("r.fecha_recepcion", &self.recepcion_desde, &self.recepcion_hasta),
+        ];
+        for (columna, desde, hasta) in rangos {
+            if let Some(desde) = desde {
+                y(qb);
+                qb.push(columna).push(" >= ").push_bind(desde);
+            }
+            if let Some(hasta) = hasta {
+                y(qb);
+                qb.push(columna).push(" <= ").push_bind(hasta);
+            }
+        }
+
+        if let Some(con_ot) = self.con_ot {
+            y(qb);
+            qb.push(if con_ot { "r.ot_id IS NOT NULL" } else { "r.ot_id IS NULL" });
+        }
+        if let Some(con_informe) = self.con_informe {
+            y(qb);
+            qb.push(if con_informe { "r.informe_pago_id IS NOT NULL" } else { "r.informe_pago_id IS NULL" });
+        }
+        if let Some(con_recepcion) = self.con_recepcion {
+            y(qb);
+            qb.push(if con_recepcion { "r.cantidad_recibida > 0" } else { "r.cantidad_recibida = 0" });
+        }
+        if self.solo_atrasados {
+            y(qb);
+            qb.push("(r.dias_atraso > 0 OR (r.fecha_recepcion IS NULL AND r.fecha_limite < ")
+                .push_bind(hoy)
+                .push("))");
+        }
+        if let Some(texto) = self.texto.as_deref().map(str::trim).filter(|t| !t.is_empty()) {
+            y(qb);
+            qb.push("r.descripcion LIKE ")
+                .push_bind(format!("%{}%", escapar_like(texto)))
+                .push(" ESCAPE '\\'");
+        }
+    }
+}
+
+/// Listados fijos que usan los comandos de requerimientos, OT e informes
+pub enum Vista {
+    Todos,
+    OrdenTrabajo(i64),
+    InformePago(i64),
+    /// Lo recibido y no informado del jardín: recepcionados completos y los
+    /// en_ot con entregas parciales, estos últimos con cantidad, precio y
+    /// monto a pago de lo recibido (ver recepciones.rs)
+    ParaInforme(String),
+}
+
+impl Vista {
+    fn filtro(&self) -> FiltroRequerimientos {
+        match self {
+            Vista::Todos => FiltroRequerimientos::default(),
+            Vista::OrdenTrabajo(ot_id) => FiltroRequerimientos { ot_id: Some(*ot_id), ..Default::default() },
+            Vista::InformePago(informe_id) => {
+                FiltroRequerimientos { informe_pago_id: Some(*informe_id), ..Default::default() }
+            }
+            Vista::ParaInforme(jardin_codigo) => FiltroRequerimientos {
+                jardin_codigo: Some(jardin_codigo.clone()),
+                estados: Some(vec![
+                    EstadoRequerimiento::EnOt.as_str().to_string(),
+                    EstadoRequerimiento::Recepcionado.as_str().to_string(),
+                ]),
+                con_recepcion: Some(true),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// `%` y `_` se buscan literalmente
+fn escapar_like(texto: &str) -> String {
+    let mut salida = String::with_capacity(texto.len());
+    for c in texto.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            salida.push('\\');
+        }
+        salida.push(c);
+    }
+    salida
+}
+
+/// Página de requerimientos según filtro y orden. `orden` es el nombre de un
+/// campo de `RequerimientoEnriquecido` (por defecto fecha_inicio descendente);
+/// `limite` None devuelve todas las filas desde `offset`.
+pub async fn consultar(
+    conn: &mut SqliteConnection,
+    filtro: &FiltroRequerimientos,
+    orden: Option<&str>,
+    descendente: bool,
+    limite: Option<i64>,
+    offset: i64,
+) -> Result<PaginaRequerimientos, ErrorComando> {
+    filtro.validar()?;
+    if limite.is_some_and(|l| l < 1) {
+        return Err(ErrorComando::validacion("limite", "El límite debe ser mayor que cero"));
+    }
+    if offset < 0 {
+        return Err(ErrorComando::validacion("offset", "El offset no puede ser negativo"));
+    }
+    let (columna, descendente) = match orden {
+        None => ("r.fecha_inicio", true),
+        Some(orden) => {
+            let columna = COLUMNAS_ORDEN
+                .iter()
+                .find(|(nombre, _)| *nombre == orden)
+                .map(|(_, columna)| *columna)
+                .ok_or_else(|| ErrorComando::validacion("orden", format!("No se puede ordenar por '{}'", orden)))?;
+            (columna, descendente)
+        }
+    };
+    let hoy = hoy();
+
+    let mut qb = QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM requerimientos r");
+    filtro.condiciones(&mut qb, &hoy);
+    let total: i64 = qb.build_query_scalar().fetch_one(&mut *conn).await?;
+
+    let mut qb = seleccion(filtro, &hoy, columna, descendente);
+    qb.push(" LIMIT ").push_bind(limite.unwrap_or(-1));
+    qb.push(" OFFSET ").push_bind(offset);
+    let filas = qb.build_query_as::<RequerimientoEnriquecido>().fetch_all(&mut *conn).await?;
+
+    Ok(PaginaRequerimientos { filas, total })
+}
+
+/// Filas completas de una vista, de la fecha de inicio más reciente a la más antigua
+pub async fn listar(conn: &mut SqliteConnection, vista: Vista) -> Result<Vec<RequerimientoEnriquecido>, ErrorComando> {
+    let filtro = vista.filtro();
+    let hoy = hoy();
+    let mut filas = seleccion(&filtro, &hoy, "r.fecha_inicio", true)
+        .build_query_as::<RequerimientoEnriquecido>()
+        .fetch_all(&mut *conn)
+        .await?;
+
+    // Al informe entra solo lo recibido; la multa guardada ya es la de las entregas
+    if let Vista::ParaInforme(_) = vista {
+        for fila in filas.iter_mut().filter(|f| f.cantidad_recibida < f.cantidad) {
+            fila.cantidad = fila.cantidad_recibida;
+            fila.precio_total = fila.cantidad * fila.precio_unitario.unwrap_or(0.0);
+            fila.a_pago = fila.precio_total - fila.multa;
+        }
+    }
+    Ok(filas)
+}
+
+fn hoy() -> String {
+    chrono::Local::now().format("%Y-%m-%d").to_string()
+}
+
+fn seleccion<'a>(
+    filtro: &'a FiltroRequerimientos,
+    hoy: &'a str,
+    columna: &str,
+    descendente: bool,
+) -> QueryBuilder<'a, Sqlite> {
+    let mut qb = QueryBuilder::<Sqlite>::new(SELECT_ENRIQUECIDO);
+    filtro.condiciones(&mut qb, hoy);
+    // r.id desempata para que las páginas no se solapen
+    let sentido = if descendente { "DESC" } else { "ASC" };
+    qb.push(format!(" ORDER BY {} {}, r.id {}", columna, sentido, sentido));
+    qb
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+    use sqlx::{Pool, Sqlite};
+
+    async fn pool_memoria() -> Pool<Sqlite> {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(SqliteConnectOptions::new().in_memory(true))
+            .await
+            .unwrap();
+        crate::migraciones::migrar(&pool).await.unwrap();
+        for sql in [
+            "INSERT INTO jardines (contrato_id, codigo, nombre) VALUES (1, 'J01', 'Jardín Uno')",
+            // Precio de catálogo distinto al guardado en el requerimiento
+            "INSERT INTO partidas (contrato_id, item, partida, unidad, precio_unitario, exenta_iva) VALUES (1, '1.1', 'Pintura', 'm2', 9999, 0)",
+            "INSERT INTO ordenes_trabajo (contrato_id, codigo, jardin_codigo, fecha_creacion) VALUES (1, 'OT-J01-M001', 'J01', '2025-03-01')",
+            "INSERT INTO informes_pago (contrato_id, codigo, jardin_codigo, fecha_creacion, neto, utilidades, iva, total_final) VALUES (1, 'IP-J01-M01', 'J01', '2025-04-01', 0, 0, 0, 0)",
+            "INSERT INTO requerimientos (contrato_id, jardin_codigo, recinto, partida_item, cantidad, cantidad_recibida, precio_unitario, precio_total,
+                fecha_inicio, fecha_registro, estado, ot_id, plazo_dias, plazo_adicional, fecha_limite, fecha_recepcion, dias_atraso, multa)
+             VALUES (1, 'J01', 'Sala Cuna', '1.1', 20, 20, 4500, 90000, '2025-02-01', '2025-02-01', 'recepcionado', 1, 10, 2,
+                '2025-02-13', '2025-02-20', 7, 3150)",
+        ] {
+            sqlx::query(sql).execute(&pool).await.unwrap();
+        }
+        pool
+    }
+
+    async fn fila(conn: &mut SqliteConnection, vista: Vista) -> serde_json::Value {
+        let filas = listar(conn, vista).await.unwrap();
+        assert_eq!(filas.len(), 1);
+        serde_json::to_value(&filas[0]).unwrap()
+    }
+
+    #[tokio::test]
+    async fn todas_las_vistas_calculan_las_mismas_columnas() {
+        let pool = pool_memoria().await;
+        let mut conn = pool.acquire().await.unwrap();
+
+        let todos = fila(&mut conn, Vista::Todos).await;
+        assert_eq!(todos["precio_unitario"], 4500.0);
+        assert_eq!(todos["plazo_total"], 12);
+        assert_eq!(todos["a_pago"], 86850.0);
+        assert_eq!(todos["ot_codigo"], "OT-J01-M001");
+        assert_eq!(fila(&mut conn, Vista::OrdenTrabajo(1)).await, todos);
+        assert_eq!(fila(&mut conn, Vista::ParaInforme("J01".to_string())).await, todos);
+
+        let filtro = FiltroRequerimientos { jardin_codigo: Some("J01".to_string()), ..Default::default() };
+        let pagina = consultar(&mut conn, &filtro, None, false, Some(10), 0).await.unwrap();
+        assert_eq!(pagina.total, 1);
+        assert_eq!(serde_json::to_value(&pagina.filas[0]).unwrap(), todos);
+
+        sqlx::query("UPDATE requerimientos SET informe_pago_id = 1, estado = 'en_informe'")
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        let todos = fila(&mut conn, Vista::Todos).await;
+        assert_eq!(todos["informe_pago_codigo"], "IP-J01-M01");
+        assert_eq!(fila(&mut conn, Vista::InformePago(1)).await, todos);
+        assert_eq!(fila(&mut conn, Vista::OrdenTrabajo(1)).await, todos);
+        assert!(listar(&mut conn, Vista::ParaInforme("J01".to_string())).await.unwrap().is_empty());
+    }
+}
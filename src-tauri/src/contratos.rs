@@ -0,0 +1,239 @@
+use crate::calculos::TasasInforme;
+use crate::errores::ErrorComando;
+use crate::precios;
+use sqlx::SqliteConnection;
+
+// ========== CONTRATOS ==========
+//
+// Una instalación puede llevar varios contratos. Cada uno tiene su propia
+// configuración (tasas, multas, calendario, presupuesto, firma), su catálogo
+// (jardines, recintos, partidas) y sus correlativos de OT e informe.
+//
+// Siempre hay exactamente un contrato activo y todos los comandos trabajan
+// sobre él: las consultas filtran por `contrato_id = (SELECT id FROM
+// contrato_activo)` y las inserciones toman ese mismo id. Cambiar de
+// contrato es solo mover la marca `activo`.
+
+#[derive(Debug, serde::Serialize, sqlx::FromRow)]
+pub struct Contrato {
+    pub id: i64,
+    pub titulo: String,
+    pub contratista: String,
+    pub prefijo_correlativo: String,
+    pub activo: bool,
+    pub fecha_inicio: Option<String>,
+    pub fecha_termino: Option<String>,
+    pub jardines: i64,
+    pub requerimientos: i64,
+    pub created_at: String,
+}
+
+/// Columnas de configuración que se copian al crear un contrato a partir de otro
+const COLUMNAS_CONFIGURACION: &str = "ito_nombre, firma_png, porcentaje_gastos_generales, porcentaje_utilidades, porcentaje_iva,
+     multa_modo, multa_monto_diario, multa_utm_diaria, valor_utm, multa_porcentaje_diario,
+     multa_tope_porcentaje, multa_dias_gracia, tipo_plazo, dias_no_habiles";
+
+pub async fn activo(conn: &mut SqliteConnection) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar("SELECT id FROM contrato_activo")
+        .fetch_one(&mut *conn)
+        .await
+}
+
+/// Tasas del contrato activo vigentes en `fecha` (ver `tasas_de`)
+pub async fn tasas_vigentes(conn: &mut SqliteConnection, fecha: &str) -> Result<TasasInforme, sqlx::Error> {
+    let contrato_id = activo(conn).await?;
+    tasas_de(conn, contrato_id, fecha).await
+}
+
+/// Tasas de un contrato vigentes en `fecha`: la última fila de
+/// `historial_tasas` desde esa fecha o, antes de la primera, las columnas del
+/// contrato. Esas columnas son solo la base anterior al historial; las tasas
+/// de hoy se leen siempre de aquí.
+pub async fn tasas_de(conn: &mut SqliteConnection, contrato_id: i64, fecha: &str) -> Result<TasasInforme, sqlx::Error> {
+    let historial = sqlx::query_as::<_, TasasInforme>(
+        "SELECT porcentaje_gastos_generales, porcentaje_utilidades, porcentaje_iva FROM historial_tasas
+         WHERE contrato_id = ? AND vigente_desde <= ?
+         ORDER BY vigente_desde DESC, id DESC
+         LIMIT 1"
+    )
+    .bind(contrato_id)
+    .bind(fecha)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    match historial {
+        Some(tasas) => Ok(tasas),
+        None => {
+            sqlx::query_as::<_, TasasInforme>(
+                "SELECT porcentaje_gastos_generales, porcentaje_utilidades, porcentaje_iva FROM contratos WHERE id = ?"
+            )
+            .bind(contrato_id)
+            .fetch_one(&mut *conn)
+            .await
+        }
+    }
+}
+
+pub async fn listar(conn: &mut SqliteConnection) -> Result<Vec<Contrato>, ErrorComando> {
+    Ok(sqlx::query_as::<_, Contrato>(
+        "SELECT c.id, c.titulo, c.contratista, c.prefijo_correlativo, c.activo, c.fecha_inicio, c.fecha_termino,
+                (SELECT COUNT(*) FROM jardines j WHERE j.contrato_id = c.id) as jardines,
+                (SELECT COUNT(*) FROM requerimientos r WHERE r.contrato_id = c.id) as requerimientos,
+                c.created_at
+         FROM contratos c
+         ORDER BY c.id"
+    )
+    .fetch_all(&mut *conn)
+    .await?)
+}
+
+async fn verificar_existe(conn: &mut SqliteConnection, id: i64) -> Result<(), ErrorComando> {
+    let existe: Option<i64> = sqlx::query_scalar("SELECT id FROM contratos WHERE id = ?")
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await?;
+    existe.map(|_| ()).ok_or_else(|| ErrorComando::no_encontrado("Contrato", id))
+}
+
+/// Crea un contrato inactivo. Con `copiar_de` hereda la configuración y el
+/// catálogo (jardines, recintos, partidas) de ese contrato; sin él parte con
+/// los valores por defecto y el catálogo vacío. Devuelve el id nuevo.
+pub async fn crear(
+    conn: &mut SqliteConnection,
+    titulo: &str,
+    contratista: &str,
+    prefijo_correlativo: &str,
+    copiar_de: Option<i64>,
+) -> Result<i64, ErrorComando> {
+    if titulo.trim().is_empty() {
+        return Err(ErrorComando::validacion("titulo", "El título del contrato es obligatorio"));
+    }
+    if prefijo_correlativo.trim().is_empty() {
+        return Err(ErrorComando::validacion("prefijo_correlativo", "El prefijo de correlativos es obligatorio"));
+    }
+
+    let id = match copiar_de {
+        Some(origen) => {
+            verificar_existe(conn, origen).await?;
+            sqlx::query(&format!(
+                "INSERT INTO contratos (titulo, contratista, prefijo_correlativo, {cols})
+                 SELECT ?, ?, ?, {cols} FROM contratos WHERE id = ?",
+                cols = COLUMNAS_CONFIGURACION
+            ))
+            .bind(titulo.trim())
+            .bind(contratista.trim())
+            .bind(prefijo_correlativo.trim())
+            .bind(origen)
+            .execute(&mut *conn)
+            .await?
+            .last_insert_rowid()
+        }
+        None => sqlx::query("INSERT INTO contratos (titulo, contratista, prefijo_correlativo) VALUES (?, ?, ?)")
+            .bind(titulo.trim())
+            .bind(contratista.trim())
+            .bind(prefijo_correlativo.trim())
+            .execute(&mut *conn)
+            .await?
+            .last_insert_rowid(),
+    };
+
+    if let Some(origen) = copiar_de {
+        // Parte con las tasas que hoy rigen en el origen, no con su base
+        let hoy = chrono::Local::now().format("%Y-%m-%d").to_string();
+        let tasas = tasas_de(conn, origen, &hoy).await?;
+        sqlx::query("UPDATE contratos SET porcentaje_gastos_generales = ?, porcentaje_utilidades = ?, porcentaje_iva = ? WHERE id = ?")
+            .bind(tasas.porcentaje_gastos_generales)
+            .bind(tasas.porcentaje_utilidades)
+            .bind(tasas.porcentaje_iva)
+            .bind(id)
+            .execute(&mut *conn)
+            .await?;
+        sqlx::query("INSERT INTO jardines (contrato_id, codigo, nombre) SELECT ?, codigo, nombre FROM jardines WHERE contrato_id = ? AND activo = 1 ORDER BY id")
+            .bind(id)
+            .bind(origen)
+            .execute(&mut *conn)
+            .await?;
+        // Solo el catálogo activo; los recintos, de los jardines copiados
+        sqlx::query(
+            "INSERT INTO recintos (contrato_id, jardin_codigo, nombre)
+             SELECT ?1, jardin_codigo, nombre FROM recintos
+             WHERE contrato_id = ?2 AND activo = 1
+               AND jardin_codigo IN (SELECT codigo FROM jardines WHERE contrato_id = ?1)
+             ORDER BY id"
+        )
+        .bind(id)
+        .bind(origen)
+        .execute(&mut *conn)
+        .await?;
+        sqlx::query(
+            "INSERT INTO partidas (contrato_id, item, partida, unidad, precio_unitario, exenta_iva)
+             SELECT ?, item, partida, unidad, precio_unitario, exenta_iva FROM partidas WHERE contrato_id = ? AND activo = 1 ORDER BY id"
+        )
+        .bind(id)
+        .bind(origen)
+        .execute(&mut *conn)
+        .await?;
+        precios::registrar_iniciales(conn, id).await?;
+    }
+
+    // Tasas iniciales en el historial, como la migración 004 para el primer contrato
+    sqlx::query(
+        "INSERT INTO historial_tasas (contrato_id, vigente_desde, porcentaje_gastos_generales, porcentaje_utilidades, porcentaje_iva)
+         SELECT id, date('now'), porcentaje_gastos_generales, porcentaje_utilidades, porcentaje_iva FROM contratos WHERE id = ?"
+    )
+    .bind(id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(id)
+}
+
+pub async fn activar(conn: &mut SqliteConnection, id: i64) -> Result<(), ErrorComando> {
+    verificar_existe(conn, id).await?;
+    sqlx::query("UPDATE contratos SET activo = 0 WHERE activo = 1 AND id <> ?")
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query("UPDATE contratos SET activo = 1 WHERE id = ?")
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// Elimina un contrato sin movimientos (requerimientos, OTs ni informes).
+/// El contrato activo no se puede eliminar: primero hay que activar otro.
+pub async fn eliminar(conn: &mut SqliteConnection, id: i64) -> Result<(), ErrorComando> {
+    verificar_existe(conn, id).await?;
+
+    if activo(conn).await? == id {
+        return Err(ErrorComando::validacion("id", "No se puede eliminar el contrato activo"));
+    }
+
+    let movimientos: i64 = sqlx::query_scalar(
+        "SELECT (SELECT COUNT(*) FROM requerimientos WHERE contrato_id = ?1)
+              + (SELECT COUNT(*) FROM ordenes_trabajo WHERE contrato_id = ?1)
+              + (SELECT COUNT(*) FROM informes_pago WHERE contrato_id = ?1)"
+    )
+    .bind(id)
+    .fetch_one(&mut *conn)
+    .await?;
+    if movimientos > 0 {
+        return Err(ErrorComando::validacion(
+            "id",
+            format!("El contrato tiene {} requerimientos, OTs o informes registrados", movimientos),
+        ));
+    }
+
+    for tabla in ["presupuesto_jardines", "recintos", "partidas", "reajustes", "jardines", "historial_tasas"] {
+        sqlx::query(&format!("DELETE FROM {} WHERE contrato_id = ?", tabla))
+            .bind(id)
+            .execute(&mut *conn)
+            .await?;
+    }
+    sqlx::query("DELETE FROM contratos WHERE id = ?")
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
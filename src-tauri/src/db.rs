@@ -1,80 +1,135 @@
+use crate::migraciones::MigracionError;
 use sqlx::{Pool, Sqlite, SqlitePool};
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
 
 #[derive(Clone)]
 pub struct DbState {
-    pub pool: Arc<Pool<Sqlite>>,
+    /// Base abierta; se reemplaza en caliente al abrir otro archivo (ver bases.rs)
+    conexion: Arc<RwLock<Conexion>>,
+    /// Nombre registrado en la auditoría (por defecto, el usuario del sistema)
+    pub operador: Arc<RwLock<String>>,
+}
+
+struct Conexion {
+    pool: Pool<Sqlite>,
+    db_path: PathBuf,
+}
+
+/// Carpeta de datos de la aplicación (base por defecto, lista de recientes)
+pub fn dir_app() -> PathBuf {
+    dirs::data_local_dir()
+        .expect("No se pudo obtener directorio de datos")
+        .join("sistema-piloto-cont-mant")
+}
+
+/// Abre el archivo, toma un respaldo si ya existía y aplica las migraciones pendientes
+pub async fn conectar(db_path: &Path, crear: bool) -> Result<Pool<Sqlite>, MigracionError> {
+    let existia = db_path.exists();
+    
+    let pool = SqlitePool::connect_with(
+        sqlx::sqlite::SqliteConnectOptions::new()
+            .filename(db_path)
+            .create_if_missing(crear)
+            // sqlx ya lo activa por defecto; se deja explícito porque el
+            // schema depende de él (cascadas y referencias de catálogo)
+            .foreign_keys(true)
+            .journal_mode(sqlx::sqlite::SqliteJournalMode::Wal)
+            .synchronous(sqlx::sqlite::SqliteSynchronous::Normal)
+    )
+    .await?;
+    
+    // Respaldo al abrir (antes de migrar)
+    if existia {
+        if let Err(e) = crate::commands_respaldo::crear_respaldo(&pool, db_path, "inicio").await {
+            println!("⚠️ No se pudo crear respaldo de inicio: {}", e);
+        }
+    }
+    
+    // Aplicar migraciones pendientes (schema_migrations)
+    let version = match crate::migraciones::migrar(&pool).await {
+        Ok(version) => version,
+        Err(e) => {
+            pool.close().await;
+            return Err(e);
+        }
+    };
+    
+    println!("✅ Base abierta: {} (schema v{})", db_path.display(), version);
+    Ok(pool)
 }
 
 impl DbState {
-    pub async fn new() -> Result<Self, sqlx::Error> {
-        let app_dir = dirs::data_local_dir()
-            .expect("No se pudo obtener directorio de datos")
-            .join("sistema-piloto-cont-mant");
-        
+    pub async fn new() -> Result<Self, MigracionError> {
+        let app_dir = dir_app();
         std::fs::create_dir_all(&app_dir).ok();
         
-        let db_path = app_dir.join("database.db");
+        let defecto = app_dir.join("database.db");
+        crate::adjuntos::mover_almacen_anterior(&defecto);
+        crate::commands_respaldo::mover_respaldos_anteriores(&defecto);
+        // La última base abierta, si todavía existe
+        let db_path = crate::bases::ultima(&app_dir)
+            .filter(|ruta| ruta.exists())
+            .unwrap_or_else(|| defecto.clone());
         
         println!("📂 DB Path: {}", db_path.display());
         
-        let pool = SqlitePool::connect_with(
-            sqlx::sqlite::SqliteConnectOptions::new()
-                .filename(&db_path)
-                .create_if_missing(true)
-                .journal_mode(sqlx::sqlite::SqliteJournalMode::Wal)
-                .synchronous(sqlx::sqlite::SqliteSynchronous::Normal)
-        )
-        .await?;
-        
-        // SSOL: Cargar schema único
-        let schema = include_str!("../sql/schema.sql");
-        
-        // Dividir statements respetando bloques BEGIN...END
-        let mut statements = Vec::new();
-        let mut current_statement = String::new();
-        let mut in_trigger = false;
-        
-        for line in schema.lines() {
-            let trimmed = line.trim();
-            current_statement.push_str(line);
-            current_statement.push('\n');
-            
-            // Detectar inicio de trigger
-            if trimmed.to_uppercase().contains("BEGIN") {
-                in_trigger = true;
-            }
-            
-            // Detectar fin de statement
-            if trimmed.ends_with(';') {
-                if !in_trigger || trimmed == "END;" {
-                    statements.push(current_statement.clone());
-                    current_statement.clear();
-                    in_trigger = false;
-                }
-            }
-        }
-        
-        // Ejecutar cada statement
-        for statement in statements {
-            let statement = statement.trim();
-            if !statement.is_empty() {
-                sqlx::query(statement).execute(&pool).await?;
+        let (pool, db_path) = match conectar(&db_path, true).await {
+            Ok(pool) => (pool, db_path),
+            Err(e) if db_path != defecto => {
+                println!("⚠️ No se pudo abrir {}: {}. Se abre la base por defecto.", db_path.display(), e);
+                (conectar(&defecto, true).await?, defecto)
             }
-        }
+            Err(e) => return Err(e),
+        };
         
         println!("✅ SSOL iniciado");
-        
-        Ok(DbState { pool: Arc::new(pool) })
+        Ok(DbState::con_pool(pool, db_path))
+    }
+    
+    pub fn con_pool(pool: Pool<Sqlite>, db_path: PathBuf) -> Self {
+        DbState {
+            conexion: Arc::new(RwLock::new(Conexion { pool, db_path })),
+            operador: Arc::new(RwLock::new(operador_sistema())),
+        }
+    }
+    
+    /// Pool de la base abierta. Las operaciones en curso conservan el suyo
+    /// hasta terminar aunque entretanto se abra otra base.
+    pub fn pool(&self) -> Pool<Sqlite> {
+        self.conexion.read().unwrap_or_else(|e| e.into_inner()).pool.clone()
+    }
+    
+    pub fn db_path(&self) -> PathBuf {
+        self.conexion.read().unwrap_or_else(|e| e.into_inner()).db_path.clone()
+    }
+    
+    /// Pasa a trabajar sobre otra base. Devuelve el pool anterior para cerrarlo.
+    pub fn reemplazar(&self, pool: Pool<Sqlite>, db_path: PathBuf) -> Pool<Sqlite> {
+        let mut conexion = self.conexion.write().unwrap_or_else(|e| e.into_inner());
+        conexion.db_path = db_path;
+        std::mem::replace(&mut conexion.pool, pool)
+    }
+    
+    pub fn operador(&self) -> String {
+        self.operador.read().map(|o| o.clone()).unwrap_or_default()
     }
 }
 
+pub fn operador_sistema() -> String {
+    std::env::var("USERNAME")
+        .or_else(|_| std::env::var("USER"))
+        .unwrap_or_else(|_| "desconocido".to_string())
+}
+
 // Tipos de datos
 #[derive(Debug, serde::Serialize, serde::Deserialize, sqlx::FromRow)]
 pub struct Jardin {
     pub id: i64,
     pub codigo: String,
     pub nombre: String,
+    /// Inactivo: ya no viene en el catálogo importado; se conserva por los movimientos que lo usan
+    pub activo: bool,
     pub created_at: String,
 }
 
@@ -85,6 +140,8 @@ pub struct Partida {
     pub partida: String,
     pub unidad: Option<String>,
     pub precio_unitario: f64,
+    pub exenta_iva: bool,
+    pub activo: bool,
     pub created_at: String,
 }
 
@@ -108,7 +165,9 @@ pub struct Requerimiento {
     pub plazo_adicional: i32,
     pub plazo_total: i32,
     pub fecha_limite: Option<String>,
+    pub dias_atraso: i32,
     pub multa: f64,
+    pub multa_detalle: Option<String>,
     pub descripcion: Option<String>,
     pub observaciones: Option<String>,
     pub created_at: String,
@@ -126,6 +185,7 @@ pub struct RequerimientoEnriquecido {
     pub partida_unidad: Option<String>,
     pub precio_unitario: Option<f64>,
     pub cantidad: f64,
+    pub cantidad_recibida: f64,
     pub precio_total: f64,
     pub fecha_inicio: String,
     pub plazo_dias: i32,
@@ -153,6 +213,7 @@ pub struct Recinto {
     pub id: i64,
     pub jardin_codigo: String,
     pub nombre: String,
+    pub activo: bool,
     pub created_at: String,
 }
 
@@ -176,9 +237,14 @@ pub struct InformePago {
     pub jardin_codigo: String,
     pub fecha_creacion: String,
     pub neto: f64,
+    pub neto_exento: f64,
+    pub gastos_generales: f64,
     pub utilidades: f64,
     pub iva: f64,
     pub total_final: f64,
+    pub porcentaje_gastos_generales: f64,
+    pub porcentaje_utilidades: f64,
+    pub porcentaje_iva: f64,
     pub observaciones: Option<String>,
     pub created_at: String,
     pub updated_at: String,
@@ -193,11 +259,22 @@ pub struct InformePagoEnriquecido {
     pub jardin_nombre: Option<String>,
     pub fecha_creacion: String,
     pub neto: f64,
+    pub neto_exento: f64,
+    pub gastos_generales: f64,
     pub utilidades: f64,
     pub iva: f64,
     pub total_final: f64,
+    pub porcentaje_gastos_generales: f64,
+    pub porcentaje_utilidades: f64,
+    pub porcentaje_iva: f64,
     pub cantidad_requerimientos: i64,
     pub observaciones: Option<String>,
+    pub estado: String,
+    pub fecha_emision: Option<String>,
+    pub fecha_aprobacion: Option<String>,
+    pub fecha_pago: Option<String>,
+    pub fecha_anulacion: Option<String>,
+    pub motivo_anulacion: Option<String>,
     pub created_at: String,
     pub updated_at: String,
 }
@@ -210,4 +287,17 @@ pub struct Configuracion {
     pub prefijo_correlativo: String,
     pub ito_nombre: Option<String>,
     pub ito_firma_base64: Option<String>,
+    pub porcentaje_gastos_generales: f64,
+    pub porcentaje_utilidades: f64,
+    pub porcentaje_iva: f64,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, sqlx::FromRow)]
+pub struct HistorialTasas {
+    pub id: i64,
+    pub vigente_desde: String,
+    pub porcentaje_gastos_generales: f64,
+    pub porcentaje_utilidades: f64,
+    pub porcentaje_iva: f64,
+    pub created_at: String,
 }
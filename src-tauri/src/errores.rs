@@ -0,0 +1,209 @@
+use crate::estados::ErrorEstado;
+
+// ========== ERRORES DE COMANDOS ==========
+//
+// Los comandos devuelven `ErrorComando`, que llega al frontend como
+// {"tipo": "conflict", "mensaje": "...", ...campos del tipo}. `mensaje` siempre
+// trae el texto para mostrar; los demás campos permiten marcar el campo del
+// formulario o la fila del archivo con problemas.
+
+#[derive(Debug)]
+pub enum ErrorComando {
+    /// La fila pedida no existe
+    NotFound { entidad: String, id: String },
+    /// Clave duplicada (p. ej. código de jardín ya registrado)
+    Conflict { tabla: String, campo: String },
+    /// Dato de entrada no válido
+    Validation { campo: String, mensaje: String },
+    /// Referencia a una fila que no existe o que otras filas todavía usan
+    ForeignKey { mensaje: String },
+    /// Cambio de estado no permitido por el flujo del requerimiento o del informe
+    StateTransition(ErrorEstado),
+    /// Archivos, respaldos
+    Io { mensaje: String },
+    /// Archivo de importación con problemas; `fila` cuenta desde 1 como en Excel
+    Import { hoja: Option<String>, fila: Option<usize>, mensaje: String },
+    /// Cualquier otro error de base de datos o interno
+    Internal { mensaje: String },
+}
+
+impl ErrorComando {
+    pub fn no_encontrado(entidad: &str, id: impl ToString) -> Self {
+        ErrorComando::NotFound { entidad: entidad.to_string(), id: id.to_string() }
+    }
+
+    pub fn validacion(campo: &str, mensaje: impl Into<String>) -> Self {
+        ErrorComando::Validation { campo: campo.to_string(), mensaje: mensaje.into() }
+    }
+
+    pub fn importacion(hoja: Option<&str>, fila: Option<usize>, mensaje: impl Into<String>) -> Self {
+        ErrorComando::Import { hoja: hoja.map(str::to_string), fila, mensaje: mensaje.into() }
+    }
+
+    /// Agrega la hoja y fila del archivo que se estaba importando
+    pub fn en_fila(self, hoja: Option<&str>, fila: usize) -> Self {
+        ErrorComando::importacion(hoja, Some(fila), self.to_string())
+    }
+
+    pub fn tipo(&self) -> &'static str {
+        match self {
+            ErrorComando::NotFound { .. } => "not_found",
+            ErrorComando::Conflict { .. } => "conflict",
+            ErrorComando::Validation { .. } => "validation",
+            ErrorComando::ForeignKey { .. } => "foreign_key",
+            ErrorComando::StateTransition(_) => "state_transition",
+            ErrorComando::Io { .. } => "io",
+            ErrorComando::Import { .. } => "import",
+            ErrorComando::Internal { .. } => "internal",
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorComando {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorComando::NotFound { entidad, id } => write!(f, "{} {} no existe", entidad, id),
+            ErrorComando::Conflict { tabla, campo } => {
+                write!(f, "Ya existe un registro en {} con el mismo {}", tabla, campo)
+            }
+            ErrorComando::Validation { mensaje, .. }
+            | ErrorComando::ForeignKey { mensaje }
+            | ErrorComando::Io { mensaje }
+            | ErrorComando::Internal { mensaje } => f.write_str(mensaje),
+            ErrorComando::StateTransition(e) => write!(f, "{}", e),
+            ErrorComando::Import { hoja, fila, mensaje } => {
+                if let Some(hoja) = hoja {
+                    write!(f, "Hoja '{}', ", hoja)?;
+                }
+                if let Some(fila) = fila {
+                    write!(f, "fila {}: ", fila)?;
+                }
+                f.write_str(mensaje)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ErrorComando {}
+
+impl serde::Serialize for ErrorComando {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde_json::json;
+
+        let mut valor = json!({ "tipo": self.tipo(), "mensaje": self.to_string() });
+        let campos = match self {
+            ErrorComando::NotFound { entidad, id } => json!({ "entidad": entidad, "id": id }),
+            ErrorComando::Conflict { tabla, campo } => json!({ "tabla": tabla, "campo": campo }),
+            ErrorComando::Validation { campo, .. } => json!({ "campo": campo }),
+            ErrorComando::StateTransition(ErrorEstado::Transicion { id, desde, hacia }) => {
+                json!({ "id": id, "desde": desde, "hacia": hacia })
+            }
+            ErrorComando::StateTransition(ErrorEstado::Bloqueado { id, estado, accion }) => {
+                json!({ "id": id, "estado": estado, "accion": accion })
+            }
+            ErrorComando::StateTransition(ErrorEstado::TransicionInforme { id, desde, hacia }) => {
+                json!({ "informe_id": id, "desde": desde, "hacia": hacia })
+            }
+            ErrorComando::StateTransition(ErrorEstado::InformeBloqueado { id, estado, accion }) => {
+                json!({ "informe_id": id, "estado": estado, "accion": accion })
+            }
+            ErrorComando::StateTransition(ErrorEstado::Desconocido(estado)) => json!({ "estado": estado }),
+            ErrorComando::Import { hoja, fila, .. } => json!({ "hoja": hoja, "fila": fila }),
+            ErrorComando::ForeignKey { .. } | ErrorComando::Io { .. } | ErrorComando::Internal { .. } => json!({}),
+        };
+        if let (Some(valor), serde_json::Value::Object(campos)) = (valor.as_object_mut(), campos) {
+            valor.extend(campos);
+        }
+        valor.serialize(serializer)
+    }
+}
+
+impl From<sqlx::Error> for ErrorComando {
+    fn from(e: sqlx::Error) -> Self {
+        let sqlx::Error::Database(db) = &e else {
+            return ErrorComando::Internal { mensaje: e.to_string() };
+        };
+        let mensaje = db.message();
+
+        // "UNIQUE constraint failed: jardines.contrato_id, jardines.codigo":
+        // la última columna es la que el usuario eligió (contrato_id va primero)
+        if db.is_unique_violation() {
+            let columna = mensaje.rsplit(": ").next().unwrap_or_default();
+            let columna = columna.rsplit(", ").next().unwrap_or_default();
+            let (tabla, campo) = columna.split_once('.').unwrap_or(("", columna));
+            return ErrorComando::Conflict { tabla: tabla.to_string(), campo: campo.to_string() };
+        }
+        if db.is_foreign_key_violation() {
+            return ErrorComando::ForeignKey {
+                mensaje: "El registro hace referencia a otro que no existe o todavía está en uso".to_string(),
+            };
+        }
+        // "CHECK constraint failed: tipo_plazo IN (...)"
+        if db.is_check_violation() {
+            let regla = mensaje.rsplit(": ").next().unwrap_or_default();
+            let campo = regla.split_whitespace().next().unwrap_or_default();
+            return ErrorComando::validacion(campo, mensaje);
+        }
+        // RAISE(ABORT) de los triggers de estado
+        if mensaje.contains("Estado de requerimiento no válido") {
+            return ErrorComando::validacion("estado", mensaje);
+        }
+        // RAISE(ABORT) de los triggers que protegen jardines y partidas en uso
+        if mensaje.contains("Catálogo en uso") {
+            return ErrorComando::ForeignKey { mensaje: mensaje.to_string() };
+        }
+        ErrorComando::Internal { mensaje: mensaje.to_string() }
+    }
+}
+
+impl From<ErrorEstado> for ErrorComando {
+    fn from(e: ErrorEstado) -> Self {
+        ErrorComando::StateTransition(e)
+    }
+}
+
+impl From<std::io::Error> for ErrorComando {
+    fn from(e: std::io::Error) -> Self {
+        ErrorComando::Io { mensaje: e.to_string() }
+    }
+}
+
+impl From<crate::migraciones::MigracionError> for ErrorComando {
+    fn from(e: crate::migraciones::MigracionError) -> Self {
+        match e {
+            crate::migraciones::MigracionError::Sql(e) => e.into(),
+            otro => ErrorComando::Internal { mensaje: otro.to_string() },
+        }
+    }
+}
+
+impl From<rust_xlsxwriter::XlsxError> for ErrorComando {
+    fn from(e: rust_xlsxwriter::XlsxError) -> Self {
+        ErrorComando::Io { mensaje: format!("Error escribiendo Excel: {}", e) }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+
+    #[tokio::test]
+    async fn unico_por_contrato_informa_la_columna_del_usuario() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(SqliteConnectOptions::new().in_memory(true))
+            .await
+            .unwrap();
+        crate::migraciones::migrar(&pool).await.unwrap();
+
+        let insertar = "INSERT INTO jardines (contrato_id, codigo, nombre) VALUES (1, 'J01', 'Jardín Uno')";
+        sqlx::query(insertar).execute(&pool).await.unwrap();
+        let error: ErrorComando = sqlx::query(insertar).execute(&pool).await.unwrap_err().into();
+        match error {
+            ErrorComando::Conflict { tabla, campo } => assert_eq!((tabla.as_str(), campo.as_str()), ("jardines", "codigo")),
+            otro => panic!("se esperaba conflicto, llegó {:?}", otro),
+        }
+    }
+}
@@ -0,0 +1,295 @@
+use crate::errores::ErrorComando;
+use sqlx::SqliteConnection;
+
+// ========== ESTADOS DE REQUERIMIENTO ==========
+//
+// pendiente → en_ot → recepcionado → en_informe → pagado
+//     ↓         ↓
+//  anulado ← ───┘
+//
+// Los retrocesos permitidos deshacen el paso anterior (sacar de la OT, quitar
+// la recepción, sacar del informe) y un anulado puede reactivarse a pendiente.
+// `pagado` es final. Cada comando que cambia `estado` verifica aquí la
+// transición antes de escribir. Un en_ot puede tener entregas parciales; pasa
+// a recepcionado cuando se completa la cantidad (ver recepciones.rs).
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EstadoRequerimiento {
+    Pendiente,
+    EnOt,
+    Recepcionado,
+    EnInforme,
+    Pagado,
+    Anulado,
+}
+
+use EstadoRequerimiento::*;
+
+impl EstadoRequerimiento {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Pendiente => "pendiente",
+            EnOt => "en_ot",
+            Recepcionado => "recepcionado",
+            EnInforme => "en_informe",
+            Pagado => "pagado",
+            Anulado => "anulado",
+        }
+    }
+
+    pub fn parse(estado: &str) -> Result<Self, ErrorEstado> {
+        match estado {
+            "pendiente" => Ok(Pendiente),
+            "en_ot" => Ok(EnOt),
+            "recepcionado" => Ok(Recepcionado),
+            "en_informe" => Ok(EnInforme),
+            "pagado" => Ok(Pagado),
+            "anulado" => Ok(Anulado),
+            otro => Err(ErrorEstado::Desconocido(otro.to_string())),
+        }
+    }
+
+    pub fn siguientes(self) -> &'static [EstadoRequerimiento] {
+        match self {
+            Pendiente => &[EnOt, Anulado],
+            EnOt => &[Pendiente, Recepcionado, Anulado],
+            Recepcionado => &[EnOt, EnInforme],
+            EnInforme => &[Recepcionado, Pagado],
+            Pagado => &[],
+            Anulado => &[Pendiente],
+        }
+    }
+
+    pub fn puede_pasar_a(self, destino: EstadoRequerimiento) -> bool {
+        self.siguientes().contains(&destino)
+    }
+
+    /// Cantidad, precio, plazos y fechas solo se editan antes de entrar a un informe
+    pub fn permite_editar(self) -> bool {
+        matches!(self, Pendiente | EnOt | Recepcionado)
+    }
+
+    /// Las líneas informadas o pagadas no se pueden eliminar
+    pub fn permite_eliminar(self) -> bool {
+        !matches!(self, EnInforme | Pagado)
+    }
+
+    /// El estado es compatible con tener OT (`ot_id`) e informe (`informe_pago_id`).
+    /// Recepcionado admite no tener OT (filas anteriores a las OTs).
+    pub fn vinculos_validos(self, ot: bool, informe: bool) -> bool {
+        match self {
+            Pendiente | Anulado => !ot && !informe,
+            EnOt => ot && !informe,
+            Recepcionado => !informe,
+            EnInforme | Pagado => informe,
+        }
+    }
+
+    /// Estado que corresponde a los vínculos de una fila creada antes de la máquina de estados
+    pub fn desde_vinculos(ot: bool, recepcion: bool, informe: bool) -> Self {
+        match (ot, recepcion, informe) {
+            (_, _, true) => EnInforme,
+            (_, true, false) => Recepcionado,
+            (true, false, false) => EnOt,
+            _ => Pendiente,
+        }
+    }
+}
+
+impl std::fmt::Display for EstadoRequerimiento {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// ========== ESTADOS DE INFORME DE PAGO ==========
+//
+// borrador → emitido → aprobado → pagado
+//     ↓         ↓         ↓
+//  anulado ← ───┴─────────┘
+//
+// Solo el borrador admite cambios de líneas y totales, y solo un borrador se
+// puede eliminar. Un informe emitido no vuelve a borrador: si el mandante lo
+// observa se anula (con motivo) y se crea otro con las mismas líneas. Anular
+// conserva el informe (con una copia de sus líneas) y libera los
+// requerimientos; pagar marca sus líneas como pagadas. `pagado` y `anulado`
+// son finales.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EstadoInforme {
+    Borrador,
+    Emitido,
+    Aprobado,
+    Pagado,
+    Anulado,
+}
+
+impl EstadoInforme {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EstadoInforme::Borrador => "borrador",
+            EstadoInforme::Emitido => "emitido",
+            EstadoInforme::Aprobado => "aprobado",
+            EstadoInforme::Pagado => "pagado",
+            EstadoInforme::Anulado => "anulado",
+        }
+    }
+
+    pub fn parse(estado: &str) -> Result<Self, ErrorEstado> {
+        match estado {
+            "borrador" => Ok(EstadoInforme::Borrador),
+            "emitido" => Ok(EstadoInforme::Emitido),
+            "aprobado" => Ok(EstadoInforme::Aprobado),
+            "pagado" => Ok(EstadoInforme::Pagado),
+            "anulado" => Ok(EstadoInforme::Anulado),
+            otro => Err(ErrorEstado::Desconocido(otro.to_string())),
+        }
+    }
+
+    pub fn siguientes(self) -> &'static [EstadoInforme] {
+        match self {
+            EstadoInforme::Borrador => &[EstadoInforme::Emitido, EstadoInforme::Anulado],
+            EstadoInforme::Emitido => &[EstadoInforme::Aprobado, EstadoInforme::Anulado],
+            EstadoInforme::Aprobado => &[EstadoInforme::Pagado, EstadoInforme::Anulado],
+            EstadoInforme::Pagado | EstadoInforme::Anulado => &[],
+        }
+    }
+
+    pub fn puede_pasar_a(self, destino: EstadoInforme) -> bool {
+        self.siguientes().contains(&destino)
+    }
+
+    /// Líneas, totales y observaciones solo se editan en borrador
+    pub fn permite_editar(self) -> bool {
+        self == EstadoInforme::Borrador
+    }
+}
+
+impl std::fmt::Display for EstadoInforme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorEstado {
+    /// Valor de `estado` que no es ninguno de los conocidos
+    Desconocido(String),
+    /// El flujo no permite pasar de `desde` a `hacia`
+    Transicion { id: i64, desde: EstadoRequerimiento, hacia: EstadoRequerimiento },
+    /// La acción no está permitida en el estado actual
+    Bloqueado { id: i64, estado: EstadoRequerimiento, accion: &'static str },
+    /// El flujo del informe no permite pasar de `desde` a `hacia`
+    TransicionInforme { id: i64, desde: EstadoInforme, hacia: EstadoInforme },
+    /// La acción no está permitida en el estado actual del informe
+    InformeBloqueado { id: i64, estado: EstadoInforme, accion: &'static str },
+}
+
+impl std::fmt::Display for ErrorEstado {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorEstado::Desconocido(estado) => write!(f, "Estado de requerimiento no válido: '{}'", estado),
+            ErrorEstado::Transicion { id, desde, hacia } => write!(
+                f,
+                "El requerimiento {} no puede pasar de '{}' a '{}'",
+                id, desde, hacia
+            ),
+            ErrorEstado::Bloqueado { id, estado, accion } => write!(
+                f,
+                "No se puede {} el requerimiento {}: está '{}'",
+                accion, id, estado
+            ),
+            ErrorEstado::TransicionInforme { id, desde, hacia } => write!(
+                f,
+                "El informe {} no puede pasar de '{}' a '{}'",
+                id, desde, hacia
+            ),
+            ErrorEstado::InformeBloqueado { id, estado, accion } => write!(
+                f,
+                "No se puede {} el informe {}: está '{}'",
+                accion, id, estado
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ErrorEstado {}
+
+impl From<ErrorEstado> for String {
+    fn from(e: ErrorEstado) -> Self {
+        e.to_string()
+    }
+}
+
+pub async fn estado_actual(conn: &mut SqliteConnection, id: i64) -> Result<EstadoRequerimiento, ErrorComando> {
+    let estado: String = sqlx::query_scalar("SELECT estado FROM requerimientos WHERE id = ?")
+        .bind(id)
+        .fetch_optional(conn)
+        .await?
+        .ok_or_else(|| ErrorComando::no_encontrado("Requerimiento", id))?;
+
+    Ok(EstadoRequerimiento::parse(&estado)?)
+}
+
+/// Verifica que el requerimiento esté en `desde` para pasarlo a `hacia`
+pub async fn verificar_transicion(
+    conn: &mut SqliteConnection,
+    id: i64,
+    desde: EstadoRequerimiento,
+    hacia: EstadoRequerimiento,
+) -> Result<(), ErrorComando> {
+    debug_assert!(desde.puede_pasar_a(hacia));
+    let actual = estado_actual(conn, id).await?;
+    if actual != desde || !actual.puede_pasar_a(hacia) {
+        return Err(ErrorEstado::Transicion { id, desde: actual, hacia }.into());
+    }
+    Ok(())
+}
+
+/// Verifica que la acción esté permitida en el estado actual. Devuelve el estado actual.
+pub async fn verificar_accion(
+    conn: &mut SqliteConnection,
+    id: i64,
+    accion: &'static str,
+    permitida: fn(EstadoRequerimiento) -> bool,
+) -> Result<EstadoRequerimiento, ErrorComando> {
+    let estado = estado_actual(conn, id).await?;
+    if !permitida(estado) {
+        return Err(ErrorEstado::Bloqueado { id, estado, accion }.into());
+    }
+    Ok(estado)
+}
+
+pub async fn estado_informe(conn: &mut SqliteConnection, id: i64) -> Result<EstadoInforme, ErrorComando> {
+    let estado: String = sqlx::query_scalar("SELECT estado FROM informes_pago WHERE id = ?")
+        .bind(id)
+        .fetch_optional(conn)
+        .await?
+        .ok_or_else(|| ErrorComando::no_encontrado("Informe", id))?;
+
+    Ok(EstadoInforme::parse(&estado)?)
+}
+
+/// Verifica que el informe pueda pasar a `hacia`. Devuelve el estado actual.
+pub async fn verificar_transicion_informe(
+    conn: &mut SqliteConnection,
+    id: i64,
+    hacia: EstadoInforme,
+) -> Result<EstadoInforme, ErrorComando> {
+    let actual = estado_informe(conn, id).await?;
+    if !actual.puede_pasar_a(hacia) {
+        return Err(ErrorEstado::TransicionInforme { id, desde: actual, hacia }.into());
+    }
+    Ok(actual)
+}
+
+/// Verifica que el informe siga en borrador para `accion`
+pub async fn verificar_borrador(conn: &mut SqliteConnection, id: i64, accion: &'static str) -> Result<(), ErrorComando> {
+    let estado = estado_informe(conn, id).await?;
+    if !estado.permite_editar() {
+        return Err(ErrorEstado::InformeBloqueado { id, estado, accion }.into());
+    }
+    Ok(())
+}
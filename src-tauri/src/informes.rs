@@ -0,0 +1,170 @@
+use crate::calculos::{calcular_totales_informe, TasasInforme, TotalesInforme};
+use crate::errores::ErrorComando;
+use sqlx::SqliteConnection;
+
+// ========== TOTALES DE INFORMES DE PAGO ==========
+//
+// Los totales de un informe salen siempre de sus líneas en la base: el a_pago
+// (precio_total − multa) de cada requerimiento vinculado, separando las
+// partidas exentas de IVA, con las tasas guardadas en el informe (fórmulas en
+// calculos.rs). El frontend solo indica qué requerimientos entran.
+//
+// `descuadrados` compara lo guardado con lo que dan las líneas. Los informes
+// anteriores a este cálculo pueden diferir (el neto venía del frontend). Solo
+// un borrador se corrige: desde emitido el mandante ya tiene el informe por
+// esos montos, así que la diferencia se informa y se resuelve anulándolo.
+
+/// Diferencia de montos que se atribuye a redondeo
+const TOLERANCIA: f64 = 0.01;
+
+/// Sumas de a_pago afecto y exento de las líneas `r` (con su partida `p`)
+const SUMAS_LINEAS: &str = "
+    COALESCE(SUM(CASE WHEN COALESCE(p.exenta_iva, 0) = 0 THEN r.precio_total - COALESCE(r.multa, 0) END), 0.0) as lineas_afectas,
+    COALESCE(SUM(CASE WHEN p.exenta_iva = 1 THEN r.precio_total - COALESCE(r.multa, 0) END), 0.0) as lineas_exentas";
+
+/// Requerimiento que entra al informe
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct LineaInforme {
+    pub id: i64,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct InformeDescuadrado {
+    pub id: i64,
+    pub contrato_id: i64,
+    pub codigo: String,
+    pub estado: String,
+    pub guardado: TotalesInforme,
+    pub calculado: TotalesInforme,
+    /// Solo los borradores se pueden recalcular
+    pub reparable: bool,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct ConciliacionInformes {
+    pub descuadrados: Vec<InformeDescuadrado>,
+    /// Informes cuyos totales se corrigieron
+    pub corregidos: Vec<i64>,
+}
+
+#[derive(sqlx::FromRow)]
+struct InformeConLineas {
+    id: i64,
+    contrato_id: i64,
+    codigo: String,
+    estado: String,
+    neto: f64,
+    neto_exento: f64,
+    gastos_generales: f64,
+    utilidades: f64,
+    iva: f64,
+    total_final: f64,
+    #[sqlx(flatten)]
+    tasas: TasasInforme,
+    lineas_afectas: f64,
+    lineas_exentas: f64,
+}
+
+impl InformeConLineas {
+    fn guardado(&self) -> TotalesInforme {
+        TotalesInforme {
+            neto: self.neto,
+            neto_exento: self.neto_exento,
+            gastos_generales: self.gastos_generales,
+            utilidades: self.utilidades,
+            iva: self.iva,
+            total_final: self.total_final,
+        }
+    }
+
+    fn calculado(&self) -> TotalesInforme {
+        calcular_totales_informe(self.lineas_afectas, self.lineas_exentas, &self.tasas)
+    }
+}
+
+/// Informes no anulados con sus totales guardados y las sumas de sus líneas
+fn seleccion_informes(condicion: &str) -> String {
+    format!(
+        "SELECT ip.id, ip.contrato_id, ip.codigo, ip.estado,
+                COALESCE(ip.neto, 0.0) as neto, ip.neto_exento, ip.gastos_generales, ip.utilidades,
+                COALESCE(ip.iva, 0.0) as iva, COALESCE(ip.total_final, 0.0) as total_final,
+                ip.porcentaje_gastos_generales, ip.porcentaje_utilidades, ip.porcentaje_iva,
+                {}
+         FROM informes_pago ip
+         LEFT JOIN requerimientos r ON r.informe_pago_id = ip.id
+         LEFT JOIN partidas p ON p.contrato_id = r.contrato_id AND p.item = r.partida_item
+         WHERE ip.estado <> 'anulado' AND {}
+         GROUP BY ip.id
+         ORDER BY ip.id",
+        SUMAS_LINEAS, condicion
+    )
+}
+
+async fn guardar_totales(conn: &mut SqliteConnection, informe_id: i64, totales: &TotalesInforme) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE informes_pago
+         SET neto = ?, neto_exento = ?, gastos_generales = ?, utilidades = ?, iva = ?, total_final = ?,
+             updated_at = datetime('now')
+         WHERE id = ?"
+    )
+    .bind(totales.neto)
+    .bind(totales.neto_exento)
+    .bind(totales.gastos_generales)
+    .bind(totales.utilidades)
+    .bind(totales.iva)
+    .bind(totales.total_final)
+    .bind(informe_id)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// Calcula y guarda los totales del informe desde sus líneas actuales
+pub async fn recalcular(conn: &mut SqliteConnection, informe_id: i64) -> Result<TotalesInforme, ErrorComando> {
+    let informe = sqlx::query_as::<_, InformeConLineas>(&seleccion_informes("ip.id = ?"))
+        .bind(informe_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| ErrorComando::no_encontrado("Informe", informe_id))?;
+    let totales = informe.calculado();
+    guardar_totales(conn, informe_id, &totales).await?;
+    Ok(totales)
+}
+
+/// Informes cuyos totales guardados no coinciden con sus líneas; `contrato_id`
+/// None = todos los contratos
+pub async fn descuadrados(conn: &mut SqliteConnection, contrato_id: Option<i64>) -> Result<Vec<InformeDescuadrado>, sqlx::Error> {
+    let informes = sqlx::query_as::<_, InformeConLineas>(&seleccion_informes("(?1 IS NULL OR ip.contrato_id = ?1)"))
+        .bind(contrato_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+    Ok(informes
+        .into_iter()
+        .filter_map(|informe| {
+            let (guardado, calculado) = (informe.guardado(), informe.calculado());
+            let diferencias = [
+                (guardado.neto, calculado.neto),
+                (guardado.neto_exento, calculado.neto_exento),
+                (guardado.gastos_generales, calculado.gastos_generales),
+                (guardado.utilidades, calculado.utilidades),
+                (guardado.iva, calculado.iva),
+                (guardado.total_final, calculado.total_final),
+            ];
+            diferencias.iter().any(|(a, b)| (a - b).abs() > TOLERANCIA).then(|| InformeDescuadrado {
+                reparable: informe.estado == "borrador",
+                id: informe.id,
+                contrato_id: informe.contrato_id,
+                codigo: informe.codigo,
+                estado: informe.estado,
+                guardado,
+                calculado,
+            })
+        })
+        .collect())
+}
+
+/// Guarda los totales calculados de un informe descuadrado
+pub async fn corregir(conn: &mut SqliteConnection, informe: &InformeDescuadrado) -> Result<(), sqlx::Error> {
+    guardar_totales(conn, informe.id, &informe.calculado).await
+}
@@ -0,0 +1,231 @@
+use crate::errores::ErrorComando;
+use crate::estados::EstadoRequerimiento;
+use crate::informes::{self, InformeDescuadrado};
+use sqlx::{Row, SqliteConnection};
+
+// ========== VERIFICACIÓN DE INTEGRIDAD ==========
+//
+// Revisa la base completa (todos los contratos):
+// - `PRAGMA integrity_check` (archivo dañado) y `PRAGMA foreign_key_check`
+//   (referencias rotas, p. ej. de bases usadas sin claves foráneas);
+// - recintos de jardines que no existen;
+// - requerimientos cuyo estado no calza con su OT e informe;
+// - informes no anulados cuyos totales guardados no coinciden con sus líneas
+//   (ver informes.rs).
+//
+// Reparar solo corrige lo que se deduce sin criterio: borra los recintos
+// huérfanos, deduce el estado de los vínculos (no en pagados ni anulados) y
+// recalcula los totales de los informes en borrador. El resto de los
+// problemas solo se informa.
+
+#[derive(Debug, serde::Serialize)]
+pub struct ReferenciaRota {
+    pub tabla: String,
+    pub rowid: Option<i64>,
+    /// Tabla a la que apunta la clave foránea
+    pub referencia: String,
+}
+
+#[derive(Debug, serde::Serialize, sqlx::FromRow)]
+pub struct RecintoHuerfano {
+    pub id: i64,
+    pub contrato_id: i64,
+    pub jardin_codigo: String,
+    pub nombre: String,
+}
+
+#[derive(Debug, serde::Serialize, sqlx::FromRow)]
+pub struct EstadoInconsistente {
+    pub id: i64,
+    pub contrato_id: i64,
+    pub estado: String,
+    pub ot_id: Option<i64>,
+    pub informe_pago_id: Option<i64>,
+    /// Estado que corresponde a los vínculos; None = requiere revisión manual
+    #[sqlx(skip)]
+    pub corregir_a: Option<EstadoRequerimiento>,
+    #[serde(skip)]
+    fecha_recepcion: Option<String>,
+}
+
+#[derive(Debug, Default, serde::Serialize)]
+pub struct Reparaciones {
+    pub recintos_borrados: usize,
+    pub estados_corregidos: usize,
+    pub informes_recalculados: usize,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct ReporteIntegridad {
+    /// Mensajes de `integrity_check` (vacío = archivo sano)
+    pub archivo: Vec<String>,
+    pub referencias_rotas: Vec<ReferenciaRota>,
+    pub recintos_huerfanos: Vec<RecintoHuerfano>,
+    pub estados_inconsistentes: Vec<EstadoInconsistente>,
+    pub informes_descuadrados: Vec<InformeDescuadrado>,
+    /// Solo al reparar; el reporte describe la base antes de la reparación
+    pub reparaciones: Option<Reparaciones>,
+}
+
+impl ReporteIntegridad {
+    pub fn problemas(&self) -> usize {
+        self.archivo.len()
+            + self.referencias_rotas.len()
+            + self.recintos_huerfanos.len()
+            + self.estados_inconsistentes.len()
+            + self.informes_descuadrados.len()
+    }
+}
+
+// ========== VERIFICACIONES ==========
+
+async fn archivo(conn: &mut SqliteConnection) -> Result<Vec<String>, sqlx::Error> {
+    let mensajes: Vec<String> = sqlx::query_scalar("PRAGMA integrity_check").fetch_all(&mut *conn).await?;
+    Ok(mensajes.into_iter().filter(|m| m != "ok").collect())
+}
+
+async fn referencias_rotas(conn: &mut SqliteConnection) -> Result<Vec<ReferenciaRota>, sqlx::Error> {
+    // Columnas: table, rowid, parent, fkid
+    let filas = sqlx::query("PRAGMA foreign_key_check").fetch_all(&mut *conn).await?;
+    Ok(filas
+        .iter()
+        .map(|fila| ReferenciaRota {
+            tabla: fila.get(0),
+            rowid: fila.get(1),
+            referencia: fila.get(2),
+        })
+        .collect())
+}
+
+async fn recintos_huerfanos(conn: &mut SqliteConnection) -> Result<Vec<RecintoHuerfano>, sqlx::Error> {
+    sqlx::query_as::<_, RecintoHuerfano>(
+        "SELECT r.id, r.contrato_id, r.jardin_codigo, r.nombre FROM recintos r
+         WHERE NOT EXISTS (SELECT 1 FROM jardines j WHERE j.contrato_id = r.contrato_id AND j.codigo = r.jardin_codigo)
+         ORDER BY r.id"
+    )
+    .fetch_all(&mut *conn)
+    .await
+}
+
+async fn estados_inconsistentes(conn: &mut SqliteConnection) -> Result<Vec<EstadoInconsistente>, sqlx::Error> {
+    let filas = sqlx::query_as::<_, EstadoInconsistente>(
+        "SELECT id, contrato_id, estado, ot_id, informe_pago_id, fecha_recepcion FROM requerimientos ORDER BY id"
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut inconsistentes = Vec::new();
+    for mut fila in filas {
+        let (ot, informe) = (fila.ot_id.is_some(), fila.informe_pago_id.is_some());
+        match EstadoRequerimiento::parse(&fila.estado) {
+            Ok(estado) if estado.vinculos_validos(ot, informe) => continue,
+            // Un pagado o un anulado no se deducen de los vínculos
+            Ok(EstadoRequerimiento::Pagado | EstadoRequerimiento::Anulado) | Err(_) => {}
+            Ok(_) => {
+                fila.corregir_a = Some(EstadoRequerimiento::desde_vinculos(ot, fila.fecha_recepcion.is_some(), informe));
+            }
+        }
+        inconsistentes.push(fila);
+    }
+    Ok(inconsistentes)
+}
+
+// ========== REPARACIÓN ==========
+
+async fn reparar(conn: &mut SqliteConnection, reporte: &ReporteIntegridad) -> Result<Reparaciones, sqlx::Error> {
+    let mut reparaciones = Reparaciones::default();
+
+    for recinto in &reporte.recintos_huerfanos {
+        sqlx::query("DELETE FROM recintos WHERE id = ?").bind(recinto.id).execute(&mut *conn).await?;
+        reparaciones.recintos_borrados += 1;
+    }
+
+    for fila in &reporte.estados_inconsistentes {
+        let Some(estado) = fila.corregir_a else { continue };
+        sqlx::query("UPDATE requerimientos SET estado = ?, updated_at = datetime('now') WHERE id = ?")
+            .bind(estado.as_str())
+            .bind(fila.id)
+            .execute(&mut *conn)
+            .await?;
+        reparaciones.estados_corregidos += 1;
+    }
+
+    for informe in reporte.informes_descuadrados.iter().filter(|i| i.reparable) {
+        informes::corregir(conn, informe).await?;
+        reparaciones.informes_recalculados += 1;
+    }
+
+    Ok(reparaciones)
+}
+
+/// Revisa la base y, con `reparar`, corrige lo que se puede deducir
+pub async fn verificar(conn: &mut SqliteConnection, reparar_problemas: bool) -> Result<ReporteIntegridad, ErrorComando> {
+    let mut reporte = ReporteIntegridad {
+        archivo: archivo(conn).await?,
+        referencias_rotas: referencias_rotas(conn).await?,
+        recintos_huerfanos: recintos_huerfanos(conn).await?,
+        estados_inconsistentes: estados_inconsistentes(conn).await?,
+        informes_descuadrados: informes::descuadrados(conn, None).await?,
+        reparaciones: None,
+    };
+    if reparar_problemas {
+        reporte.reparaciones = Some(reparar(conn, &reporte).await?);
+    }
+    Ok(reporte)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+    use sqlx::{Pool, Sqlite};
+
+    async fn pool_memoria() -> Pool<Sqlite> {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(SqliteConnectOptions::new().in_memory(true))
+            .await
+            .unwrap();
+        crate::migraciones::migrar(&pool).await.unwrap();
+        for sql in [
+            "INSERT INTO jardines (contrato_id, codigo, nombre) VALUES (1, 'J01', 'Jardín Uno')",
+            "INSERT INTO partidas (contrato_id, item, partida, unidad, precio_unitario, exenta_iva) VALUES (1, '1.1', 'Pintura', 'm2', 1000, 0)",
+            "INSERT INTO ordenes_trabajo (contrato_id, codigo, jardin_codigo, fecha_creacion) VALUES (1, 'OT-J01-M001', 'J01', '2025-03-01')",
+            // Ambos con totales guardados que no calzan con su línea
+            "INSERT INTO informes_pago (contrato_id, codigo, jardin_codigo, fecha_creacion, estado, neto, utilidades, iva, total_final)
+             VALUES (1, 'IP-J01-M01', 'J01', '2025-04-01', 'borrador', 1, 0, 0, 1)",
+            "INSERT INTO informes_pago (contrato_id, codigo, jardin_codigo, fecha_creacion, estado, fecha_emision, neto, utilidades, iva, total_final)
+             VALUES (1, 'IP-J01-M02', 'J01', '2025-04-01', 'emitido', '2025-04-02', 1, 0, 0, 1)",
+            "INSERT INTO requerimientos (contrato_id, jardin_codigo, partida_item, cantidad, precio_unitario, precio_total,
+                fecha_inicio, fecha_registro, estado, ot_id, informe_pago_id, fecha_recepcion)
+             VALUES (1, 'J01', '1.1', 1, 1000, 1000, '2025-03-01', '2025-03-01', 'en_informe', 1, 1, '2025-03-05'),
+                    (1, 'J01', '1.1', 2, 1000, 2000, '2025-03-01', '2025-03-01', 'en_informe', 1, 2, '2025-03-05')",
+        ] {
+            sqlx::query(sql).execute(&pool).await.unwrap();
+        }
+        pool
+    }
+
+    #[tokio::test]
+    async fn reparar_solo_corrige_informes_en_borrador() {
+        let pool = pool_memoria().await;
+        let mut conn = pool.acquire().await.unwrap();
+
+        let reporte = verificar(&mut conn, true).await.unwrap();
+        let reparables: Vec<(&str, bool)> =
+            reporte.informes_descuadrados.iter().map(|i| (i.estado.as_str(), i.reparable)).collect();
+        assert_eq!(reparables, vec![("borrador", true), ("emitido", false)]);
+        assert_eq!(reporte.reparaciones.unwrap().informes_recalculados, 1);
+
+        let netos: Vec<(String, f64)> = sqlx::query_as("SELECT estado, neto FROM informes_pago ORDER BY id")
+            .fetch_all(&mut *conn)
+            .await
+            .unwrap();
+        assert_eq!(netos, vec![("borrador".to_string(), 1000.0), ("emitido".to_string(), 1.0)]);
+
+        // El emitido se sigue informando
+        let reporte = verificar(&mut conn, false).await.unwrap();
+        assert_eq!(reporte.informes_descuadrados.len(), 1);
+        assert_eq!(reporte.informes_descuadrados[0].codigo, "IP-J01-M02");
+    }
+}
@@ -1,6 +1,36 @@
+mod adjuntos;
+mod auditoria;
+mod bases;
+mod calculos;
+mod calendario;
+mod catalogo;
+mod consultas;
+mod contratos;
 mod db;
+mod errores;
+mod estados;
+mod informes;
+mod integridad;
+mod migraciones;
+mod multas;
+mod pdf;
+mod precios;
+mod presupuesto;
+mod recepciones;
 mod commands;
+mod commands_adjuntos;
+mod commands_auditoria;
+mod commands_bases;
+mod commands_calendario;
+mod commands_contratos;
 mod commands_firma;
+mod commands_integridad;
+mod commands_pdf;
+mod commands_precios;
+mod commands_presupuesto;
+mod commands_recepciones;
+mod commands_respaldo;
+mod commands_xlsx;
 
 use db::DbState;
 
@@ -25,11 +55,16 @@ pub fn run() {
                 commands::get_partidas,
                 commands::add_partida,
                 commands::get_requerimientos,
+                commands::consultar_requerimientos,
                 commands::add_requerimiento,
                 commands::update_requerimiento,
                 commands::actualizar_fecha_recepcion,
                 commands::eliminar_fecha_recepcion,
+                commands_recepciones::get_recepciones,
+                commands_recepciones::agregar_recepcion,
+                commands_recepciones::eliminar_recepcion,
                 commands::delete_requerimiento,
+                commands::cambiar_estado_requerimiento,
                 commands::get_recintos,
                 commands::get_recintos_by_jardin,
                 commands::add_recinto,
@@ -44,16 +79,72 @@ pub fn run() {
                 commands::crear_informe_pago,
                 commands::update_informe_pago,
                 commands::eliminar_informe_pago,
+                commands::cambiar_estado_informe_pago,
+                commands::conciliar_informes_pago,
                 commands::get_configuracion,
                 commands::update_configuracion,
+                commands::update_tasas_contrato,
+                commands::get_historial_tasas,
+                commands::get_reglas_multa,
+                commands::update_reglas_multa,
+                commands::get_detalle_multa,
+                commands_precios::get_precios_partida,
+                commands_precios::get_precio_vigente,
+                commands_precios::get_reajustes,
+                commands_precios::aplicar_reajuste,
+                commands_presupuesto::get_datos_contrato,
+                commands_presupuesto::update_datos_contrato,
+                commands_presupuesto::asignar_presupuesto_jardin,
+                commands_presupuesto::get_resumen_presupuesto,
+                commands_contratos::get_contratos,
+                commands_contratos::crear_contrato,
+                commands_contratos::activar_contrato,
+                commands_contratos::eliminar_contrato,
                 commands::clear_all,
+                commands::previsualizar_catalogo_json,
+                commands::previsualizar_catalogo_csv,
+                commands::previsualizar_catalogo_xlsx,
+                commands::previsualizar_catalogo_xlsx_bytes,
+                commands::aplicar_catalogo,
                 commands::importar_catalogo_json,
                 commands::importar_catalogo_csv,
                 commands::importar_catalogo_xlsx,
                 commands::importar_catalogo_xlsx_bytes,
                 commands::importar_base_datos_completa,
+                commands::exportar_base_datos_completa,
+                commands_calendario::get_configuracion_calendario,
+                commands_calendario::update_configuracion_calendario,
+                commands_calendario::get_feriados,
+                commands_calendario::add_feriado,
+                commands_calendario::eliminar_feriado,
+                commands_calendario::importar_feriados_csv,
+                commands_calendario::importar_feriados_xlsx_bytes,
                 commands_firma::importar_firma,
                 commands_firma::get_firma,
+                commands_respaldo::listar_respaldos,
+                commands_respaldo::restaurar_respaldo,
+                commands_integridad::verificar_integridad,
+                commands_auditoria::get_auditoria,
+                commands_auditoria::get_operador,
+                commands_auditoria::set_operador,
+                commands_bases::get_base_actual,
+                commands_bases::get_bases_recientes,
+                commands_bases::abrir_base_datos,
+                commands_bases::crear_base_datos,
+                commands_bases::guardar_base_datos_como,
+                commands_bases::quitar_base_reciente,
+                commands_xlsx::exportar_requerimientos_xlsx,
+                commands_xlsx::exportar_orden_trabajo_xlsx,
+                commands_xlsx::exportar_informe_pago_xlsx,
+                commands_pdf::exportar_orden_trabajo_pdf,
+                commands_pdf::exportar_informe_pago_pdf,
+                commands_pdf::exportar_informes_pago_mes_pdf,
+                commands_adjuntos::agregar_adjunto,
+                commands_adjuntos::get_adjuntos,
+                commands_adjuntos::get_adjunto_contenido,
+                commands_adjuntos::get_adjunto_miniatura,
+                commands_adjuntos::exportar_adjunto,
+                commands_adjuntos::eliminar_adjunto,
             ])
             .run(tauri::generate_context!())
             .expect("error while running tauri application");
@@ -0,0 +1,467 @@
+use sha2::{Digest, Sha256};
+use sqlx::{Connection, Pool, Sqlite, SqliteConnection};
+
+// ========== MIGRACIONES ==========
+//
+// Cada migración se embebe en el binario y se aplica una sola vez, en orden,
+// dentro de su propia transacción. La tabla `schema_migrations` registra la
+// versión, el nombre y el checksum SHA-256 del SQL aplicado.
+//
+// Las migraciones corren con `foreign_keys` desactivado, como pide SQLite para
+// reconstruir tablas (crear, copiar, borrar, renombrar), y antes de confirmar
+// se verifica con `foreign_key_check` que no queden referencias rotas.
+//
+// Reglas:
+// - Nunca editar una migración ya publicada: crear una nueva con la versión siguiente.
+// - La 001 usa `IF NOT EXISTS`, por lo que también adopta bases creadas por
+//   versiones anteriores al sistema de migraciones. Como `IF NOT EXISTS` no
+//   toca las tablas que ya existen, al aplicarla se agregan las columnas de la
+//   001 que le falten a esas tablas (ver `adoptar_base_anterior`).
+
+pub struct Migracion {
+    pub version: i64,
+    pub nombre: &'static str,
+    pub sql: &'static str,
+}
+
+pub const MIGRACIONES: &[Migracion] = &[
+    Migracion {
+        version: 1,
+        nombre: "schema_inicial",
+        sql: include_str!("../migrations/001_schema_inicial.sql"),
+    },
+    Migracion {
+        version: 3,
+        nombre: "indices_performance",
+        sql: include_str!("../migrations/003_indices_performance.sql"),
+    },
+    Migracion {
+        version: 4,
+        nombre: "tasas_informe",
+        sql: include_str!("../migrations/004_tasas_informe.sql"),
+    },
+    Migracion {
+        version: 5,
+        nombre: "reglas_multa",
+        sql: include_str!("../migrations/005_reglas_multa.sql"),
+    },
+    Migracion {
+        version: 6,
+        nombre: "calendario",
+        sql: include_str!("../migrations/006_calendario.sql"),
+    },
+    Migracion {
+        version: 7,
+        nombre: "estados_requerimiento",
+        sql: include_str!("../migrations/007_estados_requerimiento.sql"),
+    },
+    Migracion {
+        version: 8,
+        nombre: "auditoria",
+        sql: include_str!("../migrations/008_auditoria.sql"),
+    },
+    Migracion {
+        version: 9,
+        nombre: "adjuntos",
+        sql: include_str!("../migrations/009_adjuntos.sql"),
+    },
+    Migracion {
+        version: 10,
+        nombre: "recepciones",
+        sql: include_str!("../migrations/010_recepciones.sql"),
+    },
+    Migracion {
+        version: 11,
+        nombre: "estado_informes",
+        sql: include_str!("../migrations/011_estado_informes.sql"),
+    },
+    Migracion {
+        version: 12,
+        nombre: "presupuesto",
+        sql: include_str!("../migrations/012_presupuesto.sql"),
+    },
+    Migracion {
+        version: 13,
+        nombre: "contratos",
+        sql: include_str!("../migrations/013_contratos.sql"),
+    },
+    Migracion {
+        version: 14,
+        nombre: "precios_partida",
+        sql: include_str!("../migrations/014_precios_partida.sql"),
+    },
+    Migracion {
+        version: 15,
+        nombre: "catalogo_activo",
+        sql: include_str!("../migrations/015_catalogo_activo.sql"),
+    },
+    Migracion {
+        version: 16,
+        nombre: "cantidad_separada",
+        sql: include_str!("../migrations/016_cantidad_separada.sql"),
+    },
+];
+
+#[derive(Debug)]
+pub enum MigracionError {
+    Sql(sqlx::Error),
+    /// El SQL embebido no coincide con el que se aplicó en su momento
+    ChecksumDistinto { version: i64, nombre: String },
+    /// La base fue migrada por una versión más nueva de la aplicación
+    BaseMasNueva { version_base: i64, version_app: i64 },
+    /// La migración dejó filas que referencian registros inexistentes
+    ClavesForaneas { version: i64, nombre: String, tablas: Vec<String> },
+    /// Una base anterior a las migraciones no tiene una columna que no se puede agregar
+    AdopcionImposible { tabla: String, columna: String },
+}
+
+impl std::fmt::Display for MigracionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MigracionError::Sql(e) => write!(f, "Error SQL en migración: {}", e),
+            MigracionError::ChecksumDistinto { version, nombre } => write!(
+                f,
+                "La migración {:03}_{} fue modificada después de aplicarse (checksum distinto)",
+                version, nombre
+            ),
+            MigracionError::BaseMasNueva { version_base, version_app } => write!(
+                f,
+                "La base de datos está en la versión de schema {} pero esta aplicación solo conoce hasta la {}. Actualice la aplicación.",
+                version_base, version_app
+            ),
+            MigracionError::ClavesForaneas { version, nombre, tablas } => write!(
+                f,
+                "La migración {:03}_{} dejó referencias rotas en: {}",
+                version, nombre, tablas.join(", ")
+            ),
+            MigracionError::AdopcionImposible { tabla, columna } => write!(
+                f,
+                "La base es de una versión anterior y a la tabla {} le falta la columna {}, que no se puede agregar. Exporte los datos y cree una base nueva.",
+                tabla, columna
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MigracionError {}
+
+impl From<sqlx::Error> for MigracionError {
+    fn from(e: sqlx::Error) -> Self {
+        MigracionError::Sql(e)
+    }
+}
+
+pub fn checksum(sql: &str) -> String {
+    Sha256::digest(sql.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Divide un script SQL en sentencias, respetando bloques BEGIN...END de triggers
+pub fn dividir_sentencias(sql: &str) -> Vec<String> {
+    let mut statements = Vec::new();
+    let mut current_statement = String::new();
+    let mut in_trigger = false;
+
+    for line in sql.lines() {
+        let trimmed = line.trim();
+        current_statement.push_str(line);
+        current_statement.push('\n');
+
+        // Detectar inicio de trigger
+        if trimmed.to_uppercase().contains("BEGIN") {
+            in_trigger = true;
+        }
+
+        // Detectar fin de statement
+        if trimmed.ends_with(';') && (!in_trigger || trimmed == "END;") {
+            statements.push(current_statement.clone());
+            current_statement.clear();
+            in_trigger = false;
+        }
+    }
+
+    statements
+        .into_iter()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Aplica las migraciones pendientes. Devuelve la versión final del schema.
+pub async fn migrar(pool: &Pool<Sqlite>) -> Result<i64, MigracionError> {
+    migrar_con(pool, MIGRACIONES).await
+}
+
+async fn migrar_con(pool: &Pool<Sqlite>, migraciones: &[Migracion]) -> Result<i64, MigracionError> {
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            nombre TEXT NOT NULL,
+            checksum TEXT NOT NULL,
+            applied_at TEXT NOT NULL DEFAULT (datetime('now'))
+        )"
+    )
+    .execute(pool)
+    .await?;
+
+    let aplicadas: Vec<(i64, String, String)> = sqlx::query_as(
+        "SELECT version, nombre, checksum FROM schema_migrations ORDER BY version"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let version_app = migraciones.iter().map(|m| m.version).max().unwrap_or(0);
+    let version_base = aplicadas.iter().map(|a| a.0).max().unwrap_or(0);
+
+    if version_base > version_app {
+        return Err(MigracionError::BaseMasNueva { version_base, version_app });
+    }
+
+    for (version, nombre, checksum_aplicado) in &aplicadas {
+        if let Some(m) = migraciones.iter().find(|m| m.version == *version) {
+            if checksum(m.sql) != *checksum_aplicado {
+                return Err(MigracionError::ChecksumDistinto {
+                    version: *version,
+                    nombre: nombre.clone(),
+                });
+            }
+        }
+    }
+
+    let mut ordenadas: Vec<&Migracion> = migraciones.iter().collect();
+    ordenadas.sort_by_key(|m| m.version);
+
+    for m in ordenadas {
+        if aplicadas.iter().any(|a| a.0 == m.version) {
+            continue;
+        }
+
+        // El PRAGMA es por conexión y no tiene efecto dentro de una transacción
+        let mut conn = pool.acquire().await?;
+        sqlx::query("PRAGMA foreign_keys = OFF").execute(&mut *conn).await?;
+        let resultado = aplicar(&mut conn, m).await;
+        sqlx::query("PRAGMA foreign_keys = ON").execute(&mut *conn).await?;
+        resultado?;
+
+        println!("📦 Migración {:03}_{} aplicada", m.version, m.nombre);
+    }
+
+    Ok(version_app.max(version_base))
+}
+
+async fn aplicar(conn: &mut SqliteConnection, m: &Migracion) -> Result<(), MigracionError> {
+    let mut tx = conn.begin().await?;
+
+    for statement in dividir_sentencias(m.sql) {
+        sqlx::query(&statement).execute(&mut *tx).await?;
+    }
+
+    if m.version == 1 {
+        adoptar_base_anterior(&mut tx, m.sql).await?;
+    }
+
+    let mut tablas: Vec<String> = sqlx::query_scalar("SELECT \"table\" FROM pragma_foreign_key_check")
+        .fetch_all(&mut *tx)
+        .await?;
+    if !tablas.is_empty() {
+        tablas.sort();
+        tablas.dedup();
+        return Err(MigracionError::ClavesForaneas {
+            version: m.version,
+            nombre: m.nombre.to_string(),
+            tablas,
+        });
+    }
+
+    sqlx::query("INSERT INTO schema_migrations (version, nombre, checksum) VALUES (?, ?, ?)")
+        .bind(m.version)
+        .bind(m.nombre)
+        .bind(checksum(m.sql))
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Columna según `pragma_table_info`
+#[derive(sqlx::FromRow)]
+struct Columna {
+    name: String,
+    #[sqlx(rename = "type")]
+    tipo: String,
+    notnull: bool,
+    dflt_value: Option<String>,
+    pk: i64,
+}
+
+/// Agrega a las tablas existentes las columnas del schema inicial que les
+/// falten. El schema de referencia se crea en una base en memoria aparte.
+async fn adoptar_base_anterior(conn: &mut SqliteConnection, sql_inicial: &str) -> Result<(), MigracionError> {
+    let mut referencia = SqliteConnection::connect("sqlite::memory:").await?;
+    for statement in dividir_sentencias(sql_inicial) {
+        sqlx::query(&statement).execute(&mut referencia).await?;
+    }
+
+    let tablas: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name"
+    )
+    .fetch_all(&mut referencia)
+    .await?;
+
+    for tabla in tablas {
+        let esperadas = sqlx::query_as::<_, Columna>("SELECT name, type, \"notnull\", dflt_value, pk FROM pragma_table_info(?)")
+            .bind(&tabla)
+            .fetch_all(&mut referencia)
+            .await?;
+        let existentes: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info(?)")
+            .bind(&tabla)
+            .fetch_all(&mut *conn)
+            .await?;
+
+        for columna in esperadas.iter().filter(|c| !existentes.contains(&c.name)) {
+            // ADD COLUMN no admite claves primarias, defaults con expresiones
+            // ni NOT NULL sin default
+            let default_constante = columna.dflt_value.as_ref().filter(|d| !d.starts_with('('));
+            if columna.pk > 0
+                || (columna.dflt_value.is_some() && default_constante.is_none())
+                || (columna.notnull && default_constante.is_none())
+            {
+                return Err(MigracionError::AdopcionImposible { tabla, columna: columna.name.clone() });
+            }
+
+            let mut definicion = format!("\"{}\" {}", columna.name, columna.tipo);
+            if columna.notnull {
+                definicion.push_str(" NOT NULL");
+            }
+            if let Some(default) = default_constante {
+                definicion.push_str(&format!(" DEFAULT {}", default));
+            }
+            let clave_foranea: Option<(String, String, String)> = sqlx::query_as(
+                "SELECT \"table\", \"to\", on_delete FROM pragma_foreign_key_list(?) WHERE \"from\" = ?"
+            )
+            .bind(&tabla)
+            .bind(&columna.name)
+            .fetch_optional(&mut referencia)
+            .await?;
+            if let Some((padre, destino, al_borrar)) = clave_foranea {
+                definicion.push_str(&format!(" REFERENCES {}({}) ON DELETE {}", padre, destino, al_borrar));
+            }
+
+            sqlx::query(&format!("ALTER TABLE \"{}\" ADD COLUMN {}", tabla, definicion))
+                .execute(&mut *conn)
+                .await?;
+            println!("📦 Columna {}.{} agregada a base anterior", tabla, columna.name);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+
+    async fn pool_memoria() -> Pool<Sqlite> {
+        SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(SqliteConnectOptions::new().in_memory(true))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn checksum_distinto_detiene_la_migracion() {
+        let pool = pool_memoria().await;
+        migrar(&pool).await.unwrap();
+        sqlx::query("UPDATE schema_migrations SET checksum = 'editada' WHERE version = 4")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let error = migrar(&pool).await.unwrap_err();
+        assert!(matches!(error, MigracionError::ChecksumDistinto { version: 4, .. }), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn base_mas_nueva_que_la_aplicacion() {
+        let pool = pool_memoria().await;
+        let version_app = migrar(&pool).await.unwrap();
+        sqlx::query("INSERT INTO schema_migrations (version, nombre, checksum) VALUES (?, 'futura', '')")
+            .bind(version_app + 1)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let error = migrar(&pool).await.unwrap_err();
+        assert!(matches!(error, MigracionError::BaseMasNueva { version_base, .. } if version_base == version_app + 1), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn migracion_fallida_se_revierte_completa() {
+        let pool = pool_memoria().await;
+        let migraciones = [
+            Migracion { version: 1, nombre: "base", sql: "CREATE TABLE a (x INTEGER);" },
+            Migracion {
+                version: 2,
+                nombre: "rota",
+                sql: "CREATE TABLE b (y INTEGER);\nINSERT INTO a (x) VALUES (1);\nINSERT INTO tabla_inexistente VALUES (1);",
+            },
+        ];
+
+        assert!(matches!(migrar_con(&pool, &migraciones).await, Err(MigracionError::Sql(_))));
+
+        let versiones: Vec<i64> = sqlx::query_scalar("SELECT version FROM schema_migrations").fetch_all(&pool).await.unwrap();
+        assert_eq!(versiones, vec![1]);
+        let tabla_b: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sqlite_master WHERE name = 'b'").fetch_one(&pool).await.unwrap();
+        assert_eq!(tabla_b, 0);
+        let filas_a: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM a").fetch_one(&pool).await.unwrap();
+        assert_eq!(filas_a, 0);
+    }
+
+    #[tokio::test]
+    async fn adopta_base_anterior_sin_columnas_nuevas() {
+        let pool = pool_memoria().await;
+        // Requerimientos de una versión anterior: sin descripción, observaciones ni multa
+        for sql in [
+            "CREATE TABLE jardines (id INTEGER PRIMARY KEY AUTOINCREMENT, codigo TEXT NOT NULL UNIQUE, nombre TEXT NOT NULL,
+                created_at TEXT NOT NULL DEFAULT (datetime('now')))",
+            "CREATE TABLE requerimientos (id INTEGER PRIMARY KEY AUTOINCREMENT, jardin_codigo TEXT NOT NULL, recinto TEXT,
+                partida_item TEXT NOT NULL, cantidad REAL NOT NULL DEFAULT 0, precio_unitario REAL NOT NULL DEFAULT 0,
+                precio_total REAL NOT NULL DEFAULT 0, fecha_inicio TEXT NOT NULL, fecha_registro TEXT NOT NULL,
+                estado TEXT NOT NULL DEFAULT 'pendiente', ot_id INTEGER, informe_pago_id INTEGER, fecha_recepcion TEXT,
+                plazo_dias INTEGER DEFAULT 0, plazo_adicional INTEGER DEFAULT 0, plazo_total INTEGER DEFAULT 0, fecha_limite TEXT,
+                created_at TEXT NOT NULL DEFAULT (datetime('now')), updated_at TEXT NOT NULL DEFAULT (datetime('now')))",
+            "CREATE TABLE partidas (id INTEGER PRIMARY KEY AUTOINCREMENT, item TEXT NOT NULL UNIQUE, partida TEXT NOT NULL,
+                unidad TEXT, precio_unitario REAL DEFAULT 0, created_at TEXT NOT NULL DEFAULT (datetime('now')))",
+            "INSERT INTO jardines (codigo, nombre) VALUES ('J01', 'Jardín Uno')",
+            "INSERT INTO partidas (item, partida) VALUES ('1.1', 'Pintura')",
+            "INSERT INTO requerimientos (jardin_codigo, partida_item, cantidad, precio_unitario, precio_total, fecha_inicio, fecha_registro)
+             VALUES ('J01', '1.1', 2, 100, 200, '2025-01-01', '2025-01-01')",
+        ] {
+            sqlx::query(sql).execute(&pool).await.unwrap();
+        }
+
+        migrar(&pool).await.unwrap();
+
+        let (multa, descripcion): (f64, Option<String>) = sqlx::query_as("SELECT multa, descripcion FROM requerimientos")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!((multa, descripcion), (0.0, None));
+    }
+
+    #[tokio::test]
+    async fn adopcion_imposible_da_error_claro() {
+        let pool = pool_memoria().await;
+        sqlx::query("CREATE TABLE jardines (id INTEGER PRIMARY KEY AUTOINCREMENT, codigo TEXT NOT NULL UNIQUE)")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let error = migrar(&pool).await.unwrap_err();
+        assert!(matches!(&error, MigracionError::AdopcionImposible { tabla, columna } if tabla == "jardines" && columna == "nombre"), "{}", error);
+    }
+}
@@ -0,0 +1,354 @@
+use crate::calendario::{self, Calendario};
+use crate::errores::ErrorComando;
+use chrono::NaiveDate;
+use sqlx::SqliteConnection;
+
+// ========== MULTAS ==========
+//
+// Única fuente del cálculo de `fecha_limite`, `dias_atraso`, `multa` y
+// `multa_detalle` de un requerimiento. Los valores se guardan en la fila y las
+// consultas los leen tal cual, así la lista, la OT y el informe muestran
+// siempre lo mismo. Los plazos y atrasos se cuentan según el calendario del
+// contrato (días corridos o hábiles, ver calendario.rs).
+//
+// Modos (contratos.multa_modo):
+// - fijo_o_proporcional: por día = MAX(monto diario, precio_total / plazo_total)
+// - utm:                 por día = UTM diarias × valor UTM
+// - porcentaje:          por día = precio_total × % diario
+//
+// multa = MIN((días atraso - días gracia) × por día, precio_total × % tope)
+//
+// Con recepciones parciales cada entrega paga la multa que tendría el total
+// recibido en su fecha, en proporción a la cantidad entregada; la multa del
+// requerimiento es la suma de sus entregas. Una única entrega por el total da
+// el mismo resultado que el cálculo sin entregas.
+//
+// Un requerimiento separado al informar lo recibido (ver recepciones.rs) se
+// sigue multando como el original: la base es cantidad + cantidad_separada, y
+// cada fila paga la parte de sus entregas.
+
+pub const MODOS_MULTA: &[&str] = &["fijo_o_proporcional", "utm", "porcentaje"];
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, sqlx::FromRow)]
+pub struct ReglaMulta {
+    pub multa_modo: String,
+    pub multa_monto_diario: f64,
+    pub multa_utm_diaria: f64,
+    pub valor_utm: f64,
+    pub multa_porcentaje_diario: f64,
+    pub multa_tope_porcentaje: Option<f64>,
+    pub multa_dias_gracia: i32,
+}
+
+impl ReglaMulta {
+    pub fn validar(&self) -> Result<(), ErrorComando> {
+        if !MODOS_MULTA.contains(&self.multa_modo.as_str()) {
+            return Err(ErrorComando::validacion("multa_modo", format!("Modo de multa no válido: {}", self.multa_modo)));
+        }
+        if self.multa_modo == "utm" && self.valor_utm <= 0.0 {
+            return Err(ErrorComando::validacion("valor_utm", "El modo UTM requiere un valor UTM mayor a 0"));
+        }
+        if self.multa_dias_gracia < 0 {
+            return Err(ErrorComando::validacion("multa_dias_gracia", "Los días de gracia no pueden ser negativos"));
+        }
+        if let Some(tope) = self.multa_tope_porcentaje {
+            if !(0.0..=100.0).contains(&tope) {
+                return Err(ErrorComando::validacion("multa_tope_porcentaje", format!("Tope de multa fuera de rango: {}%", tope)));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct DetalleMulta {
+    pub modo: String,
+    #[serde(default)]
+    pub tipo_plazo: String,
+    pub fecha_limite: Option<String>,
+    pub fecha_recepcion: Option<String>,
+    pub dias_atraso: i64,
+    pub dias_gracia: i64,
+    pub dias_multados: i64,
+    pub monto_por_dia: f64,
+    pub multa_sin_tope: f64,
+    pub tope: Option<f64>,
+    pub multa: f64,
+    /// Multa de cada recepción, cuando el requerimiento tiene recepciones
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub entregas: Vec<MultaEntrega>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct MultaEntrega {
+    pub recepcion_id: i64,
+    pub fecha: String,
+    pub cantidad: f64,
+    pub dias_atraso: i64,
+    pub multa: f64,
+}
+
+pub fn calcular_multa(
+    regla: &ReglaMulta,
+    calendario: &Calendario,
+    precio_total: f64,
+    plazo_total: i64,
+    fecha_limite: Option<NaiveDate>,
+    fecha_recepcion: Option<NaiveDate>,
+) -> DetalleMulta {
+    let dias_atraso = match (fecha_limite, fecha_recepcion) {
+        (Some(limite), Some(recepcion)) => calendario.dias_atraso(limite, recepcion),
+        _ => 0,
+    };
+    let dias_gracia = regla.multa_dias_gracia.max(0) as i64;
+    let dias_multados = (dias_atraso - dias_gracia).max(0);
+
+    let monto_por_dia = match regla.multa_modo.as_str() {
+        "utm" => regla.multa_utm_diaria * regla.valor_utm,
+        "porcentaje" => precio_total * regla.multa_porcentaje_diario / 100.0,
+        _ => {
+            let proporcional = if plazo_total > 0 { precio_total / plazo_total as f64 } else { 0.0 };
+            regla.multa_monto_diario.max(proporcional)
+        }
+    };
+
+    let multa_sin_tope = dias_multados as f64 * monto_por_dia;
+    let tope = regla.multa_tope_porcentaje.map(|pct| precio_total * pct / 100.0);
+    let multa = match tope {
+        Some(tope) => multa_sin_tope.min(tope),
+        None => multa_sin_tope,
+    };
+
+    DetalleMulta {
+        modo: regla.multa_modo.clone(),
+        tipo_plazo: calendario.tipo_plazo().to_string(),
+        fecha_limite: fecha_limite.map(|f| f.format("%Y-%m-%d").to_string()),
+        fecha_recepcion: fecha_recepcion.map(|f| f.format("%Y-%m-%d").to_string()),
+        dias_atraso,
+        dias_gracia,
+        dias_multados,
+        monto_por_dia,
+        multa_sin_tope,
+        tope,
+        multa,
+        entregas: Vec::new(),
+    }
+}
+
+pub async fn cargar_regla(conn: &mut SqliteConnection) -> Result<ReglaMulta, sqlx::Error> {
+    sqlx::query_as::<_, ReglaMulta>(
+        "SELECT multa_modo, multa_monto_diario, multa_utm_diaria, valor_utm,
+                multa_porcentaje_diario, multa_tope_porcentaje, multa_dias_gracia
+         FROM contrato_activo"
+    )
+    .fetch_one(conn)
+    .await
+}
+
+#[derive(sqlx::FromRow)]
+struct DatosMulta {
+    id: i64,
+    cantidad: f64,
+    cantidad_separada: f64,
+    precio_total: f64,
+    fecha_inicio: String,
+    plazo_total: i64,
+    fecha_recepcion: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct Entrega {
+    id: i64,
+    fecha: String,
+    cantidad: f64,
+}
+
+const SELECT_DATOS: &str = "SELECT id, COALESCE(cantidad, 0) as cantidad, cantidad_separada, COALESCE(precio_total, 0) as precio_total, fecha_inicio,
+        COALESCE(plazo_dias, 0) + COALESCE(plazo_adicional, 0) as plazo_total, fecha_recepcion
+ FROM requerimientos";
+
+async fn entregas(conn: &mut SqliteConnection, id: i64) -> Result<Vec<Entrega>, sqlx::Error> {
+    sqlx::query_as::<_, Entrega>("SELECT id, fecha, cantidad FROM recepciones WHERE requerimiento_id = ? ORDER BY fecha, id")
+        .bind(id)
+        .fetch_all(conn)
+        .await
+}
+
+/// Calcula el detalle de multa de un requerimiento con la regla y calendario dados
+pub async fn detalle_requerimiento(
+    conn: &mut SqliteConnection,
+    regla: &ReglaMulta,
+    calendario: &Calendario,
+    id: i64,
+) -> Result<DetalleMulta, ErrorComando> {
+    let datos = sqlx::query_as::<_, DatosMulta>(&format!("{} WHERE id = ?", SELECT_DATOS))
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| ErrorComando::no_encontrado("Requerimiento", id))?;
+    let entregas = entregas(&mut *conn, id).await?;
+
+    Ok(detalle(regla, calendario, &datos, &entregas))
+}
+
+fn detalle(regla: &ReglaMulta, calendario: &Calendario, datos: &DatosMulta, entregas: &[Entrega]) -> DetalleMulta {
+    let fecha_limite = calendario::parse_fecha(&datos.fecha_inicio)
+        .and_then(|inicio| calendario.fecha_limite(inicio, datos.plazo_total));
+    // Requerimiento original completo (ver migración 016)
+    let cantidad_base = datos.cantidad + datos.cantidad_separada;
+    let precio_base = if datos.cantidad > 0.0 { datos.precio_total * cantidad_base / datos.cantidad } else { datos.precio_total };
+    let calcular = |fecha_recepcion: Option<NaiveDate>| {
+        calcular_multa(regla, calendario, precio_base, datos.plazo_total, fecha_limite, fecha_recepcion)
+    };
+
+    // Sin recepciones (filas anteriores a las recepciones parciales): la fecha de la fila
+    let Some(ultima) = entregas.last() else {
+        let mut total = calcular(datos.fecha_recepcion.as_deref().and_then(calendario::parse_fecha));
+        if cantidad_base > 0.0 {
+            total.multa *= datos.cantidad / cantidad_base;
+        }
+        return total;
+    };
+
+    let mut total = calcular(calendario::parse_fecha(&ultima.fecha));
+    total.entregas = entregas
+        .iter()
+        .map(|entrega| {
+            let parcial = calcular(calendario::parse_fecha(&entrega.fecha));
+            let proporcion = if cantidad_base > 0.0 { entrega.cantidad / cantidad_base } else { 1.0 };
+            MultaEntrega {
+                recepcion_id: entrega.id,
+                fecha: entrega.fecha.clone(),
+                cantidad: entrega.cantidad,
+                dias_atraso: parcial.dias_atraso,
+                multa: parcial.multa * proporcion,
+            }
+        })
+        .collect();
+    total.multa = total.entregas.iter().map(|e| e.multa).sum();
+    total
+}
+
+async fn guardar(conn: &mut SqliteConnection, id: i64, detalle: &DetalleMulta) -> Result<(), ErrorComando> {
+    let json = serde_json::to_string(detalle).map_err(|e| ErrorComando::Internal { mensaje: e.to_string() })?;
+    sqlx::query("UPDATE requerimientos SET fecha_limite = ?, dias_atraso = ?, multa = ?, multa_detalle = ? WHERE id = ?")
+        .bind(&detalle.fecha_limite)
+        .bind(detalle.dias_atraso)
+        .bind(detalle.multa)
+        .bind(json)
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+    for entrega in &detalle.entregas {
+        sqlx::query("UPDATE recepciones SET dias_atraso = ?, multa = ? WHERE id = ?")
+            .bind(entrega.dias_atraso)
+            .bind(entrega.multa)
+            .bind(entrega.recepcion_id)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Recalcula y guarda `fecha_limite`, `dias_atraso`, `multa` y `multa_detalle` de un requerimiento
+pub async fn recalcular_requerimiento(conn: &mut SqliteConnection, id: i64) -> Result<(), ErrorComando> {
+    let regla = cargar_regla(&mut *conn).await?;
+    let calendario = calendario::cargar(&mut *conn).await?;
+    let detalle = detalle_requerimiento(&mut *conn, &regla, &calendario, id).await?;
+    guardar(conn, id, &detalle).await
+}
+
+/// Recalcula los requerimientos del contrato activo que aún no están en un
+/// informe de pago (los ya informados conservan la multa con que se pagaron).
+pub async fn recalcular_pendientes(conn: &mut SqliteConnection) -> Result<u64, ErrorComando> {
+    let regla = cargar_regla(&mut *conn).await?;
+    let calendario = calendario::cargar(&mut *conn).await?;
+    let filas = sqlx::query_as::<_, DatosMulta>(&format!(
+        "{} WHERE contrato_id = (SELECT id FROM contrato_activo) AND informe_pago_id IS NULL",
+        SELECT_DATOS
+    ))
+        .fetch_all(&mut *conn)
+        .await?;
+
+    for datos in &filas {
+        let entregas = entregas(&mut *conn, datos.id).await?;
+        guardar(&mut *conn, datos.id, &detalle(&regla, &calendario, datos, &entregas)).await?;
+    }
+
+    Ok(filas.len() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendario::ConfiguracionCalendario;
+    use std::collections::HashSet;
+
+    fn regla(modo: &str, tope: Option<f64>, dias_gracia: i32) -> ReglaMulta {
+        ReglaMulta {
+            multa_modo: modo.to_string(),
+            multa_monto_diario: 7500.0,
+            multa_utm_diaria: 0.5,
+            valor_utm: 60000.0,
+            multa_porcentaje_diario: 2.0,
+            multa_tope_porcentaje: tope,
+            multa_dias_gracia: dias_gracia,
+        }
+    }
+
+    fn fecha(texto: &str) -> Option<NaiveDate> {
+        calendario::parse_fecha(texto)
+    }
+
+    #[test]
+    fn modos_gracia_y_tope() {
+        let calendario = Calendario::new(
+            &ConfiguracionCalendario { tipo_plazo: "corridos".to_string(), dias_no_habiles: String::new() },
+            HashSet::new(),
+        );
+        // precio_total, plazo_total, recepción (límite 2025-01-11), regla -> (días multados, por día, multa)
+        let casos = [
+            // proporcional: 100000 / 10 = 10000 supera los 7500 diarios
+            (100_000.0, 10, "2025-01-16", regla("fijo_o_proporcional", None, 0), (5, 10_000.0, 50_000.0)),
+            (50_000.0, 10, "2025-01-16", regla("fijo_o_proporcional", None, 0), (5, 7_500.0, 37_500.0)),
+            (100_000.0, 0, "2025-01-16", regla("fijo_o_proporcional", None, 0), (0, 7_500.0, 0.0)),
+            (100_000.0, 10, "2025-01-16", regla("utm", None, 0), (5, 30_000.0, 150_000.0)),
+            (100_000.0, 10, "2025-01-16", regla("porcentaje", None, 0), (5, 2_000.0, 10_000.0)),
+            (100_000.0, 10, "2025-01-16", regla("porcentaje", None, 2), (3, 2_000.0, 6_000.0)),
+            (100_000.0, 10, "2025-01-12", regla("porcentaje", None, 2), (0, 2_000.0, 0.0)),
+            (100_000.0, 10, "2025-01-11", regla("utm", None, 0), (0, 30_000.0, 0.0)),
+            // tope: 10% de 100000
+            (100_000.0, 10, "2025-01-16", regla("utm", Some(10.0), 0), (5, 30_000.0, 10_000.0)),
+            (100_000.0, 10, "2025-01-16", regla("porcentaje", Some(10.0), 0), (5, 2_000.0, 10_000.0)),
+            (100_000.0, 10, "2025-01-16", regla("porcentaje", Some(0.0), 0), (5, 2_000.0, 0.0)),
+        ];
+
+        for (precio_total, plazo_total, recepcion, regla, (dias_multados, por_dia, multa)) in casos {
+            let limite = calendario.fecha_limite(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(), plazo_total);
+            let detalle = calcular_multa(&regla, &calendario, precio_total, plazo_total, limite, fecha(recepcion));
+            let caso = format!("{} {} {} {:?}", regla.multa_modo, plazo_total, recepcion, regla.multa_tope_porcentaje);
+            assert_eq!(detalle.dias_multados, dias_multados, "{}", caso);
+            assert!((detalle.monto_por_dia - por_dia).abs() < 1e-9, "{}: {}", caso, detalle.monto_por_dia);
+            assert!((detalle.multa - multa).abs() < 1e-9, "{}: {}", caso, detalle.multa);
+        }
+    }
+
+    #[test]
+    fn sin_recepcion_no_hay_multa() {
+        let calendario = Calendario::new(
+            &ConfiguracionCalendario { tipo_plazo: "corridos".to_string(), dias_no_habiles: String::new() },
+            HashSet::new(),
+        );
+        let detalle = calcular_multa(&regla("utm", None, 0), &calendario, 100_000.0, 10, fecha("2025-01-11"), None);
+        assert_eq!((detalle.dias_atraso, detalle.multa), (0, 0.0));
+    }
+
+    #[test]
+    fn validar_regla() {
+        assert!(regla("utm", None, 0).validar().is_ok());
+        assert!(regla("otro", None, 0).validar().is_err());
+        assert!(regla("porcentaje", Some(120.0), 0).validar().is_err());
+        assert!(regla("porcentaje", None, -1).validar().is_err());
+        assert!(ReglaMulta { valor_utm: 0.0, ..regla("utm", None, 0) }.validar().is_err());
+    }
+}
@@ -0,0 +1,507 @@
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::io::Write;
+
+// ========== PDF ==========
+//
+// Generador mínimo de PDF 1.4 para los documentos oficiales (OT, informes).
+// Usa las fuentes estándar Helvetica / Helvetica-Bold (no se incrustan) y
+// admite imágenes PNG (firma del ITO). No escribe fechas ni identificadores
+// aleatorios: los mismos datos producen siempre los mismos bytes.
+//
+// Las coordenadas de la API se miden en puntos desde la esquina superior
+// izquierda de la página; la conversión al sistema de PDF se hace al escribir.
+
+/// Tamaño oficio (legal) horizontal, igual al de la vista de impresión
+pub const OFICIO_HORIZONTAL: (f32, f32) = (1008.0, 612.0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fuente {
+    Normal,
+    Negrita,
+}
+
+impl Fuente {
+    fn recurso(self) -> &'static str {
+        match self {
+            Fuente::Normal => "F1",
+            Fuente::Negrita => "F2",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Alineacion {
+    Izquierda,
+    Centro,
+    Derecha,
+}
+
+struct Imagen {
+    ancho: u32,
+    alto: u32,
+    /// DeviceRGB o DeviceGray, comprimido
+    color: Vec<u8>,
+    gris: bool,
+    /// Canal alfa comprimido (SMask)
+    alfa: Option<Vec<u8>>,
+}
+
+pub struct Documento {
+    ancho: f32,
+    alto: f32,
+    paginas: Vec<String>,
+    imagenes: Vec<Imagen>,
+}
+
+impl Documento {
+    pub fn new((ancho, alto): (f32, f32)) -> Self {
+        Documento { ancho, alto, paginas: Vec::new(), imagenes: Vec::new() }
+    }
+
+    pub fn ancho(&self) -> f32 {
+        self.ancho
+    }
+
+    pub fn alto(&self) -> f32 {
+        self.alto
+    }
+
+    pub fn nueva_pagina(&mut self) {
+        self.paginas.push(String::new());
+    }
+
+    pub fn cantidad_paginas(&self) -> usize {
+        self.paginas.len()
+    }
+
+    /// Contenido de la página `n` (las operaciones siguientes se dibujan ahí)
+    fn pagina(&mut self, n: usize) -> &mut String {
+        if self.paginas.is_empty() {
+            self.nueva_pagina();
+        }
+        &mut self.paginas[n]
+    }
+
+    fn actual(&mut self) -> &mut String {
+        let n = self.paginas.len().saturating_sub(1);
+        self.pagina(n)
+    }
+
+    /// Texto en una línea; `y` es la línea base
+    pub fn texto(&mut self, x: f32, y: f32, fuente: Fuente, tamano: f32, texto: &str) {
+        let n = self.paginas.len().saturating_sub(1);
+        self.texto_en(n, x, y, fuente, tamano, texto);
+    }
+
+    /// Texto en una página ya creada (pies de página con el total de páginas)
+    pub fn texto_en(&mut self, pagina: usize, x: f32, y: f32, fuente: Fuente, tamano: f32, texto: &str) {
+        let y = self.alto - y;
+        let contenido = self.pagina(pagina);
+        contenido.push_str(&format!(
+            "BT /{} {} Tf {} {} Td ({}) Tj ET\n",
+            fuente.recurso(),
+            num(tamano),
+            num(x),
+            num(y),
+            escapar(texto)
+        ));
+    }
+
+    /// Texto alineado dentro de un ancho a partir de `x`
+    #[allow(clippy::too_many_arguments)]
+    pub fn texto_alineado(
+        &mut self,
+        x: f32,
+        ancho: f32,
+        y: f32,
+        fuente: Fuente,
+        tamano: f32,
+        alineacion: Alineacion,
+        texto: &str,
+    ) {
+        let medida = ancho_texto(texto, fuente, tamano);
+        let x = match alineacion {
+            Alineacion::Izquierda => x,
+            Alineacion::Centro => x + (ancho - medida) / 2.0,
+            Alineacion::Derecha => x + ancho - medida,
+        };
+        self.texto(x, y, fuente, tamano, texto);
+    }
+
+    pub fn linea(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, grosor: f32) {
+        let (y1, y2) = (self.alto - y1, self.alto - y2);
+        self.actual().push_str(&format!(
+            "{} w {} {} m {} {} l S\n",
+            num(grosor),
+            num(x1),
+            num(y1),
+            num(x2),
+            num(y2)
+        ));
+    }
+
+    /// Rectángulo con relleno gris opcional (0 = negro, 1 = blanco) y borde opcional
+    pub fn rectangulo(&mut self, x: f32, y: f32, ancho: f32, alto: f32, relleno: Option<f32>, borde: bool) {
+        let y = self.alto - y - alto;
+        let rect = format!("{} {} {} {} re", num(x), num(y), num(ancho), num(alto));
+        let contenido = self.actual();
+        if let Some(gris) = relleno {
+            contenido.push_str(&format!("{} g {} f 0 g\n", num(gris), rect));
+        }
+        if borde {
+            contenido.push_str(&format!("0.5 w {} S\n", rect));
+        }
+    }
+
+    /// Registra una imagen PNG y devuelve su índice para `imagen`
+    pub fn agregar_png(&mut self, bytes: &[u8]) -> Result<usize, String> {
+        let mut decoder = png::Decoder::new(bytes);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(|e| format!("PNG no válido: {}", e))?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer).map_err(|e| format!("PNG no válido: {}", e))?;
+        let pixeles = &buffer[..info.buffer_size()];
+
+        let (canales, gris, con_alfa) = match info.color_type {
+            png::ColorType::Grayscale => (1, true, false),
+            png::ColorType::GrayscaleAlpha => (2, true, true),
+            png::ColorType::Rgb => (3, false, false),
+            png::ColorType::Rgba => (4, false, true),
+            png::ColorType::Indexed => return Err("PNG indexado no soportado".to_string()),
+        };
+        let color_canales = if con_alfa { canales - 1 } else { canales };
+
+        let mut color = Vec::with_capacity(pixeles.len());
+        let mut alfa = Vec::new();
+        for pixel in pixeles.chunks_exact(canales) {
+            color.extend_from_slice(&pixel[..color_canales]);
+            if con_alfa {
+                alfa.push(pixel[canales - 1]);
+            }
+        }
+
+        self.imagenes.push(Imagen {
+            ancho: info.width,
+            alto: info.height,
+            color: comprimir(&color),
+            gris,
+            alfa: con_alfa.then(|| comprimir(&alfa)),
+        });
+        Ok(self.imagenes.len() - 1)
+    }
+
+    /// Proporción ancho / alto de una imagen registrada
+    pub fn proporcion_imagen(&self, imagen: usize) -> f32 {
+        let img = &self.imagenes[imagen];
+        img.ancho as f32 / img.alto.max(1) as f32
+    }
+
+    pub fn imagen(&mut self, imagen: usize, x: f32, y: f32, ancho: f32, alto: f32) {
+        let y = self.alto - y - alto;
+        self.actual().push_str(&format!(
+            "q {} 0 0 {} {} {} cm /Im{} Do Q\n",
+            num(ancho),
+            num(alto),
+            num(x),
+            num(y),
+            imagen + 1
+        ));
+    }
+
+    pub fn bytes(&self) -> Vec<u8> {
+        let mut objetos: Vec<Vec<u8>> = Vec::new();
+
+        // 1 catálogo, 2 árbol de páginas, 3-4 fuentes, luego imágenes y páginas
+        let n_imagenes: usize = self.imagenes.iter().map(|i| if i.alfa.is_some() { 2 } else { 1 }).sum();
+        let primera_pagina = 5 + n_imagenes;
+        let paginas: Vec<usize> = (0..self.paginas.len().max(1)).map(|i| primera_pagina + i * 2).collect();
+
+        objetos.push(b"<< /Type /Catalog /Pages 2 0 R >>".to_vec());
+        objetos.push(
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                paginas.iter().map(|p| format!("{} 0 R", p)).collect::<Vec<_>>().join(" "),
+                paginas.len()
+            )
+            .into_bytes(),
+        );
+        for base in ["Helvetica", "Helvetica-Bold"] {
+            objetos.push(
+                format!("<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>", base)
+                    .into_bytes(),
+            );
+        }
+
+        let mut recursos_imagenes = String::new();
+        for (i, img) in self.imagenes.iter().enumerate() {
+            let id = objetos.len() + 1;
+            recursos_imagenes.push_str(&format!("/Im{} {} 0 R ", i + 1, id));
+            let smask = match img.alfa {
+                Some(_) => format!(" /SMask {} 0 R", id + 1),
+                None => String::new(),
+            };
+            objetos.push(flujo(
+                &format!(
+                    "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /{} /BitsPerComponent 8{}",
+                    img.ancho,
+                    img.alto,
+                    if img.gris { "DeviceGray" } else { "DeviceRGB" },
+                    smask
+                ),
+                &img.color,
+            ));
+            if let Some(alfa) = &img.alfa {
+                objetos.push(flujo(
+                    &format!(
+                        "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceGray /BitsPerComponent 8",
+                        img.ancho, img.alto
+                    ),
+                    alfa,
+                ));
+            }
+        }
+
+        let vacia = String::new();
+        for (i, id) in paginas.iter().enumerate() {
+            let contenido = self.paginas.get(i).unwrap_or(&vacia);
+            objetos.push(
+                format!(
+                    "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Contents {} 0 R \
+                     /Resources << /Font << /F1 3 0 R /F2 4 0 R >> /XObject << {}>> >> >>",
+                    num(self.ancho),
+                    num(self.alto),
+                    id + 1,
+                    recursos_imagenes
+                )
+                .into_bytes(),
+            );
+            objetos.push(flujo("", &comprimir(contenido.as_bytes())));
+        }
+
+        let mut salida: Vec<u8> = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        let mut posiciones = Vec::with_capacity(objetos.len());
+        for (i, objeto) in objetos.iter().enumerate() {
+            posiciones.push(salida.len());
+            salida.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+            salida.extend_from_slice(objeto);
+            salida.extend_from_slice(b"\nendobj\n");
+        }
+
+        let xref = salida.len();
+        salida.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", objetos.len() + 1).as_bytes());
+        for posicion in posiciones {
+            salida.extend_from_slice(format!("{:010} 00000 n \n", posicion).as_bytes());
+        }
+        salida.extend_from_slice(
+            format!(
+                "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+                objetos.len() + 1,
+                xref
+            )
+            .as_bytes(),
+        );
+        salida
+    }
+}
+
+fn comprimir(datos: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    // Escribir en memoria no falla
+    encoder.write_all(datos).expect("compresión en memoria");
+    encoder.finish().expect("compresión en memoria")
+}
+
+/// Objeto stream comprimido con FlateDecode
+fn flujo(diccionario: &str, datos: &[u8]) -> Vec<u8> {
+    let mut objeto = format!("<< {} /Filter /FlateDecode /Length {} >>\nstream\n", diccionario, datos.len()).into_bytes();
+    objeto.extend_from_slice(datos);
+    objeto.extend_from_slice(b"\nendstream");
+    objeto
+}
+
+/// Números con dos decimales como máximo, sin ceros sobrantes
+fn num(valor: f32) -> String {
+    let texto = format!("{:.2}", valor);
+    let texto = texto.trim_end_matches('0').trim_end_matches('.');
+    if texto == "-0" { "0".to_string() } else { texto.to_string() }
+}
+
+/// Carácter en WinAnsiEncoding (Latin-1 más comillas y guiones tipográficos)
+fn win_ansi(c: char) -> u8 {
+    match c {
+        ' '..='~' => c as u8,
+        '\u{a0}'..='\u{ff}' => c as u32 as u8,
+        '€' => 0x80,
+        '…' => 0x85,
+        '‘' => 0x91,
+        '’' => 0x92,
+        '“' => 0x93,
+        '”' => 0x94,
+        '•' => 0x95,
+        '–' => 0x96,
+        '—' => 0x97,
+        '\t' | '\n' | '\r' => b' ',
+        _ => b'?',
+    }
+}
+
+fn escapar(texto: &str) -> String {
+    let mut salida = String::with_capacity(texto.len());
+    for c in texto.chars() {
+        match win_ansi(c) {
+            b'(' => salida.push_str("\\("),
+            b')' => salida.push_str("\\)"),
+            b'\\' => salida.push_str("\\\\"),
+            b @ 0x20..=0x7e => salida.push(b as char),
+            b => salida.push_str(&format!("\\{:03o}", b)),
+        }
+    }
+    salida
+}
+
+// Anchos AFM (milésimas del tamaño) de los caracteres 32..=126
+const ANCHOS_NORMAL: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+const ANCHOS_NEGRITA: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611,
+    975, 722, 722, 722, 722, 667, 611, 778, 722, 278, 556, 722, 611, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 333, 278, 333, 584, 556,
+    333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556, 278, 889, 611, 611,
+    611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584,
+];
+
+fn ancho_caracter(c: char, fuente: Fuente) -> u16 {
+    let tabla = match fuente {
+        Fuente::Normal => &ANCHOS_NORMAL,
+        Fuente::Negrita => &ANCHOS_NEGRITA,
+    };
+    // Letras acentuadas: mismo ancho que la letra base
+    let base = match c {
+        'á' | 'à' | 'ä' | 'â' => 'a',
+        'é' | 'è' | 'ë' | 'ê' => 'e',
+        'í' | 'ì' | 'ï' | 'î' => 'i',
+        'ó' | 'ò' | 'ö' | 'ô' => 'o',
+        'ú' | 'ù' | 'ü' | 'û' => 'u',
+        'ñ' => 'n',
+        'Á' | 'À' | 'Ä' | 'Â' => 'A',
+        'É' | 'È' | 'Ë' | 'Ê' => 'E',
+        'Í' | 'Ì' | 'Ï' | 'Î' => 'I',
+        'Ó' | 'Ò' | 'Ö' | 'Ô' => 'O',
+        'Ú' | 'Ù' | 'Ü' | 'Û' => 'U',
+        'Ñ' => 'N',
+        '°' | 'º' => return 400,
+        otro => otro,
+    };
+    match base {
+        ' '..='~' => tabla[base as usize - 32],
+        _ => 556,
+    }
+}
+
+pub fn ancho_texto(texto: &str, fuente: Fuente, tamano: f32) -> f32 {
+    texto.chars().map(|c| ancho_caracter(c, fuente) as f32).sum::<f32>() * tamano / 1000.0
+}
+
+/// Divide el texto en líneas que caben en `ancho` (por palabras; las palabras
+/// más largas que el ancho se cortan)
+pub fn ajustar(texto: &str, ancho: f32, fuente: Fuente, tamano: f32) -> Vec<String> {
+    let mut lineas = Vec::new();
+    for parrafo in texto.lines() {
+        let mut actual = String::new();
+        for palabra in parrafo.split_whitespace() {
+            let candidata = if actual.is_empty() { palabra.to_string() } else { format!("{} {}", actual, palabra) };
+            if ancho_texto(&candidata, fuente, tamano) <= ancho {
+                actual = candidata;
+                continue;
+            }
+            if !actual.is_empty() {
+                lineas.push(std::mem::take(&mut actual));
+            }
+            for c in palabra.chars() {
+                actual.push(c);
+                if ancho_texto(&actual, fuente, tamano) > ancho && actual.chars().count() > 1 {
+                    actual.pop();
+                    lineas.push(std::mem::replace(&mut actual, c.to_string()));
+                }
+            }
+        }
+        lineas.push(actual);
+    }
+    if lineas.is_empty() {
+        lineas.push(String::new());
+    }
+    lineas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// PNG RGBA de 2×2 (con canal alfa, para que lleve SMask)
+    fn png_rgba() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, 2, 2);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[255, 0, 0, 255, 0, 255, 0, 128, 0, 0, 255, 0, 255, 255, 255, 255]).unwrap();
+        writer.finish().unwrap();
+        bytes
+    }
+
+    fn documento() -> Documento {
+        let mut doc = Documento::new(OFICIO_HORIZONTAL);
+        let firma = doc.agregar_png(&png_rgba()).unwrap();
+        doc.nueva_pagina();
+        doc.texto(40.0, 40.0, Fuente::Negrita, 14.0, "Orden de Trabajo (año 2025)");
+        doc.rectangulo(40.0, 60.0, 200.0, 20.0, Some(0.9), true);
+        doc.imagen(firma, 40.0, 100.0, 80.0, 40.0);
+        doc.nueva_pagina();
+        doc.texto_alineado(40.0, 300.0, 40.0, Fuente::Normal, 9.0, Alineacion::Derecha, "Página 2 de 2");
+        doc.linea(40.0, 50.0, 340.0, 50.0, 0.5);
+        doc
+    }
+
+    fn buscar(datos: &[u8], patron: &[u8]) -> Option<usize> {
+        datos.windows(patron.len()).rposition(|w| w == patron)
+    }
+
+    #[test]
+    fn xref_apunta_a_cada_objeto() {
+        let bytes = documento().bytes();
+        let texto = String::from_utf8_lossy(&bytes);
+        assert!(texto.contains("/Count 2"));
+        assert!(texto.contains("/SMask"));
+
+        let inicio = buscar(&bytes, b"startxref\n").unwrap() + b"startxref\n".len();
+        let fin = inicio + bytes[inicio..].iter().position(|b| *b == b'\n').unwrap();
+        let xref: usize = std::str::from_utf8(&bytes[inicio..fin]).unwrap().parse().unwrap();
+        assert!(bytes[xref..].starts_with(b"xref\n"));
+
+        let tabla = String::from_utf8_lossy(&bytes[xref..]);
+        let mut lineas = tabla.lines().skip(1);
+        let cantidad: usize = lineas.next().unwrap().split_whitespace().nth(1).unwrap().parse().unwrap();
+        assert_eq!(lineas.next(), Some("0000000000 65535 f "));
+        for n in 1..cantidad {
+            let entrada = lineas.next().unwrap();
+            assert!(entrada.ends_with(" 00000 n "), "{}", entrada);
+            let posicion: usize = entrada[..10].parse().unwrap();
+            assert!(bytes[posicion..].starts_with(format!("{} 0 obj\n", n).as_bytes()), "objeto {}", n);
+        }
+        assert!(tabla.contains(&format!("/Size {}", cantidad)));
+    }
+
+    #[test]
+    fn mismos_datos_mismos_bytes() {
+        assert_eq!(documento().bytes(), documento().bytes());
+    }
+}
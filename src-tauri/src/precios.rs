@@ -0,0 +1,393 @@
+use crate::auditoria::{self, Entidad};
+use crate::calendario::parse_fecha;
+use crate::errores::ErrorComando;
+use crate::multas;
+use sqlx::SqliteConnection;
+
+// ========== PRECIOS DE PARTIDAS ==========
+//
+// Cada partida tiene versiones de precio con fecha de vigencia
+// (precios_partida). La primera se crea con el precio de catálogo y las
+// siguientes, con los reajustes del contrato (porcentaje o variación de un
+// índice, ej. IPC). `partidas.precio_unitario` es el precio de la última lista.
+//
+// Un requerimiento se valoriza con la versión vigente en su `fecha_inicio` y
+// guarda su id en `precio_partida_id`; con precio manual o importado queda en
+// NULL y ningún reajuste lo toca.
+
+/// Estados en que un requerimiento todavía se puede revalorizar (antes del informe)
+const ESTADOS_EDITABLES: &str = "('pendiente', 'en_ot', 'recepcionado')";
+
+#[derive(Debug, serde::Serialize, sqlx::FromRow)]
+pub struct PrecioPartida {
+    pub id: i64,
+    pub partida_id: i64,
+    pub vigente_desde: String,
+    pub precio_unitario: f64,
+    /// NULL = precio inicial o de catálogo
+    pub reajuste_id: Option<i64>,
+    pub created_at: String,
+}
+
+#[derive(Debug, serde::Serialize, sqlx::FromRow)]
+pub struct Reajuste {
+    pub id: i64,
+    pub vigente_desde: String,
+    pub tipo: String,
+    pub porcentaje: f64,
+    pub indice_base: Option<f64>,
+    pub indice_nuevo: Option<f64>,
+    pub descripcion: Option<String>,
+    /// Partidas con una versión de precio de este reajuste
+    pub partidas: i64,
+    pub created_at: String,
+}
+
+/// Reajuste a aplicar: un porcentaje o los valores del índice (base y nuevo)
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct NuevoReajuste {
+    pub vigente_desde: String,
+    pub porcentaje: Option<f64>,
+    pub indice_base: Option<f64>,
+    pub indice_nuevo: Option<f64>,
+    pub descripcion: Option<String>,
+}
+
+impl NuevoReajuste {
+    /// Tipo y porcentaje de variación
+    fn variacion(&self) -> Result<(&'static str, f64), ErrorComando> {
+        let (tipo, porcentaje) = match (self.porcentaje, self.indice_base, self.indice_nuevo) {
+            (Some(porcentaje), None, None) => ("porcentaje", porcentaje),
+            (None, Some(base), Some(nuevo)) => {
+                if !(base.is_finite() && base > 0.0 && nuevo.is_finite() && nuevo > 0.0) {
+                    return Err(ErrorComando::validacion("indice_base", "Los valores del índice deben ser mayores que cero"));
+                }
+                ("indice", (nuevo / base - 1.0) * 100.0)
+            }
+            _ => {
+                return Err(ErrorComando::validacion(
+                    "porcentaje",
+                    "Indique un porcentaje o los valores base y nuevo del índice, no ambos",
+                ))
+            }
+        };
+        if !porcentaje.is_finite() || porcentaje <= -100.0 {
+            return Err(ErrorComando::validacion("porcentaje", format!("Porcentaje de reajuste no válido: {}", porcentaje)));
+        }
+        Ok((tipo, porcentaje))
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct ResultadoReajuste {
+    pub id: i64,
+    pub porcentaje: f64,
+    pub partidas: u64,
+    /// Requerimientos aún sin informe revalorizados con el precio nuevo
+    pub requerimientos: Vec<i64>,
+}
+
+/// Versión de la partida `item` (contrato activo) vigente en `fecha`: la última
+/// que empieza en o antes de esa fecha o, si la fecha es anterior a todas, la primera
+pub async fn vigente(conn: &mut SqliteConnection, item: &str, fecha: &str) -> Result<Option<PrecioPartida>, ErrorComando> {
+    Ok(sqlx::query_as::<_, PrecioPartida>(
+        "SELECT pp.id, pp.partida_id, pp.vigente_desde, pp.precio_unitario, pp.reajuste_id, pp.created_at
+         FROM precios_partida pp
+         JOIN partidas p ON p.id = pp.partida_id
+         WHERE p.contrato_id = (SELECT id FROM contrato_activo) AND p.item = ?1
+         ORDER BY pp.vigente_desde <= ?2 DESC,
+                  CASE WHEN pp.vigente_desde <= ?2 THEN pp.vigente_desde END DESC,
+                  pp.vigente_desde
+         LIMIT 1"
+    )
+    .bind(item)
+    .bind(fecha)
+    .fetch_optional(&mut *conn)
+    .await?)
+}
+
+pub async fn historial(conn: &mut SqliteConnection, partida_id: i64) -> Result<Vec<PrecioPartida>, ErrorComando> {
+    Ok(sqlx::query_as::<_, PrecioPartida>(
+        "SELECT pp.id, pp.partida_id, pp.vigente_desde, pp.precio_unitario, pp.reajuste_id, pp.created_at
+         FROM precios_partida pp
+         JOIN partidas p ON p.id = pp.partida_id
+         WHERE p.contrato_id = (SELECT id FROM contrato_activo) AND pp.partida_id = ?
+         ORDER BY pp.vigente_desde DESC"
+    )
+    .bind(partida_id)
+    .fetch_all(&mut *conn)
+    .await?)
+}
+
+/// Crea la primera versión de precio de las partidas del contrato que no tienen
+/// ninguna (altas, importaciones y copias de catálogo), vigente desde el inicio del contrato
+pub async fn registrar_iniciales(conn: &mut SqliteConnection, contrato_id: i64) -> Result<u64, sqlx::Error> {
+    Ok(sqlx::query(
+        "INSERT INTO precios_partida (partida_id, vigente_desde, precio_unitario)
+         SELECT p.id, COALESCE(c.fecha_inicio, date('now')), COALESCE(p.precio_unitario, 0)
+         FROM partidas p
+         JOIN contratos c ON c.id = p.contrato_id
+         WHERE p.contrato_id = ?
+           AND NOT EXISTS (SELECT 1 FROM precios_partida pp WHERE pp.partida_id = p.id)"
+    )
+    .bind(contrato_id)
+    .execute(&mut *conn)
+    .await?
+    .rows_affected())
+}
+
+/// Cambio de precio desde la importación del catálogo: versión nueva vigente desde hoy
+pub async fn registrar_cambio_catalogo(conn: &mut SqliteConnection, partida_id: i64, precio: f64) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO precios_partida (partida_id, vigente_desde, precio_unitario) VALUES (?, date('now'), ?)
+         ON CONFLICT(partida_id, vigente_desde) DO UPDATE SET precio_unitario = excluded.precio_unitario, reajuste_id = NULL"
+    )
+    .bind(partida_id)
+    .bind(precio)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// Vuelve a tomar el precio vigente en la fecha de inicio, si el requerimiento
+/// usa la lista de precios. Devuelve si cambió; el cambio queda en auditoría.
+pub async fn revalorizar(conn: &mut SqliteConnection, operador: &str, requerimiento_id: i64) -> Result<bool, ErrorComando> {
+    let fila: Option<(String, String, Option<i64>, f64)> = sqlx::query_as(
+        "SELECT partida_item, fecha_inicio, precio_partida_id, precio_unitario FROM requerimientos WHERE id = ?"
+    )
+    .bind(requerimiento_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+    let Some((item, fecha_inicio, Some(precio_actual_id), precio_actual)) = fila else {
+        return Ok(false);
+    };
+    let Some(precio) = vigente(conn, &item, &fecha_inicio).await? else {
+        return Ok(false);
+    };
+    if precio.id == precio_actual_id && precio.precio_unitario == precio_actual {
+        return Ok(false);
+    }
+
+    let antes = auditoria::instantanea(&mut *conn, Entidad::Requerimiento, requerimiento_id).await?;
+    // El trigger recalcula precio_total
+    sqlx::query(
+        "UPDATE requerimientos SET precio_partida_id = ?, precio_unitario = ?, updated_at = datetime('now') WHERE id = ?"
+    )
+    .bind(precio.id)
+    .bind(precio.precio_unitario)
+    .bind(requerimiento_id)
+    .execute(&mut *conn)
+    .await?;
+    multas::recalcular_requerimiento(&mut *conn, requerimiento_id).await?;
+    auditoria::registrar_cambio(conn, operador, Entidad::Requerimiento, requerimiento_id, "revalorizar", antes).await?;
+    Ok(true)
+}
+
+pub async fn listar_reajustes(conn: &mut SqliteConnection) -> Result<Vec<Reajuste>, ErrorComando> {
+    Ok(sqlx::query_as::<_, Reajuste>(
+        "SELECT r.id, r.vigente_desde, r.tipo, r.porcentaje, r.indice_base, r.indice_nuevo, r.descripcion,
+                (SELECT COUNT(*) FROM precios_partida pp WHERE pp.reajuste_id = r.id) as partidas,
+                r.created_at
+         FROM reajustes r
+         WHERE r.contrato_id = (SELECT id FROM contrato_activo)
+         ORDER BY r.vigente_desde DESC"
+    )
+    .fetch_all(&mut *conn)
+    .await?)
+}
+
+/// Aplica un reajuste a todo el itemizado del contrato activo desde
+/// `vigente_desde`: cada partida recibe una versión nueva con el precio vigente
+/// en esa fecha reajustado (redondeado al peso). Los requerimientos aún sin
+/// informe que inician desde esa fecha se revalorizan.
+///
+/// Los reajustes se aplican en orden cronológico: la fecha debe ser posterior
+/// a la del último.
+pub async fn reajustar(conn: &mut SqliteConnection, operador: &str, nuevo: &NuevoReajuste) -> Result<ResultadoReajuste, ErrorComando> {
+    let (tipo, porcentaje) = nuevo.variacion()?;
+    let vigente_desde = parse_fecha(&nuevo.vigente_desde)
+        .ok_or_else(|| ErrorComando::validacion("vigente_desde", format!("Fecha no válida: {}", nuevo.vigente_desde)))?
+        .format("%Y-%m-%d")
+        .to_string();
+
+    let ultimo: Option<String> = sqlx::query_scalar(
+        "SELECT MAX(vigente_desde) FROM reajustes WHERE contrato_id = (SELECT id FROM contrato_activo)"
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    if let Some(ultimo) = ultimo.filter(|ultimo| *ultimo >= vigente_desde) {
+        return Err(ErrorComando::validacion(
+            "vigente_desde",
+            format!("Ya hay un reajuste vigente desde {}; los reajustes se aplican en orden", ultimo),
+        ));
+    }
+
+    let id = sqlx::query(
+        "INSERT INTO reajustes (contrato_id, vigente_desde, tipo, porcentaje, indice_base, indice_nuevo, descripcion)
+         VALUES ((SELECT id FROM contrato_activo), ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(&vigente_desde)
+    .bind(tipo)
+    .bind(porcentaje)
+    .bind(nuevo.indice_base)
+    .bind(nuevo.indice_nuevo)
+    .bind(nuevo.descripcion.as_deref().map(str::trim).filter(|d| !d.is_empty()))
+    .execute(&mut *conn)
+    .await?
+    .last_insert_rowid();
+
+    // Base: la versión vigente en la fecha del reajuste. Las partidas cuyo primer
+    // precio empieza después ya están a valor nuevo y no se tocan. Si el precio
+    // inicial empieza justo ese día, se reajusta en su lugar.
+    let partidas = sqlx::query(
+        "INSERT INTO precios_partida (partida_id, vigente_desde, precio_unitario, reajuste_id)
+         SELECT p.id, ?1, ROUND(base.precio_unitario * (1 + ?2 / 100.0)), ?3
+         FROM partidas p
+         JOIN precios_partida base ON base.id = (
+             SELECT pp.id FROM precios_partida pp
+             WHERE pp.partida_id = p.id AND pp.vigente_desde <= ?1
+             ORDER BY pp.vigente_desde DESC LIMIT 1
+         )
+         WHERE p.contrato_id = (SELECT id FROM contrato_activo)
+         ON CONFLICT(partida_id, vigente_desde) DO UPDATE
+         SET precio_unitario = excluded.precio_unitario, reajuste_id = excluded.reajuste_id"
+    )
+    .bind(&vigente_desde)
+    .bind(porcentaje)
+    .bind(id)
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+
+    // Precio de catálogo = última lista
+    sqlx::query(
+        "UPDATE partidas
+         SET precio_unitario = (
+             SELECT pp.precio_unitario FROM precios_partida pp
+             WHERE pp.partida_id = partidas.id
+             ORDER BY pp.vigente_desde DESC LIMIT 1
+         )
+         WHERE contrato_id = (SELECT id FROM contrato_activo)
+           AND EXISTS (SELECT 1 FROM precios_partida pp WHERE pp.partida_id = partidas.id)"
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let candidatos: Vec<i64> = sqlx::query_scalar(&format!(
+        "SELECT id FROM requerimientos
+         WHERE contrato_id = (SELECT id FROM contrato_activo)
+           AND precio_partida_id IS NOT NULL AND fecha_inicio >= ? AND estado IN {}
+         ORDER BY id",
+        ESTADOS_EDITABLES
+    ))
+    .bind(&vigente_desde)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut requerimientos = Vec::new();
+    for requerimiento_id in candidatos {
+        if revalorizar(conn, operador, requerimiento_id).await? {
+            requerimientos.push(requerimiento_id);
+        }
+    }
+
+    Ok(ResultadoReajuste { id, porcentaje, partidas, requerimientos })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+    use sqlx::{Pool, Sqlite};
+
+    async fn pool_memoria() -> Pool<Sqlite> {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(SqliteConnectOptions::new().in_memory(true))
+            .await
+            .unwrap();
+        crate::migraciones::migrar(&pool).await.unwrap();
+        for sql in [
+            "UPDATE contratos SET fecha_inicio = '2025-01-01' WHERE id = 1",
+            "INSERT INTO partidas (contrato_id, item, partida, unidad, precio_unitario, exenta_iva) VALUES (1, '1.1', 'Pintura', 'm2', 1001, 0)",
+        ] {
+            sqlx::query(sql).execute(&pool).await.unwrap();
+        }
+        let mut conn = pool.acquire().await.unwrap();
+        registrar_iniciales(&mut conn, 1).await.unwrap();
+        drop(conn);
+        pool
+    }
+
+    fn porcentaje(vigente_desde: &str, porcentaje: f64) -> NuevoReajuste {
+        NuevoReajuste {
+            vigente_desde: vigente_desde.to_string(),
+            porcentaje: Some(porcentaje),
+            indice_base: None,
+            indice_nuevo: None,
+            descripcion: None,
+        }
+    }
+
+    async fn precio(conn: &mut SqliteConnection, fecha: &str) -> f64 {
+        vigente(conn, "1.1", fecha).await.unwrap().unwrap().precio_unitario
+    }
+
+    #[tokio::test]
+    async fn reajustes_en_orden_cronologico() {
+        let pool = pool_memoria().await;
+        let mut conn = pool.acquire().await.unwrap();
+        reajustar(&mut conn, "test", &porcentaje("2025-06-01", 10.0)).await.unwrap();
+
+        for fecha in ["2025-03-01", "2025-06-01"] {
+            match reajustar(&mut conn, "test", &porcentaje(fecha, 5.0)).await {
+                Err(ErrorComando::Validation { campo, .. }) => assert_eq!(campo, "vigente_desde"),
+                otro => panic!("{}: se esperaba error de validación, llegó {:?}", fecha, otro.map(|r| r.id)),
+            }
+        }
+        let reajustes: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM reajustes").fetch_one(&mut *conn).await.unwrap();
+        assert_eq!(reajustes, 1);
+        reajustar(&mut conn, "test", &porcentaje("06-07-2025", 5.0)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn reajuste_redondea_a_pesos() {
+        let pool = pool_memoria().await;
+        let mut conn = pool.acquire().await.unwrap();
+        // 1001 × 1,10 = 1101,1
+        reajustar(&mut conn, "test", &porcentaje("2025-06-01", 10.0)).await.unwrap();
+        assert_eq!(precio(&mut conn, "2025-06-01").await, 1101.0);
+
+        // IPC 100 → 103,5: 1101 × 1,035 = 1139,535, sobre el precio ya redondeado
+        let indice = NuevoReajuste {
+            vigente_desde: "2025-09-01".to_string(),
+            porcentaje: None,
+            indice_base: Some(100.0),
+            indice_nuevo: Some(103.5),
+            descripcion: Some("IPC".to_string()),
+        };
+        let resultado = reajustar(&mut conn, "test", &indice).await.unwrap();
+        assert!((resultado.porcentaje - 3.5).abs() < 1e-9);
+        assert_eq!(precio(&mut conn, "2025-09-01").await, 1140.0);
+
+        let catalogo: f64 = sqlx::query_scalar("SELECT precio_unitario FROM partidas WHERE item = '1.1'")
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+        assert_eq!(catalogo, 1140.0);
+    }
+
+    #[tokio::test]
+    async fn vigente_en_el_borde_de_un_reajuste() {
+        let pool = pool_memoria().await;
+        let mut conn = pool.acquire().await.unwrap();
+        reajustar(&mut conn, "test", &porcentaje("2025-06-01", 10.0)).await.unwrap();
+
+        assert_eq!(precio(&mut conn, "2025-05-31").await, 1001.0);
+        assert_eq!(precio(&mut conn, "2025-06-01").await, 1101.0);
+        assert_eq!(precio(&mut conn, "2026-01-01").await, 1101.0);
+        // Antes de la primera versión se usa el precio inicial
+        assert_eq!(precio(&mut conn, "2024-12-31").await, 1001.0);
+        assert!(vigente(&mut conn, "9.9", "2025-06-01").await.unwrap().is_none());
+    }
+}
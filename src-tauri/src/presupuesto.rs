@@ -0,0 +1,411 @@
+use crate::calendario::parse_fecha;
+use crate::commands_pdf::{formatear_fecha, formatear_numero};
+use crate::errores::ErrorComando;
+use chrono::{Datelike, Duration, NaiveDate};
+use sqlx::SqliteConnection;
+use std::collections::BTreeMap;
+
+// ========== PRESUPUESTO DEL CONTRATO ==========
+//
+// Todos los montos son netos (sin gastos generales, utilidades ni IVA):
+// - presupuesto:  asignación del jardín (presupuesto_jardines) o monto_contratado
+//                 (ambos se ingresan netos; un contrato expresado con IVA se
+//                 debe llevar a neto antes de ingresarlo)
+// - pendiente:    requerimientos aún sin OT
+// - comprometido: requerimientos en una OT (en_ot en adelante)
+// - recibido:     parte recepcionada (cantidad_recibida × precio_unitario)
+// - facturado:    neto de los informes de pago no anulados (ya descontadas las multas)
+// - pagado:       neto de los informes pagados
+// - disponible:   presupuesto − comprometido
+// Se alerta cuando lo comprometido alcanza `umbral_alerta` % del presupuesto.
+
+const ESTADOS_COMPROMETIDOS: &str = "('en_ot', 'recepcionado', 'en_informe', 'pagado')";
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, sqlx::FromRow)]
+pub struct DatosContrato {
+    /// Monto neto del contrato (sin gastos generales, utilidades ni IVA), para
+    /// compararlo con los netos comprometidos y facturados. 0 = sin monto definido
+    pub monto_contratado: f64,
+    pub fecha_inicio: Option<String>,
+    pub fecha_termino: Option<String>,
+    /// % comprometido desde el que se avisa al mandante
+    pub umbral_alerta: f64,
+}
+
+impl DatosContrato {
+    pub fn validar(&self) -> Result<(), ErrorComando> {
+        if !self.monto_contratado.is_finite() || self.monto_contratado < 0.0 {
+            return Err(ErrorComando::validacion(
+                "monto_contratado",
+                format!("Monto contratado neto no válido: {}", self.monto_contratado),
+            ));
+        }
+        if !(self.umbral_alerta > 0.0 && self.umbral_alerta <= 100.0) {
+            return Err(ErrorComando::validacion(
+                "umbral_alerta",
+                format!("Umbral de alerta fuera de rango: {}", self.umbral_alerta),
+            ));
+        }
+        let mut fechas = [None, None];
+        for (i, (campo, fecha)) in [("fecha_inicio", &self.fecha_inicio), ("fecha_termino", &self.fecha_termino)]
+            .into_iter()
+            .enumerate()
+        {
+            if let Some(f) = fecha {
+                fechas[i] = Some(NaiveDate::parse_from_str(f, "%Y-%m-%d").map_err(|_| {
+                    ErrorComando::validacion(campo, format!("Fecha inválida '{}', se espera AAAA-MM-DD", f))
+                })?);
+            }
+        }
+        if let [Some(inicio), Some(termino)] = fechas {
+            if termino < inicio {
+                return Err(ErrorComando::validacion("fecha_termino", "La fecha de término es anterior a la de inicio"));
+            }
+        }
+        Ok(())
+    }
+
+    fn presupuesto(&self) -> Option<f64> {
+        (self.monto_contratado > 0.0).then_some(self.monto_contratado)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NivelAlerta {
+    SinPresupuesto,
+    Normal,
+    /// Comprometido sobre el umbral
+    Alerta,
+    /// Comprometido sobre el presupuesto
+    Excedido,
+}
+
+#[derive(Debug, Clone, Copy, Default, serde::Serialize, sqlx::FromRow)]
+pub struct Montos {
+    pub pendiente: f64,
+    pub comprometido: f64,
+    pub recibido: f64,
+    pub facturado: f64,
+    pub pagado: f64,
+}
+
+impl std::ops::AddAssign for Montos {
+    fn add_assign(&mut self, otro: Montos) {
+        self.pendiente += otro.pendiente;
+        self.comprometido += otro.comprometido;
+        self.recibido += otro.recibido;
+        self.facturado += otro.facturado;
+        self.pagado += otro.pagado;
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct AvancePresupuesto {
+    /// `None` = sin presupuesto asignado
+    pub presupuesto: Option<f64>,
+    #[serde(flatten)]
+    pub montos: Montos,
+    pub disponible: Option<f64>,
+    pub porcentaje_comprometido: Option<f64>,
+    pub nivel: NivelAlerta,
+}
+
+impl AvancePresupuesto {
+    fn new(presupuesto: Option<f64>, montos: Montos, umbral: f64) -> Self {
+        let porcentaje = presupuesto.map(|p| if p > 0.0 { montos.comprometido / p * 100.0 } else { 0.0 });
+        let nivel = match (presupuesto, porcentaje) {
+            (Some(p), _) if montos.comprometido > p => NivelAlerta::Excedido,
+            (Some(_), Some(pct)) if pct >= umbral => NivelAlerta::Alerta,
+            (Some(_), _) => NivelAlerta::Normal,
+            (None, _) => NivelAlerta::SinPresupuesto,
+        };
+        AvancePresupuesto {
+            presupuesto,
+            montos,
+            disponible: presupuesto.map(|p| p - montos.comprometido),
+            porcentaje_comprometido: porcentaje,
+            nivel,
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct PresupuestoJardin {
+    pub jardin_codigo: String,
+    pub jardin_nombre: String,
+    #[serde(flatten)]
+    pub avance: AvancePresupuesto,
+}
+
+/// Acumulado al cierre de cada mes (burn-down)
+#[derive(Debug, serde::Serialize)]
+pub struct PuntoEvolucion {
+    /// YYYY-MM
+    pub mes: String,
+    pub comprometido: f64,
+    pub facturado: f64,
+    pub disponible: Option<f64>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct ResumenPresupuesto {
+    pub contrato: DatosContrato,
+    pub total: AvancePresupuesto,
+    /// Monto contratado aún no asignado a un jardín
+    pub sin_asignar: Option<f64>,
+    pub jardines: Vec<PresupuestoJardin>,
+    pub evolucion: Vec<PuntoEvolucion>,
+    /// Al ritmo de compromiso desde el inicio del contrato
+    pub fecha_agotamiento_estimada: Option<String>,
+    pub alertas: Vec<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct FilaJardin {
+    jardin_codigo: String,
+    jardin_nombre: String,
+    presupuesto: Option<f64>,
+    #[sqlx(flatten)]
+    montos: Montos,
+}
+
+pub async fn cargar_contrato(conn: &mut SqliteConnection) -> Result<DatosContrato, ErrorComando> {
+    sqlx::query_as::<_, DatosContrato>(
+        "SELECT monto_contratado, fecha_inicio, fecha_termino, umbral_alerta FROM contrato_activo"
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(ErrorComando::from)
+}
+
+/// Asigna (o quita, con `None`) el presupuesto de un jardín. Las asignaciones no
+/// pueden sumar más que el monto contratado, si está definido.
+pub async fn asignar(conn: &mut SqliteConnection, jardin_codigo: &str, monto: Option<f64>) -> Result<(), ErrorComando> {
+    let existe: Option<i64> = sqlx::query_scalar(
+        "SELECT id FROM jardines WHERE contrato_id = (SELECT id FROM contrato_activo) AND codigo = ?"
+    )
+        .bind(jardin_codigo)
+        .fetch_optional(&mut *conn)
+        .await?;
+    if existe.is_none() {
+        return Err(ErrorComando::no_encontrado("Jardín", jardin_codigo));
+    }
+
+    let Some(monto) = monto else {
+        sqlx::query("DELETE FROM presupuesto_jardines WHERE contrato_id = (SELECT id FROM contrato_activo) AND jardin_codigo = ?")
+            .bind(jardin_codigo)
+            .execute(&mut *conn)
+            .await?;
+        return Ok(());
+    };
+    if !monto.is_finite() || monto < 0.0 {
+        return Err(ErrorComando::validacion("monto", format!("Monto no válido: {}", monto)));
+    }
+
+    let contrato = cargar_contrato(&mut *conn).await?;
+    if let Some(contratado) = contrato.presupuesto() {
+        let otros: f64 = sqlx::query_scalar(
+            "SELECT COALESCE(SUM(monto), 0.0) FROM presupuesto_jardines 
+             WHERE contrato_id = (SELECT id FROM contrato_activo) AND jardin_codigo <> ?"
+        )
+        .bind(jardin_codigo)
+        .fetch_one(&mut *conn)
+        .await?;
+        if otros + monto > contratado + 0.5 {
+            return Err(ErrorComando::validacion(
+                "monto",
+                format!(
+                    "Las asignaciones por jardín sumarían ${} netos y el monto contratado neto es ${}",
+                    formatear_numero(otros + monto),
+                    formatear_numero(contratado)
+                ),
+            ));
+        }
+    }
+
+    sqlx::query(
+        "INSERT INTO presupuesto_jardines (contrato_id, jardin_codigo, monto) VALUES ((SELECT id FROM contrato_activo), ?, ?)
+         ON CONFLICT(contrato_id, jardin_codigo) DO UPDATE SET monto = excluded.monto, updated_at = datetime('now')"
+    )
+    .bind(jardin_codigo)
+    .bind(monto)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// Montos por mes (fecha de la OT para lo comprometido, de emisión para lo facturado)
+async fn por_mes(conn: &mut SqliteConnection) -> Result<BTreeMap<(i32, u32), (f64, f64)>, ErrorComando> {
+    let comprometido: Vec<(String, f64)> = sqlx::query_as(&format!(
+        "SELECT ot.fecha_creacion, r.precio_total
+         FROM requerimientos r JOIN ordenes_trabajo ot ON ot.id = r.ot_id
+         WHERE r.contrato_id = (SELECT id FROM contrato_activo) AND r.estado IN {}",
+        ESTADOS_COMPROMETIDOS
+    ))
+    .fetch_all(&mut *conn)
+    .await?;
+    let facturado: Vec<(String, f64)> = sqlx::query_as(
+        "SELECT COALESCE(fecha_emision, fecha_creacion), neto FROM informes_pago 
+         WHERE contrato_id = (SELECT id FROM contrato_activo) AND estado <> 'anulado'"
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut meses: BTreeMap<(i32, u32), (f64, f64)> = BTreeMap::new();
+    for (fecha, monto) in comprometido {
+        if let Some(f) = parse_fecha(&fecha) {
+            meses.entry((f.year(), f.month())).or_default().0 += monto;
+        }
+    }
+    for (fecha, monto) in facturado {
+        if let Some(f) = parse_fecha(&fecha) {
+            meses.entry((f.year(), f.month())).or_default().1 += monto;
+        }
+    }
+    Ok(meses)
+}
+
+fn siguiente_mes((año, mes): (i32, u32)) -> (i32, u32) {
+    if mes == 12 { (año + 1, 1) } else { (año, mes + 1) }
+}
+
+fn evolucion(meses: &BTreeMap<(i32, u32), (f64, f64)>, inicio: Option<NaiveDate>, hoy: NaiveDate, presupuesto: Option<f64>) -> Vec<PuntoEvolucion> {
+    let desde = meses
+        .keys()
+        .next()
+        .copied()
+        .into_iter()
+        .chain(inicio.map(|f| (f.year(), f.month())))
+        .min();
+    let Some(mut mes) = desde else { return Vec::new() };
+    let hasta = meses.keys().next_back().copied().unwrap_or(mes).max((hoy.year(), hoy.month()));
+
+    let (mut comprometido, mut facturado) = (0.0, 0.0);
+    let mut puntos = Vec::new();
+    while mes <= hasta {
+        if let Some((c, f)) = meses.get(&mes) {
+            comprometido += c;
+            facturado += f;
+        }
+        puntos.push(PuntoEvolucion {
+            mes: format!("{:04}-{:02}", mes.0, mes.1),
+            comprometido,
+            facturado,
+            disponible: presupuesto.map(|p| p - comprometido),
+        });
+        mes = siguiente_mes(mes);
+    }
+    puntos
+}
+
+/// Proyección lineal: lo comprometido desde el inicio del contrato, por día
+fn fecha_agotamiento(inicio: Option<NaiveDate>, hoy: NaiveDate, total: &AvancePresupuesto) -> Option<NaiveDate> {
+    let disponible = total.disponible?;
+    let dias = (hoy - inicio?).num_days();
+    if disponible <= 0.0 || dias <= 0 || total.montos.comprometido <= 0.0 {
+        return None;
+    }
+    let por_dia = total.montos.comprometido / dias as f64;
+    let restantes = (disponible / por_dia).ceil().min(36_500.0) as i64;
+    Some(hoy + Duration::days(restantes))
+}
+
+fn alerta(nombre: &str, avance: &AvancePresupuesto, umbral: f64) -> Option<String> {
+    let presupuesto = avance.presupuesto?;
+    match avance.nivel {
+        NivelAlerta::Excedido => Some(format!(
+            "{}: lo comprometido (${}) supera el presupuesto de ${}",
+            nombre,
+            formatear_numero(avance.montos.comprometido),
+            formatear_numero(presupuesto)
+        )),
+        NivelAlerta::Alerta => Some(format!(
+            "{}: comprometido el {:.1}% del presupuesto (umbral {}%), quedan ${}",
+            nombre,
+            avance.porcentaje_comprometido.unwrap_or_default(),
+            umbral,
+            formatear_numero(avance.disponible.unwrap_or_default())
+        )),
+        NivelAlerta::Normal | NivelAlerta::SinPresupuesto => None,
+    }
+}
+
+pub async fn resumen(conn: &mut SqliteConnection, hoy: NaiveDate) -> Result<ResumenPresupuesto, ErrorComando> {
+    let contrato = cargar_contrato(&mut *conn).await?;
+    let umbral = contrato.umbral_alerta;
+
+    let filas = sqlx::query_as::<_, FilaJardin>(&format!(
+        "SELECT j.codigo as jardin_codigo, j.nombre as jardin_nombre, pj.monto as presupuesto,
+            COALESCE((SELECT SUM(precio_total) FROM requerimientos r
+                      WHERE r.contrato_id = j.contrato_id AND r.jardin_codigo = j.codigo AND r.estado = 'pendiente'), 0.0) as pendiente,
+            COALESCE((SELECT SUM(precio_total) FROM requerimientos r
+                      WHERE r.contrato_id = j.contrato_id AND r.jardin_codigo = j.codigo AND r.estado IN {0}), 0.0) as comprometido,
+            COALESCE((SELECT SUM(cantidad_recibida * precio_unitario) FROM requerimientos r
+                      WHERE r.contrato_id = j.contrato_id AND r.jardin_codigo = j.codigo AND r.estado IN {0}), 0.0) as recibido,
+            COALESCE((SELECT SUM(neto) FROM informes_pago ip
+                      WHERE ip.contrato_id = j.contrato_id AND ip.jardin_codigo = j.codigo AND ip.estado <> 'anulado'), 0.0) as facturado,
+            COALESCE((SELECT SUM(neto) FROM informes_pago ip
+                      WHERE ip.contrato_id = j.contrato_id AND ip.jardin_codigo = j.codigo AND ip.estado = 'pagado'), 0.0) as pagado
+         FROM jardines j
+         LEFT JOIN presupuesto_jardines pj ON pj.contrato_id = j.contrato_id AND pj.jardin_codigo = j.codigo
+         WHERE j.contrato_id = (SELECT id FROM contrato_activo)
+         ORDER BY j.codigo",
+        ESTADOS_COMPROMETIDOS
+    ))
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut montos = Montos::default();
+    let mut asignado = 0.0;
+    let mut alertas = Vec::new();
+    let mut jardines = Vec::with_capacity(filas.len());
+    for fila in filas {
+        montos += fila.montos;
+        asignado += fila.presupuesto.unwrap_or_default();
+        let avance = AvancePresupuesto::new(fila.presupuesto, fila.montos, umbral);
+        alertas.extend(alerta(&format!("Jardín {}", fila.jardin_nombre), &avance, umbral));
+        jardines.push(PresupuestoJardin {
+            jardin_codigo: fila.jardin_codigo,
+            jardin_nombre: fila.jardin_nombre,
+            avance,
+        });
+    }
+
+    let presupuesto = contrato.presupuesto();
+    let total = AvancePresupuesto::new(presupuesto, montos, umbral);
+    // La alerta del contrato va primero
+    if let Some(a) = alerta("Contrato", &total, umbral) {
+        alertas.insert(0, a);
+    }
+
+    let sin_asignar = presupuesto.map(|p| p - asignado);
+    if sin_asignar.is_some_and(|s| s < -0.5) {
+        alertas.push("Las asignaciones por jardín suman más que el monto contratado neto".to_string());
+    }
+
+    let inicio = contrato.fecha_inicio.as_deref().and_then(parse_fecha);
+    let termino = contrato.fecha_termino.as_deref().and_then(parse_fecha);
+    let agotamiento = fecha_agotamiento(inicio, hoy, &total);
+    if let (Some(agota), Some(termino)) = (agotamiento, termino) {
+        if agota < termino && hoy <= termino {
+            alertas.push(format!(
+                "Al ritmo actual el monto contratado neto se agota el {}, antes del término del contrato ({})",
+                formatear_fecha(&agota.to_string()),
+                formatear_fecha(&termino.to_string())
+            ));
+        }
+    }
+
+    let evolucion = evolucion(&por_mes(&mut *conn).await?, inicio, hoy, presupuesto);
+
+    Ok(ResumenPresupuesto {
+        contrato,
+        total,
+        sin_asignar,
+        jardines,
+        evolucion,
+        fecha_agotamiento_estimada: agotamiento.map(|f| f.to_string()),
+        alertas,
+    })
+}
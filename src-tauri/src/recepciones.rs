@@ -0,0 +1,372 @@
+use crate::auditoria::{self, Entidad};
+use crate::errores::ErrorComando;
+use crate::estados::{self, EstadoRequerimiento};
+use crate::multas;
+use sqlx::SqliteConnection;
+
+// ========== RECEPCIONES ==========
+//
+// Un requerimiento se recibe en una o más entregas. La tabla `recepciones` es
+// la fuente; el requerimiento guarda lo derivado:
+// - cantidad_recibida: suma de las entregas
+// - fecha_recepcion:   última entrega, solo cuando se recibió la cantidad completa
+// - estado:            en_ot mientras falte cantidad, recepcionado al completarse
+// - multa:             suma de la multa de cada entrega (ver multas.rs)
+//
+// Lo recibido se puede informar aunque falte cantidad: al crear el informe,
+// `separar_recibido` divide el requerimiento en una fila recepcionada con lo
+// recibido (que entra al informe) y la original con el saldo pendiente. Ambas
+// guardan en `cantidad_separada` lo que quedó en la otra, para que la multa se
+// siga calculando sobre el requerimiento completo.
+
+/// Holgura para comparar cantidades con decimales
+const EPSILON: f64 = 1e-9;
+
+#[derive(Debug, serde::Serialize, sqlx::FromRow)]
+pub struct Recepcion {
+    pub id: i64,
+    pub requerimiento_id: i64,
+    pub fecha: String,
+    pub cantidad: f64,
+    pub observaciones: Option<String>,
+    pub dias_atraso: i64,
+    pub multa: f64,
+    pub created_at: String,
+}
+
+#[derive(sqlx::FromRow)]
+struct Avance {
+    cantidad: f64,
+    recibida: f64,
+    entregas: i64,
+    ultima: Option<String>,
+}
+
+async fn avance(conn: &mut SqliteConnection, id: i64) -> Result<Avance, ErrorComando> {
+    sqlx::query_as::<_, Avance>(
+        "SELECT r.cantidad,
+                COALESCE((SELECT SUM(cantidad) FROM recepciones WHERE requerimiento_id = r.id), 0.0) as recibida,
+                (SELECT COUNT(*) FROM recepciones WHERE requerimiento_id = r.id) as entregas,
+                (SELECT MAX(fecha) FROM recepciones WHERE requerimiento_id = r.id) as ultima
+         FROM requerimientos r WHERE r.id = ?"
+    )
+    .bind(id)
+    .fetch_optional(conn)
+    .await?
+    .ok_or_else(|| ErrorComando::no_encontrado("Requerimiento", id))
+}
+
+pub async fn listar(conn: &mut SqliteConnection, requerimiento_id: i64) -> Result<Vec<Recepcion>, ErrorComando> {
+    sqlx::query_as::<_, Recepcion>("SELECT * FROM recepciones WHERE requerimiento_id = ? ORDER BY fecha, id")
+        .bind(requerimiento_id)
+        .fetch_all(conn)
+        .await
+        .map_err(ErrorComando::from)
+}
+
+fn validar_fecha(fecha: &str) -> Result<(), ErrorComando> {
+    chrono::NaiveDate::parse_from_str(fecha, "%Y-%m-%d")
+        .map(|_| ())
+        .map_err(|_| ErrorComando::validacion("fecha", format!("Fecha inválida '{}', se espera AAAA-MM-DD", fecha)))
+}
+
+async fn insertar(
+    conn: &mut SqliteConnection,
+    requerimiento_id: i64,
+    fecha: &str,
+    cantidad: f64,
+    observaciones: Option<&str>,
+) -> Result<i64, ErrorComando> {
+    let result = sqlx::query(
+        "INSERT INTO recepciones (requerimiento_id, fecha, cantidad, observaciones) VALUES (?, ?, ?, ?)"
+    )
+    .bind(requerimiento_id)
+    .bind(fecha)
+    .bind(cantidad)
+    .bind(observaciones)
+    .execute(conn)
+    .await?;
+    Ok(result.last_insert_rowid())
+}
+
+/// Registra una entrega de `cantidad` (como máximo el saldo por recibir)
+pub async fn agregar(
+    conn: &mut SqliteConnection,
+    requerimiento_id: i64,
+    fecha: &str,
+    cantidad: f64,
+    observaciones: Option<&str>,
+) -> Result<i64, ErrorComando> {
+    validar_fecha(fecha)?;
+    if cantidad.is_nan() || cantidad <= 0.0 {
+        return Err(ErrorComando::validacion("cantidad", "La cantidad recibida debe ser mayor que cero"));
+    }
+    estados::verificar_accion(conn, requerimiento_id, "recepcionar", |e| e == EstadoRequerimiento::EnOt).await?;
+
+    let avance = avance(conn, requerimiento_id).await?;
+    let saldo = avance.cantidad - avance.recibida;
+    if cantidad > saldo + EPSILON {
+        return Err(ErrorComando::validacion(
+            "cantidad",
+            format!("Se reciben {} pero quedan {} por recibir", cantidad, saldo.max(0.0)),
+        ));
+    }
+
+    let id = insertar(conn, requerimiento_id, fecha, cantidad, observaciones).await?;
+    sincronizar(conn, requerimiento_id).await?;
+    Ok(id)
+}
+
+/// Recibe todo el saldo en `fecha`. Si ya estaba recepcionado, corrige la
+/// fecha de la última entrega (comportamiento de la recepción única).
+pub async fn recibir_saldo(conn: &mut SqliteConnection, requerimiento_id: i64, fecha: &str) -> Result<(), ErrorComando> {
+    validar_fecha(fecha)?;
+    match estados::estado_actual(conn, requerimiento_id).await? {
+        EstadoRequerimiento::Recepcionado => {
+            let ultima: Option<i64> = sqlx::query_scalar(
+                "SELECT id FROM recepciones WHERE requerimiento_id = ? ORDER BY fecha DESC, id DESC LIMIT 1"
+            )
+            .bind(requerimiento_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+            match ultima {
+                Some(recepcion_id) => {
+                    sqlx::query("UPDATE recepciones SET fecha = ? WHERE id = ?")
+                        .bind(fecha)
+                        .bind(recepcion_id)
+                        .execute(&mut *conn)
+                        .await?;
+                }
+                None => {
+                    let avance = avance(conn, requerimiento_id).await?;
+                    insertar(conn, requerimiento_id, fecha, avance.cantidad.max(0.0), None).await?;
+                }
+            }
+        }
+        EstadoRequerimiento::EnOt => {
+            let avance = avance(conn, requerimiento_id).await?;
+            insertar(conn, requerimiento_id, fecha, (avance.cantidad - avance.recibida).max(0.0), None).await?;
+        }
+        _ => {
+            estados::verificar_transicion(conn, requerimiento_id, EstadoRequerimiento::EnOt, EstadoRequerimiento::Recepcionado)
+                .await?;
+        }
+    }
+    sincronizar(conn, requerimiento_id).await
+}
+
+/// Elimina una entrega. Devuelve el ID del requerimiento.
+pub async fn eliminar(conn: &mut SqliteConnection, recepcion_id: i64) -> Result<i64, ErrorComando> {
+    let requerimiento_id: i64 = sqlx::query_scalar("SELECT requerimiento_id FROM recepciones WHERE id = ?")
+        .bind(recepcion_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| ErrorComando::no_encontrado("Recepción", recepcion_id))?;
+    estados::verificar_accion(conn, requerimiento_id, "anular una recepción en", |e| {
+        matches!(e, EstadoRequerimiento::EnOt | EstadoRequerimiento::Recepcionado)
+    })
+    .await?;
+
+    sqlx::query("DELETE FROM recepciones WHERE id = ?").bind(recepcion_id).execute(&mut *conn).await?;
+    sincronizar(conn, requerimiento_id).await?;
+    Ok(requerimiento_id)
+}
+
+/// Un requerimiento con entregas no puede volver a pendiente (sacarlo de la OT)
+pub async fn verificar_sin_entregas(conn: &mut SqliteConnection, requerimiento_id: i64) -> Result<(), ErrorComando> {
+    if avance(conn, requerimiento_id).await?.entregas > 0 {
+        return Err(ErrorComando::validacion(
+            "requerimientos",
+            format!("El requerimiento {} tiene recepciones registradas; quítelas antes de sacarlo de la OT", requerimiento_id),
+        ));
+    }
+    Ok(())
+}
+
+/// Elimina todas las entregas y devuelve el requerimiento a en_ot, o a
+/// pendiente si es una fila recepcionada sin OT (anterior a las OTs)
+pub async fn quitar_todas(conn: &mut SqliteConnection, requerimiento_id: i64) -> Result<(), ErrorComando> {
+    if estados::estado_actual(conn, requerimiento_id).await? != EstadoRequerimiento::EnOt {
+        estados::verificar_transicion(conn, requerimiento_id, EstadoRequerimiento::Recepcionado, EstadoRequerimiento::EnOt)
+            .await?;
+    }
+    sqlx::query("DELETE FROM recepciones WHERE requerimiento_id = ?")
+        .bind(requerimiento_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query(
+        "UPDATE requerimientos
+         SET cantidad_recibida = 0, fecha_recepcion = NULL,
+             estado = CASE WHEN ot_id IS NULL THEN 'pendiente' ELSE 'en_ot' END, updated_at = datetime('now')
+         WHERE id = ?"
+    )
+    .bind(requerimiento_id)
+    .execute(&mut *conn)
+    .await?;
+    multas::recalcular_requerimiento(conn, requerimiento_id).await?;
+    Ok(())
+}
+
+/// Actualiza cantidad recibida, fecha de recepción, estado y multa según las entregas.
+/// Solo mueve el estado entre en_ot y recepcionado.
+pub async fn sincronizar(conn: &mut SqliteConnection, requerimiento_id: i64) -> Result<(), ErrorComando> {
+    let avance = avance(conn, requerimiento_id).await?;
+    let estado = estados::estado_actual(conn, requerimiento_id).await?;
+    let completo = avance.entregas > 0 && avance.recibida + EPSILON >= avance.cantidad;
+
+    let nuevo_estado = match estado {
+        EstadoRequerimiento::EnOt if completo => {
+            estados::verificar_transicion(conn, requerimiento_id, estado, EstadoRequerimiento::Recepcionado).await?;
+            EstadoRequerimiento::Recepcionado
+        }
+        // Sin entregas un recepcionado es una fila anterior a las recepciones: se deja igual
+        EstadoRequerimiento::Recepcionado if avance.entregas > 0 && !completo => {
+            estados::verificar_transicion(conn, requerimiento_id, estado, EstadoRequerimiento::EnOt).await?;
+            EstadoRequerimiento::EnOt
+        }
+        _ => estado,
+    };
+
+    if avance.entregas > 0 || matches!(nuevo_estado, EstadoRequerimiento::Pendiente | EstadoRequerimiento::EnOt) {
+        sqlx::query(
+            "UPDATE requerimientos SET cantidad_recibida = ?, fecha_recepcion = ?, estado = ?, updated_at = datetime('now') WHERE id = ?"
+        )
+        .bind(avance.recibida)
+        .bind(if completo { avance.ultima } else { None })
+        .bind(nuevo_estado.as_str())
+        .bind(requerimiento_id)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    multas::recalcular_requerimiento(conn, requerimiento_id).await?;
+    Ok(())
+}
+
+/// Prepara un requerimiento para entrar a un informe. Si tiene entregas pero
+/// falta cantidad, lo recibido pasa a una fila nueva (recepcionada, con sus
+/// entregas) y la original queda en_ot con el saldo. Devuelve el ID a informar.
+pub async fn separar_recibido(
+    conn: &mut SqliteConnection,
+    operador: &str,
+    requerimiento_id: i64,
+) -> Result<i64, ErrorComando> {
+    if estados::estado_actual(conn, requerimiento_id).await? != EstadoRequerimiento::EnOt {
+        return Ok(requerimiento_id);
+    }
+    let avance = avance(conn, requerimiento_id).await?;
+    if avance.entregas == 0 || avance.recibida <= EPSILON {
+        return Ok(requerimiento_id);
+    }
+
+    let result = sqlx::query(
+        "INSERT INTO requerimientos
+         (contrato_id, jardin_codigo, recinto, partida_item, cantidad, precio_unitario, precio_partida_id, precio_total, fecha_inicio, fecha_registro,
+          estado, ot_id, plazo_dias, plazo_adicional, descripcion, observaciones, cantidad_separada)
+         SELECT contrato_id, jardin_codigo, recinto, partida_item, ?, precio_unitario, precio_partida_id, ? * precio_unitario, fecha_inicio, fecha_registro,
+                'en_ot', ot_id, plazo_dias, plazo_adicional, descripcion, observaciones, cantidad_separada + ?
+         FROM requerimientos WHERE id = ?"
+    )
+    .bind(avance.recibida)
+    .bind(avance.recibida)
+    .bind(avance.cantidad - avance.recibida)
+    .bind(requerimiento_id)
+    .execute(&mut *conn)
+    .await?;
+    let nuevo_id = result.last_insert_rowid();
+
+    sqlx::query("UPDATE recepciones SET requerimiento_id = ? WHERE requerimiento_id = ?")
+        .bind(nuevo_id)
+        .bind(requerimiento_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query(
+        "UPDATE requerimientos SET cantidad = ?, cantidad_separada = cantidad_separada + ?, updated_at = datetime('now') WHERE id = ?"
+    )
+    .bind(avance.cantidad - avance.recibida)
+    .bind(avance.recibida)
+    .bind(requerimiento_id)
+    .execute(&mut *conn)
+    .await?;
+
+    sincronizar(conn, nuevo_id).await?;
+    sincronizar(conn, requerimiento_id).await?;
+
+    auditoria::registrar_cambio(conn, operador, Entidad::Requerimiento, nuevo_id, "crear", None).await?;
+    auditoria::registrar(
+        conn,
+        operador,
+        Entidad::Requerimiento,
+        Some(requerimiento_id),
+        "separar_recibido",
+        serde_json::json!({ "requerimiento_recibido": nuevo_id, "cantidad_recibida": avance.recibida }),
+    )
+    .await?;
+
+    println!("✂️ Requerimiento {} separado: {} recibido(s) en el requerimiento {}", requerimiento_id, avance.recibida, nuevo_id);
+    Ok(nuevo_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+    use sqlx::{Pool, Sqlite};
+
+    async fn pool_memoria() -> Pool<Sqlite> {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(SqliteConnectOptions::new().in_memory(true))
+            .await
+            .unwrap();
+        crate::migraciones::migrar(&pool).await.unwrap();
+        for sql in [
+            "INSERT INTO jardines (contrato_id, codigo, nombre) VALUES (1, 'J01', 'Jardín Uno')",
+            "INSERT INTO partidas (contrato_id, item, partida, unidad, precio_unitario, exenta_iva) VALUES (1, '1.1', 'Pintura', 'm2', 1000, 0)",
+            "INSERT INTO ordenes_trabajo (contrato_id, codigo, jardin_codigo, fecha_creacion) VALUES (1, 'OT-J01-M001', 'J01', '2025-01-01')",
+            "INSERT INTO informes_pago (contrato_id, codigo, jardin_codigo, fecha_creacion, neto, utilidades, iva, total_final) VALUES (1, 'IP-J01-M01', 'J01', '2025-01-20', 0, 0, 0, 0)",
+            // Límite 2025-01-11 (10 días corridos); multa por día = MAX(7500, 100000 / 10)
+            "INSERT INTO requerimientos (contrato_id, jardin_codigo, recinto, partida_item, cantidad, precio_unitario, precio_total,
+                fecha_inicio, fecha_registro, estado, ot_id, plazo_dias)
+             VALUES (1, 'J01', 'Sala Cuna', '1.1', 100, 1000, 100000, '2025-01-01', '2025-01-01', 'en_ot', 1, 10)",
+        ] {
+            sqlx::query(sql).execute(&pool).await.unwrap();
+        }
+        pool
+    }
+
+    async fn fila(conn: &mut SqliteConnection, id: i64) -> (f64, f64, f64, f64, String) {
+        sqlx::query_as("SELECT cantidad, cantidad_recibida, precio_total, multa, estado FROM requerimientos WHERE id = ?")
+            .bind(id)
+            .fetch_one(conn)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn informar_lo_recibido_separa_sin_cambiar_la_multa() {
+        let pool = pool_memoria().await;
+        let mut conn = pool.acquire().await.unwrap();
+
+        // 40 de 100, con 5 días de atraso: 10000 × 5 × 40%
+        agregar(&mut conn, 1, "2025-01-16", 40.0, None).await.unwrap();
+        assert_eq!(fila(&mut conn, 1).await, (100.0, 40.0, 100000.0, 20000.0, "en_ot".to_string()));
+
+        let recibido = separar_recibido(&mut conn, "test", 1).await.unwrap();
+        assert_ne!(recibido, 1);
+        sqlx::query("UPDATE requerimientos SET informe_pago_id = 1, estado = 'en_informe' WHERE id = ?")
+            .bind(recibido)
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+
+        assert_eq!(fila(&mut conn, recibido).await, (40.0, 40.0, 40000.0, 20000.0, "en_informe".to_string()));
+        assert_eq!(fila(&mut conn, 1).await, (60.0, 0.0, 60000.0, 0.0, "en_ot".to_string()));
+        assert_eq!(listar(&mut conn, recibido).await.unwrap().len(), 1);
+        assert!(listar(&mut conn, 1).await.unwrap().is_empty());
+
+        // El saldo con 10 días de atraso paga lo mismo que sin separar: 10000 × 10 × 60%
+        agregar(&mut conn, 1, "2025-01-21", 60.0, None).await.unwrap();
+        assert_eq!(fila(&mut conn, 1).await, (60.0, 60.0, 60000.0, 60000.0, "recepcionado".to_string()));
+    }
+}
@@ -3,7 +3,24 @@
  * Comunicación directa con comandos Rust
  */
 
-import { invoke } from '@tauri-apps/api/core';
+import { invoke as tauriInvoke } from '@tauri-apps/api/core';
+
+/**
+ * Los comandos devuelven errores como { tipo, mensaje, ...campos }
+ * (tipo: not_found, conflict, validation, foreign_key, state_transition, io, import, internal).
+ * Se convierten en Error para que `error.message` siga funcionando; el resto
+ * de los campos (error.tipo, error.campo, error.fila, ...) queda disponible.
+ */
+async function invoke(cmd, args) {
+  try {
+    return await tauriInvoke(cmd, args);
+  } catch (e) {
+    if (e && typeof e === 'object' && e.tipo) {
+      throw Object.assign(new Error(e.mensaje), e);
+    }
+    throw e;
+  }
+}
 
 // Transformar camelCase → snake_case
 function toSnake(obj) {
@@ -35,31 +52,51 @@ function toCamel(obj) {
 export const db = {
   // Jardines
   jardines: {
-    getAll: async () => toCamel(await invoke('get_jardines')),
+    getAll: async (incluirInactivos = false) => toCamel(await invoke('get_jardines', { incluir_inactivos: incluirInactivos })),
     getByCode: async (codigo) => toCamel(await invoke('get_jardin_by_codigo', { codigo })),
     add: (jardin) => invoke('add_jardin', toSnake(jardin))
   },
 
   // Partidas
   partidas: {
-    getAll: async () => toCamel(await invoke('get_partidas')),
+    getAll: async (incluirInactivos = false) => toCamel(await invoke('get_partidas', { incluir_inactivos: incluirInactivos })),
     add: (partida) => invoke('add_partida', toSnake(partida))
   },
 
   // Requerimientos
   requerimientos: {
     getAll: async () => toCamel(await invoke('get_requerimientos')),
+    // Filtro (jardinCodigo, estados, inicioDesde, conOt, soloAtrasados, texto, ...), orden y paginación
+    // en el backend; devuelve { filas, total }
+    consultar: async ({ filtro = {}, orden = null, descendente = false, limite = null, offset = 0 } = {}) =>
+      toCamel(await invoke('consultar_requerimientos', {
+        filtro: toSnake(filtro),
+        orden: orden ? orden.replace(/[A-Z]/g, (letra) => `_${letra.toLowerCase()}`) : null,
+        descendente,
+        limite,
+        offset
+      })),
     add: (req) => invoke('add_requerimiento', toSnake(req)),
     update: (id, data) => {
       console.log('🚀 [TAURI-API] update_requerimiento:', { id, data });
       return invoke('update_requerimiento', { id, ...toSnake(data) });
     },
-    delete: (id) => invoke('delete_requerimiento', { id })
+    delete: (id) => invoke('delete_requerimiento', { id }),
+    // Solo anular, reactivar (anulado → pendiente) y marcar pagado
+    cambiarEstado: (id, estado) => invoke('cambiar_estado_requerimiento', { id, estado })
+  },
+
+  // Recepciones parciales de un requerimiento (fecha 'YYYY-MM-DD'; cantidad ≤ saldo por recibir)
+  recepciones: {
+    listar: async (requerimientoId) => toCamel(await invoke('get_recepciones', { requerimiento_id: requerimientoId })),
+    agregar: (requerimientoId, fecha, cantidad, observaciones = null) =>
+      invoke('agregar_recepcion', { requerimiento_id: requerimientoId, fecha, cantidad, observaciones }),
+    eliminar: (id) => invoke('eliminar_recepcion', { id })
   },
 
   // Recintos
   recintos: {
-    getAll: async () => toCamel(await invoke('get_recintos')),
+    getAll: async (incluirInactivos = false) => toCamel(await invoke('get_recintos', { incluir_inactivos: incluirInactivos })),
     getByJardin: async (jardinCodigo) => toCamel(await invoke('get_recintos_by_jardin', { jardin_codigo: jardinCodigo })),
     add: (recinto) => invoke('add_recinto', toSnake(recinto))
   },
@@ -88,18 +125,75 @@ export const db = {
     getAll: async () => toCamel(await invoke('get_informes_pago')),
     getDetalle: async (informeId) => toCamel(await invoke('get_informe_pago_detalle', { informe_id: informeId })),
     getRequerimientosParaInforme: async (jardinCodigo) => toCamel(await invoke('get_requerimientos_para_informe', { jardin_codigo: jardinCodigo })),
+    // requerimientos: [{ id }]; los totales se calculan en el backend desde las líneas
     crear: (data) => invoke('crear_informe_pago', toSnake(data)),
     update: (informeId, data) => invoke('update_informe_pago', {
       informe_id: informeId,
       ...toSnake(data)
     }),
-    eliminar: (id) => invoke('eliminar_informe_pago', { informe_id: id })
+    eliminar: (id) => invoke('eliminar_informe_pago', { informe_id: id }),
+    // estado: 'emitido' | 'aprobado' | 'pagado' | 'anulado'; motivo obligatorio al anular
+    cambiarEstado: (informeId, estado, { fecha = null, motivo = null } = {}) =>
+      invoke('cambiar_estado_informe_pago', { informe_id: informeId, estado, fecha, motivo }),
+    // { descuadrados: [{ id, codigo, estado, guardado, calculado, reparable }], corregidos: [ids] }
+    conciliar: async (corregir = false) => toCamel(await invoke('conciliar_informes_pago', { corregir }))
   },
 
   // Configuración
   configuracion: {
     get: async () => toCamel(await invoke('get_configuracion')),
-    update: (data) => invoke('update_configuracion', toSnake(data))
+    update: (data) => invoke('update_configuracion', toSnake(data)),
+    updateTasas: (tasas) => invoke('update_tasas_contrato', toSnake(tasas)),
+    getHistorialTasas: async () => toCamel(await invoke('get_historial_tasas'))
+  },
+
+  // Multas
+  multas: {
+    getReglas: async () => toCamel(await invoke('get_reglas_multa')),
+    updateReglas: (regla) => invoke('update_reglas_multa', { regla: toSnake(regla) }),
+    getDetalle: async (id) => toCamel(await invoke('get_detalle_multa', { id }))
+  },
+
+  // Presupuesto del contrato (montos netos)
+  presupuesto: {
+    getContrato: async () => toCamel(await invoke('get_datos_contrato')),
+    // montoContratado es neto: sin gastos generales, utilidades ni IVA
+    updateContrato: (datos) => invoke('update_datos_contrato', { datos: toSnake(datos) }),
+    // monto = null quita la asignación del jardín
+    asignarJardin: (jardinCodigo, monto) => invoke('asignar_presupuesto_jardin', { jardin_codigo: jardinCodigo, monto }),
+    getResumen: async () => toCamel(await invoke('get_resumen_presupuesto'))
+  },
+
+  // Lista de precios por fecha de vigencia y reajustes del itemizado
+  precios: {
+    getHistorial: async (partidaId) => toCamel(await invoke('get_precios_partida', { partida_id: partidaId })),
+    // Precio con que se valoriza un requerimiento que inicia en `fecha` (null si la partida no existe)
+    getVigente: async (partidaItem, fecha) =>
+      toCamel(await invoke('get_precio_vigente', { partida_item: partidaItem, fecha })),
+    getReajustes: async () => toCamel(await invoke('get_reajustes')),
+    // { vigenteDesde, porcentaje } o { vigenteDesde, indiceBase, indiceNuevo }, más descripcion opcional
+    aplicarReajuste: async (reajuste) => toCamel(await invoke('aplicar_reajuste', { reajuste: toSnake(reajuste) }))
+  },
+
+  // Contratos de la instalación; todo lo demás opera sobre el activo
+  contratos: {
+    listar: async () => toCamel(await invoke('get_contratos')),
+    // copiarDe: id del contrato cuya configuración y catálogo se copian (opcional)
+    crear: (titulo, contratista, prefijoCorrelativo, copiarDe = null) =>
+      invoke('crear_contrato', { titulo, contratista, prefijo_correlativo: prefijoCorrelativo, copiar_de: copiarDe }),
+    activar: (id) => invoke('activar_contrato', { id }),
+    eliminar: (id) => invoke('eliminar_contrato', { id })
+  },
+
+  // Calendario de plazos (corridos / hábiles) y feriados
+  calendario: {
+    getConfiguracion: async () => toCamel(await invoke('get_configuracion_calendario')),
+    updateConfiguracion: (config) => invoke('update_configuracion_calendario', { config: toSnake(config) }),
+    getFeriados: async () => toCamel(await invoke('get_feriados')),
+    addFeriado: (fecha, nombre) => invoke('add_feriado', { fecha, nombre }),
+    eliminarFeriado: (fecha) => invoke('eliminar_feriado', { fecha }),
+    importarCsv: (csvStr) => invoke('importar_feriados_csv', { csv_str: csvStr }),
+    importarXlsxBytes: (fileBytes) => invoke('importar_feriados_xlsx_bytes', { file_bytes: fileBytes })
   },
 
   // Importar
@@ -114,11 +208,84 @@ export const db = {
     catalogoXlsxBytes: (fileBytes) => invoke('importar_catalogo_xlsx_bytes', { 
       file_bytes: fileBytes  // ✅ FIXED: snake_case para match con comando Tauri
     }),
+    // Vista previa sin escribir: { jardines, partidas, recintos } con { nuevos, cambiados, eliminados, sinCambios },
+    // más configuracion e invalidas ({ hoja, fila, columna, mensaje }). Se confirma con aplicarCatalogo(diff);
+    // con { desactivarAusentes: true } los eliminados quedan inactivos (no se borran).
+    // El resumen trae { jardines, partidas, recintos, desactivados, contrato }.
+    previsualizarJson: async (data) => toCamel(await invoke('previsualizar_catalogo_json', {
+      json_str: typeof data === 'string' ? data : JSON.stringify(data)
+    })),
+    previsualizarCsv: async (csvStr, tipo) => toCamel(await invoke('previsualizar_catalogo_csv', { csv_str: csvStr, tipo })),
+    previsualizarXlsx: async (filePath, sheetName, tipo) => toCamel(await invoke('previsualizar_catalogo_xlsx', {
+      file_path: filePath, sheet_name: sheetName, tipo
+    })),
+    previsualizarXlsxBytes: async (fileBytes) => toCamel(await invoke('previsualizar_catalogo_xlsx_bytes', { file_bytes: fileBytes })),
+    aplicarCatalogo: async (diff, { desactivarAusentes = false } = {}) => toCamel(await invoke('aplicar_catalogo', {
+      diff: toSnake(diff), desactivar_ausentes: desactivarAusentes
+    })),
     baseDatosCompleta: (jsonStr) => invoke('importar_base_datos_completa', {
       json_str: typeof jsonStr === 'string' ? jsonStr : JSON.stringify(jsonStr)  // ✅ FIXED: snake_case
     }),
     firma: (imagenBase64) => invoke('importar_firma', { imagenBase64 }),
     getFirma: async () => toCamel(await invoke('get_firma')),
     clearAll: () => invoke('clear_all')
+  },
+
+  // Exportar
+  exportar: {
+    baseDatosCompleta: () => invoke('exportar_base_datos_completa'),
+    // Planillas Excel: filePath viene del diálogo de guardado
+    requerimientosXlsx: (filePath, ids = null) => invoke('exportar_requerimientos_xlsx', { file_path: filePath, ids }),
+    ordenTrabajoXlsx: (filePath, otId) => invoke('exportar_orden_trabajo_xlsx', { file_path: filePath, ot_id: otId }),
+    informePagoXlsx: (filePath, informeId) => invoke('exportar_informe_pago_xlsx', { file_path: filePath, informe_id: informeId }),
+    // Documentos PDF generados en Rust (mismo registro → mismo archivo)
+    ordenTrabajoPdf: (filePath, otId) => invoke('exportar_orden_trabajo_pdf', { file_path: filePath, ot_id: otId }),
+    informePagoPdf: (filePath, informeId) => invoke('exportar_informe_pago_pdf', { file_path: filePath, informe_id: informeId }),
+    // mes: 'YYYY-MM'; devuelve las rutas de los PDF creados en la carpeta
+    informesPagoMesPdf: (carpeta, mes) => invoke('exportar_informes_pago_mes_pdf', { carpeta, mes })
+  },
+
+  // Adjuntos (fotos y documentos). entidad: 'requerimiento' | 'orden_trabajo' | 'informe_pago'
+  adjuntos: {
+    // contenido: Array de bytes (Array.from(new Uint8Array(buffer))); etapa: 'antes' | 'despues' | 'recepcion' | null
+    agregar: async (entidad, entidadId, nombre, contenido, etapa = null, descripcion = null) =>
+      toCamel(await invoke('agregar_adjunto', { entidad, entidad_id: entidadId, nombre, contenido, etapa, descripcion })),
+    listar: async (entidad, entidadId) => toCamel(await invoke('get_adjuntos', { entidad, entidad_id: entidadId })),
+    // base64
+    contenido: (id) => invoke('get_adjunto_contenido', { id }),
+    miniatura: (id) => invoke('get_adjunto_miniatura', { id }),
+    exportar: (id, filePath) => invoke('exportar_adjunto', { id, file_path: filePath }),
+    eliminar: (id) => invoke('eliminar_adjunto', { id })
+  },
+
+  // Respaldos automáticos
+  respaldos: {
+    listar: async () => toCamel(await invoke('listar_respaldos')),
+    restaurar: (nombre) => invoke('restaurar_respaldo', { nombre })
+  },
+
+  // Diagnóstico de la base: { archivo, referenciasRotas, recintosHuerfanos, estadosInconsistentes,
+  // informesDescuadrados, reparaciones }. Con reparar = true corrige lo deducible (toma respaldo antes).
+  integridad: {
+    verificar: async (reparar = false) => toCamel(await invoke('verificar_integridad', { reparar }))
+  },
+
+  // Archivo .db abierto; después de abrir, crear o guardar como hay que recargar todo
+  bases: {
+    actual: async () => toCamel(await invoke('get_base_actual')),
+    recientes: async () => toCamel(await invoke('get_bases_recientes')),
+    abrir: async (ruta) => toCamel(await invoke('abrir_base_datos', { ruta })),
+    crear: async (ruta) => toCamel(await invoke('crear_base_datos', { ruta })),
+    guardarComo: async (ruta) => toCamel(await invoke('guardar_base_datos_como', { ruta })),
+    quitarReciente: (ruta) => invoke('quitar_base_reciente', { ruta })
+  },
+
+  // Auditoría de cambios (entidad: 'requerimiento', 'orden_trabajo', 'informe_pago', ...)
+  auditoria: {
+    get: async (entidad, entidadId = null, limite = null) => toCamel(await invoke('get_auditoria', {
+      entidad, entidad_id: entidadId, limite
+    })),
+    getOperador: () => invoke('get_operador'),
+    setOperador: (nombre) => invoke('set_operador', { nombre })
   }
 };
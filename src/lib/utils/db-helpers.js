@@ -4,7 +4,6 @@
  */
 import { db } from '$lib/api/tauri';
 import { calcularPlazoTotal, calcularFechaLimite, calcularDiasAtraso, calcularMulta, calcularAPago } from './calculos';
-import { enriquecerRequerimientos } from './enriquecimiento.js';  // ✅ FIXED: import missing
 
 // ============================================
 // EXPORTS PRINCIPALES
@@ -185,17 +184,12 @@ export async function crearInformePago(jardinCodigo, requerimientoIds) {
     const reqs = await db.informesPago.getRequerimientosParaInforme(jardinCodigo);
     console.log('📊 [INFORME] Requerimientos disponibles:', reqs.length);
     
+    // Los montos los calcula el backend desde los requerimientos
     const requerimientosData = reqs
       .filter(r => requerimientoIds.includes(r.id))
-      .map(r => {
-        console.log('📝 [INFORME] Req ID:', r.id, 'aPago:', r.aPago, 'precioTotal:', r.precioTotal);
-        return {
-          id: r.id,
-          monto: r.aPago || r.precioTotal || 0  // Fallback a precioTotal si aPago no existe
-        };
-      });
+      .map(r => ({ id: r.id }));
     
-    console.log('💰 [INFORME] Datos a enviar:', requerimientosData);
+    console.log('💰 [INFORME] Requerimientos a enviar:', requerimientosData);
     
     return await db.informesPago.crear({
       jardinCodigo,
@@ -209,15 +203,8 @@ export async function crearInformePago(jardinCodigo, requerimientoIds) {
 }
 
 export async function editarInformePago(informeId, requerimientoIds) {
-  // ✅ Obtener requerimientos enriquecidos con campos actualizados
-  const reqs = await db.requerimientos.getAll();
-  const requerimientosFiltrados = reqs.filter(r => requerimientoIds.includes(r.id));
-  const requerimientosEnriquecidos = await enriquecerRequerimientos(requerimientosFiltrados);
-  
-  const requerimientosData = requerimientosEnriquecidos.map(r => ({
-    id: r.id,
-    monto: calcularAPago(r.precioTotal, r.multa) || 0
-  }));
+  // Los montos los calcula el backend desde los requerimientos
+  const requerimientosData = requerimientoIds.map(id => ({ id }));
   
   console.log('📝 [EDITAR-INFORME] Datos a enviar:', requerimientosData);
   
@@ -2,7 +2,7 @@
 set -e
 
 DB_PATH="$HOME/Library/Application Support/sistema-piloto-cont-mant/database.db"
-SCHEMA="/Users/junji/- FLAD/03 Tauri Sqlite/src-tauri/sql/schema.sql"
+SCHEMA="/Users/junji/- FLAD/03 Tauri Sqlite/src-tauri/migrations/001_schema_inicial.sql"
 
 # Limpiar
 rm -rf "$(dirname "$DB_PATH")"